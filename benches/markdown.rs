@@ -0,0 +1,76 @@
+//! Benchmarks of the hot paths of MarkdownString: escaping, appending and formatting.
+//!
+//! Run with `cargo bench --bench markdown`, each case prints the average time per iteration.
+
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use telluride::{
+    markdown::MarkdownString, markdown_format, markdown_string, markdown_write, markdown_writeln,
+};
+
+/// Minimal measurement time of each case
+const MEASUREMENT_TIME: Duration = Duration::from_millis(500);
+
+/// Run the case until the measurement time passes and print the average time per iteration
+fn bench(name: &str, mut case: impl FnMut()) {
+    // Warm up the caches and the allocator
+    for _ in 0..100 {
+        case();
+    }
+    let start = Instant::now();
+    let mut iterations = 0u64;
+    while start.elapsed() < MEASUREMENT_TIME {
+        for _ in 0..100 {
+            case();
+        }
+        iterations += 100;
+    }
+    let per_iteration = start.elapsed().as_nanos() / iterations as u128;
+    println!("{:<32} {:>10} ns/iter", name, per_iteration);
+}
+
+fn main() {
+    let plain = "The quick brown fox jumps over the lazy dog ".repeat(20);
+    let special = "Price: 1.5 + 2 = 3.5 (approx.) - see [docs]! ".repeat(20);
+    let unicode = "Привет, мир! Ünïcödé текст 👋 ".repeat(20);
+
+    bench("escape plain", || {
+        black_box(MarkdownString::escape(black_box(plain.as_str())));
+    });
+    bench("escape special", || {
+        black_box(MarkdownString::escape(black_box(special.as_str())));
+    });
+    bench("escape unicode", || {
+        black_box(MarkdownString::escape(black_box(unicode.as_str())));
+    });
+    bench("literal", || {
+        black_box(markdown_string!("*Status:* all systems operational\\."));
+    });
+    bench("push 100 lines", || {
+        let mut report = markdown_string!("*Report*\n");
+        for i in 0..100 {
+            markdown_writeln!(report, "Line {}: _{}_", i, "value.");
+        }
+        black_box(report);
+    });
+    bench("format", || {
+        black_box(markdown_format!(
+            "*{}* has {} items, total {}",
+            black_box("Alice"),
+            black_box("42"),
+            black_box("1.5 EUR")
+        ));
+    });
+    bench("write", || {
+        let mut text = MarkdownString::new();
+        markdown_write!(text, "{} \\- {}", black_box("a.b"), black_box("c!d"));
+        black_box(text);
+    });
+    let long = MarkdownString::escape("line of the long report\n".repeat(500));
+    bench("split_for_sending", || {
+        black_box(black_box(&long).split_for_sending());
+    });
+}
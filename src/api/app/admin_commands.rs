@@ -0,0 +1,357 @@
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use teloxide::{
+    prelude::ResponseResult,
+    types::{Chat, ChatId, UserId},
+};
+
+use crate::{
+    api::{
+        command::{
+            command_metrics::{CommandMetrics, render_command_stats},
+            command_reply_target::CommandReplyTarget,
+        },
+        config::bot_config::BotConfig,
+        data_store::data_store_trait::DataStoreTrait,
+        markdown::string::MarkdownString,
+    },
+    markdown_format, markdown_string,
+};
+
+/// Names of the operator commands handled by [`AdminCommands`]
+pub const ADMIN_COMMANDS: [&str; 5] = ["stats", "chats", "dump_key", "set_loglevel", "maintenance"];
+
+/// Maximum number of chats listed by the `/chats` command
+const MAX_LISTED_CHATS: usize = 50;
+
+/// Number of tracked chats after which the idle chats are forgotten
+const CHATS_CLEANUP_THRESHOLD: usize = 1000;
+
+/// Time without updates after which the chat is idle
+const CHAT_IDLE_TIMEOUT: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Default)]
+struct AdminState {
+    updates: u64,
+    commands: u64,
+    errors: u64,
+    // Names of the chats with the time of their last update
+    chats: BTreeMap<ChatId, (String, Instant)>,
+    maintenance: bool,
+}
+
+/// Built-in operator commands of the bot
+///
+/// Provides the basic ops console available only to the configured admin users:
+///
+/// - `/stats` - uptime and the numbers of the processed updates, commands and errors,
+///   with the table of the runs, errors and latency of each command if the metrics are collected
+/// - `/chats` - the chats seen by the bot since the start, the chats idle for a day are forgotten
+///   once there are more than a thousand of them
+/// - `/dump_key <chat> <key>` - the value of the key in the inspected stores
+/// - `/set_loglevel <level>` - change the maximum level of the log
+/// - `/maintenance on|off` - answer the other users that the bot is under maintenance
+///
+/// Registered with [`BotApp::with_admin_commands`](crate::app::BotApp::with_admin_commands),
+/// the commands are invisible to the other users and are not listed in [`BotApp::commands`](crate::app::BotApp::commands).
+#[derive(Clone)]
+pub struct AdminCommands {
+    admins: Vec<UserId>,
+    admin_chats: Vec<ChatId>,
+    stores: Vec<(String, Arc<dyn DataStoreTrait<serde_yaml::Value>>)>,
+    command_metrics: Option<Arc<dyn CommandMetrics>>,
+    started: Instant,
+    chat_idle_timeout: Duration,
+    state: Arc<Mutex<AdminState>>,
+}
+
+impl AdminCommands {
+    /// Create the commands available to the given users
+    pub fn new(admins: impl IntoIterator<Item = UserId>) -> Self {
+        Self {
+            admins: admins.into_iter().collect(),
+            admin_chats: Vec::new(),
+            stores: Vec::new(),
+            command_metrics: None,
+            started: Instant::now(),
+            chat_idle_timeout: CHAT_IDLE_TIMEOUT,
+            state: Arc::new(Mutex::new(AdminState::default())),
+        }
+    }
+
+    /// Create the commands available to the users of the config's admin private chats
+    /// and to all members of its admin group chats
+    pub fn from_config(config: &BotConfig) -> Self {
+        let (users, groups): (Vec<ChatId>, Vec<ChatId>) = config
+            .admin_chat_ids
+            .iter()
+            .partition(|chat_id| chat_id.is_user());
+        Self::new(users.into_iter().map(|chat_id| UserId(chat_id.0 as u64))).with_admin_chats(groups)
+    }
+
+    /// Allow the commands to all members of the given chats, e.g. the operators' group
+    pub fn with_admin_chats(mut self, chat_ids: impl IntoIterator<Item = ChatId>) -> Self {
+        self.admin_chats.extend(chat_ids);
+        self
+    }
+
+    /// Make the store's values available to the `/dump_key` command
+    /// The store is read as untyped YAML values, e.g. a [`FilesystemYamlStore`](crate::data_store::FilesystemYamlStore)
+    /// sharing the directory with the typed store
+    pub fn inspect_store(
+        mut self,
+        name: impl Into<String>,
+        store: Arc<dyn DataStoreTrait<serde_yaml::Value>>,
+    ) -> Self {
+        self.stores.push((name.into(), store));
+        self
+    }
+
+    /// Show the statistics of the commands collected by the metrics in `/stats`,
+    /// see [`BotApp::with_command_metrics`](crate::app::BotApp::with_command_metrics)
+    pub fn with_command_metrics(mut self, metrics: Arc<dyn CommandMetrics>) -> Self {
+        self.command_metrics = Some(metrics);
+        self
+    }
+
+    /// Check if the user is allowed to use the commands
+    pub fn is_admin(&self, user_id: Option<UserId>) -> bool {
+        user_id.is_some_and(|user_id| self.admins.contains(&user_id))
+    }
+
+    /// Check if the chat is an admin chat whose members may use the commands
+    pub fn is_admin_chat(&self, chat_id: ChatId) -> bool {
+        self.admin_chats.contains(&chat_id)
+    }
+
+    /// Internal helper function to check if the target's user may use the commands in its chat
+    pub(crate) fn allows(&self, target: &CommandReplyTarget) -> bool {
+        self.is_admin(target.user_id) || self.is_admin_chat(target.chat.id)
+    }
+
+    /// Check if the maintenance mode is on
+    pub fn is_maintenance(&self) -> bool {
+        self.state.lock().unwrap().maintenance
+    }
+
+    /// Internal helper function to record the update received in the chat
+    pub(crate) fn record_update(&self, chat: &Chat) {
+        let mut state = self.state.lock().unwrap();
+        state.updates += 1;
+        let name = chat
+            .title()
+            .or(chat.username())
+            .or(chat.first_name())
+            .unwrap_or_default()
+            .to_string();
+        let now = Instant::now();
+        state.chats.insert(chat.id, (name, now));
+        if state.chats.len() > CHATS_CLEANUP_THRESHOLD {
+            state.chats.retain(|chat_id, (_, last_update)| {
+                *chat_id == chat.id || now.duration_since(*last_update) < self.chat_idle_timeout
+            });
+        }
+    }
+
+    /// Internal helper function to record the result of the command handler
+    pub(crate) fn record_command(&self, ok: bool) {
+        let mut state = self.state.lock().unwrap();
+        state.commands += 1;
+        if !ok {
+            state.errors += 1;
+        }
+    }
+
+    /// Internal helper function to run the operator command
+    /// Returns `None` if the command is not an operator one or the user is not an admin
+    pub(crate) async fn run(
+        &self,
+        target: &CommandReplyTarget,
+        name: &str,
+        args: &str,
+    ) -> Option<ResponseResult<()>> {
+        if !ADMIN_COMMANDS.contains(&name) {
+            return None;
+        }
+        if !self.allows(target) {
+            log::warn!(
+                "User {:?} is not allowed to run /{} in chat {}",
+                target.user_id,
+                name,
+                target.chat.id
+            );
+            return None;
+        }
+        let args: Vec<&str> = args.split_whitespace().collect();
+        let reply = match (name, args.as_slice()) {
+            ("stats", _) => self.render_stats() + self.render_command_stats().await,
+            ("chats", _) => self.render_chats(),
+            ("dump_key", [chat_id, key]) => match chat_id.parse() {
+                Ok(chat_id) => self.dump_key(ChatId(chat_id), key).await,
+                Err(_) => markdown_format!("Invalid chat id: {}", *chat_id),
+            },
+            ("dump_key", _) => markdown_string!("Usage: /dump\\_key \\<chat\\> \\<key\\>"),
+            ("set_loglevel", [level]) => match level.parse::<log::LevelFilter>() {
+                Ok(level) => {
+                    log::set_max_level(level);
+                    log::info!("Log level set to {} by {:?}", level, target.user_id);
+                    markdown_format!("Log level set to {}", level.to_string())
+                }
+                Err(_) => markdown_format!("Invalid log level: {}", *level),
+            },
+            ("set_loglevel", _) => markdown_format!(
+                "Usage: /set\\_loglevel off\\|error\\|warn\\|info\\|debug\\|trace\nCurrent level: {}",
+                log::max_level().to_string()
+            ),
+            ("maintenance", ["on"]) => self.set_maintenance(true),
+            ("maintenance", ["off"]) => self.set_maintenance(false),
+            _ => markdown_format!(
+                "Usage: /maintenance on\\|off\nMaintenance: {}",
+                on_off(self.is_maintenance())
+            ),
+        };
+        Some(target.markdown_message(reply).await.map(|_| ()))
+    }
+
+    /// Render the statistics of the bot
+    pub fn render_stats(&self) -> MarkdownString {
+        let state = self.state.lock().unwrap();
+        markdown_format!(
+            "*Bot statistics*\nUptime: {}\nUpdates: {}\nCommands: {}\nErrors: {}\nChats: {}\nMaintenance: {}",
+            format_duration(self.started.elapsed()),
+            state.updates.to_string(),
+            state.commands.to_string(),
+            state.errors.to_string(),
+            state.chats.len(),
+            on_off(state.maintenance)
+        )
+    }
+
+    /// Render the table of the command statistics, empty if the metrics are not collected yet
+    pub async fn render_command_stats(&self) -> MarkdownString {
+        let Some(metrics) = &self.command_metrics else {
+            return MarkdownString::new();
+        };
+        let stats = metrics.stats().await;
+        if stats.is_empty() {
+            return MarkdownString::new();
+        }
+        markdown_string!("\n\n*Commands*\n") + render_command_stats(&stats)
+    }
+
+    /// Render the list of the chats seen by the bot
+    pub fn render_chats(&self) -> MarkdownString {
+        let state = self.state.lock().unwrap();
+        if state.chats.is_empty() {
+            return markdown_string!("No chats yet");
+        }
+        let list = state
+            .chats
+            .iter()
+            .take(MAX_LISTED_CHATS)
+            .map(|(chat_id, (name, _))| format!("{} {}", chat_id, name))
+            .collect::<Vec<_>>()
+            .join("\n");
+        markdown_format!("*Chats* \\({}\\)\n{}", state.chats.len(), @code list)
+    }
+
+    /// Internal helper function to render the values of the key in the inspected stores
+    async fn dump_key(&self, chat_id: ChatId, key: &str) -> MarkdownString {
+        let mut reply = MarkdownString::new();
+        for (name, store) in &self.stores {
+            let Some(value) = store.get(chat_id, key).await else {
+                continue;
+            };
+            let yaml = serde_yaml::to_string(&value).unwrap_or_else(|err| err.to_string());
+            reply.push(&markdown_format!("*{}*\n{}", name.as_str(), @code "yaml" yaml));
+        }
+        if reply.as_str().is_empty() {
+            return markdown_format!("Key {} not found in chat {}", key, chat_id.to_string());
+        }
+        reply
+    }
+
+    /// Internal helper function to switch the maintenance mode
+    fn set_maintenance(&self, maintenance: bool) -> MarkdownString {
+        self.state.lock().unwrap().maintenance = maintenance;
+        log::warn!("Maintenance mode {}", on_off(maintenance));
+        markdown_format!("Maintenance: {}", on_off(maintenance))
+    }
+}
+
+/// Internal helper function to render the flag
+fn on_off(flag: bool) -> &'static str {
+    if flag { "on" } else { "off" }
+}
+
+/// Internal helper function to render the duration as days, hours, minutes and seconds
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let (days, hours, minutes, seconds) =
+        (secs / 86400, secs / 3600 % 24, secs / 60 % 60, secs % 60);
+    if days > 0 {
+        format!("{}d {}h {}m", days, hours, minutes)
+    } else if hours > 0 {
+        format!("{}h {}m {}s", hours, minutes, seconds)
+    } else if minutes > 0 {
+        format!("{}m {}s", minutes, seconds)
+    } else {
+        format!("{}s", seconds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_admin_stats() {
+        let config: BotConfig =
+            serde_yaml::from_str("{token: token, admin_chat_ids: [1, -100200]}").unwrap();
+        let admin = AdminCommands::from_config(&config);
+        assert!(admin.is_admin(Some(UserId(1))));
+        assert!(!admin.is_admin(Some(UserId(2))));
+        assert!(!admin.is_admin(None));
+        // The members of the admin groups are allowed in the group only
+        assert!(admin.is_admin_chat(ChatId(-100200)));
+        assert!(!admin.is_admin_chat(ChatId(1)));
+
+        let chat: Chat = serde_yaml::from_str(
+            "{id: 12345, type: private, first_name: Alice}",
+        )
+        .unwrap();
+        admin.record_update(&chat);
+        admin.record_command(true);
+        admin.record_command(false);
+        admin.set_maintenance(true);
+        assert!(admin.is_maintenance());
+        let stats = admin.render_stats().to_plain_text();
+        assert!(stats.contains("Commands: 2\nErrors: 1\nChats: 1\nMaintenance: on"));
+        assert!(admin.render_chats().as_str().contains("12345 Alice"));
+
+        assert_eq!(format_duration(Duration::from_secs(59)), "59s");
+        assert_eq!(format_duration(Duration::from_secs(3723)), "1h 2m 3s");
+        assert_eq!(format_duration(Duration::from_secs(90061)), "1d 1h 1m");
+    }
+
+    #[test]
+    fn test_idle_chats_forgotten() {
+        let mut admin = AdminCommands::new([]);
+        admin.chat_idle_timeout = Duration::ZERO;
+        let chat = |id| -> Chat {
+            serde_yaml::from_str(&format!("{{id: {}, type: private, first_name: A}}", id)).unwrap()
+        };
+        for id in 1..=CHATS_CLEANUP_THRESHOLD as i64 {
+            admin.record_update(&chat(id));
+        }
+        assert_eq!(admin.state.lock().unwrap().chats.len(), CHATS_CLEANUP_THRESHOLD);
+        // Above the threshold the idle chats are forgotten, the updated one is kept
+        admin.record_update(&chat(0));
+        let state = admin.state.lock().unwrap();
+        assert_eq!(state.chats.keys().collect::<Vec<_>>(), [&ChatId(0)]);
+    }
+}
@@ -0,0 +1,785 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    future::Future,
+    pin::Pin,
+    sync::{Arc, LazyLock, Mutex},
+};
+
+use serde::{Deserialize, Serialize};
+use teloxide::{
+    RequestError,
+    dispatching::{Dispatcher, UpdateFilterExt},
+    dptree,
+    prelude::{Requester, ResponseResult},
+    types::{CallbackQuery, ChatId, Me, Message, Update, UpdateKind},
+    Bot,
+};
+
+use crate::api::{
+    command::{
+        command_button::{CallbackData, CallbackDataStorage, unpack_callback_data},
+        confirm::CONFIRM_COMMAND,
+        command_metrics::CommandMetrics,
+        command_middleware::{CommandCall, CommandMiddleware},
+        command_registry::{CommandRegistry, flush_batch},
+        command_reply_target::{CommandReplyTarget, RenderedMessage},
+        command_trait::CommandTrait,
+        outgoing_middleware::OutgoingMiddleware,
+        paginator::Paginator,
+        pending_operation::CANCEL_COMMAND,
+        prompt::is_answer_awaited,
+        session::{SessionStore, SessionWriteBack},
+        subcommand_router::SubcommandRouter,
+    },
+    app::{
+        admin_commands::AdminCommands, polling_supervisor::PollingSupervisor,
+        update_dedup::UpdateDeduplicator,
+    },
+    config::bot_config::BotConfig,
+    markdown::string::MarkdownString,
+    retry::retry_policy::RetryPolicy,
+    parse::command_string::{is_command_to_other_bot, split_command},
+    data_store::{data_store_trait::DataStoreTrait, in_mem::InMemStore, namespaced::NamespacedStore},
+};
+use crate::{markdown_format, markdown_string};
+
+/// Number of the bots of the process whose updates are distributed with the awaited answers of their own,
+/// the updates of the other bots are handled concurrently if an answer is awaited in the chat by any bot
+const DISTRIBUTION_SLOTS: usize = 8;
+
+/// Tokens of the bots owning the distribution functions with the same index,
+/// the distribution function can't capture its bot, so each bot gets its own instance of it
+static DISTRIBUTION_BOTS: LazyLock<Mutex<Vec<String>>> = LazyLock::new(Default::default);
+
+/// Boxed future returned by the handlers registered in [`BotApp`]
+pub type BoxFuture<T> = Pin<Box<dyn Future<Output = T> + Send>>;
+
+type TextHandler<Ctx> =
+    Arc<dyn Fn(CommandReplyTarget, Ctx, Message) -> BoxFuture<ResponseResult<()>> + Send + Sync>;
+type ErrorHandler = Arc<dyn Fn(CommandReplyTarget, RequestError) -> BoxFuture<()> + Send + Sync>;
+type TargetConfigurator = Arc<dyn Fn(CommandReplyTarget) -> CommandReplyTarget + Send + Sync>;
+
+/// Bot served by the [`BotApp`] with its stores
+struct BotInstance {
+    name: Option<String>,
+    bot: Bot,
+    callback_store: Arc<dyn DataStoreTrait<CallbackData>>,
+    rendered_messages: Option<Arc<dyn DataStoreTrait<RenderedMessage>>>,
+    update_dedup: Option<UpdateDeduplicator>,
+    retry_policy: Option<RetryPolicy>,
+}
+
+/// Builder of a teloxide [`Dispatcher`] wiring the telluride stack together
+///
+/// Incoming commands and callback queries are routed to the registered command handlers,
+/// each handler receives a [`CommandReplyTarget`] configured with the app's stores and middlewares,
+/// the shared context and the command arguments. Callback data packed with
+/// [`pack_callback_data`](crate::command::pack_callback_data) is unpacked automatically,
+/// so menu buttons containing commands invoke the same handlers, editing the menu's message.
+///
+/// Several bots with different tokens can run the same handlers, see [`add_bot`](Self::add_bot).
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use telluride::{app::BotApp, markdown_format, markdown_string};
+/// use teloxide::Bot;
+///
+/// # async fn run() {
+/// BotApp::new(Bot::from_env(), ())
+///     .command("start", "start the bot", |target, _, _| async move {
+///         target.markdown_message(markdown_string!("Welcome\\!")).await?;
+///         Ok(())
+///     })
+///     .on_text(|target, _, msg| async move {
+///         let text = msg.text().unwrap_or_default().to_string();
+///         target.markdown_message(markdown_format!("You said: {}", text)).await?;
+///         Ok(())
+///     })
+///     .dispatch()
+///     .await;
+/// # }
+/// ```
+pub struct BotApp<Ctx = ()> {
+    bot: Bot,
+    extra_bots: Vec<(String, Bot)>,
+    context: Ctx,
+    callback_store: Arc<dyn DataStoreTrait<CallbackData>>,
+    rendered_messages: Option<Arc<dyn DataStoreTrait<RenderedMessage>>>,
+    middlewares: Vec<Arc<dyn OutgoingMiddleware>>,
+    sessions: Vec<Arc<dyn SessionWriteBack>>,
+    update_dedup: Option<UpdateDeduplicator>,
+    retry_policy: Option<RetryPolicy>,
+    bot_retry_policies: HashMap<String, RetryPolicy>,
+    admin_commands: Option<AdminCommands>,
+    locale: Option<String>,
+    features: Arc<BTreeMap<String, bool>>,
+    configure_target: Option<TargetConfigurator>,
+    commands: CommandRegistry<Ctx>,
+    text_handler: Option<TextHandler<Ctx>>,
+    error_handler: Option<ErrorHandler>,
+}
+
+impl<Ctx> BotApp<Ctx>
+where
+    Ctx: Clone + Send + Sync + 'static,
+{
+    /// Create an app for the bot, the context is passed to all handlers
+    /// By default the callback data is kept in memory
+    pub fn new(bot: Bot, context: Ctx) -> Self {
+        Self {
+            bot,
+            extra_bots: Vec::new(),
+            context,
+            callback_store: Arc::new(InMemStore::new()),
+            rendered_messages: None,
+            middlewares: Vec::new(),
+            sessions: Vec::new(),
+            update_dedup: None,
+            retry_policy: None,
+            bot_retry_policies: HashMap::new(),
+            admin_commands: None,
+            locale: None,
+            features: Arc::default(),
+            configure_target: None,
+            commands: CommandRegistry::new(),
+            text_handler: None,
+            error_handler: None,
+        }
+    }
+
+    /// Create an app for the bot configured by the config,
+    /// the callback data and the rendered messages are kept in the configured storage,
+    /// the admin chats get the [`AdminCommands`], the locale and the features are passed to the targets
+    pub fn from_config(config: &BotConfig, context: Ctx) -> Self {
+        let mut app = Self::new(config.bot(), context)
+            .with_callback_store(config.store("callbacks"))
+            .track_rendered_messages(config.store("rendered_messages"))
+            .with_features(config.features.clone());
+        if let Some(locale) = &config.locale {
+            app = app.with_locale(locale);
+        }
+        if !config.admin_chat_ids.is_empty() {
+            app = app.with_admin_commands(AdminCommands::from_config(config));
+        }
+        app
+    }
+
+    /// Add another bot running the same handlers, e.g. for a white-label deployment
+    /// The bot's data is kept in the app's stores under the bot's name,
+    /// the name is available to the handlers as [`CommandReplyTarget::bot_name`]
+    pub fn add_bot(mut self, name: impl Into<String>, bot: Bot) -> Self {
+        self.extra_bots.push((name.into(), bot));
+        self
+    }
+
+    /// Retry the requests of the targets rejected by flood control or, if the policy allows, failed by transient errors,
+    /// see [`RetryPolicy`]
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(retry_policy);
+        self
+    }
+
+    /// Use the given retry policy for the bot added with [`add_bot`](Self::add_bot) under the name
+    /// instead of the app's one, e.g. for the bot with the stricter limits
+    pub fn with_bot_retry_policy(mut self, name: impl Into<String>, retry_policy: RetryPolicy) -> Self {
+        self.bot_retry_policies.insert(name.into(), retry_policy);
+        self
+    }
+
+    /// Keep the callback data of the menus in the given store
+    pub fn with_callback_store(mut self, store: Arc<dyn DataStoreTrait<CallbackData>>) -> Self {
+        self.callback_store = store;
+        self
+    }
+
+    /// Track the last rendered state of the messages in the given store,
+    /// see [`CommandReplyTarget::track_rendered_messages`]
+    pub fn track_rendered_messages(
+        mut self,
+        rendered_messages: Arc<dyn DataStoreTrait<RenderedMessage>>,
+    ) -> Self {
+        self.rendered_messages = Some(rendered_messages);
+        self
+    }
+
+    /// Add the middleware to the outgoing messages interceptor chain of all targets
+    pub fn with_middleware(mut self, middleware: impl OutgoingMiddleware + 'static) -> Self {
+        self.middlewares.push(Arc::new(middleware));
+        self
+    }
+
+    /// Write back the changed sessions of the store after each handler run
+    /// The handlers get the sessions from the store passed to them in the context,
+    /// see [`SessionStore::session`]
+    pub fn with_sessions<S>(mut self, sessions: SessionStore<S>) -> Self
+    where
+        S: Serialize + for<'de> Deserialize<'de> + Default + Clone + Send + Sync + 'static,
+    {
+        self.sessions.push(Arc::new(sessions));
+        self
+    }
+
+    /// Drop the updates re-delivered by Telegram which were already processed
+    pub fn with_update_dedup(mut self, update_dedup: UpdateDeduplicator) -> Self {
+        self.update_dedup = Some(update_dedup);
+        self
+    }
+
+    /// Handle the operator commands of the admins and collect the statistics for them
+    pub fn with_admin_commands(mut self, admin_commands: AdminCommands) -> Self {
+        self.admin_commands = Some(admin_commands);
+        self
+    }
+
+    /// Set the default locale of the bot's messages, available to the handlers as [`CommandReplyTarget::locale`]
+    pub fn with_locale(mut self, locale: impl Into<String>) -> Self {
+        self.locale = Some(locale.into());
+        self
+    }
+
+    /// Set the feature toggles, checked by the handlers with [`CommandReplyTarget::feature`]
+    pub fn with_features(mut self, features: BTreeMap<String, bool>) -> Self {
+        self.features = Arc::new(features);
+        self
+    }
+
+    /// Apply additional options to each target created by the app,
+    /// e.g. the retry policy, the rate limiter or the last message tracker
+    pub fn configure_target(
+        mut self,
+        configure: impl Fn(CommandReplyTarget) -> CommandReplyTarget + Send + Sync + 'static,
+    ) -> Self {
+        self.configure_target = Some(Arc::new(configure));
+        self
+    }
+
+    /// Register the handler of the command with the given name (without the leading slash)
+    /// The handler receives the rest of the message after the command name as the arguments
+    pub fn command<F, Fut>(
+        mut self,
+        name: impl Into<String>,
+        description: impl Into<String>,
+        handler: F,
+    ) -> Self
+    where
+        F: Fn(CommandReplyTarget, Ctx, String) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ResponseResult<()>> + Send + 'static,
+    {
+        self.commands = self.commands.command(name, description, handler);
+        self
+    }
+
+    /// Register the handler of the command returning the crate's error, see [`CommandRegistry::try_command`]
+    pub fn try_command<F, Fut>(
+        mut self,
+        name: impl Into<String>,
+        description: impl Into<String>,
+        handler: F,
+    ) -> Self
+    where
+        F: Fn(CommandReplyTarget, Ctx, String) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = crate::Result<()>> + Send + 'static,
+    {
+        self.commands = self.commands.try_command(name, description, handler);
+        self
+    }
+
+    /// Register the command type, see [`CommandRegistry::register`]
+    pub fn register<C>(mut self, description: impl Into<String>) -> Self
+    where
+        C: CommandTrait<Context = Ctx> + 'static,
+    {
+        self.commands = self.commands.register::<C>(description);
+        self
+    }
+
+    /// Register the command dispatching to the subcommands of the router, see [`CommandRegistry::router`]
+    pub fn router(mut self, router: SubcommandRouter<Ctx>, description: impl Into<String>) -> Self {
+        self.commands = self.commands.router(router, description);
+        self
+    }
+
+    /// Register the command of the paginator's buttons, see [`CommandRegistry::paginator`]
+    pub fn paginator(mut self, paginator: Paginator) -> Self {
+        self.commands = self.commands.paginator(paginator);
+        self
+    }
+
+    /// Add the commands of the registry, replacing the ones with the same names
+    pub fn with_commands(mut self, commands: CommandRegistry<Ctx>) -> Self {
+        self.commands = self.commands.merge(commands);
+        self
+    }
+
+    /// Record the runs of the registered commands with the given collector, see [`CommandRegistry::with_metrics`]
+    /// Pass the same collector to [`AdminCommands::with_command_metrics`] to show the numbers in `/stats`.
+    pub fn with_command_metrics(mut self, metrics: Arc<dyn CommandMetrics>) -> Self {
+        self.commands = self.commands.with_metrics(metrics);
+        self
+    }
+
+    /// Add the middleware wrapping all registered commands, see [`CommandMiddleware`]
+    pub fn with_command_middleware(mut self, middleware: impl CommandMiddleware + 'static) -> Self {
+        self.commands = self.commands.with_middleware(middleware);
+        self
+    }
+
+    /// Register the handler of the text messages which are not registered commands
+    pub fn on_text<F, Fut>(mut self, handler: F) -> Self
+    where
+        F: Fn(CommandReplyTarget, Ctx, Message) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ResponseResult<()>> + Send + 'static,
+    {
+        self.text_handler = Some(Arc::new(move |target, context, msg| {
+            Box::pin(handler(target, context, msg))
+        }));
+        self
+    }
+
+    /// Register the handler of the errors returned by the command and text handlers
+    /// By default the errors are logged
+    pub fn on_error<F, Fut>(mut self, handler: F) -> Self
+    where
+        F: Fn(CommandReplyTarget, RequestError) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.error_handler = Some(Arc::new(move |target, err| Box::pin(handler(target, err))));
+        self
+    }
+
+    /// Reply to the user whose command failed with the rendered error, see [`CommandRegistry::on_error`]
+    /// The error is passed to the [`on_error`](Self::on_error) handler afterwards.
+    pub fn on_command_error<F>(mut self, render: F) -> Self
+    where
+        F: Fn(&CommandCall, &crate::Error, &str) -> MarkdownString + Send + Sync + 'static,
+    {
+        self.commands = self.commands.on_error(render);
+        self
+    }
+
+    /// Send the full errors of the failed commands to the chat, see [`CommandRegistry::forward_errors_to`]
+    pub fn forward_errors_to(mut self, chat_id: ChatId) -> Self {
+        self.commands = self.commands.forward_errors_to(chat_id);
+        self
+    }
+
+    /// The names and the descriptions of the registered commands
+    pub fn commands(&self) -> Vec<(&str, &str)> {
+        self.commands.commands()
+    }
+
+    /// Set the command menu of the main bot and of the added bots to the registered commands,
+    /// see [`CommandRegistry::sync_bot_commands`]
+    pub async fn sync_bot_commands(&self) -> ResponseResult<()> {
+        self.commands.sync_bot_commands(&self.bot).await?;
+        for (_, bot) in &self.extra_bots {
+            self.commands.sync_bot_commands(bot).await?;
+        }
+        Ok(())
+    }
+
+    /// Build the dispatcher of the main bot handling the messages and the callback queries,
+    /// the messages of each chat are handled in order, the callback queries concurrently
+    /// The bots added with [`add_bot`](Self::add_bot) are ignored, use [`build_all`](Self::build_all) for them
+    pub fn build(mut self) -> Dispatcher<Bot, RequestError, ChatId> {
+        self.extra_bots.clear();
+        self.build_all().remove(0)
+    }
+
+    /// Build the dispatchers of the main bot and of all added bots, sharing the app's handlers
+    pub fn build_all(mut self) -> Vec<Dispatcher<Bot, RequestError, ChatId>> {
+        let main = BotInstance {
+            name: None,
+            bot: self.bot.clone(),
+            callback_store: self.callback_store.clone(),
+            rendered_messages: self.rendered_messages.clone(),
+            update_dedup: self.update_dedup.clone(),
+            retry_policy: self.retry_policy.clone(),
+        };
+        let extra = std::mem::take(&mut self.extra_bots)
+            .into_iter()
+            .map(|(name, bot)| BotInstance {
+                callback_store: Arc::new(NamespacedStore::new(self.callback_store.clone(), &name)),
+                rendered_messages: self.rendered_messages.clone().map(|store| {
+                    Arc::new(NamespacedStore::new(store, &name))
+                        as Arc<dyn DataStoreTrait<RenderedMessage>>
+                }),
+                update_dedup: self.update_dedup.as_ref().map(|dedup| dedup.namespaced(&name)),
+                retry_policy: self
+                    .bot_retry_policies
+                    .get(&name)
+                    .or(self.retry_policy.as_ref())
+                    .cloned(),
+                name: Some(name),
+                bot,
+            });
+        let instances: Vec<_> = std::iter::once(main).chain(extra).collect();
+        let app = Arc::new(self);
+        instances
+            .into_iter()
+            .map(|instance| {
+                let handler = dptree::filter_async(Self::filter_duplicates)
+                    .branch(Update::filter_message().endpoint(Self::handle_message))
+                    .branch(Update::filter_callback_query().endpoint(Self::handle_callback_query));
+                Dispatcher::builder(instance.bot.clone(), handler)
+                    .distribution_function(Self::DISTRIBUTION_KEYS[distribution_slot(&instance.bot)])
+                    .dependencies(dptree::deps![app.clone(), Arc::new(instance)])
+                    .enable_ctrlc_handler()
+                    .build()
+            })
+            .collect()
+    }
+
+    /// Build the dispatchers of all bots and run them until Ctrl+C is pressed
+    pub async fn dispatch(self) {
+        let mut dispatchers = self.build_all();
+        if dispatchers.len() == 1 {
+            return dispatchers[0].dispatch().await;
+        }
+        let tasks = dispatchers
+            .into_iter()
+            .map(|mut dispatcher| tokio::spawn(async move { dispatcher.dispatch().await }))
+            .collect();
+        join_dispatchers(tasks).await;
+    }
+
+    /// Build the dispatchers of all bots and run them on the long polling supervised
+    /// by the [`PollingSupervisor`] until Ctrl+C is pressed
+    /// The bots added with [`add_bot`](Self::add_bot) are polled by the same supervisor,
+    /// its [`health`](PollingSupervisor::health) reflects the polling of all bots.
+    pub async fn dispatch_supervised(self, supervisor: PollingSupervisor) {
+        let bots: Vec<Bot> = std::iter::once(self.bot.clone())
+            .chain(self.extra_bots.iter().map(|(_, bot)| bot.clone()))
+            .collect();
+        let tasks = self
+            .build_all()
+            .into_iter()
+            .zip(bots)
+            .map(|(mut dispatcher, bot)| {
+                let listener = supervisor.listener(bot);
+                tokio::spawn(async move {
+                    dispatcher
+                        .dispatch_with_listener(
+                            listener,
+                            // The polling errors are reported by the supervisor
+                            Arc::new(teloxide::error_handlers::IgnoringErrorHandler),
+                        )
+                        .await
+                })
+            })
+            .collect();
+        join_dispatchers(tasks).await;
+    }
+
+    /// Build the dispatcher and run it on the webhook until Ctrl+C is pressed,
+    /// see [`webhook_listener`](crate::webhook::webhook_listener)
+    /// The webhook serves a single bot, fails if bots were added with [`add_bot`](Self::add_bot).
+    #[cfg(feature = "webhook")]
+    pub async fn dispatch_with_webhook(
+        self,
+        config: crate::api::webhook::webhook_listener::WebhookConfig,
+    ) -> ResponseResult<()> {
+        if !self.extra_bots.is_empty() {
+            return Err(RequestError::Io(Arc::new(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "The webhook serves only the main bot, run the added bots with dispatch or dispatch_supervised",
+            ))));
+        }
+        let listener =
+            crate::api::webhook::webhook_listener::webhook_listener(self.bot.clone(), config)
+                .await?;
+        self.build()
+            .dispatch_with_listener(
+                listener,
+                teloxide::error_handlers::LoggingErrorHandler::with_custom_text(
+                    "An error from the webhook listener",
+                ),
+            )
+            .await;
+        Ok(())
+    }
+
+    /// Internal helper function to create a target of the bot configured with the app's options
+    fn target(&self, instance: &BotInstance, msg: &Message, edit: bool) -> CommandReplyTarget {
+        let callback_data_storage = Arc::new(CallbackDataStorage::new(
+            instance.callback_store.clone(),
+            msg.chat.id,
+        ));
+        let msg_id = edit.then_some(msg.id);
+        let mut target = CommandReplyTarget::new(
+            instance.bot.clone(),
+            msg.chat.clone(),
+            msg_id,
+            callback_data_storage,
+        );
+        target.bot_name = instance.name.clone();
+        target.locale = self.locale.clone();
+        target.features = self.features.clone();
+        target.user_id = msg.from.as_ref().map(|user| user.id);
+        if let Some(rendered_messages) = &instance.rendered_messages {
+            target = target.track_rendered_messages(rendered_messages.clone());
+        }
+        target.retry_policy = instance.retry_policy.clone();
+        target.middlewares.extend(self.middlewares.iter().cloned());
+        match &self.configure_target {
+            Some(configure) => configure(target),
+            None => target,
+        }
+    }
+
+    /// Internal helper function to route the command text to its handler
+    /// Returns false if the text is not a registered command
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "command",
+            skip_all,
+            fields(
+                chat_id = %target.chat.id,
+                command = tracing::field::Empty,
+                duration_ms = tracing::field::Empty,
+                ok = tracing::field::Empty,
+            )
+        )
+    )]
+    async fn run_command(&self, target: CommandReplyTarget, text: &str) -> bool {
+        let Some((name, args)) = split_command(text) else {
+            return false;
+        };
+        if let Some(admin_commands) = &self.admin_commands
+            && let Some(result) = admin_commands.run(&target, name, args).await
+        {
+            if let Err(err) = result {
+                self.handle_error(target, err).await;
+            }
+            return true;
+        }
+        let Some(command) = self.commands.find(name) else {
+            if name == CANCEL_COMMAND && target.operation_registry.is_some() {
+                self.cancel_operation(target).await;
+                return true;
+            }
+            return false;
+        };
+        #[cfg(feature = "tracing")]
+        let started = std::time::Instant::now();
+        let result = self
+            .commands
+            .run_command(command, target.clone(), self.context.clone(), args)
+            .await;
+        self.write_back_sessions(&target).await;
+        if let Some(admin_commands) = &self.admin_commands {
+            admin_commands.record_command(result.is_ok());
+        }
+        #[cfg(feature = "tracing")]
+        tracing::Span::current()
+            .record("command", name)
+            .record("duration_ms", started.elapsed().as_millis() as u64)
+            .record("ok", result.is_ok());
+        if let Err(err) = result {
+            self.handle_error(target, err).await;
+        }
+        true
+    }
+
+    /// Internal helper function to run the built-in `/cancel` command aborting the user's pending operation
+    async fn cancel_operation(&self, target: CommandReplyTarget) {
+        let text = match target.cancel_operation().await {
+            Some(operation) => markdown_format!("Cancelled {}", operation.name),
+            None => markdown_string!("Nothing to cancel"),
+        };
+        if let Err(err) = target.markdown_message(text).await {
+            self.handle_error(target, err).await;
+        }
+    }
+
+    /// Internal helper function to write back the sessions changed by the handler
+    async fn write_back_sessions(&self, target: &CommandReplyTarget) {
+        for sessions in &self.sessions {
+            sessions.write_back(target.chat.id, target.user_id).await;
+        }
+    }
+
+    /// Internal helper function to record the update for the admin commands
+    /// Returns true if the bot is under maintenance for the target's user, the user is notified
+    async fn check_maintenance(&self, target: &CommandReplyTarget) -> bool {
+        let Some(admin_commands) = &self.admin_commands else {
+            return false;
+        };
+        admin_commands.record_update(&target.chat);
+        if !admin_commands.is_maintenance() || admin_commands.allows(target) {
+            return false;
+        }
+        if let Err(err) = target
+            .markdown_message(markdown_string!("The bot is under maintenance, try again later"))
+            .await
+        {
+            log::warn!("Can't send the maintenance notice to chat {}: {}", target.chat.id, err);
+        }
+        true
+    }
+
+    /// Internal helper function to pass the error to the error handler
+    async fn handle_error(&self, target: CommandReplyTarget, err: RequestError) {
+        match &self.error_handler {
+            Some(handler) => handler(target, err).await,
+            None => log::error!("Error handling update in chat {}: {}", target.chat.id, err),
+        }
+    }
+
+    /// Internal helper function to choose the queue of the update: the messages of each chat are handled
+    /// in order, the callback queries concurrently, so the commands waiting for a button press,
+    /// e.g. [`confirm`](CommandReplyTarget::confirm), don't block it.
+    /// The `/cancel` command is handled concurrently too, to abort the command running in the chat,
+    /// as well as the messages of the chats where a handler waits for a [`prompt`](CommandReplyTarget::prompt) answer
+    /// of the bot owning the slot, see [`distribution_slot`]
+    fn distribution_key<const SLOT: usize>(update: &Update) -> Option<ChatId> {
+        match &update.kind {
+            UpdateKind::CallbackQuery(_) => None,
+            UpdateKind::Message(msg) if msg.text().is_some_and(is_cancel_command) => None,
+            UpdateKind::Message(msg) if is_slot_answer_awaited(SLOT, msg.chat.id) => None,
+            _ => update.chat().map(|chat| chat.id),
+        }
+    }
+
+    /// The distribution functions of the slots, the last one is shared by the bots which didn't get a slot
+    const DISTRIBUTION_KEYS: [fn(&Update) -> Option<ChatId>; DISTRIBUTION_SLOTS + 1] = [
+        Self::distribution_key::<0>,
+        Self::distribution_key::<1>,
+        Self::distribution_key::<2>,
+        Self::distribution_key::<3>,
+        Self::distribution_key::<4>,
+        Self::distribution_key::<5>,
+        Self::distribution_key::<6>,
+        Self::distribution_key::<7>,
+        Self::distribution_key::<DISTRIBUTION_SLOTS>,
+    ];
+
+    async fn filter_duplicates(instance: Arc<BotInstance>, update: Update) -> bool {
+        match &instance.update_dedup {
+            Some(update_dedup) => update_dedup.check(update.id).await,
+            None => true,
+        }
+    }
+
+    async fn handle_message(
+        app: Arc<Self>,
+        instance: Arc<BotInstance>,
+        me: Me,
+        msg: Message,
+    ) -> ResponseResult<()> {
+        let Some(text) = msg.text() else {
+            return Ok(());
+        };
+        // Each bot of the app gets the commands addressed to the other bots of the group too
+        if is_command_to_other_bot(text, me.username()) {
+            return Ok(());
+        }
+        let target = app.target(&instance, &msg, false);
+        if app.check_maintenance(&target).await {
+            return Ok(());
+        }
+        // The pending operation is cancelled before the prompts and the wizard take the text as an answer
+        if target.operation_registry.is_some() && is_cancel_command(text) {
+            app.run_command(target, text).await;
+            return Ok(());
+        }
+        // Answers to the pending prompts are consumed by the waiting handlers
+        if let Some(prompt_registry) = &target.prompt_registry
+            && prompt_registry.resolve(&msg).await
+        {
+            return Ok(());
+        }
+        // Answers to the command wizard complete its pending command
+        let wizard_command = match &target.command_wizard {
+            Some(command_wizard) => command_wizard.answer(&target, text).await,
+            None => None,
+        };
+        let text = wizard_command.as_deref().unwrap_or(text);
+        if app.run_command(target.clone(), text).await {
+            return Ok(());
+        }
+        if let Some(handler) = &app.text_handler {
+            let result = handler(target.clone(), app.context.clone(), msg.clone()).await;
+            let result = result.and(flush_batch(&target).await);
+            app.write_back_sessions(&target).await;
+            if let Err(err) = result {
+                app.handle_error(target, err).await;
+            }
+        }
+        Ok(())
+    }
+
+    async fn handle_callback_query(
+        app: Arc<Self>,
+        instance: Arc<BotInstance>,
+        query: CallbackQuery,
+    ) -> ResponseResult<()> {
+        if let (Some(data), Some(msg)) = (&query.data, query.regular_message()) {
+            let mut target = app.target(&instance, msg, true);
+            // The callback's message is the bot's one, the command is triggered by the pressing user
+            target.user_id = Some(query.from.id);
+            target.callback_query_id = Some(query.id.clone());
+            if app.check_maintenance(&target).await {
+                return Ok(());
+            }
+            let command = unpack_callback_data(&target.callback_data_storage, data).await;
+            // The buttons of the confirmation dialogs are answered to the waiting commands
+            if let Some(confirm_registry) = &target.confirm_registry
+                && let Some((CONFIRM_COMMAND, args)) = split_command(&command)
+            {
+                if confirm_registry.resolve(&target, args).await.is_none() {
+                    target
+                        .answer_callback(markdown_string!("This confirmation has expired"))
+                        .await?;
+                }
+            } else if !app.run_command(target, &command).await {
+                log::warn!("Unknown command in callback data: {}", command);
+            }
+        }
+        // Remove the loading state of the button if the handler didn't answer the query
+        if let Err(err) = instance.bot.answer_callback_query(query.id).await {
+            log::debug!("Callback query already answered: {}", err);
+        }
+        Ok(())
+    }
+}
+
+/// Internal helper function to wait for the dispatchers of the bots running in the tasks
+async fn join_dispatchers(tasks: Vec<tokio::task::JoinHandle<()>>) {
+    for task in tasks {
+        if let Err(err) = task.await {
+            log::error!("Bot dispatcher failed: {}", err);
+        }
+    }
+}
+
+/// Internal helper function to get the distribution slot of the bot, the same bot always gets the same slot
+/// The bots above [`DISTRIBUTION_SLOTS`] share the last slot, which checks the answers awaited by all bots
+fn distribution_slot(bot: &Bot) -> usize {
+    let mut bots = DISTRIBUTION_BOTS.lock().unwrap_or_else(|err| err.into_inner());
+    if let Some(slot) = bots.iter().position(|token| token == bot.token()) {
+        return slot;
+    }
+    if bots.len() == DISTRIBUTION_SLOTS {
+        log::warn!(
+            "More than {} bots in the process, the chats waiting for an answer are shared by the rest",
+            DISTRIBUTION_SLOTS
+        );
+        return DISTRIBUTION_SLOTS;
+    }
+    bots.push(bot.token().to_string());
+    bots.len() - 1
+}
+
+/// Internal helper function to check if an answer is awaited in the chat by the bot owning the slot
+fn is_slot_answer_awaited(slot: usize, chat_id: ChatId) -> bool {
+    let bots = DISTRIBUTION_BOTS.lock().unwrap_or_else(|err| err.into_inner());
+    is_answer_awaited(bots.get(slot).map(String::as_str), chat_id)
+}
+
+/// Internal helper function to check if the text is the built-in `/cancel` command
+fn is_cancel_command(text: &str) -> bool {
+    matches!(split_command(text), Some((CANCEL_COMMAND, _)))
+}
@@ -0,0 +1,4 @@
+pub(crate) mod admin_commands;
+pub(crate) mod bot_app;
+pub(crate) mod polling_supervisor;
+pub(crate) mod update_dedup;
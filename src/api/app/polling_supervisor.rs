@@ -0,0 +1,318 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use futures::{StreamExt, stream::BoxStream};
+use teloxide::{
+    ApiError, Bot, RequestError,
+    stop::StopToken,
+    types::{AllowedUpdate, Update},
+    update_listeners::{AsUpdateStream, Polling, UpdateListener},
+};
+
+/// Default delay before the first reconnect
+const DEFAULT_INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Default maximum delay between the reconnects
+const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Default long polling timeout, must be less than the timeout of the bot's HTTP client
+/// See: https://core.telegram.org/bots/api#getupdates
+const DEFAULT_POLLING_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// State of the long polling loop reported by [`PollingHealth`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PollingStatus {
+    /// The updates are received normally
+    Healthy,
+    /// The last requests failed, e.g. because of the network outage, the polling is retried with backoff
+    Reconnecting,
+    /// Telegram refuses the polling because another instance of the bot polls with the same token
+    /// or a webhook is set, the polling is retried but won't recover without an operator action
+    Conflict,
+}
+
+#[derive(Default)]
+struct HealthState {
+    consecutive_errors: u32,
+    last_error: Option<(Instant, String)>,
+    last_update: Option<Instant>,
+    conflict: bool,
+}
+
+/// Shared health status of the polling loop run by the [`PollingSupervisor`]
+///
+/// The loop is considered recovered when an update is received after the errors, or when no error
+/// was reported for longer than the maximum backoff plus the polling timeout.
+#[derive(Clone)]
+pub struct PollingHealth {
+    state: Arc<Mutex<HealthState>>,
+    recovery_window: Duration,
+}
+
+impl PollingHealth {
+    fn new(recovery_window: Duration) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(HealthState::default())),
+            recovery_window,
+        }
+    }
+
+    /// The current status of the polling loop
+    pub fn status(&self) -> PollingStatus {
+        let state = self.state.lock().unwrap();
+        match &state.last_error {
+            Some((at, _)) if state.consecutive_errors > 0 && at.elapsed() < self.recovery_window => {
+                if state.conflict {
+                    PollingStatus::Conflict
+                } else {
+                    PollingStatus::Reconnecting
+                }
+            }
+            _ => PollingStatus::Healthy,
+        }
+    }
+
+    /// Check if the updates are received normally
+    pub fn is_healthy(&self) -> bool {
+        self.status() == PollingStatus::Healthy
+    }
+
+    /// Number of the failed polling requests since the last received update
+    pub fn consecutive_errors(&self) -> u32 {
+        self.state.lock().unwrap().consecutive_errors
+    }
+
+    /// The description of the last polling error
+    pub fn last_error(&self) -> Option<String> {
+        let state = self.state.lock().unwrap();
+        state.last_error.as_ref().map(|(_, err)| err.clone())
+    }
+
+    /// Time passed since the last received update
+    pub fn since_last_update(&self) -> Option<Duration> {
+        self.state.lock().unwrap().last_update.map(|at| at.elapsed())
+    }
+
+    /// Internal helper function to record the received update
+    fn record_update(&self) {
+        let mut state = self.state.lock().unwrap();
+        if state.consecutive_errors > 0 {
+            log::info!(
+                "Polling recovered after {} failed requests",
+                state.consecutive_errors
+            );
+        }
+        state.consecutive_errors = 0;
+        state.conflict = false;
+        state.last_update = Some(Instant::now());
+    }
+
+    /// Internal helper function to record the failed polling request, logging the diagnostics
+    fn record_error(&self, err: &RequestError) {
+        let mut state = self.state.lock().unwrap();
+        // The errors reported after the recovery window start a new streak
+        if let Some((at, _)) = &state.last_error
+            && at.elapsed() >= self.recovery_window
+        {
+            state.consecutive_errors = 0;
+            state.conflict = false;
+        }
+        let conflict = matches!(
+            err,
+            RequestError::Api(ApiError::TerminatedByOtherGetUpdates | ApiError::CantGetUpdates)
+        );
+        // Report the conflict once per streak, it's repeated on each retry
+        if conflict && !state.conflict {
+            match err {
+                RequestError::Api(ApiError::CantGetUpdates) => log::error!(
+                    "Polling conflict: a webhook is set for the bot, delete it with deleteWebhook \
+                     or run the bot on the webhook"
+                ),
+                _ => log::error!(
+                    "Polling conflict: another instance of the bot is polling with the same token, \
+                     stop it or use a different token"
+                ),
+            }
+        } else if !conflict {
+            log::warn!(
+                "Polling failed (attempt {}): {}",
+                state.consecutive_errors + 1,
+                err
+            );
+        }
+        state.consecutive_errors = state.consecutive_errors.saturating_add(1);
+        state.conflict = conflict;
+        state.last_error = Some((Instant::now(), err.to_string()));
+    }
+}
+
+/// Supervisor of the long polling loop
+///
+/// Runs the teloxide polling with exponential backoff reconnects on the network errors,
+/// reports the conflicts with another `getUpdates` consumer with clear diagnostics
+/// and exposes the health status of the loop, so that the outages are visible to the application.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use telluride::app::{BotApp, PollingSupervisor};
+/// use teloxide::Bot;
+///
+/// # async fn run() {
+/// let supervisor = PollingSupervisor::new();
+/// let health = supervisor.health();
+/// tokio::spawn(async move {
+///     loop {
+///         tokio::time::sleep(std::time::Duration::from_secs(60)).await;
+///         log::info!("Polling status: {:?}", health.status());
+///     }
+/// });
+/// BotApp::new(Bot::from_env(), ()).dispatch_supervised(supervisor).await;
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct PollingSupervisor {
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    polling_timeout: Duration,
+    health: PollingHealth,
+}
+
+impl Default for PollingSupervisor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PollingSupervisor {
+    /// Create the supervisor with the backoff from 1 second up to 1 minute
+    pub fn new() -> Self {
+        Self {
+            initial_backoff: DEFAULT_INITIAL_BACKOFF,
+            max_backoff: DEFAULT_MAX_BACKOFF,
+            polling_timeout: DEFAULT_POLLING_TIMEOUT,
+            health: PollingHealth::new(DEFAULT_MAX_BACKOFF + DEFAULT_POLLING_TIMEOUT),
+        }
+    }
+
+    /// Set the delay before the first reconnect and the maximum delay between the reconnects
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self.health.recovery_window = max + self.polling_timeout;
+        self
+    }
+
+    /// The health status of the polling loop, shared with the supervisor
+    pub fn health(&self) -> PollingHealth {
+        self.health.clone()
+    }
+
+    /// Delay before the reconnect after the given number of failed requests
+    pub fn backoff(&self, errors: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(2u32.saturating_pow(errors))
+            .min(self.max_backoff)
+    }
+
+    /// Create the polling update listener of the bot reporting to the supervisor's health status
+    pub fn listener(&self, bot: Bot) -> SupervisedPolling {
+        let supervisor = self.clone();
+        let polling = Polling::builder(bot)
+            .timeout(self.polling_timeout)
+            .backoff_strategy(move |errors| supervisor.backoff(errors))
+            .build();
+        SupervisedPolling {
+            polling,
+            health: self.health.clone(),
+        }
+    }
+}
+
+/// Polling update listener created by [`PollingSupervisor::listener`]
+pub struct SupervisedPolling {
+    polling: Polling<Bot>,
+    health: PollingHealth,
+}
+
+impl UpdateListener for SupervisedPolling {
+    type Err = RequestError;
+
+    fn stop_token(&mut self) -> StopToken {
+        self.polling.stop_token()
+    }
+
+    fn hint_allowed_updates(&mut self, hint: &mut dyn Iterator<Item = AllowedUpdate>) {
+        self.polling.hint_allowed_updates(hint)
+    }
+}
+
+impl<'a> AsUpdateStream<'a> for SupervisedPolling {
+    type StreamErr = RequestError;
+    type Stream = BoxStream<'a, Result<Update, RequestError>>;
+
+    fn as_stream(&'a mut self) -> Self::Stream {
+        let health = self.health.clone();
+        self.polling
+            .as_stream()
+            .inspect(move |result| match result {
+                Ok(_) => health.record_update(),
+                Err(err) => health.record_error(err),
+            })
+            .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_polling_health() {
+        let supervisor =
+            PollingSupervisor::new().with_backoff(Duration::from_millis(100), Duration::from_secs(5));
+        assert_eq!(supervisor.backoff(0), Duration::from_millis(100));
+        assert_eq!(supervisor.backoff(2), Duration::from_millis(400));
+        assert_eq!(supervisor.backoff(100), Duration::from_secs(5));
+
+        let health = supervisor.health();
+        assert!(health.is_healthy());
+        health.record_error(&RequestError::Api(ApiError::Unknown("Bad Gateway".into())));
+        health.record_error(&RequestError::Api(ApiError::Unknown("Bad Gateway".into())));
+        assert_eq!(health.status(), PollingStatus::Reconnecting);
+        assert_eq!(health.consecutive_errors(), 2);
+        health.record_update();
+        assert!(health.is_healthy());
+        assert!(health.last_error().unwrap().contains("Bad Gateway"));
+
+        health.record_error(&RequestError::Api(ApiError::TerminatedByOtherGetUpdates));
+        assert_eq!(health.status(), PollingStatus::Conflict);
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_supervised_added_bot() {
+        use teloxide::types::ChatId;
+
+        use crate::{api::app::bot_app::BotApp, markdown_string, testing::MockBotApi};
+
+        let main = MockBotApi::start().await;
+        let added = MockBotApi::start().await;
+        let app = BotApp::new(main.bot(), ())
+            .add_bot("added", added.bot())
+            .command("ping", "", |target, _, _| async move {
+                target.markdown_message(markdown_string!("pong")).await?;
+                Ok(())
+            });
+        let dispatcher_task = tokio::spawn(app.dispatch_supervised(PollingSupervisor::new()));
+
+        // The added bot is polled along with the main one
+        added.send_text(ChatId(1), "/ping").await;
+        let reply = added.next_request("sendMessage").await.unwrap();
+        assert_eq!(reply.str_param("text"), Some("pong"));
+
+        dispatcher_task.abort();
+    }
+}
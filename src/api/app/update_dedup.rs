@@ -0,0 +1,156 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicU32, Ordering},
+    },
+    time::{Duration, SystemTime},
+};
+
+use teloxide::types::UpdateId;
+use tokio::sync::{Mutex, OnceCell};
+
+use crate::api::data_store::{
+    data_store_trait::{DataStoreTrait, GLOBAL_NAMESPACE},
+    namespaced::NamespacedStore,
+};
+
+/// Prefix of the keys of the processed updates in the data store
+const UPDATE_KEY_PREFIX: &str = "update_";
+
+/// Default time the processed updates are remembered
+const DEFAULT_UPDATE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Number of the locks serializing the checks of the same update
+const LOCK_SHARDS: usize = 16;
+
+/// Guard dropping the updates which were already processed
+///
+/// Telegram re-delivers updates when the webhook doesn't respond in time or when
+/// the bot restarts before confirming them. The guard records the ids of the processed
+/// updates with the processing time and drops the repeated ones, so commands aren't executed twice.
+/// The records expire after the TTL, use a persistent store to survive restarts.
+/// Update ids grow, so only the ids not above the highest recorded one are looked up in the store.
+/// Registered with [`BotApp::with_update_dedup`](crate::app::BotApp::with_update_dedup).
+#[derive(Clone)]
+pub struct UpdateDeduplicator {
+    store: Arc<dyn DataStoreTrait<SystemTime>>,
+    ttl: Duration,
+    // The highest recorded update id, loaded from the store before the first check
+    high_water: Arc<AtomicU32>,
+    loaded: Arc<OnceCell<()>>,
+    // Serialize the checks of the same update, the shard is picked by the update id
+    shards: Arc<[Mutex<()>]>,
+    // The time of the last cleanup, locked only by the check running it
+    last_cleanup: Arc<Mutex<SystemTime>>,
+}
+
+impl UpdateDeduplicator {
+    /// Create the guard keeping the processed updates in the given store for 24 hours
+    pub fn new(store: Arc<dyn DataStoreTrait<SystemTime>>) -> Self {
+        Self {
+            store,
+            ttl: DEFAULT_UPDATE_TTL,
+            high_water: Arc::new(AtomicU32::new(0)),
+            loaded: Arc::new(OnceCell::new()),
+            shards: (0..LOCK_SHARDS).map(|_| Mutex::new(())).collect(),
+            last_cleanup: Arc::new(Mutex::new(SystemTime::UNIX_EPOCH)),
+        }
+    }
+
+    /// Remember the processed updates for the given time
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Create the guard with the same settings keeping its records under the namespace of the store
+    pub(crate) fn namespaced(&self, namespace: &str) -> Self {
+        Self::new(Arc::new(NamespacedStore::new(self.store.clone(), namespace))).with_ttl(self.ttl)
+    }
+
+    /// Record the update as processed
+    /// Returns false if it was already processed within the TTL and should be dropped
+    pub async fn check(&self, update_id: UpdateId) -> bool {
+        let now = SystemTime::now();
+        self.loaded
+            .get_or_init(|| async {
+                let mut last_cleanup = self.last_cleanup.lock().await;
+                self.remove_expired(now).await;
+                *last_cleanup = now;
+            })
+            .await;
+        // The other checks don't wait for the cleanup
+        if let Ok(mut last_cleanup) = self.last_cleanup.try_lock()
+            && is_expired(*last_cleanup, self.ttl, now)
+        {
+            self.remove_expired(now).await;
+            *last_cleanup = now;
+        }
+        let _lock = self.shards[update_id.0 as usize % LOCK_SHARDS].lock().await;
+        let key = update_key(update_id);
+        let high_water = self.high_water.fetch_max(update_id.0, Ordering::SeqCst);
+        if update_id.0 <= high_water
+            && let Some(processed_at) = self.store.get(GLOBAL_NAMESPACE, &key).await
+            && !is_expired(processed_at, self.ttl, now)
+        {
+            log::warn!("Dropping duplicate update {}", update_id.0);
+            return false;
+        }
+        self.store.set(GLOBAL_NAMESPACE, &key, now).await;
+        true
+    }
+
+    /// Internal helper function to forget the updates processed before the TTL
+    /// and to raise the high-water mark to the remaining ones
+    async fn remove_expired(&self, now: SystemTime) {
+        for key in self.store.keys(GLOBAL_NAMESPACE).await {
+            let Some(id) = key.strip_prefix(UPDATE_KEY_PREFIX) else {
+                continue;
+            };
+            let Some(processed_at) = self.store.get(GLOBAL_NAMESPACE, &key).await else {
+                continue;
+            };
+            if is_expired(processed_at, self.ttl, now) {
+                self.store.remove(GLOBAL_NAMESPACE, &key).await;
+            } else if let Ok(id) = id.parse() {
+                self.high_water.fetch_max(id, Ordering::SeqCst);
+            }
+        }
+    }
+}
+
+/// Internal helper function to build the data store key of the update
+fn update_key(update_id: UpdateId) -> String {
+    format!("{}{}", UPDATE_KEY_PREFIX, update_id.0)
+}
+
+/// Internal helper function to check if the time is older than the TTL
+fn is_expired(time: SystemTime, ttl: Duration, now: SystemTime) -> bool {
+    now.duration_since(time).is_ok_and(|age| age >= ttl)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::data_store::in_mem::InMemStore;
+
+    #[tokio::test]
+    async fn test_update_dedup() {
+        let store: Arc<dyn DataStoreTrait<SystemTime>> = Arc::new(InMemStore::new());
+        let dedup = UpdateDeduplicator::new(store.clone());
+        assert!(dedup.check(UpdateId(1)).await);
+        assert!(dedup.check(UpdateId(2)).await);
+        assert!(!dedup.check(UpdateId(1)).await);
+
+        // The records are loaded from the store after a restart
+        let restarted = UpdateDeduplicator::new(store.clone());
+        assert!(!restarted.check(UpdateId(2)).await);
+        assert!(restarted.check(UpdateId(3)).await);
+        assert!(!restarted.check(UpdateId(3)).await);
+
+        // Expired records are processed again and cleaned up
+        let dedup = dedup.with_ttl(Duration::ZERO);
+        assert!(dedup.check(UpdateId(1)).await);
+        assert_eq!(store.keys(GLOBAL_NAMESPACE).await, vec![update_key(UpdateId(1))]);
+    }
+}
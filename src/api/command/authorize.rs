@@ -0,0 +1,164 @@
+use std::{error::Error, fmt::Display, sync::Arc};
+
+use teloxide::{
+    RequestError,
+    prelude::Requester,
+    types::{ChatKind, UserId},
+};
+
+use crate::api::{
+    command::command_reply_target::CommandReplyTarget,
+    data_store::data_store_trait::{DataStoreTrait, GLOBAL_NAMESPACE},
+};
+
+/// Prefix of the keys of the allowed users in the data store
+const ALLOWED_USER_KEY_PREFIX: &str = "allowed_user_";
+
+/// Reason of the command being denied by [`CommandTrait::authorize`](crate::command::CommandTrait::authorize),
+/// shown to the user
+#[derive(Debug)]
+pub enum DenyReason {
+    /// The command is available only to the chat administrators
+    NotChatAdmin,
+    /// The command is available only in the private chat with the bot
+    NotPrivateChat,
+    /// The user is not in the allowlist
+    NotAllowed,
+    /// The permissions can't be checked, e.g. the chat member request failed
+    CheckFailed(RequestError),
+    /// Reason described by the command
+    Custom(String),
+}
+
+impl Display for DenyReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DenyReason::NotChatAdmin => write!(f, "This command is available only to the chat administrators"),
+            DenyReason::NotPrivateChat => write!(f, "This command is available only in the private chat with the bot"),
+            DenyReason::NotAllowed => write!(f, "You are not allowed to use this command"),
+            DenyReason::CheckFailed(err) => write!(f, "Can't check the permissions: {}", err),
+            DenyReason::Custom(reason) => write!(f, "{}", reason),
+        }
+    }
+}
+
+impl Error for DenyReason {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            DenyReason::CheckFailed(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// Users allowed to run the restricted commands, kept in a data store
+///
+/// Checked by [`CommandReplyTarget::require_allowed_user`], the target gets the allowlist with
+/// [`CommandReplyTarget::with_user_allowlist`]. Use a persistent store to keep the list between restarts.
+#[derive(Clone)]
+pub struct UserAllowlist {
+    store: Arc<dyn DataStoreTrait<bool>>,
+}
+
+impl UserAllowlist {
+    /// Create a new UserAllowlist with the given DataStore
+    pub fn new(store: Arc<dyn DataStoreTrait<bool>>) -> Self {
+        Self { store }
+    }
+
+    /// Add the user to the allowlist
+    pub async fn allow(&self, user_id: UserId) {
+        self.store.set(GLOBAL_NAMESPACE, &allowed_user_key(user_id), true).await;
+    }
+
+    /// Remove the user from the allowlist, returns true if the user was in it
+    pub async fn disallow(&self, user_id: UserId) -> bool {
+        self.store.remove(GLOBAL_NAMESPACE, &allowed_user_key(user_id)).await
+    }
+
+    /// Check if the user is in the allowlist
+    pub async fn is_allowed(&self, user_id: UserId) -> bool {
+        self.store
+            .get(GLOBAL_NAMESPACE, &allowed_user_key(user_id))
+            .await
+            .unwrap_or(false)
+    }
+
+    /// The users in the allowlist
+    pub async fn users(&self) -> Vec<UserId> {
+        self.store
+            .keys(GLOBAL_NAMESPACE)
+            .await
+            .iter()
+            .filter_map(|key| key.strip_prefix(ALLOWED_USER_KEY_PREFIX)?.parse().ok())
+            .map(UserId)
+            .collect()
+    }
+}
+
+/// Internal helper function to build the data store key of the allowed user
+fn allowed_user_key(user_id: UserId) -> String {
+    format!("{}{}", ALLOWED_USER_KEY_PREFIX, user_id)
+}
+
+impl CommandReplyTarget {
+    /// Allow the command only in the private chat with the bot
+    pub fn require_private_chat(&self) -> Result<(), DenyReason> {
+        match self.chat.kind {
+            ChatKind::Private(_) => Ok(()),
+            ChatKind::Public(_) => Err(DenyReason::NotPrivateChat),
+        }
+    }
+
+    /// Allow the command only to the administrators of the group or the channel,
+    /// anyone can run it in the private chat
+    pub async fn require_chat_admin(&self) -> Result<(), DenyReason> {
+        if self.require_private_chat().is_ok() {
+            return Ok(());
+        }
+        let Some(user_id) = self.user_id else {
+            return Err(DenyReason::NotChatAdmin);
+        };
+        let member = self
+            .bot
+            .get_chat_member(self.chat.id, user_id)
+            .await
+            .map_err(DenyReason::CheckFailed)?;
+        if member.is_privileged() {
+            Ok(())
+        } else {
+            Err(DenyReason::NotChatAdmin)
+        }
+    }
+
+    /// Allow the command only to the users in the target's [`UserAllowlist`],
+    /// everyone is denied if the target has no allowlist
+    pub async fn require_allowed_user(&self) -> Result<(), DenyReason> {
+        let Some(user_allowlist) = &self.user_allowlist else {
+            log::warn!("User allowlist is not set, the command is denied");
+            return Err(DenyReason::NotAllowed);
+        };
+        match self.user_id {
+            Some(user_id) if user_allowlist.is_allowed(user_id).await => Ok(()),
+            _ => Err(DenyReason::NotAllowed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::data_store::in_mem::InMemStore;
+
+    #[tokio::test]
+    async fn test_user_allowlist() {
+        let allowlist = UserAllowlist::new(Arc::new(InMemStore::new()));
+        assert!(!allowlist.is_allowed(UserId(1)).await);
+        allowlist.allow(UserId(1)).await;
+        allowlist.allow(UserId(2)).await;
+        assert!(allowlist.is_allowed(UserId(1)).await);
+        assert!(allowlist.disallow(UserId(2)).await);
+        assert!(!allowlist.disallow(UserId(2)).await);
+        assert_eq!(allowlist.users().await, [UserId(1)]);
+    }
+}
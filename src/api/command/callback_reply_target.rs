@@ -0,0 +1,183 @@
+use std::{
+    ops::Deref,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use teloxide::{
+    Bot,
+    prelude::{Requester, ResponseResult},
+    types::{CallbackQuery, ChatId},
+};
+
+use crate::api::{
+    command::{
+        command_button::{CallbackData, CallbackDataStorage},
+        command_reply_target::CommandReplyTarget,
+    },
+    data_store::data_store_trait::DataStoreTrait,
+};
+
+/// Reply target of the callback query bound to the message with the pressed button
+///
+/// Dereferences to the [`CommandReplyTarget`] of the message, so the `markdown_message*` helpers
+/// edit the message in place. The query is answered once with [`ack`](Self::ack),
+/// [`toast`](Self::toast) or [`alert`](Self::alert), the later answers are ignored.
+///
+/// # Example
+/// ```ignore
+/// Update::filter_callback_query().endpoint(move |bot: Bot, query: CallbackQuery| {
+///     let store = store.clone();
+///     async move {
+///         let Some(target) = CallbackReplyTarget::new(bot, query, store) else {
+///             return Ok(());
+///         };
+///         target.markdown_message(markdown_string!("Saved")).await?;
+///         target.toast("Saved").await
+///     }
+/// })
+/// ```
+#[derive(Clone)]
+pub struct CallbackReplyTarget {
+    target: CommandReplyTarget,
+    query: CallbackQuery,
+    answered: Arc<AtomicBool>,
+}
+
+impl CallbackReplyTarget {
+    /// Create the target of the query's message, regular or sent via inline mode
+    /// Returns `None` if the message is inaccessible, e.g. too old
+    pub fn new(
+        bot: Bot,
+        query: CallbackQuery,
+        callback_store: Arc<dyn DataStoreTrait<CallbackData>>,
+    ) -> Option<Self> {
+        let mut target = if let Some(msg) = query.regular_message() {
+            let storage = Arc::new(CallbackDataStorage::new(callback_store, msg.chat.id));
+            CommandReplyTarget::new(bot, msg.chat.clone(), Some(msg.id), storage)
+        } else {
+            let inline_message_id = query.inline_message_id.clone()?;
+            let chat_id = ChatId::from(query.from.id);
+            let storage = Arc::new(CallbackDataStorage::new(callback_store, chat_id));
+            CommandReplyTarget::new_inline(bot, &query.from, inline_message_id, storage)
+        };
+        target.user_id = Some(query.from.id);
+        target.callback_query_id = Some(query.id.clone());
+        Some(Self {
+            target,
+            query,
+            answered: Arc::new(AtomicBool::new(false)),
+        })
+    }
+
+    /// Configure the target of the message, e.g. set its middlewares or retry policy
+    pub fn configure(
+        mut self,
+        configure: impl FnOnce(CommandReplyTarget) -> CommandReplyTarget,
+    ) -> Self {
+        self.target = configure(self.target);
+        self
+    }
+
+    /// The answered callback query
+    pub fn query(&self) -> &CallbackQuery {
+        &self.query
+    }
+
+    /// The callback data of the pressed button
+    pub fn data(&self) -> Option<&str> {
+        self.query.data.as_deref()
+    }
+
+    /// Check if the query was already answered
+    pub fn is_answered(&self) -> bool {
+        self.answered.load(Ordering::SeqCst)
+    }
+
+    /// Answer the query without a notification, removing the loading state of the button
+    pub async fn ack(&self) -> ResponseResult<()> {
+        if self.answered.swap(true, Ordering::SeqCst) {
+            return Ok(());
+        }
+        self.target
+            .send_request(self.target.bot.answer_callback_query(self.query.id.clone()))
+            .await?;
+        Ok(())
+    }
+
+    /// Answer the query with a notification at the top of the chat screen
+    /// The text is truncated to Telegram's 200 characters limit
+    pub async fn toast(&self, text: impl AsRef<str>) -> ResponseResult<()> {
+        self.answer(text.as_ref(), false).await
+    }
+
+    /// Answer the query with an alert dialog
+    /// The text is truncated to Telegram's 200 characters limit
+    pub async fn alert(&self, text: impl AsRef<str>) -> ResponseResult<()> {
+        self.answer(text.as_ref(), true).await
+    }
+
+    /// Internal helper function to answer the query with the text unless it was answered already
+    async fn answer(&self, text: &str, show_alert: bool) -> ResponseResult<()> {
+        if self.answered.swap(true, Ordering::SeqCst) {
+            return Ok(());
+        }
+        self.target.answer_callback_plain(text, show_alert).await
+    }
+}
+
+impl Deref for CallbackReplyTarget {
+    type Target = CommandReplyTarget;
+
+    fn deref(&self) -> &Self::Target {
+        &self.target
+    }
+}
+
+#[cfg(all(test, feature = "testing"))]
+mod tests {
+    use teloxide::{
+        dispatching::{Dispatcher, UpdateFilterExt},
+        dptree,
+        types::{MessageId, Update},
+    };
+
+    use super::*;
+    use crate::{api::data_store::in_mem::InMemStore, markdown_string, testing::MockBotApi};
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_callback_reply_target() {
+        let api = MockBotApi::start().await;
+        let store: Arc<dyn DataStoreTrait<CallbackData>> = Arc::new(InMemStore::new());
+        let handler = dptree::entry().branch(Update::filter_callback_query().endpoint(
+            move |bot: Bot, query: CallbackQuery| {
+                let store = store.clone();
+                async move {
+                    let target = CallbackReplyTarget::new(bot, query, store).unwrap();
+                    assert_eq!(target.data(), Some("save"));
+                    target.markdown_message(markdown_string!("Saved")).await?;
+                    target.toast("Saved").await?;
+                    // The query is answered only once
+                    target.alert("Ignored").await?;
+                    assert!(target.is_answered());
+                    Ok::<_, teloxide::RequestError>(())
+                }
+            },
+        ));
+        let mut dispatcher = Dispatcher::builder(api.bot(), handler).build();
+        let dispatcher_task = tokio::spawn(async move { dispatcher.dispatch().await });
+
+        api.press_button(ChatId(1), MessageId(5), "save").await;
+        let edit = api.next_request("editMessageText").await.unwrap();
+        assert_eq!(edit.str_param("text"), Some("Saved"));
+        assert_eq!(edit.message_id, Some(MessageId(5)));
+        let answer = api.next_request("answerCallbackQuery").await.unwrap();
+        assert_eq!(answer.str_param("text"), Some("Saved"));
+        assert_eq!(answer.params["show_alert"], false);
+        assert!(api.next_request("answerCallbackQuery").await.is_none());
+
+        dispatcher_task.abort();
+    }
+}
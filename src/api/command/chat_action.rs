@@ -0,0 +1,93 @@
+use std::time::Duration;
+
+use teloxide::{prelude::Requester, types::ChatAction};
+use tokio::task::JoinHandle;
+
+use crate::api::command::command_reply_target::CommandReplyTarget;
+
+/// Interval of re-sending the chat action, Telegram shows it for 5 seconds or until the next message
+const CHAT_ACTION_INTERVAL: Duration = Duration::from_secs(4);
+
+/// Chat action shown to the user while the guard is alive, see [`CommandReplyTarget::typing`]
+///
+/// The action is re-sent every 4 seconds until the guard is dropped or [`stop`](Self::stop)ped.
+#[must_use = "the chat action stops when the guard is dropped"]
+pub struct ChatActionGuard {
+    task: Option<JoinHandle<()>>,
+}
+
+impl ChatActionGuard {
+    /// Stop showing the chat action, the same as dropping the guard
+    pub fn stop(self) {}
+}
+
+impl Drop for ChatActionGuard {
+    fn drop(&mut self) {
+        if let Some(task) = self.task.take() {
+            task.abort();
+        }
+    }
+}
+
+impl CommandReplyTarget {
+    /// Show the "typing…" indicator in the chat until the returned guard is dropped
+    ///
+    /// # Example
+    /// ```ignore
+    /// let _typing = target.typing();
+    /// let report = build_report().await;
+    /// target.markdown_message(report).await?;
+    /// ```
+    pub fn typing(&self) -> ChatActionGuard {
+        self.chat_action(ChatAction::Typing)
+    }
+
+    /// Show the chat action, e.g. "sending photo…", until the returned guard is dropped
+    /// Does nothing for the inline messages, which have no chat to show the action in
+    pub fn chat_action(&self, action: ChatAction) -> ChatActionGuard {
+        if self.inline_message_id.is_some() {
+            return ChatActionGuard { task: None };
+        }
+        let (bot, chat_id) = (self.bot.clone(), self.chat.id);
+        let task = tokio::spawn(async move {
+            loop {
+                if let Err(err) = bot.send_chat_action(chat_id, action).await {
+                    log::debug!("Can't send the chat action to chat {}: {}", chat_id, err);
+                }
+                tokio::time::sleep(CHAT_ACTION_INTERVAL).await;
+            }
+        });
+        ChatActionGuard { task: Some(task) }
+    }
+}
+
+#[cfg(all(test, feature = "testing"))]
+mod tests {
+    use teloxide::types::ChatId;
+
+    use super::*;
+    use crate::{api::app::bot_app::BotApp, markdown_string, testing::MockBotApi};
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_typing() {
+        let api = MockBotApi::start().await;
+        let mut dispatcher = BotApp::new(api.bot(), ())
+            .command("report", "", |target, _, _| async move {
+                let typing = target.typing();
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                typing.stop();
+                target.markdown_message(markdown_string!("Done")).await?;
+                Ok(())
+            })
+            .build();
+        let dispatcher_task = tokio::spawn(async move { dispatcher.dispatch().await });
+
+        api.send_text(ChatId(1), "/report").await;
+        let action = api.next_request("sendChatAction").await.unwrap();
+        assert_eq!(action.str_param("action"), Some("typing"));
+        let reply = api.next_request("sendMessage").await.unwrap();
+        assert_eq!(reply.str_param("text"), Some("Done"));
+
+        dispatcher_task.abort();
+    }
+}
@@ -1,14 +1,111 @@
-use std::{error::Error, fmt::Display, str::FromStr};
+use std::{any::TypeId, collections::BTreeMap, error::Error, fmt::Display, ops::Deref, str::FromStr};
 
 use teloxide::utils::command::ParseError;
 
+use crate::api::parse::command_string::screen_spaces;
+
 #[derive(Default, Debug, Clone, PartialEq)]
 pub struct EmptyArg;
 
 pub trait ParseCommandArg {
+    /// The argument takes the rest of the message verbatim, see [`RestArg`]
+    const CAPTURES_REST: bool = false;
+
     fn parse_command_arg(arg: &str) -> Result<Self, ParseError>
     where
         Self: Sized;
+
+    /// Value of the argument missing in the command, by default the argument is optional
+    fn missing_command_arg(_placeholder: &str, _found: usize) -> Result<Option<Self>, ParseError>
+    where
+        Self: Sized,
+    {
+        Ok(None)
+    }
+}
+
+/// Required argument, the command without it fails to parse with the error naming its placeholder
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct Req<T>(pub T);
+
+/// Optional argument, the same as the plain type, the command without it runs with fewer arguments
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct Opt<T>(pub T);
+
+impl<T> Deref for Req<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> Deref for Opt<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: Display> Display for Req<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl<T: Display> Display for Opt<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl<T: ParseCommandArg> ParseCommandArg for Req<T> {
+    fn parse_command_arg(arg: &str) -> Result<Self, ParseError> {
+        T::parse_command_arg(arg).map(Req)
+    }
+
+    fn missing_command_arg(placeholder: &str, found: usize) -> Result<Option<Self>, ParseError> {
+        Err(ParseError::TooFewArguments {
+            expected: found + 1,
+            found,
+            message: format!("Missing required argument {}", placeholder),
+        })
+    }
+}
+
+impl<T: ParseCommandArg> ParseCommandArg for Opt<T> {
+    fn parse_command_arg(arg: &str) -> Result<Self, ParseError> {
+        T::parse_command_arg(arg).map(Opt)
+    }
+}
+
+/// The last argument taking the rest of the message verbatim, with the spaces and the line breaks,
+/// e.g. the text of `/note buy milk and eggs`
+/// The flags are parsed only before the rest, `--` separates the rest starting with `--`.
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct RestArg(pub String);
+
+impl Deref for RestArg {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Display for RestArg {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl ParseCommandArg for RestArg {
+    const CAPTURES_REST: bool = true;
+
+    fn parse_command_arg(arg: &str) -> Result<Self, ParseError> {
+        Ok(RestArg(arg.to_string()))
+    }
 }
 
 impl Display for EmptyArg {
@@ -46,3 +143,258 @@ where
             .map_err(|e| ParseError::Custom(Box::new(e)))
     }
 }
+
+/// Error of the enum argument derived with [`CommandArgEnum`](crate::command::CommandArgEnum),
+/// lists the accepted values
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnumArgError {
+    value: String,
+    expected: &'static [&'static str],
+}
+
+impl EnumArgError {
+    pub fn new(value: impl Into<String>, expected: &'static [&'static str]) -> Self {
+        Self {
+            value: value.into(),
+            expected,
+        }
+    }
+
+    /// The accepted values of the argument
+    pub fn expected(&self) -> &'static [&'static str] {
+        self.expected
+    }
+}
+
+impl Display for EnumArgError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Invalid value {}, expected one of: {}",
+            self.value,
+            self.expected.join(", ")
+        )
+    }
+}
+
+impl Error for EnumArgError {}
+
+/// Named arguments of the command, `--key value`, `--key=value` or the boolean `--key`
+///
+/// The flags can be mixed with the positional arguments. The value after `--key` is taken
+/// unless it's another flag, use `--key=value` or quote the values starting with `--`.
+/// The quoted arguments and the arguments after the standalone `--` are positional.
+/// The commands without flags, i.e. with the [`EmptyArg`] as `FlagArgs`, take all arguments as positional.
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct Flags(BTreeMap<String, Option<String>>);
+
+impl Flags {
+    /// Check if the flag is present, with or without the value
+    pub fn has(&self, name: &str) -> bool {
+        self.0.contains_key(name)
+    }
+
+    /// Parse the value of the flag, `None` if the flag is absent
+    /// Fails if the flag has no value or the value can't be parsed.
+    pub fn get<T: ParseCommandArg>(&self, name: &str) -> Result<Option<T>, ParseError> {
+        match self.0.get(name) {
+            None => Ok(None),
+            Some(None) => Err(invalid_input(format!("Expected a value for --{}", name))),
+            Some(Some(value)) => T::parse_command_arg(value).map(Some),
+        }
+    }
+
+    /// Set the flag, the boolean one if the value is `None`
+    pub fn set(&mut self, name: impl Into<String>, value: Option<String>) {
+        self.0.insert(name.into(), value);
+    }
+
+    /// Fail if there are flags other than the given ones
+    pub fn expect_only(&self, names: &[&str]) -> Result<(), ParseError> {
+        match self.0.keys().find(|name| !names.contains(&name.as_str())) {
+            Some(name) => Err(invalid_input(format!("Unknown flag --{}", name))),
+            None => Ok(()),
+        }
+    }
+
+    /// Check if there are no flags
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl Display for Flags {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut first = true;
+        for (name, value) in &self.0 {
+            if !first {
+                write!(f, " ")?;
+            }
+            first = false;
+            match value {
+                Some(value) => write!(f, "--{}={}", name, screen_spaces(value))?,
+                None => write!(f, "--{}", name)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Named arguments of the command parsed from the [`Flags`], the `FlagArgs` type of
+/// [`CommandTrait`](crate::command::CommandTrait)
+///
+/// [`EmptyArg`] accepts no flags, [`Flags`] accepts any flags as is.
+pub trait ParseFlagArgs {
+    fn parse_flag_args(flags: &Flags) -> Result<Self, ParseError>
+    where
+        Self: Sized;
+}
+
+impl ParseFlagArgs for EmptyArg {
+    fn parse_flag_args(flags: &Flags) -> Result<Self, ParseError> {
+        flags.expect_only(&[])?;
+        Ok(EmptyArg)
+    }
+}
+
+impl ParseFlagArgs for Flags {
+    fn parse_flag_args(flags: &Flags) -> Result<Self, ParseError> {
+        Ok(flags.clone())
+    }
+}
+
+/// Internal helper function to mark the arguments split from the text which may be the flags:
+/// the unquoted ones, if the command has flags, i.e. its `FlagArgs` is not [`EmptyArg`]
+pub(crate) fn mark_flag_candidates<F: 'static>(
+    text: &str,
+    args: &[(String, usize)],
+) -> Vec<(String, bool)> {
+    let has_flags = TypeId::of::<F>() != TypeId::of::<EmptyArg>();
+    args.iter()
+        .map(|(arg, offset)| (arg.clone(), has_flags && !text[*offset..].starts_with('"')))
+        .collect()
+}
+
+/// Internal helper function to separate the flags from the positional arguments,
+/// only the arguments marked by [`mark_flag_candidates`] are parsed as the flags and the `--` separator
+/// With the limit, the parsing stops at the positional argument after the given number of them,
+/// the index of this argument is returned, see [`RestArg`].
+pub(crate) fn split_flags(
+    args: Vec<(String, bool)>,
+    limit: Option<usize>,
+) -> (Vec<String>, Flags, Option<usize>) {
+    let mut positional = Vec::new();
+    let mut flags = Flags::default();
+    let mut index = 0;
+    while index < args.len() {
+        let (arg, candidate) = &args[index];
+        let separator = *candidate && arg == "--";
+        let flag = arg.strip_prefix("--").filter(|_| *candidate && !separator);
+        if flag.is_none() && limit == Some(positional.len()) {
+            // The separator is not a part of the rest
+            let rest = if separator { index + 1 } else { index };
+            return (positional, flags, Some(rest));
+        }
+        index += 1;
+        if separator {
+            positional.extend(args[index..].iter().map(|(arg, _)| arg.clone()));
+            return (positional, flags, None);
+        }
+        let Some(flag) = flag else {
+            positional.push(arg.clone());
+            continue;
+        };
+        match flag.split_once('=') {
+            Some((name, value)) => flags.set(name, Some(value.to_string())),
+            None => {
+                let value = args
+                    .get(index)
+                    .filter(|(next, candidate)| !(*candidate && next.starts_with("--")))
+                    .map(|(next, _)| next.clone());
+                if value.is_some() {
+                    index += 1;
+                }
+                flags.set(flag, value);
+            }
+        }
+    }
+    // The rest may start on the next line
+    let rest = (limit == Some(positional.len())).then_some(args.len());
+    (positional, flags, rest)
+}
+
+/// Internal helper function to build the parse error of the invalid argument
+pub(crate) fn invalid_input(message: String) -> ParseError {
+    ParseError::Custom(Box::new(std::io::Error::new(
+        std::io::ErrorKind::InvalidInput,
+        message,
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::parse::command_string::split_with_offsets;
+
+    fn flag_candidates(text: &str) -> Vec<(String, bool)> {
+        mark_flag_candidates::<Flags>(text, &split_with_offsets(text))
+    }
+
+    #[test]
+    fn test_split_flags() {
+        let args = "report --from 2024-01-01 --limit=10 --verbose -- --raw";
+        let (positional, flags, _) = split_flags(flag_candidates(args), None);
+        assert_eq!(positional, ["report", "--raw"]);
+        assert_eq!(flags.get::<String>("from").unwrap().as_deref(), Some("2024-01-01"));
+        assert_eq!(flags.get::<u32>("limit").unwrap(), Some(10));
+        assert!(flags.has("verbose"));
+        assert!(flags.get::<u32>("verbose").is_err());
+        assert_eq!(flags.get::<u32>("missing").unwrap(), None);
+        assert!(flags.expect_only(&["from", "limit"]).is_err());
+        assert_eq!(flags.to_string(), "--from=2024-01-01 --limit=10 --verbose");
+        assert!(EmptyArg::parse_flag_args(&flags).is_err());
+
+        let (positional, flags, rest) = split_flags(flag_candidates("--pin buy --milk -- eggs"), Some(0));
+        assert!(positional.is_empty());
+        assert_eq!(flags.get::<String>("pin").unwrap().as_deref(), Some("buy"));
+        assert!(flags.has("milk"));
+        assert_eq!(rest, Some(4));
+        let (_, _, rest) = split_flags(flag_candidates("-- --milk"), Some(0));
+        assert_eq!(rest, Some(1));
+
+        // The quoted arguments are positional, including the quoted separator
+        let (positional, flags, _) = split_flags(flag_candidates(r#"--title "--x" "--" "--y" --z"#), None);
+        assert_eq!(positional, ["--", "--y"]);
+        assert_eq!(flags.get::<String>("title").unwrap().as_deref(), Some("--x"));
+        assert!(flags.has("z"));
+
+        // The commands without flags take all arguments as positional
+        let args = "--x a -- b";
+        let candidates = mark_flag_candidates::<EmptyArg>(args, &split_with_offsets(args));
+        let (positional, flags, _) = split_flags(candidates, None);
+        assert_eq!(positional, ["--x", "a", "--", "b"]);
+        assert!(flags.is_empty());
+    }
+
+    #[cfg(feature = "macros")]
+    #[test]
+    fn test_command_arg_enum() {
+        use crate::command::CommandArgEnum;
+
+        #[derive(CommandArgEnum, Debug, PartialEq)]
+        enum Status {
+            #[arg(alias = "Open")]
+            InProgress,
+            #[arg(name = "done", alias = "closed", alias = "fixed")]
+            Resolved,
+        }
+
+        assert_eq!(Status::parse_command_arg("in-progress").unwrap(), Status::InProgress);
+        assert_eq!(Status::parse_command_arg("OPEN").unwrap(), Status::InProgress);
+        assert_eq!(Status::parse_command_arg("Fixed").unwrap(), Status::Resolved);
+        assert_eq!(Status::Resolved.to_string(), "done");
+        let err = "later".parse::<Status>().unwrap_err();
+        assert_eq!(err.to_string(), "Invalid value later, expected one of: in-progress, done");
+        assert!(Status::parse_command_arg("later").is_err());
+    }
+}
@@ -1,8 +1,50 @@
-use std::{fmt::Display, str::FromStr, sync::Arc};
+use std::{
+    fmt::{self, Display},
+    str::FromStr,
+    sync::Arc,
+};
 
-use teloxide::types::{ChatId, InlineKeyboardButton, InlineKeyboardMarkup};
+use serde::{Serialize, de::DeserializeOwned};
+use teloxide::{
+    types::{ChatId, InlineKeyboardButton, InlineKeyboardMarkup},
+    utils::command::ParseError,
+};
 
-use crate::api::data_store::data_store_trait::DataStoreTrait;
+use crate::api::{
+    command::command_trait::CommandTrait, data_store::data_store_trait::DataStoreTrait,
+    parse::command_string::split_command,
+};
+
+/// Error reading the typed payload of the button, see [`unpack_typed_callback_data`]
+#[derive(Debug)]
+pub enum CallbackPayloadError {
+    /// The payload was kept in the callback data storage, but it's not there anymore
+    Expired(String),
+    /// The callback data is not the payload of the expected type, e.g. a command or another payload
+    Mismatch(String, serde_json::Error),
+}
+
+impl Display for CallbackPayloadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CallbackPayloadError::Expired(reference) => {
+                write!(f, "callback data {} is not in the storage", reference)
+            }
+            CallbackPayloadError::Mismatch(data, err) => {
+                write!(f, "unexpected callback payload {:?}: {}", data, err)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CallbackPayloadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CallbackPayloadError::Mismatch(_, err) => Some(err),
+            _ => None,
+        }
+    }
+}
 
 /// Type alias for callback data (the actual callback string)
 pub type CallbackData = String;
@@ -16,6 +58,20 @@ pub enum ButtonData {
     SwitchInlineQuery(String, String),
 }
 
+impl ButtonData {
+    /// Create the callback button carrying the payload serialized as compact JSON
+    /// The payload longer than Telegram's 64 bytes limit is kept in the callback data storage
+    /// when the menu is attached to the message, see [`pack_callback_data`].
+    /// Read the payload back with [`unpack_typed_callback_data`].
+    ///
+    /// # Panics
+    /// If the payload can't be serialized to JSON, e.g. a map with non-string keys
+    pub fn typed<T: Serialize>(label: impl Into<String>, payload: &T) -> Self {
+        let data = serde_json::to_string(payload).expect("callback payload must serialize to JSON");
+        ButtonData::Callback(label.into(), data)
+    }
+}
+
 impl From<(String, String)> for ButtonData {
     fn from((label, data): (String, String)) -> Self {
         ButtonData::Callback(label, data)
@@ -249,3 +305,102 @@ pub async fn unpack_callback_data(
     // Not a reference or not found in storage, return as-is
     callback_data.to_string()
 }
+
+/// Read the payload of the button created by [`ButtonData::typed`],
+/// retrieving the long payload from storage if needed.
+///
+/// # Returns
+/// The deserialized payload, or the error if the payload has expired from storage
+/// or the callback data is not the payload of this type
+pub async fn unpack_typed_callback_data<T: DeserializeOwned>(
+    storage: &Arc<dyn CallbackDataStorageTrait>,
+    callback_data: &str,
+) -> Result<T, CallbackPayloadError> {
+    let data = if callback_data.starts_with("cb:") {
+        storage
+            .get_callback_data(callback_data)
+            .await
+            .ok_or_else(|| CallbackPayloadError::Expired(callback_data.to_string()))?
+    } else {
+        callback_data.to_string()
+    };
+    serde_json::from_str(&data).map_err(|err| CallbackPayloadError::Mismatch(data, err))
+}
+
+/// Create the callback button running the command when pressed, e.g. `/add 2 3`
+/// The command longer than Telegram's 64 bytes limit is kept in the callback data storage
+/// when the menu is attached to the message, see [`pack_callback_data`].
+pub fn pack_command_button(label: impl Into<String>, command: &impl CommandTrait) -> ButtonData {
+    ButtonData::Callback(label.into(), command.to_command_string(false))
+}
+
+/// Reconstruct the command of the given type from the callback data of the button
+/// created by [`pack_command_button`], retrieving the long command from storage if needed.
+///
+/// # Returns
+/// The parsed command or the parse error, `None` if the callback data is not the command of this type
+pub async fn parse_command_callback<C: CommandTrait>(
+    storage: &Arc<dyn CallbackDataStorageTrait>,
+    callback_data: &str,
+) -> Option<Result<C, ParseError>> {
+    let command = unpack_callback_data(storage, callback_data).await;
+    let (name, args) = split_command(&command)?;
+    if name != C::NAME {
+        return None;
+    }
+    Some(C::parse_arguments(args.to_string()).map(|(command,)| command))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+    use teloxide::types::InlineKeyboardButtonKind;
+
+    use super::*;
+    use crate::api::data_store::in_mem::InMemStore;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Action {
+        Delete { id: u64 },
+        Rename { id: u64, name: String },
+    }
+
+    #[tokio::test]
+    async fn test_typed_callback_data() {
+        let storage: Arc<dyn CallbackDataStorageTrait> =
+            Arc::new(CallbackDataStorage::new(Arc::new(InMemStore::new()), ChatId(1)));
+        let delete = Action::Delete { id: 7 };
+        let rename = Action::Rename {
+            id: 7,
+            name: "the name which doesn't fit into the callback data".to_string(),
+        };
+        let buttons = [[
+            ButtonData::typed("Delete", &delete),
+            ButtonData::typed("Rename", &rename),
+        ]];
+        let keyboard = pack_callback_data(&storage, 5, buttons).await;
+        let callbacks: Vec<String> = keyboard.inline_keyboard[0]
+            .iter()
+            .map(|button| match &button.kind {
+                InlineKeyboardButtonKind::CallbackData(data) => data.clone(),
+                _ => panic!("not a callback button"),
+            })
+            .collect();
+        assert_eq!(callbacks[0], r#"{"Delete":{"id":7}}"#);
+        assert!(callbacks[1].starts_with("cb:"));
+
+        for (data, expected) in callbacks.iter().zip([delete, rename]) {
+            let action = unpack_typed_callback_data::<Action>(&storage, data).await;
+            assert_eq!(action.unwrap(), expected);
+        }
+        assert!(matches!(
+            unpack_typed_callback_data::<Action>(&storage, "/add 2 3").await,
+            Err(CallbackPayloadError::Mismatch(..))
+        ));
+        storage.clear_message_callbacks(5).await;
+        assert!(matches!(
+            unpack_typed_callback_data::<Action>(&storage, &callbacks[1]).await,
+            Err(CallbackPayloadError::Expired(_))
+        ));
+    }
+}
@@ -0,0 +1,167 @@
+use std::{
+    hash::{BuildHasher, RandomState},
+    sync::Arc,
+    time::SystemTime,
+};
+
+use teloxide::{RequestError, prelude::Requester, types::ChatId};
+
+use crate::{
+    api::{
+        command::{command_middleware::CommandCall, command_reply_target::CommandReplyTarget},
+        error::crate_error::Error,
+        markdown::string::MarkdownString,
+    },
+    markdown_format,
+};
+
+/// Renderer of the reply to the user whose command failed, receives the error's correlation id
+pub(crate) type ErrorRenderer = Arc<dyn Fn(&CommandCall, &Error, &str) -> MarkdownString + Send + Sync>;
+
+/// Reporter of the failed commands to the users and to the admin chat
+/// Disabled until the renderer or the admin chat is set
+#[derive(Clone, Default)]
+pub(crate) struct ErrorReporter {
+    pub(crate) render: Option<ErrorRenderer>,
+    pub(crate) admin_chat_id: Option<ChatId>,
+}
+
+impl ErrorReporter {
+    /// Internal helper function to reply to the user and to forward the error to the admin chat
+    /// Both messages carry the same correlation id, which is also logged with the error
+    pub(crate) async fn report(
+        &self,
+        call: &CommandCall,
+        target: &CommandReplyTarget,
+        err: &RequestError,
+    ) {
+        if self.render.is_none() && self.admin_chat_id.is_none() {
+            return;
+        }
+        // The custom errors of the handlers are found in it with `Error::downcast_ref`
+        let err = Error::from(err.clone());
+        let id = correlation_id();
+        log::error!(
+            "Command /{} failed in chat {}, error id {}: {:#}",
+            call.name,
+            target.chat.id,
+            id,
+            err
+        );
+        if let Some(render) = &self.render {
+            // The reply is a new message, the current one may be the menu the user pressed
+            let mut reply_target = target.clone();
+            reply_target.msg_id = None;
+            if let Err(reply_err) = reply_target
+                .render_markdown_message(render(call, &err, &id))
+                .await
+            {
+                log::warn!(
+                    "Can't reply to the failed command, error id {}: {}",
+                    id,
+                    reply_err
+                );
+            }
+        }
+        if let Some(admin_chat_id) = self.admin_chat_id {
+            let command = format!("/{} {}", call.name, call.args);
+            let text = format!(
+                "Error {}\n{}\nChat: {}, user: {:?}\n{:?}",
+                id,
+                command.trim_end(),
+                target.chat.id,
+                target.user_id,
+                err
+            );
+            if let Err(forward_err) = target
+                .send_request(target.bot.send_message(admin_chat_id, text))
+                .await
+            {
+                log::warn!(
+                    "Can't forward the error {} to the admin chat: {}",
+                    id,
+                    forward_err
+                );
+            }
+        }
+    }
+}
+
+/// The default reply to the user whose command failed, with the error's correlation id
+/// to quote to the bot's operators
+///
+/// The custom errors returned by the commands registered with
+/// [`try_command`](crate::command::CommandRegistry::try_command) are found with [`Error::downcast_ref`]:
+/// ```ignore
+/// registry.on_error(|call, err, id| {
+///     if let Some(quota) = err.downcast_ref::<QuotaError>() {
+///         return markdown_format!("{}\\. Error id: `{}`", quota.to_string(), id);
+///     }
+///     default_error_reply(call, err, id)
+/// })
+/// ```
+pub fn default_error_reply(call: &CommandCall, _err: &Error, id: &str) -> MarkdownString {
+    markdown_format!("Sorry, /{} failed\\. Error id: `{}`", &call.name, id)
+}
+
+/// Internal helper function to generate the short random id of the error
+fn correlation_id() -> String {
+    format!(
+        "{:08x}",
+        RandomState::new().hash_one(SystemTime::now()) as u32
+    )
+}
+
+#[cfg(all(test, feature = "testing"))]
+mod tests {
+    use std::fmt;
+
+    use teloxide::{dispatching::Dispatcher, types::ChatId};
+
+    use super::*;
+    use crate::{api::command::command_registry::CommandRegistry, testing::MockBotApi};
+
+    #[derive(Debug)]
+    struct QuotaError(u32);
+
+    impl fmt::Display for QuotaError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "quota exceeded, {} left", self.0)
+        }
+    }
+
+    impl std::error::Error for QuotaError {}
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_error_reply() {
+        let api = MockBotApi::start().await;
+        let registry = CommandRegistry::new()
+            .try_command("quota", "", |_, _, _| async { Err(Error::other(QuotaError(0))) })
+            .try_command("limit", "", |_, _, _| async { Err(Error::other(QuotaError(3))) })
+            .on_error(|call, err, id| match err.downcast_ref::<QuotaError>() {
+                Some(QuotaError(left)) if *left > 0 => markdown_format!("{} left", left.to_string()),
+                _ => default_error_reply(call, err, id),
+            })
+            .forward_errors_to(ChatId(100));
+        let mut dispatcher = Dispatcher::builder(api.bot(), registry.handler(())).build();
+        let dispatcher_task = tokio::spawn(async move { dispatcher.dispatch().await });
+
+        api.send_text(ChatId(1), "/quota").await;
+        let reply = api.next_request("sendMessage").await.unwrap();
+        let text = reply.str_param("text").unwrap();
+        assert!(text.starts_with("Sorry, /quota failed\\. Error id: `"));
+        let id = &text[text.len() - 9..text.len() - 1];
+        let forward = api.next_request("sendMessage").await.unwrap();
+        assert_eq!(forward.params["chat_id"], 100);
+        let forwarded = forward.str_param("text").unwrap();
+        assert!(forwarded.starts_with(&format!("Error {}\n/quota\nChat: 1", id)));
+        assert!(forwarded.contains("QuotaError(0)"));
+
+        // The custom renderer gets the error returned by the handler
+        api.send_text(ChatId(1), "/limit").await;
+        let reply = api.next_request("sendMessage").await.unwrap();
+        assert_eq!(reply.str_param("text"), Some("3 left"));
+
+        dispatcher_task.abort();
+    }
+}
@@ -0,0 +1,199 @@
+use std::{sync::Arc, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::api::{
+    data_store::data_store_trait::{DataStoreTrait, GLOBAL_NAMESPACE},
+    markdown::{
+        string::MarkdownString,
+        table::{Alignment, TableBuilder},
+    },
+};
+
+/// Upper bounds of the latency histogram buckets in milliseconds,
+/// the slower runs are counted in the last overflow bucket
+pub const LATENCY_BUCKETS_MS: [u64; 6] = [10, 50, 100, 500, 1000, 5000];
+
+/// Prefix of the keys of the command statistics in the data store, followed by the command name
+const COMMAND_STATS_KEY_PREFIX: &str = "command_stats_";
+
+/// Usage statistics of the command
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct CommandStats {
+    /// The name of the command without the leading slash
+    pub name: String,
+    /// The number of the runs
+    pub runs: u64,
+    /// The number of the runs which returned an error
+    pub errors: u64,
+    /// The total duration of the runs in milliseconds
+    pub total_ms: u64,
+    /// The number of the runs in each bucket of [`LATENCY_BUCKETS_MS`] and the overflow bucket
+    pub latency_buckets: Vec<u64>,
+}
+
+impl CommandStats {
+    /// Add the run with the given duration and result
+    pub fn record(&mut self, duration: Duration, ok: bool) {
+        let ms = duration.as_millis() as u64;
+        self.runs += 1;
+        if !ok {
+            self.errors += 1;
+        }
+        self.total_ms += ms;
+        self.latency_buckets.resize(LATENCY_BUCKETS_MS.len() + 1, 0);
+        let bucket = LATENCY_BUCKETS_MS
+            .iter()
+            .position(|bound| ms <= *bound)
+            .unwrap_or(LATENCY_BUCKETS_MS.len());
+        self.latency_buckets[bucket] += 1;
+    }
+
+    /// The average duration of the runs in milliseconds
+    pub fn average_ms(&self) -> u64 {
+        self.total_ms.checked_div(self.runs).unwrap_or_default()
+    }
+
+    /// The upper bound of the latency bucket containing the given percentile of the runs, e.g. 0.95
+    /// Returns `None` if there are no runs or the percentile falls into the overflow bucket
+    pub fn percentile_ms(&self, percentile: f64) -> Option<u64> {
+        let rank = (self.runs as f64 * percentile).ceil().max(1.0) as u64;
+        let mut count = 0;
+        for (bucket, runs) in self.latency_buckets.iter().enumerate() {
+            count += runs;
+            if count >= rank {
+                return LATENCY_BUCKETS_MS.get(bucket).copied();
+            }
+        }
+        None
+    }
+}
+
+/// Collector of the command usage metrics: the runs, the errors and the latency of each command
+///
+/// Called by the [`CommandRegistry`](crate::command::CommandRegistry) around each command run,
+/// see [`CommandRegistry::with_metrics`](crate::command::CommandRegistry::with_metrics).
+/// The commands skipped by a [`CommandMiddleware`](crate::command::CommandMiddleware) are not recorded.
+/// Implement the trait to export the numbers to an external monitoring system,
+/// or use the [`StoreCommandMetrics`] shown by the `/stats` command of the
+/// [`AdminCommands`](crate::app::AdminCommands).
+#[async_trait::async_trait]
+pub trait CommandMetrics: Send + Sync {
+    /// Record the run of the command with its duration and result
+    async fn record(&self, name: &str, duration: Duration, ok: bool);
+
+    /// The statistics of the commands sorted by name, empty if the collector doesn't keep them
+    async fn stats(&self) -> Vec<CommandStats> {
+        Vec::new()
+    }
+}
+
+/// The CommandMetrics implementation keeping the statistics of the commands in the data store
+#[derive(Clone)]
+pub struct StoreCommandMetrics {
+    store: Arc<dyn DataStoreTrait<CommandStats>>,
+    // Serializes the read-modify-write of the statistics
+    lock: Arc<Mutex<()>>,
+}
+
+impl StoreCommandMetrics {
+    /// Create a new StoreCommandMetrics with the given DataStore
+    pub fn new(store: Arc<dyn DataStoreTrait<CommandStats>>) -> Self {
+        Self {
+            store,
+            lock: Arc::new(Mutex::new(())),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl CommandMetrics for StoreCommandMetrics {
+    async fn record(&self, name: &str, duration: Duration, ok: bool) {
+        let key = format!("{}{}", COMMAND_STATS_KEY_PREFIX, name);
+        let _lock = self.lock.lock().await;
+        let mut stats = self
+            .store
+            .get(GLOBAL_NAMESPACE, &key)
+            .await
+            .unwrap_or_else(|| CommandStats {
+                name: name.to_string(),
+                ..Default::default()
+            });
+        stats.record(duration, ok);
+        self.store.set(GLOBAL_NAMESPACE, &key, stats).await;
+    }
+
+    async fn stats(&self) -> Vec<CommandStats> {
+        let mut stats = Vec::new();
+        for key in self.store.keys(GLOBAL_NAMESPACE).await {
+            if key.starts_with(COMMAND_STATS_KEY_PREFIX)
+                && let Some(command_stats) = self.store.get(GLOBAL_NAMESPACE, &key).await
+            {
+                stats.push(command_stats);
+            }
+        }
+        stats.sort_by(|a, b| a.name.cmp(&b.name));
+        stats
+    }
+}
+
+/// Render the statistics of the commands as a table with the runs, the errors,
+/// the average latency and the 95th percentile latency bucket
+pub fn render_command_stats(stats: &[CommandStats]) -> MarkdownString {
+    let mut table = TableBuilder::new()
+        .column("Command", Alignment::Left)
+        .column("Runs", Alignment::Right)
+        .column("Errors", Alignment::Right)
+        .column("Avg ms", Alignment::Right)
+        .column("p95 ms", Alignment::Right);
+    for command in stats {
+        let p95 = match command.percentile_ms(0.95) {
+            Some(bound) => format!("≤{}", bound),
+            None => format!(">{}", LATENCY_BUCKETS_MS[LATENCY_BUCKETS_MS.len() - 1]),
+        };
+        table.push_row([
+            format!("/{}", command.name),
+            command.runs.to_string(),
+            command.errors.to_string(),
+            command.average_ms().to_string(),
+            p95,
+        ]);
+    }
+    table.build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::data_store::in_mem::InMemStore;
+
+    #[tokio::test]
+    async fn test_store_command_metrics() {
+        let metrics = StoreCommandMetrics::new(Arc::new(InMemStore::new()));
+        for ms in [5, 20, 40, 70] {
+            metrics
+                .record("start", Duration::from_millis(ms), true)
+                .await;
+        }
+        metrics.record("add", Duration::from_secs(10), false).await;
+
+        let stats = metrics.stats().await;
+        assert_eq!(stats.len(), 2);
+        assert_eq!(
+            (stats[0].name.as_str(), stats[0].runs, stats[0].errors),
+            ("add", 1, 1)
+        );
+        assert_eq!(stats[0].percentile_ms(0.95), None);
+        assert_eq!(stats[1].average_ms(), 33);
+        assert_eq!(stats[1].percentile_ms(0.5), Some(50));
+        assert_eq!(stats[1].percentile_ms(0.95), Some(100));
+        assert_eq!(
+            render_command_stats(&stats).to_plain_text(),
+            "Command | Runs | Errors | Avg ms | p95 ms\n\
+             --------+------+--------+--------+-------\n\
+             /add    |    1 |      1 |  10000 |  >5000\n\
+             /start  |    4 |      0 |     33 |   ≤100"
+        );
+    }
+}
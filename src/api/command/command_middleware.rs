@@ -0,0 +1,131 @@
+use std::sync::Arc;
+
+use teloxide::prelude::ResponseResult;
+
+use crate::api::command::command_reply_target::CommandReplyTarget;
+
+/// The command being run by the [`CommandRegistry`](crate::command::CommandRegistry)
+#[derive(Clone, Debug, PartialEq)]
+pub struct CommandCall {
+    /// The name of the command without the leading slash
+    pub name: String,
+    /// The text after the command name
+    pub args: String,
+}
+
+/// Hooks around each command run by the [`CommandRegistry`](crate::command::CommandRegistry)
+///
+/// The middlewares are added to the registry once and wrap all its commands, so logging,
+/// metrics, access checks or tracing don't require wrapping every `run`.
+/// The `before` hooks are called in the order the middlewares were added, the `after` hooks
+/// in the reverse order. The command skipped by a `before` hook is not passed to the `after` hooks
+/// of the middlewares added later.
+///
+/// # Example
+///
+/// ```rust
+/// use telluride::command::{CommandCall, CommandMiddleware, CommandReplyTarget};
+/// use teloxide::prelude::ResponseResult;
+///
+/// struct Logger;
+///
+/// #[async_trait::async_trait]
+/// impl CommandMiddleware for Logger {
+///     async fn after(&self, cmd: &CommandCall, target: &CommandReplyTarget, result: &ResponseResult<()>) {
+///         log::info!("/{} in chat {}: {:?}", cmd.name, target.chat.id, result);
+///     }
+/// }
+/// ```
+#[async_trait::async_trait]
+pub trait CommandMiddleware: Send + Sync {
+    /// Called before the command, return false to skip it, e.g. after replying that it's not allowed
+    async fn before(&self, _cmd: &CommandCall, _target: &CommandReplyTarget) -> bool {
+        true
+    }
+
+    /// Called after the command with its result
+    async fn after(&self, _cmd: &CommandCall, _target: &CommandReplyTarget, _result: &ResponseResult<()>) {}
+}
+
+/// Run the command between the hooks of the middlewares
+/// Returns `Ok(())` if the command was skipped
+pub(crate) async fn run_with_middlewares<F>(
+    middlewares: &[Arc<dyn CommandMiddleware>],
+    cmd: &CommandCall,
+    target: &CommandReplyTarget,
+    run: F,
+) -> ResponseResult<()>
+where
+    F: Future<Output = ResponseResult<()>>,
+{
+    let mut entered = 0;
+    let mut result = Ok(());
+    for middleware in middlewares {
+        if !middleware.before(cmd, target).await {
+            break;
+        }
+        entered += 1;
+    }
+    if entered == middlewares.len() {
+        result = run.await;
+    }
+    for middleware in middlewares[..entered].iter().rev() {
+        middleware.after(cmd, target, &result).await;
+    }
+    result
+}
+
+#[cfg(all(test, feature = "testing"))]
+mod tests {
+    use teloxide::{dispatching::Dispatcher, types::ChatId};
+    use tokio::sync::Mutex;
+
+    use super::*;
+    use crate::{
+        api::command::command_registry::CommandRegistry, markdown_string, testing::MockBotApi,
+    };
+
+    /// Records the hook calls, skips the `ban` command
+    struct Recorder(Arc<Mutex<Vec<String>>>);
+
+    #[async_trait::async_trait]
+    impl CommandMiddleware for Recorder {
+        async fn before(&self, cmd: &CommandCall, target: &CommandReplyTarget) -> bool {
+            self.0.lock().await.push(format!("before {} {}", cmd.name, cmd.args));
+            if cmd.name == "ban" {
+                target.markdown_message(markdown_string!("Not allowed")).await.ok();
+                return false;
+            }
+            true
+        }
+
+        async fn after(&self, cmd: &CommandCall, _target: &CommandReplyTarget, result: &ResponseResult<()>) {
+            self.0.lock().await.push(format!("after {} {}", cmd.name, result.is_ok()));
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_command_middleware() {
+        let api = MockBotApi::start().await;
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let registry = CommandRegistry::new()
+            .command("ping", "", |target, _, _| async move {
+                target.markdown_message(markdown_string!("pong")).await?;
+                Ok(())
+            })
+            .command("ban", "", |_, _, _| async { panic!("skipped by the middleware") })
+            .with_middleware(Recorder(calls.clone()));
+        let mut dispatcher = Dispatcher::builder(api.bot(), registry.handler(())).build();
+        let dispatcher_task = tokio::spawn(async move { dispatcher.dispatch().await });
+
+        api.send_text(ChatId(1), "/ping now").await;
+        let reply = api.next_request("sendMessage").await.unwrap();
+        assert_eq!(reply.str_param("text"), Some("pong"));
+        api.send_text(ChatId(1), "/ban").await;
+        let reply = api.next_request("sendMessage").await.unwrap();
+        assert_eq!(reply.str_param("text"), Some("Not allowed"));
+        dispatcher_task.abort();
+
+        assert_eq!(*calls.lock().await, ["before ping now", "after ping true", "before ban "]);
+    }
+}
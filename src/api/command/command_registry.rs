@@ -0,0 +1,734 @@
+use std::{future::Future, sync::Arc, time::Instant};
+
+use teloxide::{
+    Bot, RequestError,
+    dispatching::{UpdateFilterExt, UpdateHandler},
+    dptree,
+    payloads::SetMyCommandsSetters,
+    prelude::{Requester, ResponseResult},
+    types::{BotCommand, BotCommandScope, CallbackQuery, ChatId, Me, Message, Update},
+    utils::command::ParseError,
+};
+
+use crate::{
+    api::{
+        app::bot_app::BoxFuture,
+        command::{
+            command_button::{CallbackData, CallbackDataStorage, unpack_callback_data},
+            command_error::ErrorReporter,
+            command_metrics::CommandMetrics,
+            command_middleware::{CommandCall, CommandMiddleware, run_with_middlewares}, command_reply_target::CommandReplyTarget,
+            command_trait::CommandTrait, paginator::Paginator, subcommand_router::SubcommandRouter,
+        },
+        data_store::in_mem::InMemStore,
+        markdown::string::MarkdownString,
+        parse::command_string::{is_command_to_other_bot, split_command},
+    },
+    markdown_format,
+};
+
+pub(crate) type CommandHandler<Ctx> =
+    Arc<dyn Fn(CommandReplyTarget, Ctx, String) -> BoxFuture<ResponseResult<()>> + Send + Sync>;
+
+/// Command registered in the [`CommandRegistry`]
+pub(crate) struct RegisteredCommand<Ctx> {
+    pub(crate) name: String,
+    pub(crate) description: String,
+    pub(crate) handler: CommandHandler<Ctx>,
+    /// Scopes of the command menu where the command is listed
+    pub(crate) scopes: Vec<BotCommandScope>,
+}
+
+/// Set of the commands dispatched by their names
+///
+/// The [`CommandTrait`] types are registered once, the registry parses the arguments of the command
+/// and calls its [`run`](CommandTrait::run). If the arguments can't be parsed, the user gets the error
+/// with the command's usage. The registry can be passed to [`BotApp::with_commands`](crate::app::BotApp::with_commands)
+/// or turned into the teloxide handler with [`handler`](Self::handler).
+///
+/// # Example
+/// ```ignore
+/// let registry = CommandRegistry::new()
+///     .register::<StartCommand>("start the bot")
+///     .register::<AddCommand>("add two numbers");
+/// Dispatcher::builder(bot, registry.handler(context)).build().dispatch().await;
+/// ```
+pub struct CommandRegistry<Ctx = ()> {
+    commands: Vec<RegisteredCommand<Ctx>>,
+    middlewares: Vec<Arc<dyn CommandMiddleware>>,
+    metrics: Option<Arc<dyn CommandMetrics>>,
+    errors: ErrorReporter,
+}
+
+impl<Ctx> Default for CommandRegistry<Ctx> {
+    fn default() -> Self {
+        Self {
+            commands: Vec::new(),
+            middlewares: Vec::new(),
+            metrics: None,
+            errors: ErrorReporter::default(),
+        }
+    }
+}
+
+impl<Ctx> CommandRegistry<Ctx>
+where
+    Ctx: Clone + Send + Sync + 'static,
+{
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register the command type under its [`NAME`](CommandTrait::NAME)
+    /// The command registered earlier with the same name is replaced.
+    pub fn register<C>(self, description: impl Into<String>) -> Self
+    where
+        C: CommandTrait<Context = Ctx> + 'static,
+    {
+        self.register_command::<C>(description, false)
+    }
+
+    /// Register the command type like [`register`](Self::register), asking for the missing arguments
+    /// one by one if the target has the [`CommandWizard`](crate::command::CommandWizard)
+    pub fn register_wizard<C>(self, description: impl Into<String>) -> Self
+    where
+        C: CommandTrait<Context = Ctx> + 'static,
+    {
+        self.register_command::<C>(description, true)
+    }
+
+    /// Internal helper function to register the handler parsing the arguments of the command type
+    fn register_command<C>(self, description: impl Into<String>, wizard: bool) -> Self
+    where
+        C: CommandTrait<Context = Ctx> + 'static,
+    {
+        self.command(C::NAME, description, move |target, context, args| async move {
+            if wizard && let Some(command_wizard) = target.command_wizard.clone() {
+                return command_wizard.run::<C>(&target, context, args).await;
+            }
+            match C::parse_arguments(args) {
+                Ok((command,)) => run_authorized(command, &target, context).await,
+                Err(err) => reply_usage::<C>(&target, err).await,
+            }
+        })
+    }
+
+    /// Register the handler of the command with the given name (without the leading slash)
+    /// The handler receives the rest of the message after the command name as the arguments.
+    /// The command registered earlier with the same name is replaced.
+    pub fn command<F, Fut>(
+        mut self,
+        name: impl Into<String>,
+        description: impl Into<String>,
+        handler: F,
+    ) -> Self
+    where
+        F: Fn(CommandReplyTarget, Ctx, String) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ResponseResult<()>> + Send + 'static,
+    {
+        let name = name.into();
+        self.commands.retain(|command| command.name != name);
+        self.commands.push(RegisteredCommand {
+            name,
+            description: description.into(),
+            handler: Arc::new(move |target, context, args| Box::pin(handler(target, context, args))),
+            scopes: vec![BotCommandScope::Default],
+        });
+        self
+    }
+
+    /// Register the handler of the command like [`command`](Self::command), the handler returns
+    /// the crate's [`Error`](crate::Error), e.g. wrapping the custom error of the application with
+    /// [`Error::other`](crate::Error::other). The custom errors are passed to the [`on_error`](Self::on_error) renderer.
+    pub fn try_command<F, Fut>(
+        self,
+        name: impl Into<String>,
+        description: impl Into<String>,
+        handler: F,
+    ) -> Self
+    where
+        F: Fn(CommandReplyTarget, Ctx, String) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = crate::Result<()>> + Send + 'static,
+    {
+        self.command(name, description, move |target, context, args| {
+            let run = handler(target, context, args);
+            async move { run.await.map_err(RequestError::from) }
+        })
+    }
+
+    /// Register the command dispatching to the subcommands of the router, see [`SubcommandRouter`]
+    /// The command registered earlier with the same name is replaced.
+    pub fn router(self, router: SubcommandRouter<Ctx>, description: impl Into<String>) -> Self {
+        let name = router.name().to_string();
+        let router = Arc::new(router);
+        self.command(name, description, move |target, context, args| {
+            let router = router.clone();
+            async move { router.run(&target, context, &args).await }
+        })
+    }
+
+    /// Register the command of the paginator's buttons, see [`Paginator`]
+    /// The command is hidden from the command menu.
+    pub fn paginator(self, paginator: Paginator) -> Self {
+        let name = paginator.command().to_string();
+        self.command(name, "", move |target, _, args| {
+            let paginator = paginator.clone();
+            async move { paginator.flip(&target, &args).await }
+        })
+        .scopes([])
+    }
+
+    /// Record the runs of the commands with the given collector, see [`CommandMetrics`]
+    pub fn with_metrics(mut self, metrics: Arc<dyn CommandMetrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Reply to the user whose command failed with the rendered error, e.g. [`default_error_reply`](crate::command::default_error_reply)
+    /// The renderer receives the random correlation id of the error, which is also logged
+    /// and forwarded to the admin chat, see [`forward_errors_to`](Self::forward_errors_to).
+    /// The error is still returned from the run, e.g. to the [`CommandMiddleware`] hooks.
+    pub fn on_error<F>(mut self, render: F) -> Self
+    where
+        F: Fn(&CommandCall, &crate::Error, &str) -> MarkdownString + Send + Sync + 'static,
+    {
+        self.errors.render = Some(Arc::new(render));
+        self
+    }
+
+    /// Send the full errors of the failed commands to the chat, e.g. the operators' group
+    pub fn forward_errors_to(mut self, chat_id: ChatId) -> Self {
+        self.errors.admin_chat_id = Some(chat_id);
+        self
+    }
+
+    /// Set the scopes of the command menu where the last registered command is listed,
+    /// e.g. only in the groups or only for the chat administrators, see [`sync_bot_commands`](Self::sync_bot_commands)
+    /// By default the commands are listed in all chats, the empty scopes hide the command from the menu.
+    pub fn scopes(mut self, scopes: impl IntoIterator<Item = BotCommandScope>) -> Self {
+        if let Some(command) = self.commands.last_mut() {
+            command.scopes = scopes.into_iter().collect();
+        }
+        self
+    }
+
+    /// Add the middleware wrapping all commands of the registry, see [`CommandMiddleware`]
+    pub fn with_middleware(mut self, middleware: impl CommandMiddleware + 'static) -> Self {
+        self.middlewares.push(Arc::new(middleware));
+        self
+    }
+
+    /// Add the commands of the other registry, replacing the ones with the same names
+    /// The middlewares of the other registry are added after the own ones and wrap all commands.
+    pub fn merge(mut self, other: CommandRegistry<Ctx>) -> Self {
+        for command in other.commands {
+            self.commands.retain(|registered| registered.name != command.name);
+            self.commands.push(command);
+        }
+        self.middlewares.extend(other.middlewares);
+        self.metrics = other.metrics.or(self.metrics);
+        self.errors.render = other.errors.render.or(self.errors.render);
+        self.errors.admin_chat_id = other.errors.admin_chat_id.or(self.errors.admin_chat_id);
+        self
+    }
+
+    /// The names and the descriptions of the registered commands
+    pub fn commands(&self) -> Vec<(&str, &str)> {
+        self.commands
+            .iter()
+            .map(|command| (command.name.as_str(), command.description.as_str()))
+            .collect()
+    }
+
+    /// Set the command menu of the bot to the registered commands with `set_my_commands`,
+    /// a separate list is set for each scope used by the commands, see [`scopes`](Self::scopes)
+    /// The commands without the description are not listed, Telegram requires it.
+    pub async fn sync_bot_commands(&self, bot: &Bot) -> ResponseResult<()> {
+        let mut menus: Vec<(BotCommandScope, Vec<BotCommand>)> = Vec::new();
+        for command in &self.commands {
+            if command.description.is_empty() {
+                continue;
+            }
+            for scope in &command.scopes {
+                let bot_command = BotCommand::new(&command.name, &command.description);
+                match menus.iter_mut().find(|(menu_scope, _)| menu_scope == scope) {
+                    Some((_, commands)) => commands.push(bot_command),
+                    None => menus.push((scope.clone(), vec![bot_command])),
+                }
+            }
+        }
+        for (scope, commands) in menus {
+            bot.set_my_commands(commands).scope(scope).await?;
+        }
+        Ok(())
+    }
+
+    /// Internal helper function to find the command by the name
+    pub(crate) fn find(&self, name: &str) -> Option<&RegisteredCommand<Ctx>> {
+        self.commands.iter().find(|command| command.name == name)
+    }
+
+    /// Run the command in the text like `/name args` or `/name@bot args`
+    /// Returns `None` if the text is not a registered command
+    pub async fn run(
+        &self,
+        target: CommandReplyTarget,
+        context: Ctx,
+        text: &str,
+    ) -> Option<ResponseResult<()>> {
+        let (name, args) = split_command(text)?;
+        let command = self.find(name)?;
+        Some(self.run_command(command, target, context, args).await)
+    }
+
+    /// Internal helper function to run the command's handler wrapped by the middlewares
+    pub(crate) async fn run_command(
+        &self,
+        command: &RegisteredCommand<Ctx>,
+        target: CommandReplyTarget,
+        context: Ctx,
+        args: &str,
+    ) -> ResponseResult<()> {
+        let call = CommandCall {
+            name: command.name.clone(),
+            args: args.to_string(),
+        };
+        let run = async {
+            let started = Instant::now();
+            let result = (command.handler)(target.clone(), context, call.args.clone()).await;
+            // The messages left in the batch buffer are sent even if the command failed
+            let flushed = flush_batch(&target).await;
+            let result = result.and(flushed);
+            if let Some(metrics) = &self.metrics {
+                metrics.record(&call.name, started.elapsed(), result.is_ok()).await;
+            }
+            result
+        };
+        let result = run_with_middlewares(&self.middlewares, &call, &target, run).await;
+        if let Err(err) = &result {
+            self.errors.report(&call, &target, err).await;
+        }
+        result
+    }
+
+    /// Run the command in the callback data of the pressed button, see [`pack_command_button`](crate::command::pack_command_button)
+    /// The long command is retrieved from the target's callback data storage.
+    /// Returns `None` if the callback data is not a registered command
+    pub async fn run_callback(
+        &self,
+        target: CommandReplyTarget,
+        context: Ctx,
+        callback_data: &str,
+    ) -> Option<ResponseResult<()>> {
+        let command = unpack_callback_data(&target.callback_data_storage, callback_data).await;
+        self.run(target, context, &command).await
+    }
+
+    /// Build the teloxide handler of the messages with the registered commands and of the callback queries
+    /// of the menu buttons running them
+    /// The targets reply to the message's chat, the callback data of the menus is kept in memory.
+    /// Use [`BotApp`](crate::app::BotApp) for the persistent stores and the target options.
+    pub fn handler(self, context: Ctx) -> UpdateHandler<RequestError> {
+        let registry = Arc::new(self);
+        let callback_store: Arc<InMemStore<CallbackData>> = Arc::new(InMemStore::new());
+        let (query_registry, query_store, query_context) =
+            (registry.clone(), callback_store.clone(), context.clone());
+        let messages = Update::filter_message().endpoint(move |bot: Bot, me: Me, msg: Message| {
+            let registry = registry.clone();
+            let callback_store = callback_store.clone();
+            let context = context.clone();
+            async move {
+                let Some(text) = msg.text() else {
+                    return Ok(());
+                };
+                if is_command_to_other_bot(text, me.username()) {
+                    return Ok(());
+                }
+                let storage = Arc::new(CallbackDataStorage::new(callback_store, msg.chat.id));
+                let mut target = CommandReplyTarget::new(bot, msg.chat.clone(), None, storage);
+                target.user_id = msg.from.as_ref().map(|user| user.id);
+                match registry.run(target, context, text).await {
+                    Some(Err(err)) => {
+                        log::error!("Error handling command in chat {}: {}", msg.chat.id, err);
+                        Ok(())
+                    }
+                    _ => Ok(()),
+                }
+            }
+        });
+        let queries = Update::filter_callback_query().endpoint(move |bot: Bot, query: CallbackQuery| {
+            let registry = query_registry.clone();
+            let callback_store = query_store.clone();
+            let context = query_context.clone();
+            async move {
+                if let (Some(data), Some(msg)) = (&query.data, query.regular_message()) {
+                    let storage = Arc::new(CallbackDataStorage::new(callback_store, msg.chat.id));
+                    let mut target = CommandReplyTarget::new(bot.clone(), msg.chat.clone(), Some(msg.id), storage);
+                    target.user_id = Some(query.from.id);
+                    target.callback_query_id = Some(query.id.clone());
+                    match registry.run_callback(target, context, data).await {
+                        Some(Err(err)) => {
+                            log::error!("Error handling callback query in chat {}: {}", msg.chat.id, err)
+                        }
+                        Some(Ok(())) => {}
+                        None => log::warn!("Unknown command in callback data: {}", data),
+                    }
+                }
+                // Remove the loading state of the button if the command didn't answer the query
+                if let Err(err) = bot.answer_callback_query(query.id).await {
+                    log::debug!("Callback query already answered: {}", err);
+                }
+                Ok(())
+            }
+        });
+        dptree::entry().branch(messages).branch(queries)
+    }
+}
+
+/// Internal helper function to send the messages accumulated by the handler in batch mode
+pub(crate) async fn flush_batch(target: &CommandReplyTarget) -> ResponseResult<()> {
+    if target.batch {
+        target.flush().await?;
+    }
+    Ok(())
+}
+
+/// Internal helper function to run the command if the user is authorized,
+/// otherwise to show the reason of the denial
+pub(crate) async fn run_authorized<C: CommandTrait>(
+    command: C,
+    target: &CommandReplyTarget,
+    context: C::Context,
+) -> ResponseResult<()> {
+    let reason = match command.authorize(target).await {
+        Ok(()) => return command.run(target, context).await,
+        Err(reason) => reason,
+    };
+    log::info!(
+        "Command /{} denied in chat {}: {}",
+        C::NAME,
+        target.chat.id,
+        reason
+    );
+    let text = markdown_format!("{}", reason.to_string());
+    if target.callback_query_id.is_some() {
+        target.answer_callback_alert(text).await
+    } else {
+        target.markdown_message(text).await.map(|_| ())
+    }
+}
+
+/// Internal helper function to reply with the parse error and the usage of the command
+pub(crate) async fn reply_usage<C: CommandTrait>(
+    target: &CommandReplyTarget,
+    err: ParseError,
+) -> ResponseResult<()> {
+    reply_usage_at::<C>(target, err, &format!("/{}", C::NAME)).await
+}
+
+/// Internal helper function to reply with the parse error and the usage of the command
+/// called with the given path, e.g. `/settings notify` for the subcommand
+pub(crate) async fn reply_usage_at<C: CommandTrait>(
+    target: &CommandReplyTarget,
+    err: ParseError,
+    path: &str,
+) -> ResponseResult<()> {
+    let usage = usage(path, &C::PLACEHOLDERS.join(" "));
+    target
+        .markdown_message(markdown_format!("{}\nUsage: {}", err.to_string(), usage))
+        .await?;
+    Ok(())
+}
+
+/// Internal helper function to render the usage of the command, e.g. `/add <a> <b>`
+pub(crate) fn usage(path: &str, placeholders: &str) -> String {
+    match placeholders {
+        "" => path.to_string(),
+        placeholders => format!("{} {}", path, placeholders),
+    }
+}
+
+#[cfg(all(test, feature = "testing"))]
+mod tests {
+    use teloxide::{dispatching::Dispatcher, types::ChatId};
+
+    use super::*;
+    use crate::{
+        api::command::{
+            authorize::DenyReason,
+            command_arg::{EmptyArg, Req, RestArg},
+            command_button::{CallbackDataStorageTrait, pack_command_button, parse_command_callback},
+        },
+        markdown_string,
+        testing::MockBotApi,
+    };
+
+    #[derive(Clone)]
+    struct AddCommand(Option<i64>, Option<Req<i64>>);
+
+    impl CommandTrait for AddCommand {
+        type A = i64;
+        type B = Req<i64>;
+        type C = EmptyArg;
+        type D = EmptyArg;
+        type E = EmptyArg;
+        type F = EmptyArg;
+        type G = EmptyArg;
+        type H = EmptyArg;
+        type I = EmptyArg;
+        type FlagArgs = EmptyArg;
+        type Context = i64;
+        const NAME: &'static str = "add";
+        const PLACEHOLDERS: &[&'static str] = &["<a>", "<b>"];
+
+        fn from_arguments(
+            a: Option<i64>,
+            b: Option<Req<i64>>,
+            _c: Option<EmptyArg>,
+            _d: Option<EmptyArg>,
+            _e: Option<EmptyArg>,
+            _f: Option<EmptyArg>,
+            _g: Option<EmptyArg>,
+            _h: Option<EmptyArg>,
+            _i: Option<EmptyArg>,
+        ) -> Self {
+            Self(a, b)
+        }
+
+        fn param1(&self) -> Option<&i64> {
+            self.0.as_ref()
+        }
+
+        fn param2(&self) -> Option<&Req<i64>> {
+            self.1.as_ref()
+        }
+
+        async fn run2(
+            &self,
+            target: &CommandReplyTarget,
+            offset: i64,
+            a: &i64,
+            b: &Req<i64>,
+        ) -> ResponseResult<()> {
+            target
+                .markdown_message(markdown_format!("{}", (a + **b + offset).to_string()))
+                .await?;
+            Ok(())
+        }
+    }
+
+    #[derive(Clone)]
+    struct NoteCommand(Option<RestArg>);
+
+    impl CommandTrait for NoteCommand {
+        type A = RestArg;
+        type B = EmptyArg;
+        type C = EmptyArg;
+        type D = EmptyArg;
+        type E = EmptyArg;
+        type F = EmptyArg;
+        type G = EmptyArg;
+        type H = EmptyArg;
+        type I = EmptyArg;
+        type FlagArgs = EmptyArg;
+        type Context = i64;
+        const NAME: &'static str = "note";
+        const PLACEHOLDERS: &[&'static str] = &["<text>"];
+
+        fn from_arguments(
+            a: Option<RestArg>,
+            _b: Option<EmptyArg>,
+            _c: Option<EmptyArg>,
+            _d: Option<EmptyArg>,
+            _e: Option<EmptyArg>,
+            _f: Option<EmptyArg>,
+            _g: Option<EmptyArg>,
+            _h: Option<EmptyArg>,
+            _i: Option<EmptyArg>,
+        ) -> Self {
+            Self(a)
+        }
+
+        fn param1(&self) -> Option<&RestArg> {
+            self.0.as_ref()
+        }
+
+        async fn authorize(&self, target: &CommandReplyTarget) -> Result<(), DenyReason> {
+            target.require_allowed_user().await
+        }
+
+        async fn run1(&self, target: &CommandReplyTarget, _: i64, text: &RestArg) -> ResponseResult<()> {
+            target.markdown_message(markdown_format!("{}", text.to_string())).await?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_rest_arg() {
+        let (note,) = NoteCommand::parse_arguments("buy  milk\\ and\neggs".to_string()).unwrap();
+        assert_eq!(note.0.as_deref(), Some("buy  milk\\ and\neggs"));
+        assert_eq!(note.to_command_string(true), "/note buy  milk\\ and\neggs");
+        let (note,) = NoteCommand::parse_arguments("\nnext line".to_string()).unwrap();
+        assert_eq!(note.0.as_deref(), Some("next line"));
+        let (note,) = NoteCommand::parse_arguments("  ".to_string()).unwrap();
+        assert!(note.0.is_none());
+    }
+
+    #[derive(Clone)]
+    struct EchoCommand(Option<String>, Option<String>, Option<String>);
+
+    impl CommandTrait for EchoCommand {
+        type A = String;
+        type B = String;
+        type C = String;
+        type D = EmptyArg;
+        type E = EmptyArg;
+        type F = EmptyArg;
+        type G = EmptyArg;
+        type H = EmptyArg;
+        type I = EmptyArg;
+        type FlagArgs = EmptyArg;
+        type Context = i64;
+        const NAME: &'static str = "echo";
+        const PLACEHOLDERS: &[&'static str] = &["<a>", "<b>", "<c>"];
+
+        fn from_arguments(
+            a: Option<String>,
+            b: Option<String>,
+            c: Option<String>,
+            _d: Option<EmptyArg>,
+            _e: Option<EmptyArg>,
+            _f: Option<EmptyArg>,
+            _g: Option<EmptyArg>,
+            _h: Option<EmptyArg>,
+            _i: Option<EmptyArg>,
+        ) -> Self {
+            Self(a, b, c)
+        }
+
+        fn param1(&self) -> Option<&String> {
+            self.0.as_ref()
+        }
+
+        fn param2(&self) -> Option<&String> {
+            self.1.as_ref()
+        }
+
+        fn param3(&self) -> Option<&String> {
+            self.2.as_ref()
+        }
+    }
+
+    #[test]
+    fn test_flag_like_arguments() {
+        // The command without flags takes the dashed arguments as positional
+        let (echo,) = EchoCommand::parse_arguments("--x".to_string()).unwrap();
+        assert_eq!(echo.0.as_deref(), Some("--x"));
+        let (echo,) = EchoCommand::parse_arguments("\"--x\" y".to_string()).unwrap();
+        assert_eq!((echo.0.as_deref(), echo.1.as_deref()), (Some("--x"), Some("y")));
+        let (echo,) = EchoCommand::parse_arguments("a -- b".to_string()).unwrap();
+        assert_eq!(
+            (echo.0.as_deref(), echo.1.as_deref(), echo.2.as_deref()),
+            (Some("a"), Some("--"), Some("b"))
+        );
+
+        let echo = EchoCommand(Some("--dry-run".to_string()), Some("-5".to_string()), None);
+        let command = echo.to_command_string(false);
+        assert_eq!(command, "/echo \"--dry-run\" \"-5\" ");
+        let (parsed,) = EchoCommand::parse_arguments(command["/echo ".len()..].to_string()).unwrap();
+        assert_eq!((parsed.0, parsed.1), (echo.0, echo.1));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_command_registry() {
+        let api = MockBotApi::start().await;
+        let registry = CommandRegistry::new()
+            .register::<AddCommand>("add two numbers")
+            .register::<NoteCommand>("")
+            .command("ban", "ban the user", |_, _, _| async { Ok(()) })
+            .scopes([BotCommandScope::AllChatAdministrators])
+            .command("pressed", "", |_, _, _| async { Ok(()) });
+        assert_eq!(registry.commands()[0], ("add", "add two numbers"));
+
+        registry.sync_bot_commands(&api.bot()).await.unwrap();
+        let default_menu = api.next_request("setMyCommands").await.unwrap();
+        assert_eq!(default_menu.params["commands"][0]["command"], "add");
+        assert_eq!(default_menu.params["commands"].as_array().unwrap().len(), 1);
+        let admin_menu = api.next_request("setMyCommands").await.unwrap();
+        assert_eq!(admin_menu.params["scope"]["type"], "all_chat_administrators");
+        assert_eq!(admin_menu.params["commands"][0]["command"], "ban");
+
+        let mut dispatcher = Dispatcher::builder(api.bot(), registry.handler(100)).build();
+        let dispatcher_task = tokio::spawn(async move { dispatcher.dispatch().await });
+
+        api.send_text(ChatId(1), "/add 2 3").await;
+        let reply = api.next_request("sendMessage").await.unwrap();
+        assert_eq!(reply.str_param("text"), Some("105"));
+
+        api.send_text(ChatId(1), "/add 2").await;
+        let reply = api.next_request("sendMessage").await.unwrap();
+        assert!(reply.str_param("text").unwrap().contains("Missing required argument <b\\>"));
+
+        api.send_text(ChatId(1), "/add two").await;
+        let reply = api.next_request("sendMessage").await.unwrap();
+        assert!(reply.str_param("text").unwrap().ends_with("Usage: /add <a\\> <b\\>"));
+
+        api.send_text(ChatId(1), "/note secret").await;
+        let reply = api.next_request("sendMessage").await.unwrap();
+        assert_eq!(reply.str_param("text"), Some("You are not allowed to use this command"));
+
+        dispatcher_task.abort();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_command_button() {
+        let api = MockBotApi::start().await;
+        let registry = CommandRegistry::new()
+            .register::<AddCommand>("add two numbers")
+            .register::<NoteCommand>("")
+            .command("menu", "", |target, _, _| async move {
+                let note = NoteCommand(Some(RestArg(
+                    "the note which doesn't fit into the callback data of the button".to_string(),
+                )));
+                let buttons = [[
+                    pack_command_button("2 + 3", &AddCommand(Some(2), Some(Req(3)))),
+                    pack_command_button("Note", &note),
+                ]];
+                target.markdown_message_with_menu(markdown_string!("Menu"), buttons).await?;
+                Ok(())
+            });
+        let mut dispatcher = Dispatcher::builder(api.bot(), registry.handler(100)).build();
+        let dispatcher_task = tokio::spawn(async move { dispatcher.dispatch().await });
+
+        api.send_text(ChatId(1), "/menu").await;
+        let menu = api.next_request("editMessageReplyMarkup").await.unwrap();
+        let callbacks = menu.keyboard_callbacks();
+        assert_eq!(callbacks[0][0], "/add 2 3");
+        assert!(callbacks[0][1].starts_with("cb:"));
+
+        // The command's reply replaces the menu
+        api.press_button(ChatId(1), menu.message_id.unwrap(), &callbacks[0][0]).await;
+        let reply = api.next_request("editMessageText").await.unwrap();
+        assert_eq!(reply.str_param("text"), Some("105"));
+
+        // The long command is restored from the storage, the denial is shown as an alert
+        api.press_button(ChatId(1), menu.message_id.unwrap(), &callbacks[0][1]).await;
+        let answer = loop {
+            let answer = api.next_request("answerCallbackQuery").await.unwrap();
+            if answer.str_param("text").is_some() {
+                break answer;
+            }
+        };
+        assert_eq!(answer.str_param("text"), Some("You are not allowed to use this command"));
+
+        dispatcher_task.abort();
+
+        let storage: Arc<dyn CallbackDataStorageTrait> =
+            Arc::new(CallbackDataStorage::new(Arc::new(InMemStore::new()), ChatId(1)));
+        let add = parse_command_callback::<AddCommand>(&storage, "/add 2 3").await;
+        assert_eq!(add.unwrap().unwrap().param2().map(|b| **b), Some(3));
+        assert!(parse_command_callback::<NoteCommand>(&storage, "/add 2 3").await.is_none());
+        assert!(parse_command_callback::<AddCommand>(&storage, "/add two").await.unwrap().is_err());
+    }
+}
@@ -1,61 +1,672 @@
-use std::sync::Arc;
+use std::{collections::BTreeMap, hash::{Hash, Hasher}, sync::Arc, time::{Duration, SystemTime}};
 
-use teloxide::{Bot, payloads::{EditMessageReplyMarkupSetters, SendMessage}, prelude::{Requester, ResponseResult}, requests::JsonRequest, types::{Chat, Message, MessageId}};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
 
-use crate::{api::{command::command_button::{ButtonData, CallbackDataStorageTrait, pack_callback_data}, markdown::string::MarkdownString}, markdown::MarkdownStringMessage};
+use teloxide::{ApiError, Bot, RequestError, payloads::{AnswerCallbackQuerySetters, EditMessageReplyMarkupSetters, EditMessageTextInlineSetters, EditMessageTextSetters, SendDocumentSetters, SendMediaGroupSetters, SendMessage, SendMessageSetters, SendPhotoSetters, SendPollSetters}, prelude::{Requester, ResponseResult}, requests::{JsonRequest, Output, Payload, Request}, types::{CallbackQueryId, Chat, ChatId, ChatKind, ChatPrivate, ChatPublic, InlineKeyboardMarkup, InputFile, InputMedia, InputPollOption, LinkPreviewOptions, Message, MessageId, ParseMode, PollType, PublicChatChannel, PublicChatKind, ReplyParameters, User, UserId}};
 
+use crate::{api::{command::{authorize::UserAllowlist, command_button::{ButtonData, CallbackDataStorageTrait, pack_callback_data}, confirm::ConfirmRegistry, last_message_tracker::LastMessageTracker, outgoing_middleware::{OutgoingMiddleware, apply_middlewares}, pending_operation::OperationRegistry, poll::{POLL_EXPLANATION_MAX_LENGTH, POLL_MAX_OPTIONS, POLL_OPTION_MAX_LENGTH, POLL_QUESTION_MAX_LENGTH, PollRecord, PollSettings, PollTracker}, prompt::PromptRegistry, wizard::CommandWizard}, data_store::data_store_trait::DataStoreTrait, markdown::{caption::MarkdownCaption, string::{MarkdownString, TELEGRAM_MAX_MESSAGE_LENGTH}}, rate_limit::rate_limiter::RateLimiter, retry::retry_policy::RetryPolicy, schedule::message_scheduler::MessageScheduler}, markdown::MarkdownStringMessage, markdown_format, markdown_string};
+
+
+/// Apply the reply, notification and content protection options of the target
+/// to a send request. Works with any payload having the corresponding setters.
+macro_rules! apply_send_options {
+    ($target:expr, $request:expr) => {{
+        let mut request = $request;
+        if let Some(reply_to) = $target.reply_to {
+            request = request.reply_parameters(
+                ReplyParameters::new(reply_to).allow_sending_without_reply(),
+            );
+        }
+        if $target.disable_notification {
+            request = request.disable_notification(true);
+        }
+        if $target.protect_content {
+            request = request.protect_content(true);
+        }
+        request
+    }};
+}
+
+/// The last rendered state of a message sent or edited through [`CommandReplyTarget`]
+/// Used to skip edits which wouldn't change the message
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RenderedMessage {
+    /// The markdown text of the message
+    pub text: String,
+    /// The inline keyboard attached to the message
+    pub reply_markup: Option<InlineKeyboardMarkup>,
+    /// The message as returned by Telegram
+    pub message: Message,
+}
+
+/// What to do when the current message of the target can't be edited
+/// because it was deleted, is too old or is not found
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EditFailurePolicy {
+    /// Return the error to the caller
+    #[default]
+    Fail,
+    /// Send the text as a new message
+    Resend,
+    /// Send the text as a new message and forget the old one,
+    /// clearing its stored callback data and rendered state
+    Replace,
+}
 
 #[derive(Clone)]
 pub struct CommandReplyTarget {
     pub bot: Bot,
+    /// The name of the bot in multi-bot deployments, `None` for the app's main bot
+    pub bot_name: Option<String>,
+    /// The default locale of the bot's messages, e.g. for the [`t!`](crate::t!) macro
+    pub locale: Option<String>,
+    /// The feature toggles of the bot, see [`feature`](Self::feature)
+    pub features: Arc<BTreeMap<String, bool>>,
     pub chat: Chat,
+    /// The user who triggered the command, if known
+    pub user_id: Option<UserId>,
     pub msg_id: Option<MessageId>,
+    /// The inline message to edit instead of the chat's messages, for commands triggered from inline mode
+    pub inline_message_id: Option<String>,
+    /// Accumulate markdown messages until [`flush`](Self::flush) instead of sending them one by one
     pub batch: bool,
+    /// Send long multipart texts as numbered continuation messages instead of truncating them
+    pub continuation_messages: bool,
+    /// Resend markdown messages rejected by Telegram's parser as escaped plain text
+    pub plain_text_fallback: bool,
+    /// What to do when the current message can't be edited
+    pub edit_failure_policy: EditFailurePolicy,
     pub callback_data_storage: Arc<dyn CallbackDataStorageTrait>,
+    /// The callback query which triggered the command, if any
+    pub callback_query_id: Option<CallbackQueryId>,
+    /// The message which new messages are sent as a reply to, if any
+    pub reply_to: Option<MessageId>,
+    /// Send new messages without notification sound
+    pub disable_notification: bool,
+    /// Protect new messages from forwarding and saving
+    pub protect_content: bool,
+    /// Link preview generation options for sent and edited messages
+    pub link_preview_options: Option<LinkPreviewOptions>,
+    /// Storage of the last rendered state of the messages, keyed by message id
+    /// If set, edits which wouldn't change the message are skipped
+    pub rendered_messages: Option<Arc<dyn DataStoreTrait<RenderedMessage>>>,
+    /// Policy for retrying requests rejected by Telegram's flood control
+    pub retry_policy: Option<RetryPolicy>,
+    /// Limiter of the outgoing messages rate shared by all targets of the bot,
+    /// the edits, the deletions and the other requests not creating messages are not limited
+    pub rate_limiter: Option<RateLimiter>,
+    /// Tracker of the bot's last message in the chat, updated on each new message sent by the target
+    pub last_message_tracker: Option<LastMessageTracker>,
+    /// Interceptors applied to the texts and captions of all messages sent or edited by the async methods
+    pub middlewares: Vec<Arc<dyn OutgoingMiddleware>>,
+    /// Tracker of the sent polls, used to aggregate the answers and to stop the polls later
+    pub poll_tracker: Option<PollTracker>,
+    /// Registry of the questions waiting for the user's answer, required for [`prompt`](Self::prompt)
+    pub prompt_registry: Option<PromptRegistry>,
+    /// Guided input of the missing command arguments, see [`CommandWizard`]
+    pub command_wizard: Option<CommandWizard>,
+    /// Users allowed to run the commands checked by [`require_allowed_user`](Self::require_allowed_user)
+    pub user_allowlist: Option<UserAllowlist>,
+    /// Registry of the confirmation dialogs, required to await the answer to [`confirm`](Self::confirm)
+    pub confirm_registry: Option<ConfirmRegistry>,
+    /// Registry of the operations in progress, required for `/cancel`, see [`start_operation`](Self::start_operation)
+    pub operation_registry: Option<OperationRegistry>,
+    /// Scheduler persisting the deletions of the messages sent by [`send_ephemeral`](Self::send_ephemeral)
+    pub message_scheduler: Option<MessageScheduler>,
+    // Markdown messages accumulated in batch mode, shared by the clones of the target
+    batch_buffer: Arc<Mutex<Vec<MarkdownString>>>,
 }
 
+/// Maximum length of the callback query answer text allowed by Telegram Bot API
+/// See: https://core.telegram.org/bots/api#answercallbackquery
+const CALLBACK_ANSWER_MAX_LENGTH: usize = 200;
+
+/// Maximum number of items in a media group allowed by Telegram Bot API
+/// See: https://core.telegram.org/bots/api#sendmediagroup
+const MEDIA_GROUP_MAX_SIZE: usize = 10;
+
+/// Space reserved in each continuation message for its number, e.g. "(2/3)"
+const CONTINUATION_NUMBER_RESERVE: usize = 32;
+
+/// Number of the last messages of each chat whose rendered state is tracked
+const RENDERED_MESSAGES_PER_CHAT: usize = 100;
+
 impl CommandReplyTarget {
+    /// Create a target for the given chat and, optionally, the message to edit
+    pub fn new(
+        bot: Bot,
+        chat: Chat,
+        msg_id: Option<MessageId>,
+        callback_data_storage: Arc<dyn CallbackDataStorageTrait>,
+    ) -> Self {
+        Self {
+            bot,
+            bot_name: None,
+            locale: None,
+            features: Arc::default(),
+            chat,
+            user_id: None,
+            msg_id,
+            inline_message_id: None,
+            batch: false,
+            continuation_messages: false,
+            plain_text_fallback: false,
+            edit_failure_policy: EditFailurePolicy::Fail,
+            callback_data_storage,
+            callback_query_id: None,
+            reply_to: None,
+            disable_notification: false,
+            protect_content: false,
+            link_preview_options: None,
+            rendered_messages: None,
+            retry_policy: None,
+            rate_limiter: None,
+            last_message_tracker: None,
+            middlewares: Vec::new(),
+            poll_tracker: None,
+            prompt_registry: None,
+            command_wizard: None,
+            user_allowlist: None,
+            confirm_registry: None,
+            operation_registry: None,
+            message_scheduler: None,
+            batch_buffer: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Create a target for the message sent via inline mode on behalf of the user,
+    /// e.g. from a chosen inline result or a callback query on an inline message
+    /// Markdown messages and menus edit the inline message, as Telegram doesn't return it,
+    /// the methods editing it return `None` instead of the message
+    pub fn new_inline(
+        bot: Bot,
+        user: &User,
+        inline_message_id: impl Into<String>,
+        callback_data_storage: Arc<dyn CallbackDataStorageTrait>,
+    ) -> Self {
+        let chat = Chat {
+            id: user.id.into(),
+            kind: ChatKind::Private(ChatPrivate {
+                username: user.username.clone(),
+                first_name: Some(user.first_name.clone()),
+                last_name: user.last_name.clone(),
+            }),
+        };
+        let mut target = Self::new(bot, chat, None, callback_data_storage);
+        target.inline_message_id = Some(inline_message_id.into());
+        target
+    }
+
+    /// Create a target for the channel administered by the bot, without an incoming message
+    /// Used to publish posts to the channel and to edit them later by passing the post's message id
+    pub fn for_channel(
+        bot: Bot,
+        channel_id: ChatId,
+        post_id: Option<MessageId>,
+        callback_data_storage: Arc<dyn CallbackDataStorageTrait>,
+    ) -> Self {
+        let chat = Chat {
+            id: channel_id,
+            kind: ChatKind::Public(ChatPublic {
+                title: None,
+                kind: PublicChatKind::Channel(PublicChatChannel { username: None }),
+            }),
+        };
+        Self::new(bot, chat, post_id, callback_data_storage)
+    }
+
+    /// Accumulate markdown messages instead of sending them immediately
+    /// The accumulated messages are sent by [`flush`](Self::flush) joined into as few messages as possible.
+    /// The commands run by the [`CommandRegistry`](crate::command::CommandRegistry) and the text handler
+    /// of [`BotApp`](crate::app::BotApp) are flushed when they finish, in other handlers
+    /// the messages not flushed before the target is dropped are lost.
+    pub fn batched(mut self) -> Self {
+        self.batch = true;
+        self
+    }
+
+    /// Split texts exceeding Telegram's message length limit into numbered continuation messages
+    /// instead of truncating them with "...", see [`markdown_message_parts`](Self::markdown_message_parts)
+    pub fn with_continuation_messages(mut self) -> Self {
+        self.continuation_messages = true;
+        self
+    }
+
+    /// Track the last rendered state of the messages in the given storage
+    /// to skip edits which wouldn't change the message
+    /// and to avoid the "message is not modified" errors.
+    /// Only the last 100 messages of each chat are tracked
+    pub fn track_rendered_messages(
+        mut self,
+        rendered_messages: Arc<dyn DataStoreTrait<RenderedMessage>>,
+    ) -> Self {
+        self.rendered_messages = Some(rendered_messages);
+        self
+    }
+
+    /// Retry requests rejected by Telegram's flood control according to the given policy
+    /// Applies to all requests sent by the target's async methods
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(retry_policy);
+        self
+    }
+
+    /// Limit the rate of new messages to the chat and in total with the given limiter
+    /// The same limiter should be shared by all targets of the bot. Telegram limits the messages,
+    /// so the edits, the callback answers and the chat actions are sent without waiting.
+    pub fn with_rate_limiter(mut self, rate_limiter: RateLimiter) -> Self {
+        self.rate_limiter = Some(rate_limiter);
+        self
+    }
+
+    /// Track the bot's last message in the chat with the given tracker
+    /// Required for [`edit_or_send`](Self::edit_or_send)
+    pub fn with_last_message_tracker(mut self, last_message_tracker: LastMessageTracker) -> Self {
+        self.last_message_tracker = Some(last_message_tracker);
+        self
+    }
+
+    /// Record the polls sent by [`send_poll`](Self::send_poll) with the given tracker
+    pub fn with_poll_tracker(mut self, poll_tracker: PollTracker) -> Self {
+        self.poll_tracker = Some(poll_tracker);
+        self
+    }
+
+    /// Set the name of the bot sending the messages, see [`bot_name`](Self::bot_name)
+    pub fn with_bot_name(mut self, bot_name: impl Into<String>) -> Self {
+        self.bot_name = Some(bot_name.into());
+        self
+    }
+
+    /// Await the answers to the questions asked by [`prompt`](Self::prompt) with the given registry
+    pub fn with_prompt_registry(mut self, prompt_registry: PromptRegistry) -> Self {
+        self.prompt_registry = Some(prompt_registry);
+        self
+    }
+
+    /// Ask for the arguments missing in the commands registered with
+    /// [`register_wizard`](crate::command::CommandRegistry::register_wizard) with the given wizard
+    pub fn with_command_wizard(mut self, command_wizard: CommandWizard) -> Self {
+        self.command_wizard = Some(command_wizard);
+        self
+    }
+
+    /// Check the users of the restricted commands against the given allowlist,
+    /// see [`require_allowed_user`](Self::require_allowed_user)
+    pub fn with_user_allowlist(mut self, user_allowlist: UserAllowlist) -> Self {
+        self.user_allowlist = Some(user_allowlist);
+        self
+    }
+
+    /// Await the answers to the dialogs sent by [`confirm`](Self::confirm) with the given registry
+    pub fn with_confirm_registry(mut self, confirm_registry: ConfirmRegistry) -> Self {
+        self.confirm_registry = Some(confirm_registry);
+        self
+    }
+
+    /// Register the wizards, the confirmation dialogs and the long-running commands
+    /// in the given registry, so that `/cancel` can abort them
+    pub fn with_operation_registry(mut self, operation_registry: OperationRegistry) -> Self {
+        self.operation_registry = Some(operation_registry);
+        self
+    }
+
+    /// Delete the messages sent by [`send_ephemeral`](Self::send_ephemeral) with the given scheduler,
+    /// so that the deletions survive a restart
+    pub fn with_message_scheduler(mut self, message_scheduler: MessageScheduler) -> Self {
+        self.message_scheduler = Some(message_scheduler);
+        self
+    }
+
+    /// Check if the feature is enabled, unknown features are disabled
+    pub fn feature(&self, name: &str) -> bool {
+        self.features.get(name).copied().unwrap_or(false)
+    }
+
+    /// If Telegram can't parse the markdown of a message, e.g. due to a bug in a template,
+    /// log the error and send the message as escaped plain text instead of failing
+    pub fn with_plain_text_fallback(mut self) -> Self {
+        self.plain_text_fallback = true;
+        self
+    }
+
+    /// Set what to do when the current message can't be edited
+    pub fn on_edit_failure(mut self, policy: EditFailurePolicy) -> Self {
+        self.edit_failure_policy = policy;
+        self
+    }
+
+    /// Add the middleware to the end of the outgoing messages interceptor chain
+    pub fn with_middleware(mut self, middleware: impl OutgoingMiddleware + 'static) -> Self {
+        self.middlewares.push(Arc::new(middleware));
+        self
+    }
+
+    /// Send new messages as a reply to the given message, e.g. the message which triggered the command
+    /// Edits of the current message are not affected
+    pub fn reply_to(mut self, message_id: MessageId) -> Self {
+        self.reply_to = Some(message_id);
+        self
+    }
+
+    /// Send new messages silently, users will receive a notification with no sound
+    pub fn silent(mut self) -> Self {
+        self.disable_notification = true;
+        self
+    }
+
+    /// Protect the contents of new messages from forwarding and saving
+    pub fn protect_content(mut self) -> Self {
+        self.protect_content = true;
+        self
+    }
+
+    /// Set link preview generation options for sent and edited messages
+    pub fn link_preview_options(mut self, options: LinkPreviewOptions) -> Self {
+        self.link_preview_options = Some(options);
+        self
+    }
+
+    /// Disable link previews in sent and edited messages
+    pub fn disable_link_preview(self) -> Self {
+        self.update_link_preview_options(|options| options.is_disabled = true)
+    }
+
+    /// Prefer small media in link previews
+    pub fn link_preview_small_media(self) -> Self {
+        self.update_link_preview_options(|options| {
+            options.prefer_small_media = true;
+            options.prefer_large_media = false;
+        })
+    }
+
+    /// Generate the link preview for the given URL instead of the first URL in the message
+    pub fn link_preview_url(self, url: impl Into<String>) -> Self {
+        let url = url.into();
+        self.update_link_preview_options(|options| options.url = Some(url))
+    }
+
+    /// Internal helper function to modify link preview options starting from the defaults
+    fn update_link_preview_options(mut self, update: impl FnOnce(&mut LinkPreviewOptions)) -> Self {
+        let mut options = self.link_preview_options.take().unwrap_or(LinkPreviewOptions {
+            is_disabled: false,
+            url: None,
+            prefer_small_media: false,
+            prefer_large_media: false,
+            show_above_text: false,
+        });
+        update(&mut options);
+        self.link_preview_options = Some(options);
+        self
+    }
+
     /// Send a new or edit a current markdown message without a menu
-    pub async fn markdown_message(&self, text: MarkdownString) -> ResponseResult<Message> {
-        if let Some(message_id) = self.msg_id {
-            self.bot.edit_markdown_message_text(self.chat.id, message_id, text)
-                .await
+    /// If the rendered messages are tracked, editing the message to the same text is skipped
+    /// In batch mode the text is only buffered until [`flush`](Self::flush) and `None` is returned,
+    /// `None` is also returned if the message was dropped by a middleware or the target is an inline message.
+    /// Without the tracking Telegram's "message is not modified" error is ignored and `None` is returned
+    pub async fn markdown_message(&self, text: MarkdownString) -> ResponseResult<Option<Message>> {
+        if self.batch {
+            self.batch_buffer.lock().await.push(text);
+            return Ok(None);
+        }
+        self.render_markdown_message(text).await
+    }
+
+    /// Send the markdown messages accumulated in batch mode
+    /// The messages are joined with line breaks into as few Telegram messages as the length limit allows.
+    /// The first message replaces the current message of the target if present, the rest are sent as new ones.
+    /// In continuation mode the messages are numbered.
+    pub async fn flush(&self) -> ResponseResult<Vec<Message>> {
+        let texts = std::mem::take(&mut *self.batch_buffer.lock().await);
+        self.send_packed(texts).await
+    }
+
+    /// Send a long text consisting of several parts, e.g. lines of a log or items of a listing
+    /// In continuation mode the parts are packed into as few messages as possible, each numbered as "(2/3)".
+    /// The text is split between the parts, the parts too long for one message are split
+    /// with [`MarkdownString::split_for_sending`], so formatting entities are never broken.
+    /// Otherwise the parts are joined into a single message truncated with "..." if too long.
+    /// In batch mode the parts are buffered until [`flush`](Self::flush).
+    pub async fn markdown_message_parts(
+        &self,
+        parts: impl IntoIterator<Item = MarkdownString>,
+    ) -> ResponseResult<Vec<Message>> {
+        if self.batch {
+            self.batch_buffer.lock().await.extend(parts);
+            return Ok(Vec::new());
+        }
+        if self.continuation_messages {
+            return self.send_packed(parts.into_iter().collect()).await;
+        }
+        let mut text = MarkdownString::new();
+        for (i, part) in parts.into_iter().enumerate() {
+            if i > 0 {
+                text.push(&markdown_string!("\n"));
+            }
+            text.push(&part);
+        }
+        Ok(self.render_markdown_message(text).await?.into_iter().collect())
+    }
+
+    /// Internal helper function to pack the texts into as few messages as possible and send them
+    /// The first message replaces the current message of the target if present
+    async fn send_packed(&self, texts: Vec<MarkdownString>) -> ResponseResult<Vec<Message>> {
+        let reserve = if self.continuation_messages {
+            CONTINUATION_NUMBER_RESERVE
+        } else {
+            0
+        };
+        let max_length = TELEGRAM_MAX_MESSAGE_LENGTH - reserve;
+        let texts = texts.iter().flat_map(|text| text.split(max_length)).collect();
+        let packed = pack_batch(texts, max_length);
+        let count = packed.len();
+        let mut target = self.clone();
+        let mut messages = Vec::new();
+        for (i, text) in packed.into_iter().enumerate() {
+            let text = if self.continuation_messages && count > 1 {
+                number_continuation(text, i + 1, count)
+            } else {
+                text
+            };
+            messages.extend(target.render_markdown_message(text).await?);
+            target.msg_id = None;
+        }
+        Ok(messages)
+    }
+
+    /// Internal helper function to send a new or edit a current markdown message bypassing the batch buffer
+    /// Returns `None` if the message was dropped by a middleware or an edit without the rendered state didn't change it
+    pub(crate) async fn render_markdown_message(
+        &self,
+        text: MarkdownString,
+    ) -> ResponseResult<Option<Message>> {
+        let Some(text) = self.process_outgoing(text).await else {
+            return Ok(None);
+        };
+        match self.send_or_edit_markdown_message(text.clone()).await {
+            Err(RequestError::Api(ApiError::CantParseEntities(error))) if self.plain_text_fallback => {
+                log::error!(
+                    "Telegram can't parse markdown message ({}), sending it as plain text: {}",
+                    error,
+                    text
+                );
+                let plain_text = MarkdownString::escape(text.to_plain_text());
+                self.send_or_edit_markdown_message(plain_text).await
+            }
+            result => result,
+        }
+    }
+
+    /// Internal helper function to send a new or edit a current markdown message
+    /// and remember its rendered state
+    async fn send_or_edit_markdown_message(
+        &self,
+        text: MarkdownString,
+    ) -> ResponseResult<Option<Message>> {
+        if let Some(inline_message_id) = &self.inline_message_id {
+            self.edit_inline_message(inline_message_id, text, None).await?;
+            return Ok(None);
+        }
+        let msg = if let Some(message_id) = self.msg_id {
+            let rendered = self.rendered_message(message_id).await;
+            if let Some(rendered) = &rendered
+                && rendered.text == text.as_str()
+            {
+                return Ok(Some(rendered.message.clone()));
+            }
+            let mut request = self
+                .bot
+                .edit_markdown_message_text(self.chat.id, message_id, text.clone());
+            if let Some(options) = &self.link_preview_options {
+                request = request.link_preview_options(options.clone());
+            }
+            match (self.send_request(request).await, rendered) {
+                (Err(RequestError::Api(ApiError::MessageNotModified)), Some(rendered)) => {
+                    rendered.message
+                }
+                // Without the rendered state there is no message to return, the edit is a no-op anyway
+                (Err(RequestError::Api(ApiError::MessageNotModified)), None) => return Ok(None),
+                (Err(err), _)
+                    if is_edit_failure(&err) && self.edit_failure_policy != EditFailurePolicy::Fail =>
+                {
+                    log::warn!(
+                        "Can't edit message {} ({}), sending a new one",
+                        message_id,
+                        err
+                    );
+                    let msg = self
+                        .send_new_message(self.send_markdown_message(text.clone()))
+                        .await?;
+                    if self.edit_failure_policy == EditFailurePolicy::Replace {
+                        self.forget_message(message_id).await;
+                    }
+                    msg
+                }
+                (result, _) => result?,
+            }
         } else {
-            self.bot.send_markdown_message(self.chat.id, text)
-                .await
+            self.send_new_message(self.send_markdown_message(text.clone())).await?
+        };
+        self.remember_rendered_message(Some(&text), &msg).await;
+        Ok(Some(msg))
+    }
+
+    /// Internal helper function to edit the inline message, replacing its menu if given
+    async fn edit_inline_message(
+        &self,
+        inline_message_id: &str,
+        text: MarkdownString,
+        menu: Option<InlineKeyboardMarkup>,
+    ) -> ResponseResult<()> {
+        let mut request = self
+            .bot
+            .edit_markdown_message_text_inline(inline_message_id, text);
+        // Inline messages support only disabling the link preview
+        if self
+            .link_preview_options
+            .as_ref()
+            .is_some_and(|options| options.is_disabled)
+        {
+            request = request.disable_web_page_preview(true);
+        }
+        if let Some(menu) = menu {
+            request = request.reply_markup(menu);
+        }
+        match self.send_request(request).await {
+            Ok(_) | Err(RequestError::Api(ApiError::MessageNotModified)) => Ok(()),
+            Err(err) => Err(err),
         }
     }
 
+    /// Edit the bot's last message in the chat or send a new one if there is no last message
+    /// or it can't be edited anymore (deleted, too old). Keeps a single status message in the chat.
+    /// Requires the last message tracker, without it behaves as [`markdown_message`](Self::markdown_message)
+    pub async fn edit_or_send(&self, text: MarkdownString) -> ResponseResult<Option<Message>> {
+        let last_message_id = match &self.last_message_tracker {
+            Some(tracker) => tracker.get(self.chat.id).await,
+            None => None,
+        };
+        let Some(last_message_id) = last_message_id else {
+            return self.render_markdown_message(text).await;
+        };
+        let mut target = self.clone();
+        target.msg_id = Some(last_message_id);
+        match target.render_markdown_message(text.clone()).await {
+            Err(err) if is_edit_failure(&err) => {
+                target.msg_id = None;
+                target.render_markdown_message(text).await
+            }
+            result => result,
+        }
+    }
+
+    /// Ask the question and wait for the next text message of the target's user in the chat
+    /// The question is sent as a new message or replaces the current message of the target.
+    /// Returns `None` if there was no answer before the timeout, if the question was replaced by a newer one
+    /// or if it was dropped by a middleware. Without the prompt registry the answer is not awaited.
+    pub async fn prompt(
+        &self,
+        text: MarkdownString,
+        timeout: Duration,
+    ) -> ResponseResult<Option<Message>> {
+        let Some(question) = self.render_markdown_message(text).await? else {
+            return Ok(None);
+        };
+        let Some(prompt_registry) = &self.prompt_registry else {
+            log::warn!("Prompt registry is not set, the answer can't be awaited");
+            return Ok(None);
+        };
+        Ok(prompt_registry
+            .wait_answer(self.bot.token(), self.chat.id, self.user_id, question.id, timeout)
+            .await)
+    }
+
     /// Send a new or edit a current markdown message with an inline keyboard menu
     /// The menu is automatically packed using pack_callback_data to handle long callback data
+    /// In batch mode the buffered messages are flushed first and the menu is sent as a new message after them
     pub async fn markdown_message_with_menu<R, B>(
         &self,
         text: MarkdownString,
         menu: impl IntoIterator<Item = R>,
-    ) -> ResponseResult<Message>
+    ) -> ResponseResult<Option<Message>>
     where
         R: IntoIterator<Item = B>,
         B: Into<ButtonData>,
     {
-        let msg = self
-            .markdown_message(text)
-            .await?;
-
-        Self::attach_menu_to_message(
-            &self.bot,
-            &self.callback_data_storage,
-            self.chat.id,
-            msg.id,
-            menu,
-        )
-        .await?;
+        if let Some(inline_message_id) = &self.inline_message_id {
+            let Some(text) = self.process_outgoing(text).await else {
+                return Ok(None);
+            };
+            let menu = pack_callback_data(
+                &self.callback_data_storage,
+                inline_message_callbacks_id(inline_message_id),
+                menu,
+            )
+            .await;
+            self.edit_inline_message(inline_message_id, text, Some(menu)).await?;
+            return Ok(None);
+        }
+        // The messages buffered in batch mode are sent first, the menu follows them as a new message
+        let mut target = self.clone();
+        if self.batch && !self.flush().await?.is_empty() {
+            target.msg_id = None;
+        }
+        let Some(msg) = target.render_markdown_message(text).await? else {
+            return Ok(None);
+        };
 
-        Ok(msg)
+        self.attach_menu_to_message(msg.id, menu).await.map(Some)
     }
 
     /// Send a new markdown message without a menu
     pub fn send_markdown_message(&self, text: MarkdownString) -> JsonRequest<SendMessage> {
-        self.bot.send_markdown_message(self.chat.id, text)
+        let mut request = apply_send_options!(self, self.bot.send_markdown_message(self.chat.id, text));
+        if let Some(options) = &self.link_preview_options {
+            request = request.link_preview_options(options.clone());
+        }
+        request
     }
 
     /// Send a markdown message with an inline keyboard menu using a request builder
@@ -64,43 +675,769 @@ impl CommandReplyTarget {
         &self,
         text: MarkdownString,
         menu: impl IntoIterator<Item = R>,
-    ) -> ResponseResult<Message>
+    ) -> ResponseResult<Option<Message>>
     where
         R: IntoIterator<Item = B>,
         B: Into<ButtonData>,
     {
-        let msg = self.bot.send_markdown_message(self.chat.id, text).await?;
-
-        Self::attach_menu_to_message(
-            &self.bot,
-            &self.callback_data_storage,
-            self.chat.id,
-            msg.id,
-            menu,
-        )
-        .await?;
+        // The messages buffered in batch mode are sent first
+        if self.batch {
+            self.flush().await?;
+        }
+        let mut target = self.clone();
+        target.msg_id = None;
+        let Some(msg) = target.render_markdown_message(text).await? else {
+            return Ok(None);
+        };
+
+        self.attach_menu_to_message(msg.id, menu).await.map(Some)
+    }
 
+    /// Send a document with a markdown caption
+    /// The document can be created from memory with `InputFile::memory` or from a file with `InputFile::file`.
+    /// The caption is limited to Telegram's 1024 characters caption limit
+    pub async fn send_document(
+        &self,
+        document: InputFile,
+        filename: impl Into<String>,
+        caption: MarkdownString,
+    ) -> ResponseResult<Option<Message>> {
+        let Some(caption) = self.process_outgoing(caption).await else {
+            return Ok(None);
+        };
+        let caption = MarkdownCaption::from(caption);
+        let request = self
+            .bot
+            .send_document(self.chat.id, document.file_name(filename.into()))
+            .caption(caption)
+            .parse_mode(ParseMode::MarkdownV2);
+        self.send_new_message(apply_send_options!(self, request)).await.map(Some)
+    }
+
+    /// Send the markdown message if it fits into Telegram's 4096 characters limit,
+    /// otherwise attach the full text as a document with a short caption instead of truncating it
+    /// The document contains the markdown source if the filename ends with `.md`, and the plain text otherwise.
+    pub async fn markdown_or_document(
+        &self,
+        text: MarkdownString,
+        filename: impl Into<String>,
+    ) -> ResponseResult<Option<Message>> {
+        if text.len_utf16() <= TELEGRAM_MAX_MESSAGE_LENGTH {
+            return self.markdown_message(text).await;
+        }
+        let filename = filename.into();
+        let content = if filename.ends_with(".md") {
+            text.into_string()
+        } else {
+            text.to_plain_text()
+        };
+        let caption = markdown_format!("The output is too long for a message, see {}", &filename);
+        self.send_document(InputFile::memory(content), filename, caption)
+            .await
+    }
+
+    /// Send a photo with a markdown caption
+    /// If `spoiler` is true, the photo is covered with a spoiler animation.
+    /// The caption is limited to Telegram's 1024 characters caption limit
+    pub async fn send_photo(
+        &self,
+        photo: InputFile,
+        caption: MarkdownString,
+        spoiler: bool,
+    ) -> ResponseResult<Option<Message>> {
+        let Some(caption) = self.process_outgoing(caption).await else {
+            return Ok(None);
+        };
+        let caption = MarkdownCaption::from(caption);
+        let mut request = self
+            .bot
+            .send_photo(self.chat.id, photo)
+            .caption(caption)
+            .parse_mode(ParseMode::MarkdownV2);
+        if spoiler {
+            request = request.has_spoiler(true);
+        }
+        self.send_new_message(apply_send_options!(self, request)).await.map(Some)
+    }
+
+    /// Send an album of photos, videos, audios or documents, each with its own markdown caption
+    /// Empty captions are omitted, others are limited to Telegram's 1024 characters caption limit.
+    /// Albums larger than Telegram's 10 items limit are split into several evenly sized media groups.
+    /// Note that Telegram requires at least 2 items in a media group.
+    /// Items whose captions are dropped by a middleware are not sent.
+    pub async fn send_album(
+        &self,
+        items: impl IntoIterator<Item = (InputMedia, MarkdownString)>,
+    ) -> ResponseResult<Vec<Message>> {
+        let mut media = Vec::new();
+        for (item, caption) in items {
+            if let Some(caption) = self.process_outgoing(caption).await {
+                media.push(with_markdown_caption(item, caption));
+            }
+        }
+        let mut messages = Vec::new();
+        for group_size in media_group_sizes(media.len()) {
+            let group: Vec<InputMedia> = media.drain(..group_size).collect();
+            let request = self.bot.send_media_group(self.chat.id, group);
+            messages.extend(self.send_request(apply_send_options!(self, request)).await?);
+        }
+        if let Some(msg) = messages.last() {
+            self.track_last_message(msg).await;
+        }
+        Ok(messages)
+    }
+
+    /// Send a poll with the given question and options
+    /// The question, the options and the explanation exceeding Telegram's length limits are truncated,
+    /// options above Telegram's 10 options limit are dropped.
+    /// If the poll tracker is set, the poll is recorded to aggregate the answers and to stop it later
+    pub async fn send_poll(
+        &self,
+        question: impl Into<String>,
+        options: impl IntoIterator<Item = impl Into<String>>,
+        settings: PollSettings,
+    ) -> ResponseResult<Message> {
+        let question = truncate_plain_text(&question.into(), POLL_QUESTION_MAX_LENGTH);
+        let mut options: Vec<String> = options
+            .into_iter()
+            .map(|option| truncate_plain_text(&option.into(), POLL_OPTION_MAX_LENGTH))
+            .collect();
+        if options.len() > POLL_MAX_OPTIONS {
+            log::warn!(
+                "Poll has {} options, only the first {} are sent",
+                options.len(),
+                POLL_MAX_OPTIONS
+            );
+            options.truncate(POLL_MAX_OPTIONS);
+        }
+        let mut request = self
+            .bot
+            .send_poll(
+                self.chat.id,
+                question,
+                options.iter().cloned().map(InputPollOption::new),
+            )
+            .is_anonymous(!settings.public);
+        if let Some(correct_option) = settings.quiz_correct_option {
+            request = request.type_(PollType::Quiz).correct_option_id(correct_option);
+        } else if settings.allows_multiple_answers {
+            request = request.allows_multiple_answers(true);
+        }
+        if let Some(explanation) = settings.explanation {
+            request = request.explanation(truncate_plain_text(&explanation, POLL_EXPLANATION_MAX_LENGTH));
+        }
+        if let Some(open_period) = settings.open_period {
+            request = request.open_period(open_period);
+        }
+        let msg = self.send_new_message(apply_send_options!(self, request)).await?;
+        if let Some(poll_tracker) = &self.poll_tracker
+            && let Some(poll) = msg.poll()
+        {
+            let record = PollRecord {
+                chat_id: self.chat.id,
+                message_id: msg.id,
+                options,
+                answers: Default::default(),
+            };
+            poll_tracker.track(&poll.id.0, record).await;
+        }
         Ok(msg)
     }
 
+    /// Remove the inline keyboard from the current message and clear its stored callback data
+    /// Does nothing if the target has no current message
+    pub async fn clear_menu(&self) -> ResponseResult<()> {
+        if let Some(inline_message_id) = &self.inline_message_id {
+            self.send_request(self.bot.edit_message_reply_markup_inline(inline_message_id))
+                .await?;
+            self.callback_data_storage
+                .clear_message_callbacks(inline_message_callbacks_id(inline_message_id))
+                .await;
+        } else if let Some(message_id) = self.msg_id {
+            let msg = self
+                .send_request(self.bot.edit_message_reply_markup(self.chat.id, message_id))
+                .await?;
+            self.remember_rendered_message(None, &msg).await;
+            self.callback_data_storage
+                .clear_message_callbacks(message_id.0)
+                .await;
+        }
+        Ok(())
+    }
+
+    /// Delete the current message and clear its stored callback data and rendered state
+    /// Returns false if the target has no current message or the message was already deleted
+    pub async fn delete(&self) -> ResponseResult<bool> {
+        let Some(message_id) = self.msg_id else {
+            return Ok(false);
+        };
+        let deleted = match self
+            .send_request(self.bot.delete_message(self.chat.id, message_id))
+            .await
+        {
+            Ok(_) => true,
+            Err(RequestError::Api(ApiError::MessageToDeleteNotFound)) => false,
+            Err(err) => return Err(err),
+        };
+        self.forget_message(message_id).await;
+        Ok(deleted)
+    }
+
+    /// Send a new markdown message deleted after the given time, e.g. a one-time password or a transient notice
+    /// With the message scheduler the deletion is persisted and survives a restart,
+    /// otherwise the message is deleted by a background task.
+    /// Returns `None` if the message was dropped by a middleware or the target is an inline message
+    pub async fn send_ephemeral(&self, text: MarkdownString, ttl: Duration) -> ResponseResult<Option<Message>> {
+        let mut target = self.clone();
+        target.msg_id = None;
+        let Some(msg) = target.render_markdown_message(text).await? else {
+            return Ok(None);
+        };
+        match &self.message_scheduler {
+            Some(scheduler) => {
+                scheduler
+                    .schedule_deletion(self.chat.id, msg.id, SystemTime::now() + ttl)
+                    .await;
+            }
+            None => {
+                target.msg_id = Some(msg.id);
+                tokio::spawn(async move {
+                    tokio::time::sleep(ttl).await;
+                    if let Err(err) = target.delete().await {
+                        log::warn!("Can't delete the ephemeral message in chat {}: {}", target.chat.id, err);
+                    }
+                });
+            }
+        }
+        Ok(Some(msg))
+    }
+
+    /// Answer the originating callback query with a notification at the top of the chat screen
+    /// The markdown is converted to plain text and truncated to Telegram's 200 characters limit
+    /// Does nothing if the target wasn't created from a callback query
+    pub async fn answer_callback(&self, text: MarkdownString) -> ResponseResult<()> {
+        self.answer_callback_query(text, false).await
+    }
+
+    /// Answer the originating callback query with an alert dialog
+    /// The markdown is converted to plain text and truncated to Telegram's 200 characters limit
+    /// Does nothing if the target wasn't created from a callback query
+    pub async fn answer_callback_alert(&self, text: MarkdownString) -> ResponseResult<()> {
+        self.answer_callback_query(text, true).await
+    }
+
+    /// Internal helper function to answer the callback query
+    async fn answer_callback_query(&self, text: MarkdownString, show_alert: bool) -> ResponseResult<()> {
+        self.answer_callback_plain(&text.to_plain_text(), show_alert).await
+    }
+
+    /// Internal helper function to answer the callback query with the plain text
+    pub(crate) async fn answer_callback_plain(&self, text: &str, show_alert: bool) -> ResponseResult<()> {
+        if let Some(callback_query_id) = &self.callback_query_id {
+            let request = self
+                .bot
+                .answer_callback_query(callback_query_id.clone())
+                .text(truncate_plain_text(text, CALLBACK_ANSWER_MAX_LENGTH))
+                .show_alert(show_alert);
+            self.send_request(request).await?;
+        }
+        Ok(())
+    }
+
     /// Internal helper function to attach a menu to an existing message
     /// Extracted to avoid code duplication between different send methods
+    /// Returns the message with the attached menu
     async fn attach_menu_to_message<R, B>(
-        bot: &Bot,
-        callback_data_storage: &Arc<dyn CallbackDataStorageTrait>,
-        chat_id: teloxide::types::ChatId,
+        &self,
         message_id: MessageId,
         menu: impl IntoIterator<Item = R>,
-    ) -> ResponseResult<()>
+    ) -> ResponseResult<Message>
     where
         R: IntoIterator<Item = B>,
         B: Into<ButtonData>,
     {
         // Pack callback data and attach keyboard to the message
-        let keyboard = pack_callback_data(callback_data_storage, message_id.0, menu).await;
-        bot.edit_message_reply_markup(chat_id, message_id)
-            .reply_markup(keyboard)
-            .await?;
-        Ok(())
+        let keyboard = pack_callback_data(&self.callback_data_storage, message_id.0, menu).await;
+        let rendered = self.rendered_message(message_id).await;
+        if let Some(rendered) = &rendered
+            && rendered.reply_markup.as_ref() == Some(&keyboard)
+        {
+            return Ok(rendered.message.clone());
+        }
+        let request = self
+            .bot
+            .edit_message_reply_markup(self.chat.id, message_id)
+            .reply_markup(keyboard);
+        let result = self.send_request(request).await;
+        let msg = match (result, rendered) {
+            (Err(RequestError::Api(ApiError::MessageNotModified)), Some(rendered)) => {
+                rendered.message
+            }
+            (result, _) => result?,
+        };
+        self.remember_rendered_message(None, &msg).await;
+        Ok(msg)
+    }
+
+    /// Internal helper function to clear the stored callback data and rendered state of the message
+    async fn forget_message(&self, message_id: MessageId) {
+        self.callback_data_storage
+            .clear_message_callbacks(message_id.0)
+            .await;
+        if let Some(rendered_messages) = &self.rendered_messages {
+            rendered_messages
+                .remove(self.chat.id, &message_id.0.to_string())
+                .await;
+        }
+    }
+
+    /// Internal helper function to pass the outgoing text through the middlewares
+    async fn process_outgoing(&self, text: MarkdownString) -> Option<MarkdownString> {
+        apply_middlewares(&self.middlewares, self.chat.id, text).await
+    }
+
+    /// Internal helper function to send a request, waiting for the rate limiter
+    /// and retrying it according to the retry policy if set
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "send_request",
+            skip_all,
+            fields(chat_id = %self.chat.id, method = <R::Payload as Payload>::NAME),
+            err(Display)
+        )
+    )]
+    pub(crate) async fn send_request<R>(&self, request: R) -> ResponseResult<Output<R>>
+    where
+        R: Request<Err = RequestError>,
+    {
+        if let Some(rate_limiter) = &self.rate_limiter
+            && creates_message(<R::Payload as Payload>::NAME)
+        {
+            rate_limiter.acquire(self.chat.id).await;
+        }
+        match &self.retry_policy {
+            Some(retry_policy) => retry_policy.send(request).await,
+            None => request.send().await,
+        }
+    }
+
+    /// Internal helper function to send a request producing a new message
+    /// and track it as the bot's last message in the chat
+    async fn send_new_message<R>(&self, request: R) -> ResponseResult<Message>
+    where
+        R: Request<Err = RequestError>,
+        R::Payload: Payload<Output = Message>,
+    {
+        let msg = self.send_request(request).await?;
+        self.track_last_message(&msg).await;
+        Ok(msg)
+    }
+
+    /// Internal helper function to record the message as the bot's last message in the chat
+    async fn track_last_message(&self, msg: &Message) {
+        if let Some(tracker) = &self.last_message_tracker {
+            tracker.set(self.chat.id, msg.id).await;
+        }
+    }
+
+    /// Internal helper function to get the last rendered state of the message if it is tracked
+    async fn rendered_message(&self, message_id: MessageId) -> Option<RenderedMessage> {
+        let rendered_messages = self.rendered_messages.as_ref()?;
+        rendered_messages
+            .get(self.chat.id, &message_id.0.to_string())
+            .await
+    }
+
+    /// Internal helper function to track the rendered state of the message
+    /// If the text is not given, the previously tracked text is kept
+    async fn remember_rendered_message(&self, text: Option<&MarkdownString>, msg: &Message) {
+        let Some(rendered_messages) = &self.rendered_messages else {
+            return;
+        };
+        let key = msg.id.0.to_string();
+        let previous = rendered_messages.get(self.chat.id, &key).await;
+        let is_new = previous.is_none();
+        let text = match (text, previous) {
+            (Some(text), _) => text.as_str().to_string(),
+            (None, Some(rendered)) => rendered.text,
+            (None, None) => return,
+        };
+        let rendered = RenderedMessage {
+            text,
+            reply_markup: msg.reply_markup().cloned(),
+            message: msg.clone(),
+        };
+        rendered_messages.set(self.chat.id, &key, rendered).await;
+        if is_new {
+            forget_old_rendered_messages(rendered_messages.as_ref(), self.chat.id).await;
+        }
+    }
+}
+
+/// Internal helper function to forget the rendered state of the oldest messages of the chat
+/// above [`RENDERED_MESSAGES_PER_CHAT`], the message ids grow with time
+async fn forget_old_rendered_messages(
+    rendered_messages: &dyn DataStoreTrait<RenderedMessage>,
+    chat_id: ChatId,
+) {
+    let mut message_ids: Vec<i32> = rendered_messages
+        .keys(chat_id)
+        .await
+        .iter()
+        .filter_map(|key| key.parse().ok())
+        .collect();
+    if message_ids.len() <= RENDERED_MESSAGES_PER_CHAT {
+        return;
+    }
+    message_ids.sort_unstable();
+    for message_id in &message_ids[..message_ids.len() - RENDERED_MESSAGES_PER_CHAT] {
+        rendered_messages.remove(chat_id, &message_id.to_string()).await;
+    }
+}
+
+/// Truncate plain text to the given number of characters, marking the truncation with "..."
+fn truncate_plain_text(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let mut truncated: String = text.chars().take(max_chars.saturating_sub(3)).collect();
+    truncated.push_str("...");
+    truncated
+}
+
+/// Set the markdown caption and the MarkdownV2 parse mode on the media
+fn with_markdown_caption(mut media: InputMedia, caption: MarkdownString) -> InputMedia {
+    let caption = MarkdownCaption::from(caption);
+    let caption = (!caption.as_str().is_empty()).then(|| caption.into());
+    let parse_mode = caption.as_ref().map(|_| ParseMode::MarkdownV2);
+    match &mut media {
+        InputMedia::Photo(m) => (m.caption, m.parse_mode) = (caption, parse_mode),
+        InputMedia::Video(m) => (m.caption, m.parse_mode) = (caption, parse_mode),
+        InputMedia::Animation(m) => (m.caption, m.parse_mode) = (caption, parse_mode),
+        InputMedia::Audio(m) => (m.caption, m.parse_mode) = (caption, parse_mode),
+        InputMedia::Document(m) => (m.caption, m.parse_mode) = (caption, parse_mode),
+    }
+    media
+}
+
+/// Split the number of album items into the minimal number of media groups
+/// with sizes as even as possible, so that no group is left with a single item
+fn media_group_sizes(count: usize) -> Vec<usize> {
+    let groups = count.div_ceil(MEDIA_GROUP_MAX_SIZE);
+    (0..groups)
+        .map(|i| count / groups + usize::from(i < count % groups))
+        .collect()
+}
+
+/// Check if the request method creates a message in the chat, e.g. `SendMessage` or `CopyMessage`,
+/// only such requests are subject to Telegram's rate limits
+fn creates_message(method: &str) -> bool {
+    (method.starts_with("Send") && method != "SendChatAction")
+        || matches!(
+            method,
+            "ForwardMessage" | "ForwardMessages" | "CopyMessage" | "CopyMessages"
+        )
+}
+
+/// Check if the error means that the message can't be edited anymore
+fn is_edit_failure(err: &RequestError) -> bool {
+    matches!(
+        err,
+        RequestError::Api(
+            ApiError::MessageToEditNotFound
+                | ApiError::MessageCantBeEdited
+                | ApiError::MessageIdInvalid
+        )
+    )
+}
+
+/// Pseudo message id under which the callback data of the inline message's menu is stored
+/// Inline messages have no message id, so a negative id is derived from the inline message id
+fn inline_message_callbacks_id(inline_message_id: &str) -> i32 {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    inline_message_id.hash(&mut hasher);
+    -((hasher.finish() % i32::MAX as u64) as i32) - 1
+}
+
+/// Join the texts with line breaks into as few messages as possible
+/// without exceeding the given message length
+fn pack_batch(texts: Vec<MarkdownString>, max_length: usize) -> Vec<MarkdownString> {
+    let mut packed: Vec<MarkdownString> = Vec::new();
+    for text in texts {
+        match packed.last_mut() {
+            Some(last) if last.len_utf16() + 1 + text.len_utf16() <= max_length => {
+                *last = markdown_format!("{}\n{}", @raw last.clone(), @raw text);
+            }
+            _ => packed.push(text),
+        }
+    }
+    packed
+}
+
+/// Append the continuation number, e.g. "(2/3)", to the message if it fits into the length limit
+fn number_continuation(text: MarkdownString, number: usize, count: usize) -> MarkdownString {
+    let numbered = markdown_format!("{}\n\\({}/{}\\)", @raw text.clone(), number.to_string(), count.to_string());
+    if numbered.len_utf16() > TELEGRAM_MAX_MESSAGE_LENGTH { text } else { numbered }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{command::command_button::CallbackDataStorage, data_store::in_mem::InMemStore};
+
+    #[test]
+    fn test_truncate_plain_text() {
+        assert_eq!(truncate_plain_text("short", 10), "short");
+        assert_eq!(truncate_plain_text("exactly 10", 10), "exactly 10");
+        assert_eq!(truncate_plain_text("a bit too long", 10), "a bit t...");
+    }
+
+    #[test]
+    fn test_media_group_sizes() {
+        assert_eq!(media_group_sizes(0), Vec::<usize>::new());
+        assert_eq!(media_group_sizes(3), vec![3]);
+        assert_eq!(media_group_sizes(10), vec![10]);
+        assert_eq!(media_group_sizes(11), vec![6, 5]);
+        assert_eq!(media_group_sizes(25), vec![9, 8, 8]);
+    }
+
+    #[test]
+    fn test_pack_batch() {
+        assert!(pack_batch(Vec::new(), TELEGRAM_MAX_MESSAGE_LENGTH).is_empty());
+
+        let packed = pack_batch(vec![
+            MarkdownString::escape("first"),
+            MarkdownString::escape("second"),
+        ], TELEGRAM_MAX_MESSAGE_LENGTH);
+        assert_eq!(packed.len(), 1);
+        assert_eq!(packed[0].as_str(), "first\nsecond");
+
+        let long = MarkdownString::escape("a".repeat(3000));
+        let packed = pack_batch(
+            vec![long.clone(), long.clone(), MarkdownString::escape("tail")],
+            TELEGRAM_MAX_MESSAGE_LENGTH,
+        );
+        assert_eq!(packed.len(), 2);
+        assert_eq!(packed[0].as_str(), long.as_str());
+        assert_eq!(packed[1].as_str(), format!("{}\ntail", long.as_str()));
+    }
+
+    #[test]
+    fn test_creates_message() {
+        assert!(creates_message("SendMessage"));
+        assert!(creates_message("SendMediaGroup"));
+        assert!(creates_message("CopyMessage"));
+        assert!(!creates_message("SendChatAction"));
+        assert!(!creates_message("EditMessageReplyMarkup"));
+        assert!(!creates_message("AnswerCallbackQuery"));
+    }
+
+    #[test]
+    fn test_is_edit_failure() {
+        assert!(is_edit_failure(&RequestError::Api(ApiError::MessageToEditNotFound)));
+        assert!(is_edit_failure(&RequestError::Api(ApiError::MessageCantBeEdited)));
+        assert!(!is_edit_failure(&RequestError::Api(ApiError::MessageNotModified)));
+    }
+
+    #[test]
+    fn test_for_channel() {
+        let storage = Arc::new(CallbackDataStorage::new(Arc::new(InMemStore::new()), ChatId(-100123)));
+        let target = CommandReplyTarget::for_channel(
+            Bot::new("token"),
+            ChatId(-100123),
+            Some(MessageId(5)),
+            storage,
+        );
+        assert!(target.chat.is_channel());
+        assert_eq!(target.chat.id, ChatId(-100123));
+        assert_eq!(target.msg_id, Some(MessageId(5)));
+    }
+
+    #[test]
+    fn test_inline_message_callbacks_id() {
+        let id = inline_message_callbacks_id("AgAAAEZ8AQBhc2Rm");
+        assert!(id < 0);
+        assert_eq!(id, inline_message_callbacks_id("AgAAAEZ8AQBhc2Rm"));
+        assert_ne!(id, inline_message_callbacks_id("AgAAAEZ8AQBxd2Vy"));
+    }
+
+    #[test]
+    fn test_number_continuation() {
+        let numbered = number_continuation(MarkdownString::escape("text"), 2, 3);
+        assert_eq!(numbered.as_str(), "text\n\\(2/3\\)");
+
+        let long = MarkdownString::escape("a".repeat(4092));
+        assert_eq!(number_continuation(long.clone(), 1, 2), long);
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_send_ephemeral() {
+        use crate::{api::app::bot_app::BotApp, testing::MockBotApi};
+
+        let api = MockBotApi::start().await;
+        let mut dispatcher = BotApp::new(api.bot(), ())
+            .command("otp", "", |target, _, _| async move {
+                target
+                    .send_ephemeral(markdown_string!("Code: 1234"), Duration::from_millis(50))
+                    .await?;
+                Ok(())
+            })
+            .build();
+        let dispatcher_task = tokio::spawn(async move { dispatcher.dispatch().await });
+
+        api.send_text(ChatId(1), "/otp").await;
+        let sent = api.next_request("sendMessage").await.unwrap();
+        assert_eq!(sent.str_param("text"), Some("Code: 1234"));
+        let deleted = api.next_request("deleteMessage").await.unwrap();
+        assert_eq!(deleted.params["message_id"], sent.message_id.unwrap().0);
+
+        dispatcher_task.abort();
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_batch_flushed_after_command() {
+        use crate::{api::app::bot_app::BotApp, testing::MockBotApi};
+
+        let api = MockBotApi::start().await;
+        let mut dispatcher = BotApp::new(api.bot(), ())
+            .configure_target(|target| target.batched())
+            .command("report", "", |target, _, _| async move {
+                for line in ["Users: 3", "Chats: 2"] {
+                    target.markdown_message(MarkdownString::escape(line)).await?;
+                }
+                Ok(())
+            })
+            .command("menu", "", |target, _, _| async move {
+                target.markdown_message(markdown_string!("Users: 3")).await?;
+                target
+                    .markdown_message_with_menu(markdown_string!("Choose:"), [[("One", "/one")]])
+                    .await?;
+                Ok(())
+            })
+            .build();
+        let dispatcher_task = tokio::spawn(async move { dispatcher.dispatch().await });
+
+        api.send_text(ChatId(1), "/report").await;
+        let report = api.next_request("sendMessage").await.unwrap();
+        assert_eq!(report.str_param("text"), Some("Users: 3\nChats: 2"));
+
+        // The buffered messages are sent before the menu
+        api.send_text(ChatId(1), "/menu").await;
+        let report = api.next_request("sendMessage").await.unwrap();
+        assert_eq!(report.str_param("text"), Some("Users: 3"));
+        let menu = api.next_request("sendMessage").await.unwrap();
+        assert_eq!(menu.str_param("text"), Some("Choose:"));
+        let markup = api.next_request("editMessageReplyMarkup").await.unwrap();
+        assert_eq!(markup.message_id, menu.message_id);
+
+        dispatcher_task.abort();
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_markdown_or_document() {
+        use crate::{api::app::bot_app::BotApp, testing::MockBotApi};
+
+        let api = MockBotApi::start().await;
+        let mut dispatcher = BotApp::new(api.bot(), ())
+            .command("log", "", |target, _, args| async move {
+                let lines: usize = args.parse().unwrap_or(1);
+                let text = MarkdownString::escape("line\n".repeat(lines));
+                target.markdown_or_document(text, "log.txt").await?;
+                Ok(())
+            })
+            .build();
+        let dispatcher_task = tokio::spawn(async move { dispatcher.dispatch().await });
+
+        api.send_text(ChatId(1), "/log 2").await;
+        let reply = api.next_request("sendMessage").await.unwrap();
+        assert_eq!(reply.str_param("text"), Some("line\nline\n"));
+
+        api.send_text(ChatId(1), "/log 1000").await;
+        assert!(api.next_request("sendDocument").await.is_some());
+
+        dispatcher_task.abort();
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_rate_limiter_skips_edits() {
+        use crate::{api::app::bot_app::BotApp, testing::MockBotApi};
+
+        let api = MockBotApi::start().await;
+        let limiter = RateLimiter::new(Duration::from_secs(60), Duration::from_secs(60), 30);
+        let mut dispatcher = BotApp::new(api.bot(), ())
+            .configure_target(move |target| target.with_rate_limiter(limiter.clone()))
+            .command("menu", "", |target, _, _| async move {
+                target
+                    .markdown_message_with_menu(markdown_string!("Choose:"), [[("One", "/one")]])
+                    .await?;
+                Ok(())
+            })
+            .build();
+        let dispatcher_task = tokio::spawn(async move { dispatcher.dispatch().await });
+
+        api.send_text(ChatId(1), "/menu").await;
+        assert!(api.next_request("sendMessage").await.is_some());
+        // The menu is attached by the edit, which doesn't wait for the next message slot
+        assert!(api.next_request("editMessageReplyMarkup").await.is_some());
+
+        dispatcher_task.abort();
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_rendered_messages() {
+        use crate::{api::app::bot_app::BotApp, testing::MockBotApi};
+
+        let api = MockBotApi::start().await;
+        let rendered_messages = Arc::new(InMemStore::<RenderedMessage>::new());
+        let tracker = LastMessageTracker::new(Arc::new(InMemStore::new()));
+        let mut dispatcher = BotApp::new(api.bot(), ())
+            .track_rendered_messages(rendered_messages.clone())
+            .configure_target(move |target| target.with_last_message_tracker(tracker.clone()))
+            .command("status", "", |target, _, _| async move {
+                for text in ["Working", "Working", "Done"] {
+                    target.edit_or_send(MarkdownString::escape(text)).await?;
+                }
+                Ok(())
+            })
+            .command("spam", "", |target, _, _| async move {
+                for _ in 0..RENDERED_MESSAGES_PER_CHAT + 2 {
+                    target.markdown_message(markdown_string!("spam")).await?;
+                }
+                Ok(())
+            })
+            .build();
+        let dispatcher_task = tokio::spawn(async move { dispatcher.dispatch().await });
+
+        // The unchanged edit is skipped
+        api.send_text(ChatId(1), "/status").await;
+        let sent = api.next_request("sendMessage").await.unwrap();
+        assert_eq!(sent.str_param("text"), Some("Working"));
+        let edited = api.next_request("editMessageText").await.unwrap();
+        assert_eq!(edited.str_param("text"), Some("Done"));
+        assert_eq!(edited.message_id, sent.message_id);
+
+        // Only the last messages of the chat are tracked
+        api.send_text(ChatId(1), "/spam").await;
+        let mut message_ids = Vec::new();
+        for _ in 0..RENDERED_MESSAGES_PER_CHAT + 2 {
+            let sent = api.next_request("sendMessage").await.unwrap();
+            message_ids.push(sent.message_id.unwrap().0.to_string());
+        }
+        // The last message is remembered after its request is answered
+        let last_id = message_ids.last().unwrap();
+        while rendered_messages.get(ChatId(1), last_id).await.is_none() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let keys = rendered_messages.keys(ChatId(1)).await;
+        assert_eq!(keys.len(), RENDERED_MESSAGES_PER_CHAT);
+        assert!(!keys.contains(&message_ids[1]) && keys.contains(&message_ids[2]));
+
+        dispatcher_task.abort();
     }
 }
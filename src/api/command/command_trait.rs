@@ -2,9 +2,10 @@ use std::{any::TypeId, fmt::Display};
 
 use teloxide::{prelude::ResponseResult, utils::command::ParseError};
 
-use crate::api::command::{command_arg::{EmptyArg, ParseCommandArg}, command_reply_target::CommandReplyTarget};
+use crate::api::command::{authorize::DenyReason, command_arg::{EmptyArg, ParseCommandArg, ParseFlagArgs, mark_flag_candidates, split_flags}, command_reply_target::CommandReplyTarget};
+use crate::api::parse::command_string::{screen_spaces, split_with_offsets};
 
-pub trait CommandTrait: Sized + Clone {
+pub trait CommandTrait: Sized + Clone + Send + Sync {
     type A: ParseCommandArg + Default + Display + Send + Sync + 'static;
     type B: ParseCommandArg + Default + Display + Send + Sync + 'static;
     type C: ParseCommandArg + Default + Display + Send + Sync + 'static;
@@ -14,8 +15,10 @@ pub trait CommandTrait: Sized + Clone {
     type G: ParseCommandArg + Default + Display + Send + Sync + 'static;
     type H: ParseCommandArg + Default + Display + Send + Sync + 'static;
     type I: ParseCommandArg + Default + Display + Send + Sync + 'static;
+    /// Named arguments like `--limit 10`, see [`Flags`](crate::command::Flags), [`EmptyArg`] if the command has none
+    type FlagArgs: ParseFlagArgs + Default + Display + Send + Sync + 'static;
 
-    type Context;
+    type Context: Send;
 
     const NAME: &'static str;
     const PLACEHOLDERS: &[&'static str];
@@ -60,7 +63,24 @@ pub trait CommandTrait: Sized + Clone {
                 || TypeId::of::<Self::I>() == TypeId::of::<EmptyArg>()
         );
 
-        let args = split_with_screened_spaces(&args);
+        let rest_slot = Self::rest_slot();
+        assert!(rest_slot.is_none_or(|slot| slot + 1 == Self::PLACEHOLDERS.len()));
+
+        let tokens = split_with_offsets(&args);
+        let words = mark_flag_candidates::<Self::FlagArgs>(&args, &tokens);
+        let (mut positional, flags, rest) = split_flags(words, rest_slot);
+        if let Some(rest) = rest {
+            // The rest starts at the remaining argument or at the next line
+            let rest = match tokens.get(rest) {
+                Some((_, offset)) => &args[*offset..],
+                None => args.split_once('\n').map(|(_, rest)| rest).unwrap_or_default(),
+            };
+            if !rest.trim().is_empty() {
+                positional.push(rest.to_string());
+            }
+        }
+        let args = positional;
+        let flags = Self::FlagArgs::parse_flag_args(&flags)?;
         if args.len() > Self::PLACEHOLDERS.len() {
             return Err(ParseError::TooManyArguments {
                 expected: Self::PLACEHOLDERS.len(),
@@ -72,16 +92,18 @@ pub trait CommandTrait: Sized + Clone {
                 ),
             });
         }
-        let a = get::<Self::A>(&args, 0)?;
-        let b = get::<Self::B>(&args, 1)?;
-        let c = get::<Self::C>(&args, 2)?;
-        let d = get::<Self::D>(&args, 3)?;
-        let e = get::<Self::E>(&args, 4)?;
-        let f = get::<Self::F>(&args, 5)?;
-        let g = get::<Self::G>(&args, 6)?;
-        let h = get::<Self::H>(&args, 7)?;
-        let i = get::<Self::I>(&args, 8)?;
-        Ok((Self::from_arguments(a, b, c, d, e, f, g, h, i),))
+        let a = get::<Self::A>(&args, 0, Self::PLACEHOLDERS)?;
+        let b = get::<Self::B>(&args, 1, Self::PLACEHOLDERS)?;
+        let c = get::<Self::C>(&args, 2, Self::PLACEHOLDERS)?;
+        let d = get::<Self::D>(&args, 3, Self::PLACEHOLDERS)?;
+        let e = get::<Self::E>(&args, 4, Self::PLACEHOLDERS)?;
+        let f = get::<Self::F>(&args, 5, Self::PLACEHOLDERS)?;
+        let g = get::<Self::G>(&args, 6, Self::PLACEHOLDERS)?;
+        let h = get::<Self::H>(&args, 7, Self::PLACEHOLDERS)?;
+        let i = get::<Self::I>(&args, 8, Self::PLACEHOLDERS)?;
+        let mut command = Self::from_arguments(a, b, c, d, e, f, g, h, i);
+        command.set_flags(flags);
+        Ok((command,))
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -97,6 +119,31 @@ pub trait CommandTrait: Sized + Clone {
         i: Option<Self::I>,
     ) -> Self;
 
+    /// Index of the argument taking the rest of the message, see [`RestArg`](crate::command::RestArg)
+    fn rest_slot() -> Option<usize> {
+        [
+            Self::A::CAPTURES_REST,
+            Self::B::CAPTURES_REST,
+            Self::C::CAPTURES_REST,
+            Self::D::CAPTURES_REST,
+            Self::E::CAPTURES_REST,
+            Self::F::CAPTURES_REST,
+            Self::G::CAPTURES_REST,
+            Self::H::CAPTURES_REST,
+            Self::I::CAPTURES_REST,
+        ]
+        .iter()
+        .position(|&captures_rest| captures_rest)
+    }
+
+    /// Store the parsed named arguments, they are dropped by default
+    fn set_flags(&mut self, _flags: Self::FlagArgs) {}
+
+    /// Named arguments of the command, added to the command string
+    fn flags(&self) -> Option<&Self::FlagArgs> {
+        None
+    }
+
     fn param1(&self) -> Option<&Self::A> {
         assert!(TypeId::of::<Self::A>() == TypeId::of::<EmptyArg>());
         None
@@ -138,7 +185,7 @@ pub trait CommandTrait: Sized + Clone {
         &self,
         _target: &CommandReplyTarget,
         _context: Self::Context,
-    ) -> impl std::future::Future<Output = ResponseResult<()>> {
+    ) -> impl std::future::Future<Output = ResponseResult<()>> + Send {
         async { Ok(()) }
     }
 
@@ -147,7 +194,7 @@ pub trait CommandTrait: Sized + Clone {
         _target: &CommandReplyTarget,
         _context: Self::Context,
         _a: &Self::A,
-    ) -> impl std::future::Future<Output = ResponseResult<()>> {
+    ) -> impl std::future::Future<Output = ResponseResult<()>> + Send {
         async { Ok(()) }
     }
 
@@ -157,7 +204,7 @@ pub trait CommandTrait: Sized + Clone {
         _context: Self::Context,
         _a: &Self::A,
         _b: &Self::B,
-    ) -> impl std::future::Future<Output = ResponseResult<()>> {
+    ) -> impl std::future::Future<Output = ResponseResult<()>> + Send {
         async { Ok(()) }
     }
 
@@ -168,7 +215,7 @@ pub trait CommandTrait: Sized + Clone {
         _a: &Self::A,
         _b: &Self::B,
         _c: &Self::C,
-    ) -> impl std::future::Future<Output = ResponseResult<()>> {
+    ) -> impl std::future::Future<Output = ResponseResult<()>> + Send {
         async { Ok(()) }
     }
 
@@ -180,7 +227,7 @@ pub trait CommandTrait: Sized + Clone {
         _b: &Self::B,
         _c: &Self::C,
         _d: &Self::D,
-    ) -> impl std::future::Future<Output = ResponseResult<()>> {
+    ) -> impl std::future::Future<Output = ResponseResult<()>> + Send {
         async { Ok(()) }
     }
 
@@ -194,7 +241,7 @@ pub trait CommandTrait: Sized + Clone {
         _c: &Self::C,
         _d: &Self::D,
         _e: &Self::E,
-    ) -> impl std::future::Future<Output = ResponseResult<()>> {
+    ) -> impl std::future::Future<Output = ResponseResult<()>> + Send {
         async { Ok(()) }
     }
 
@@ -209,7 +256,7 @@ pub trait CommandTrait: Sized + Clone {
         _d: &Self::D,
         _e: &Self::E,
         _f: &Self::F,
-    ) -> impl std::future::Future<Output = ResponseResult<()>> {
+    ) -> impl std::future::Future<Output = ResponseResult<()>> + Send {
         async { Ok(()) }
     }
 
@@ -225,7 +272,7 @@ pub trait CommandTrait: Sized + Clone {
         _e: &Self::E,
         _f: &Self::F,
         _g: &Self::G,
-    ) -> impl std::future::Future<Output = ResponseResult<()>> {
+    ) -> impl std::future::Future<Output = ResponseResult<()>> + Send {
         async { Ok(()) }
     }
 
@@ -242,7 +289,7 @@ pub trait CommandTrait: Sized + Clone {
         _f: &Self::F,
         _g: &Self::G,
         _h: &Self::H,
-    ) -> impl std::future::Future<Output = ResponseResult<()>> {
+    ) -> impl std::future::Future<Output = ResponseResult<()>> + Send {
         async { Ok(()) }
     }
 
@@ -260,7 +307,17 @@ pub trait CommandTrait: Sized + Clone {
         _g: &Self::G,
         _h: &Self::H,
         _i: &Self::I,
-    ) -> impl std::future::Future<Output = ResponseResult<()>> {
+    ) -> impl std::future::Future<Output = ResponseResult<()>> + Send {
+        async { Ok(()) }
+    }
+
+    /// Check if the user may run the command in the target's chat, called by the dispatcher before
+    /// [`run`](Self::run), the reason of the denial is shown to the user. Everyone is allowed by default.
+    /// See the checkers like [`CommandReplyTarget::require_chat_admin`].
+    fn authorize(
+        &self,
+        _target: &CommandReplyTarget,
+    ) -> impl std::future::Future<Output = Result<(), DenyReason>> + Send {
         async { Ok(()) }
     }
 
@@ -268,7 +325,7 @@ pub trait CommandTrait: Sized + Clone {
         &self,
         target: &CommandReplyTarget,
         context: Self::Context,
-    ) -> impl std::future::Future<Output = ResponseResult<()>> {
+    ) -> impl std::future::Future<Output = ResponseResult<()>> + Send {
         async {
             match (
                 self.param1(),
@@ -354,11 +411,23 @@ pub trait CommandTrait: Sized + Clone {
                 let part = params[i]
                     .clone()
                     .unwrap_or(Self::PLACEHOLDERS[i].to_string());
-                command_parts.push(screen_spaces(&part));
+                // The rest of the message is taken verbatim
+                if Some(i) == Self::rest_slot() && params[i].is_some() {
+                    command_parts.push(part);
+                } else {
+                    command_parts.push(screen_spaces(&part));
+                }
             }
         }
+        let incomplete = command_parts.len() < Self::PLACEHOLDERS.len() + 1;
+        // The flags go first, so that the incomplete command can be continued with the positional arguments
+        if let Some(flags) = self.flags().map(|flags| flags.to_string())
+            && !flags.is_empty()
+        {
+            command_parts.insert(1, flags);
+        }
         let mut command = command_parts.join(" ");
-        if command_parts.len() < Self::PLACEHOLDERS.len() + 1 {
+        if incomplete {
             command.push(' ');
         }
         command
@@ -378,6 +447,7 @@ impl CommandTrait for NoopCommand {
     type G = EmptyArg;
     type H = EmptyArg;
     type I = EmptyArg;
+    type FlagArgs = EmptyArg;
     type Context = ();
     const NAME: &'static str = "_noop";
     const PLACEHOLDERS: &[&'static str] = &[];
@@ -396,50 +466,12 @@ impl CommandTrait for NoopCommand {
     }
 }
 
-fn split_with_screened_spaces(arg: &str) -> Vec<String> {
-    let mut args = Vec::new();
-    let mut current = String::new();
-    let mut chars = arg.lines().next().unwrap_or("").chars().peekable();
-    while let Some(c) = chars.next() {
-        match c {
-            '\\' => {
-                if let Some(&next_c) = chars.peek() {
-                    if next_c == '\\' {
-                        current.push('\\');
-                        chars.next();
-                    } else if next_c == ' ' {
-                        current.push(' ');
-                        chars.next();
-                    } else {
-                        current.push('\\');
-                    }
-                } else {
-                    current.push('\\');
-                }
-            }
-            ' ' => {
-                if !current.is_empty() {
-                    args.push(current.clone());
-                    current.clear();
-                }
-            }
-            _ => current.push(c),
-        }
-    }
-    if !current.is_empty() {
-        args.push(current);
-    }
-    args
-}
-
-fn screen_spaces(s: &str) -> String {
-    s.replace('\\', "\\\\").replace(' ', "\\ ")
-}
-
-fn get<A>(args: &[String], pos: usize) -> Result<Option<A>, ParseError>
+fn get<A>(args: &[String], pos: usize, placeholders: &[&str]) -> Result<Option<A>, ParseError>
 where
     A: ParseCommandArg,
 {
-    let parsed = args.get(pos).map(|s| A::parse_command_arg(s)).transpose()?;
-    Ok(parsed)
+    match args.get(pos) {
+        Some(arg) => A::parse_command_arg(arg).map(Some),
+        None => A::missing_command_arg(placeholders.get(pos).copied().unwrap_or_default(), args.len()),
+    }
 }
@@ -0,0 +1,262 @@
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use serde::{Deserialize, Serialize};
+use teloxide::{
+    prelude::ResponseResult,
+    types::{ChatId, Message, MessageId},
+};
+use tokio::sync::{Mutex, oneshot};
+
+use crate::api::{
+    command::{command_reply_target::CommandReplyTarget, pending_operation::OperationHandle},
+    data_store::data_store_trait::DataStoreTrait,
+    markdown::string::MarkdownString,
+};
+
+/// The command of the confirmation buttons, `/confirm yes` or `/confirm no`
+pub(crate) const CONFIRM_COMMAND: &str = "confirm";
+
+/// The name of the pending operation registered by the dialog
+const CONFIRM_OPERATION: &str = "confirmation";
+
+/// Prefix of the keys of the pending confirmations in the data store, followed by the question id
+const PENDING_CONFIRMATION_KEY_PREFIX: &str = "pending_confirmation_";
+
+/// Default time the confirmation waits for the answer
+const DEFAULT_CONFIRM_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+/// The answer to the confirmation dialog
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Confirmation {
+    Confirmed,
+    Cancelled,
+}
+
+/// Confirmation dialog waiting for the user's answer
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PendingConfirmation {
+    /// The time after which the answer is not awaited anymore
+    pub expires_at: SystemTime,
+}
+
+/// Identity of the dialog: the chat and the question message
+type DialogId = (ChatId, MessageId);
+
+/// Registry of the confirmation dialogs sent by [`CommandReplyTarget::confirm`]
+///
+/// The dialog's Yes and No buttons run the `/confirm yes` and `/confirm no` commands on its message,
+/// the update handler should pass them to [`resolve`](Self::resolve), [`BotApp`](crate::app::BotApp) does it.
+/// The pending dialogs are persisted in the data store, so after a restart the bot can tell
+/// the user that the dialog has expired, even though the waiting command is gone.
+#[derive(Clone)]
+pub struct ConfirmRegistry {
+    store: Arc<dyn DataStoreTrait<PendingConfirmation>>,
+    timeout: Duration,
+    waiters: Arc<Mutex<HashMap<DialogId, oneshot::Sender<Confirmation>>>>,
+}
+
+impl ConfirmRegistry {
+    /// Create a new ConfirmRegistry with the given DataStore, the dialogs wait for 10 minutes
+    pub fn new(store: Arc<dyn DataStoreTrait<PendingConfirmation>>) -> Self {
+        Self {
+            store,
+            timeout: DEFAULT_CONFIRM_TIMEOUT,
+            waiters: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Wait for the answers to the dialogs for the given time
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Get the pending dialog with the question message in the chat, if it hasn't expired
+    pub async fn pending(&self, chat_id: ChatId, question_id: MessageId) -> Option<PendingConfirmation> {
+        let key = pending_confirmation_key(question_id);
+        let pending = self.store.get(chat_id, &key).await?;
+        if pending.expires_at < SystemTime::now() {
+            self.store.remove(chat_id, &key).await;
+            return None;
+        }
+        Some(pending)
+    }
+
+    /// Pass the answer of the `/confirm` command triggered by the dialog's button to the waiting command
+    /// Returns `None` if the target's message is not a pending dialog, e.g. it has expired
+    pub async fn resolve(&self, target: &CommandReplyTarget, args: &str) -> Option<Confirmation> {
+        let confirmation = match args.trim() {
+            "yes" => Confirmation::Confirmed,
+            "no" => Confirmation::Cancelled,
+            _ => return None,
+        };
+        let question_id = target.msg_id?;
+        self.pending(target.chat.id, question_id).await?;
+        self.store
+            .remove(target.chat.id, &pending_confirmation_key(question_id))
+            .await;
+        let waiter = self.waiters.lock().await.remove(&(target.chat.id, question_id))?;
+        waiter.send(confirmation).ok()?;
+        Some(confirmation)
+    }
+
+    /// Internal helper function to register the dialog waiting for the answer
+    async fn register(&self, chat_id: ChatId, question_id: MessageId) -> oneshot::Receiver<Confirmation> {
+        let (sender, receiver) = oneshot::channel();
+        self.waiters.lock().await.insert((chat_id, question_id), sender);
+        let pending = PendingConfirmation {
+            expires_at: SystemTime::now() + self.timeout,
+        };
+        self.store
+            .set(chat_id, &pending_confirmation_key(question_id), pending)
+            .await;
+        receiver
+    }
+
+    /// Internal helper function to forget the dialog which wasn't answered in time
+    async fn expire(&self, chat_id: ChatId, question_id: MessageId) {
+        self.waiters.lock().await.remove(&(chat_id, question_id));
+        self.store
+            .remove(chat_id, &pending_confirmation_key(question_id))
+            .await;
+    }
+}
+
+/// Internal helper function to build the data store key of the pending dialog
+fn pending_confirmation_key(question_id: MessageId) -> String {
+    format!("{}{}", PENDING_CONFIRMATION_KEY_PREFIX, question_id)
+}
+
+/// Confirmation dialog sent by [`CommandReplyTarget::confirm`]
+pub struct ConfirmHandle {
+    registry: Option<ConfirmRegistry>,
+    chat_id: ChatId,
+    question: Option<Message>,
+    receiver: Option<oneshot::Receiver<Confirmation>>,
+    operation: OperationHandle,
+}
+
+impl ConfirmHandle {
+    /// The message with the question and the buttons, `None` if it was dropped by a middleware
+    pub fn question(&self) -> Option<&Message> {
+        self.question.as_ref()
+    }
+
+    /// Wait for the user to press one of the buttons, `/cancel` is the same as No
+    /// Returns `None` if there was no answer before the registry's timeout
+    /// or if the dialog can't be answered, e.g. without the confirm registry
+    pub async fn wait(self) -> Option<Confirmation> {
+        let mut operation = self.operation;
+        let answer = match (self.registry, self.question, self.receiver) {
+            (Some(registry), Some(question), Some(receiver)) => tokio::select! {
+                answer = tokio::time::timeout(registry.timeout, receiver) => match answer {
+                    Ok(answer) => answer.ok(),
+                    Err(_) => {
+                        registry.expire(self.chat_id, question.id).await;
+                        None
+                    }
+                },
+                _ = operation.cancelled() => {
+                    registry.expire(self.chat_id, question.id).await;
+                    Some(Confirmation::Cancelled)
+                }
+            },
+            _ => None,
+        };
+        operation.finish().await;
+        answer
+    }
+
+    /// Wait for the answer, true if the user pressed Yes
+    pub async fn confirmed(self) -> bool {
+        self.wait().await == Some(Confirmation::Confirmed)
+    }
+}
+
+impl CommandReplyTarget {
+    /// Ask the user to confirm the action with the Yes and No buttons under the text
+    /// The dialog is sent as a new message or replaces the current message of the target, e.g. the menu.
+    /// Wait for the answer with [`ConfirmHandle::wait`], it requires the confirm registry of the target.
+    /// The dialog is registered as the user's operation, so `/cancel` answers it with No and removes the buttons.
+    pub async fn confirm(&self, text: MarkdownString) -> ResponseResult<ConfirmHandle> {
+        let buttons = [[
+            ("Yes", format!("/{} yes", CONFIRM_COMMAND)),
+            ("No", format!("/{} no", CONFIRM_COMMAND)),
+        ]];
+        let buttons = buttons.map(|row| row.map(|(label, data)| (label.to_string(), data)));
+        let question = self.markdown_message_with_menu(text, buttons).await?;
+        let receiver = match (&self.confirm_registry, &question) {
+            (Some(registry), Some(question)) => Some(registry.register(self.chat.id, question.id).await),
+            (None, _) => {
+                log::warn!("Confirm registry is not set, the answer can't be awaited");
+                None
+            }
+            _ => None,
+        };
+        let operation = self
+            .start_operation(CONFIRM_OPERATION, question.as_ref().map(|question| question.id))
+            .await;
+        Ok(ConfirmHandle {
+            registry: self.confirm_registry.clone(),
+            chat_id: self.chat.id,
+            question,
+            receiver,
+            operation,
+        })
+    }
+}
+
+#[cfg(all(test, feature = "testing"))]
+mod tests {
+    use super::*;
+    use crate::{
+        api::{app::bot_app::BotApp, data_store::in_mem::InMemStore},
+        markdown_string,
+        testing::MockBotApi,
+    };
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_confirm_dialog() {
+        let api = MockBotApi::start().await;
+        let registry = ConfirmRegistry::new(Arc::new(InMemStore::new()));
+        let mut dispatcher = BotApp::new(api.bot(), ())
+            .configure_target(move |target| target.with_confirm_registry(registry.clone()))
+            .command("wipe", "", |target, _, _| async move {
+                let answer = target.confirm(markdown_string!("Wipe all data?")).await?.wait().await;
+                let text = match answer {
+                    Some(Confirmation::Confirmed) => markdown_string!("Wiped"),
+                    _ => markdown_string!("Kept"),
+                };
+                target.markdown_message(text).await?;
+                Ok(())
+            })
+            .build();
+        let dispatcher_task = tokio::spawn(async move { dispatcher.dispatch().await });
+        let chat_id = ChatId(1);
+
+        api.send_text(chat_id, "/wipe").await;
+        let menu = api.next_request("editMessageReplyMarkup").await.unwrap();
+        assert_eq!(menu.keyboard_callbacks(), [["/confirm yes", "/confirm no"]]);
+        let question_id = menu.message_id.unwrap();
+
+        api.press_button(chat_id, question_id, "/confirm yes").await;
+        let reply = api.next_request("sendMessage").await.unwrap();
+        assert_eq!(reply.str_param("text"), Some("Wiped"));
+
+        // The dialog is answered only once
+        api.press_button(chat_id, question_id, "/confirm no").await;
+        let answer = loop {
+            let answer = api.next_request("answerCallbackQuery").await.unwrap();
+            if answer.str_param("text").is_some() {
+                break answer;
+            }
+        };
+        assert_eq!(answer.str_param("text"), Some("This confirmation has expired"));
+
+        dispatcher_task.abort();
+    }
+}
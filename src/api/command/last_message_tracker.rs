@@ -0,0 +1,63 @@
+use std::sync::Arc;
+
+use teloxide::types::{ChatId, MessageId};
+
+use crate::api::data_store::data_store_trait::DataStoreTrait;
+
+/// The key under which the last message id is stored for each chat
+const LAST_MESSAGE_KEY: &str = "last_message";
+
+/// Tracker of the bot's most recent message in each chat
+///
+/// Used by [`CommandReplyTarget::edit_or_send`](crate::command::CommandReplyTarget::edit_or_send)
+/// to keep a single status message in the chat, editing it instead of sending new messages.
+/// The message ids are stored in the provided data store, so they survive restarts
+/// if a persistent store is used.
+#[derive(Clone)]
+pub struct LastMessageTracker {
+    store: Arc<dyn DataStoreTrait<MessageId>>,
+}
+
+impl LastMessageTracker {
+    /// Create a new LastMessageTracker with the given DataStore
+    pub fn new(store: Arc<dyn DataStoreTrait<MessageId>>) -> Self {
+        Self { store }
+    }
+
+    /// Get the id of the bot's last message in the chat
+    pub async fn get(&self, chat_id: ChatId) -> Option<MessageId> {
+        self.store.get(chat_id, LAST_MESSAGE_KEY).await
+    }
+
+    /// Record the message as the bot's last message in the chat
+    pub async fn set(&self, chat_id: ChatId, message_id: MessageId) {
+        self.store.set(chat_id, LAST_MESSAGE_KEY, message_id).await
+    }
+
+    /// Forget the bot's last message in the chat, returns true if it was tracked
+    pub async fn forget(&self, chat_id: ChatId) -> bool {
+        self.store.remove(chat_id, LAST_MESSAGE_KEY).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::data_store::in_mem::InMemStore;
+
+    const TEST_CHAT_ID: ChatId = ChatId(12345);
+
+    #[tokio::test]
+    async fn test_last_message_tracker() {
+        let tracker = LastMessageTracker::new(Arc::new(InMemStore::new()));
+        assert_eq!(tracker.get(TEST_CHAT_ID).await, None);
+
+        tracker.set(TEST_CHAT_ID, MessageId(1)).await;
+        tracker.set(TEST_CHAT_ID, MessageId(2)).await;
+        assert_eq!(tracker.get(TEST_CHAT_ID).await, Some(MessageId(2)));
+        assert_eq!(tracker.get(ChatId(67890)).await, None);
+
+        assert!(tracker.forget(TEST_CHAT_ID).await);
+        assert_eq!(tracker.get(TEST_CHAT_ID).await, None);
+    }
+}
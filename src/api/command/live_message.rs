@@ -0,0 +1,178 @@
+use std::{
+    future::Future,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use teloxide::{prelude::ResponseResult, types::Message};
+use tokio::sync::Notify;
+
+use crate::api::{
+    command::{command_button::ButtonData, command_reply_target::CommandReplyTarget},
+    markdown::string::MarkdownString,
+};
+
+/// Minimal allowed interval between message edits
+/// Telegram starts rejecting edits with "Too Many Requests" if they are sent too often
+const MIN_REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Message periodically re-rendered and edited in place
+///
+/// Suitable for countdowns, queue status and monitoring dashboards. The message is rendered
+/// by a user-supplied async closure on each refresh, edits are skipped when the text didn't change.
+/// Refreshing stops when the closure returns `None`, when the timeout expires
+/// or when the [`LiveMessageStopHandle`] is triggered, e.g. from the handler of the stop button.
+///
+/// # Example
+/// ```ignore
+/// let live = LiveMessage::new(&target, Duration::from_secs(5))
+///     .with_timeout(Duration::from_secs(600))
+///     .with_stop_button("Stop", "/stop_status");
+/// let stop_handle = live.stop_handle(); // keep it to stop the message on the button press
+/// live.run(|| async {
+///     let queue = queue_length().await;
+///     (queue > 0).then(|| markdown_format!("Queue: {}", queue.to_string()))
+/// })
+/// .await?;
+/// ```
+pub struct LiveMessage {
+    target: CommandReplyTarget,
+    refresh_interval: Duration,
+    timeout: Option<Duration>,
+    stop_button: Option<(String, String)>,
+    stop: LiveMessageStopHandle,
+}
+
+/// Handle to stop refreshing a [`LiveMessage`] from another task
+#[derive(Clone, Default)]
+pub struct LiveMessageStopHandle {
+    stopped: Arc<AtomicBool>,
+    notify: Arc<Notify>,
+}
+
+impl LiveMessageStopHandle {
+    /// Stop refreshing the message, the last rendered state stays in the chat
+    pub fn stop(&self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        self.notify.notify_waiters();
+    }
+
+    /// Check if the message was stopped
+    pub fn is_stopped(&self) -> bool {
+        self.stopped.load(Ordering::SeqCst)
+    }
+}
+
+impl LiveMessage {
+    /// Create a live message refreshed with the given interval
+    /// The current message of the target is edited if present, otherwise a new message is sent.
+    /// Intervals shorter than one second are rounded up to avoid hitting Telegram's limits
+    pub fn new(target: &CommandReplyTarget, refresh_interval: Duration) -> Self {
+        Self {
+            target: target.clone(),
+            refresh_interval: refresh_interval.max(MIN_REFRESH_INTERVAL),
+            timeout: None,
+            stop_button: None,
+            stop: LiveMessageStopHandle::default(),
+        }
+    }
+
+    /// Stop refreshing the message after the given time
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Attach a button with the given label and callback data to the message while it's refreshed
+    /// The handler of the callback should call [`LiveMessageStopHandle::stop`]
+    pub fn with_stop_button(mut self, label: impl Into<String>, callback_data: impl Into<String>) -> Self {
+        self.stop_button = Some((label.into(), callback_data.into()));
+        self
+    }
+
+    /// Get the handle to stop refreshing the message
+    pub fn stop_handle(&self) -> LiveMessageStopHandle {
+        self.stop.clone()
+    }
+
+    /// Render and refresh the message until it's stopped
+    /// Returns the message in its last rendered state, the stop button is removed from it
+    pub async fn run<F, Fut>(mut self, mut render: F) -> ResponseResult<Option<Message>>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Option<MarkdownString>>,
+    {
+        let deadline = self.timeout.map(|timeout| Instant::now() + timeout);
+        let mut last_text: Option<MarkdownString> = None;
+        let mut last_message = None;
+        while !self.stop.is_stopped() {
+            let Some(text) = render().await else {
+                break;
+            };
+            if last_text.as_ref() != Some(&text) {
+                if let Some(msg) = self.render(text.clone()).await? {
+                    self.target.msg_id = Some(msg.id);
+                    last_message = Some(msg);
+                }
+                last_text = Some(text);
+            }
+            let mut sleep = self.refresh_interval;
+            if let Some(deadline) = deadline {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                if remaining.is_zero() {
+                    break;
+                }
+                sleep = sleep.min(remaining);
+            }
+            let stopped = self.stop.notify.notified();
+            if self.stop.is_stopped() {
+                break;
+            }
+            tokio::select! {
+                _ = tokio::time::sleep(sleep) => {}
+                _ = stopped => {}
+            }
+        }
+        if self.stop_button.is_some() && last_message.is_some() {
+            self.target.clear_menu().await?;
+        }
+        Ok(last_message)
+    }
+
+    /// Internal helper function to edit the message, attaching the stop button if configured
+    async fn render(&self, text: MarkdownString) -> ResponseResult<Option<Message>> {
+        match &self.stop_button {
+            Some((label, callback_data)) => {
+                let button = ButtonData::Callback(label.clone(), callback_data.clone());
+                self.target
+                    .markdown_message_with_menu(text, vec![vec![button]])
+                    .await
+            }
+            None => self.target.render_markdown_message(text).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_stop_handle() {
+        let handle = LiveMessageStopHandle::default();
+        let waiter = handle.clone();
+        let task = tokio::spawn(async move {
+            let stopped = waiter.notify.notified();
+            if !waiter.is_stopped() {
+                stopped.await;
+            }
+            waiter.is_stopped()
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        handle.stop();
+        assert!(task.await.unwrap());
+    }
+}
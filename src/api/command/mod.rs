@@ -1,4 +1,24 @@
 pub(crate) mod command_trait;
+pub(crate) mod authorize;
+pub(crate) mod callback_reply_target;
+pub(crate) mod chat_action;
+pub(crate) mod command_registry;
+pub(crate) mod command_error;
+pub(crate) mod command_middleware;
+pub(crate) mod command_metrics;
 pub(crate) mod command_arg;
 pub(crate) mod command_reply_target;
-pub(crate) mod command_button;
\ No newline at end of file
+pub(crate) mod command_button;
+pub(crate) mod confirm;
+pub(crate) mod progress_message;
+pub(crate) mod last_message_tracker;
+pub(crate) mod outgoing_middleware;
+pub(crate) mod paginator;
+pub(crate) mod pending_operation;
+pub(crate) mod live_message;
+pub(crate) mod poll;
+pub(crate) mod prompt;
+pub(crate) mod session;
+pub(crate) mod subcommand_router;
+pub(crate) mod time_arg;
+pub(crate) mod wizard;
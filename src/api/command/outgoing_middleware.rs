@@ -0,0 +1,84 @@
+use std::sync::Arc;
+
+use teloxide::types::ChatId;
+
+use crate::api::markdown::string::MarkdownString;
+
+/// Interceptor of the outgoing messages sent or edited through
+/// [`CommandReplyTarget`](crate::command::CommandReplyTarget)
+///
+/// Middlewares are configured once on the target and are inherited by all its clones,
+/// so cross-cutting concerns like adding a footer, logging or suppressing messages
+/// in test mode don't require touching every command.
+/// Message texts and media captions pass through the middlewares in the order they were added.
+///
+/// # Example
+///
+/// ```rust
+/// use telluride::{command::OutgoingMiddleware, markdown::MarkdownString, markdown_string};
+/// use teloxide::types::ChatId;
+///
+/// struct Footer;
+///
+/// #[async_trait::async_trait]
+/// impl OutgoingMiddleware for Footer {
+///     async fn process(&self, _chat_id: ChatId, text: MarkdownString) -> Option<MarkdownString> {
+///         Some(text + markdown_string!("\n_sent by bot_"))
+///     }
+/// }
+/// ```
+#[async_trait::async_trait]
+pub trait OutgoingMiddleware: Send + Sync {
+    /// Process the text of the outgoing message, return `None` to drop the message
+    async fn process(&self, chat_id: ChatId, text: MarkdownString) -> Option<MarkdownString>;
+}
+
+/// Allow plain functions and closures to be used as middlewares
+#[async_trait::async_trait]
+impl<F> OutgoingMiddleware for F
+where
+    F: Fn(ChatId, MarkdownString) -> Option<MarkdownString> + Send + Sync,
+{
+    async fn process(&self, chat_id: ChatId, text: MarkdownString) -> Option<MarkdownString> {
+        self(chat_id, text)
+    }
+}
+
+/// Pass the text through the chain of middlewares, stopping when one of them drops the message
+pub(crate) async fn apply_middlewares(
+    middlewares: &[Arc<dyn OutgoingMiddleware>],
+    chat_id: ChatId,
+    mut text: MarkdownString,
+) -> Option<MarkdownString> {
+    for middleware in middlewares {
+        text = middleware.process(chat_id, text).await?;
+    }
+    Some(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::markdown_string;
+
+    const TEST_CHAT_ID: ChatId = ChatId(12345);
+
+    #[tokio::test]
+    async fn test_apply_middlewares() {
+        let footer: Arc<dyn OutgoingMiddleware> =
+            Arc::new(|_, text: MarkdownString| Some(text + markdown_string!(" footer")));
+        let drop_in_group: Arc<dyn OutgoingMiddleware> = Arc::new(|chat_id: ChatId, text| {
+            if chat_id.is_user() { Some(text) } else { None }
+        });
+        let middlewares = vec![footer.clone(), drop_in_group, footer];
+
+        let text = apply_middlewares(&middlewares, TEST_CHAT_ID, markdown_string!("text")).await;
+        assert_eq!(text.unwrap().as_str(), "text footer footer");
+
+        let text = apply_middlewares(&middlewares, ChatId(-12345), markdown_string!("text")).await;
+        assert!(text.is_none());
+
+        let text = apply_middlewares(&[], TEST_CHAT_ID, markdown_string!("text")).await;
+        assert_eq!(text.unwrap().as_str(), "text");
+    }
+}
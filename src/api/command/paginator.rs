@@ -0,0 +1,266 @@
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use teloxide::{
+    prelude::ResponseResult,
+    types::{ChatId, Message, MessageId},
+};
+
+use crate::{
+    api::{
+        command::command_reply_target::CommandReplyTarget,
+        data_store::data_store_trait::DataStoreTrait, markdown::string::MarkdownString,
+    },
+    markdown_format, markdown_string,
+};
+
+/// The default command of the page buttons, `/page N`
+const DEFAULT_PAGE_COMMAND: &str = "page";
+
+/// Prefix of the keys of the paginated lists in the data store, followed by the message id
+const PAGINATION_KEY_PREFIX: &str = "pagination_";
+
+/// Paginated list shown in the message
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PaginationState {
+    /// The markdown items of the list
+    pub items: Vec<String>,
+    /// The number of items on each page
+    pub page_size: usize,
+    /// The index of the shown page, starting from 0
+    pub page: usize,
+}
+
+impl PaginationState {
+    /// The number of pages, at least one
+    pub fn page_count(&self) -> usize {
+        self.items.len().div_ceil(self.page_size).max(1)
+    }
+
+    /// Internal helper function to render the shown page with its number
+    fn render(&self) -> MarkdownString {
+        if self.items.is_empty() {
+            return markdown_string!("The list is empty");
+        }
+        let items = self
+            .items
+            .iter()
+            .skip(self.page * self.page_size)
+            .take(self.page_size)
+            .map(|item| MarkdownString::from_validated_string(item.clone()));
+        let text = MarkdownString::join(items, &markdown_string!("\n"));
+        match self.page_count() {
+            1 => text,
+            count => text + markdown_format!("\n\nPage {}/{}", self.page + 1, count),
+        }
+    }
+}
+
+/// Long list shown page by page in a single message with the ⬅️ and ➡️ buttons
+///
+/// The buttons run the `/page N` command on the list's message, register the paginator
+/// with [`CommandRegistry::paginator`](crate::command::CommandRegistry::paginator) to flip the pages.
+/// The lists are persisted in the data store, so the buttons keep working after a restart.
+///
+/// # Example
+/// ```ignore
+/// let paginator = Paginator::new(Arc::new(InMemStore::new()));
+/// let registry = CommandRegistry::new()
+///     .paginator(paginator.clone())
+///     .command("users", "list the users", move |target, _, _| {
+///         let paginator = paginator.clone();
+///         async move {
+///             let users = load_users().await.into_iter().map(MarkdownString::escape).collect();
+///             paginator.send(&target, users, 10).await?;
+///             Ok(())
+///         }
+///     });
+/// ```
+#[derive(Clone)]
+pub struct Paginator {
+    store: Arc<dyn DataStoreTrait<PaginationState>>,
+    command: String,
+}
+
+impl Paginator {
+    /// Create a new Paginator with the given DataStore, the buttons run the `/page` command
+    pub fn new(store: Arc<dyn DataStoreTrait<PaginationState>>) -> Self {
+        Self {
+            store,
+            command: DEFAULT_PAGE_COMMAND.to_string(),
+        }
+    }
+
+    /// Run the command with the given name (without the leading slash) by the page buttons
+    pub fn with_command(mut self, command: impl Into<String>) -> Self {
+        self.command = command.into();
+        self
+    }
+
+    /// The name of the command run by the page buttons
+    pub fn command(&self) -> &str {
+        &self.command
+    }
+
+    /// Show the first page of the list, as a new message or replacing the current message of the target
+    /// Returns `None` if the message was dropped by a middleware or the target is an inline message
+    pub async fn send(
+        &self,
+        target: &CommandReplyTarget,
+        items: Vec<MarkdownString>,
+        page_size: usize,
+    ) -> ResponseResult<Option<Message>> {
+        let state = PaginationState {
+            items: items.into_iter().map(MarkdownString::into_string).collect(),
+            page_size: page_size.max(1),
+            page: 0,
+        };
+        let Some(msg) = self.render(target, &state).await? else {
+            return Ok(None);
+        };
+        if state.page_count() > 1 {
+            self.store
+                .set(target.chat.id, &pagination_key(msg.id), state)
+                .await;
+        }
+        Ok(Some(msg))
+    }
+
+    /// Show the page of the list requested by the button, the handler of the paginator's command
+    /// The list's message is edited in place, the user is notified if the list has expired
+    pub async fn flip(&self, target: &CommandReplyTarget, args: &str) -> ResponseResult<()> {
+        let state = match target.msg_id {
+            Some(message_id) => {
+                self.store
+                    .get(target.chat.id, &pagination_key(message_id))
+                    .await
+            }
+            None => None,
+        };
+        let (Some(message_id), Some(mut state)) = (target.msg_id, state) else {
+            return target
+                .answer_callback(markdown_string!("This list has expired"))
+                .await;
+        };
+        let Ok(page) = args.trim().parse::<usize>() else {
+            log::warn!("Invalid page in the callback data: {}", args);
+            return Ok(());
+        };
+        let page = page.min(state.page_count() - 1);
+        if page == state.page {
+            return Ok(());
+        }
+        state.page = page;
+        self.render(target, &state).await?;
+        self.store
+            .set(target.chat.id, &pagination_key(message_id), state)
+            .await;
+        Ok(())
+    }
+
+    /// Forget the list shown in the message, its buttons stop working
+    /// Returns true if the list was removed
+    pub async fn forget(&self, chat_id: ChatId, message_id: MessageId) -> bool {
+        self.store
+            .remove(chat_id, &pagination_key(message_id))
+            .await
+    }
+
+    /// Internal helper function to render the shown page with the buttons of the adjacent pages
+    async fn render(
+        &self,
+        target: &CommandReplyTarget,
+        state: &PaginationState,
+    ) -> ResponseResult<Option<Message>> {
+        let mut buttons = Vec::new();
+        if state.page > 0 {
+            buttons.push((
+                "⬅️".to_string(),
+                format!("/{} {}", self.command, state.page - 1),
+            ));
+        }
+        if state.page + 1 < state.page_count() {
+            buttons.push((
+                "➡️".to_string(),
+                format!("/{} {}", self.command, state.page + 1),
+            ));
+        }
+        target
+            .markdown_message_with_menu(state.render(), [buttons])
+            .await
+    }
+}
+
+/// Internal helper function to build the data store key of the list shown in the message
+fn pagination_key(message_id: MessageId) -> String {
+    format!("{}{}", PAGINATION_KEY_PREFIX, message_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pagination_state_render() {
+        let mut state = PaginationState {
+            items: ["a", "b", "c"].map(String::from).to_vec(),
+            page_size: 2,
+            page: 0,
+        };
+        assert_eq!(state.page_count(), 2);
+        assert_eq!(state.render().as_str(), "a\nb\n\nPage 1/2");
+        state.page = 1;
+        assert_eq!(state.render().as_str(), "c\n\nPage 2/2");
+        state.items.clear();
+        assert_eq!(state.page_count(), 1);
+        assert_eq!(state.render().as_str(), "The list is empty");
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_paginator() {
+        use teloxide::dispatching::Dispatcher;
+
+        use crate::{
+            api::{command::command_registry::CommandRegistry, data_store::in_mem::InMemStore},
+            testing::MockBotApi,
+        };
+
+        let api = MockBotApi::start().await;
+        let paginator = Paginator::new(Arc::new(InMemStore::new()));
+        let list = paginator.clone();
+        let registry = CommandRegistry::new().paginator(paginator).command(
+            "users",
+            "",
+            move |target, _, _| {
+                let list = list.clone();
+                async move {
+                    let users = ["alice", "bob", "carol"]
+                        .map(MarkdownString::escape)
+                        .to_vec();
+                    list.send(&target, users, 2).await?;
+                    Ok(())
+                }
+            },
+        );
+        let mut dispatcher = Dispatcher::builder(api.bot(), registry.handler(())).build();
+        let dispatcher_task = tokio::spawn(async move { dispatcher.dispatch().await });
+        let chat_id = ChatId(1);
+
+        api.send_text(chat_id, "/users").await;
+        let first = api.next_request("sendMessage").await.unwrap();
+        assert_eq!(first.str_param("text"), Some("alice\nbob\n\nPage 1/2"));
+        let menu = api.next_request("editMessageReplyMarkup").await.unwrap();
+        assert_eq!(menu.keyboard_callbacks(), [["/page 1"]]);
+        let message_id = first.message_id.unwrap();
+
+        api.press_button(chat_id, message_id, "/page 1").await;
+        let second = api.next_request("editMessageText").await.unwrap();
+        assert_eq!(second.str_param("text"), Some("carol\n\nPage 2/2"));
+        assert_eq!(second.message_id, Some(message_id));
+        let menu = api.next_request("editMessageReplyMarkup").await.unwrap();
+        assert_eq!(menu.keyboard_callbacks(), [["/page 0"]]);
+
+        dispatcher_task.abort();
+    }
+}
@@ -0,0 +1,349 @@
+use std::{collections::HashMap, sync::Arc, time::SystemTime};
+
+use serde::{Deserialize, Serialize};
+use teloxide::{
+    prelude::Requester,
+    types::{ChatId, MessageId, UserId},
+};
+use tokio::sync::{Mutex, oneshot};
+
+use crate::api::{
+    command::command_reply_target::CommandReplyTarget, data_store::data_store_trait::DataStoreTrait,
+};
+
+/// The built-in command aborting the pending operation of the user, `/cancel`
+pub(crate) const CANCEL_COMMAND: &str = "cancel";
+
+/// The key under which the pending operation is stored for each user in the chat
+const PENDING_OPERATION_KEY: &str = "pending_operation";
+
+/// Operation of the user in progress, e.g. a wizard, a confirmation dialog or a long-running command
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PendingOperation {
+    /// The name shown to the user on cancellation, e.g. `/export`
+    pub name: String,
+    /// The message with the operation's keyboard, removed on cancellation
+    pub message_id: Option<MessageId>,
+    /// The time the operation was started
+    pub started_at: SystemTime,
+}
+
+/// Identity of the operation's owner: the chat and the user
+type OwnerId = (ChatId, Option<UserId>);
+
+/// The running operation with the sender notifying its handle on cancellation
+type Canceller = (PendingOperation, oneshot::Sender<()>);
+
+/// Registry of the operations in progress, one per user in each chat
+///
+/// The wizards, the confirmation dialogs and the long-running commands register with it when
+/// the target has the registry, see [`CommandReplyTarget::with_operation_registry`].
+/// The built-in `/cancel` command aborts the user's current operation with
+/// [`CommandReplyTarget::cancel_operation`], [`BotApp`](crate::app::BotApp) handles it.
+/// The operations are persisted in the data store, so after a restart `/cancel` still
+/// cleans up the state and the keyboards left by the interrupted operation.
+#[derive(Clone)]
+pub struct OperationRegistry {
+    store: Arc<dyn DataStoreTrait<PendingOperation>>,
+    cancellers: Arc<Mutex<HashMap<OwnerId, Canceller>>>,
+}
+
+impl OperationRegistry {
+    /// Create a new OperationRegistry with the given DataStore
+    pub fn new(store: Arc<dyn DataStoreTrait<PendingOperation>>) -> Self {
+        Self {
+            store,
+            cancellers: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Register the operation of the user in the chat, replacing the previous one
+    pub async fn start(
+        &self,
+        chat_id: ChatId,
+        user_id: Option<UserId>,
+        name: impl Into<String>,
+        message_id: Option<MessageId>,
+    ) -> OperationHandle {
+        let operation = PendingOperation {
+            name: name.into(),
+            message_id,
+            started_at: SystemTime::now(),
+        };
+        let (sender, receiver) = oneshot::channel();
+        self.cancellers
+            .lock()
+            .await
+            .insert((chat_id, user_id), (operation.clone(), sender));
+        self.store
+            .set(chat_id, &operation_key(user_id), operation.clone())
+            .await;
+        OperationHandle {
+            registry: Some(self.clone()),
+            chat_id,
+            user_id,
+            operation,
+            receiver: Some(receiver),
+            cancelled: false,
+        }
+    }
+
+    /// Get the operation of the user in the chat
+    pub async fn current(
+        &self,
+        chat_id: ChatId,
+        user_id: Option<UserId>,
+    ) -> Option<PendingOperation> {
+        self.store.get(chat_id, &operation_key(user_id)).await
+    }
+
+    /// Forget the operation of the user in the chat if it has the given name, e.g. when the wizard is completed
+    /// Returns true if the operation was removed
+    pub async fn finish(&self, chat_id: ChatId, user_id: Option<UserId>, name: &str) -> bool {
+        match self.current(chat_id, user_id).await {
+            Some(operation) if operation.name == name => {
+                self.forget(chat_id, user_id, &operation).await;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Abort the operation of the user in the chat, its handle is notified
+    /// Returns the cancelled operation, `None` if there was none
+    pub async fn cancel(
+        &self,
+        chat_id: ChatId,
+        user_id: Option<UserId>,
+    ) -> Option<PendingOperation> {
+        let operation = self.current(chat_id, user_id).await?;
+        self.store.remove(chat_id, &operation_key(user_id)).await;
+        if let Some((_, canceller)) = self.cancellers.lock().await.remove(&(chat_id, user_id)) {
+            canceller.send(()).ok();
+        }
+        Some(operation)
+    }
+
+    /// Internal helper function to forget the operation unless it was replaced by another one
+    async fn forget(&self, chat_id: ChatId, user_id: Option<UserId>, operation: &PendingOperation) {
+        if self.current(chat_id, user_id).await.as_ref() == Some(operation) {
+            self.store.remove(chat_id, &operation_key(user_id)).await;
+        }
+        let mut cancellers = self.cancellers.lock().await;
+        if cancellers
+            .get(&(chat_id, user_id))
+            .is_some_and(|(current, _)| current == operation)
+        {
+            cancellers.remove(&(chat_id, user_id));
+        }
+    }
+}
+
+/// Internal helper function to build the data store key of the user's operation
+fn operation_key(user_id: Option<UserId>) -> String {
+    match user_id {
+        Some(user_id) => format!("{}_{}", PENDING_OPERATION_KEY, user_id),
+        None => PENDING_OPERATION_KEY.to_string(),
+    }
+}
+
+/// Operation registered by [`CommandReplyTarget::start_operation`]
+///
+/// The long-running command checks [`is_cancelled`](Self::is_cancelled) between its steps
+/// or races its work against [`cancelled`](Self::cancelled), and calls [`finish`](Self::finish) when done.
+pub struct OperationHandle {
+    registry: Option<OperationRegistry>,
+    chat_id: ChatId,
+    user_id: Option<UserId>,
+    operation: PendingOperation,
+    receiver: Option<oneshot::Receiver<()>>,
+    cancelled: bool,
+}
+
+impl OperationHandle {
+    /// The registered operation
+    pub fn operation(&self) -> &PendingOperation {
+        &self.operation
+    }
+
+    /// Wait until the operation is cancelled by the user
+    /// Never completes if the operation can't be cancelled, e.g. without the operation registry
+    /// or after it was replaced by another operation
+    pub async fn cancelled(&mut self) {
+        if self.is_cancelled() {
+            return;
+        }
+        if let Some(receiver) = self.receiver.as_mut() {
+            self.cancelled = receiver.await.is_ok();
+            self.receiver = None;
+        }
+        if !self.cancelled {
+            std::future::pending::<()>().await;
+        }
+    }
+
+    /// Check if the operation was cancelled by the user
+    pub fn is_cancelled(&mut self) -> bool {
+        if let Some(receiver) = self.receiver.as_mut()
+            && receiver.try_recv().is_ok()
+        {
+            self.cancelled = true;
+            self.receiver = None;
+        }
+        self.cancelled
+    }
+
+    /// Unregister the completed operation
+    pub async fn finish(self) {
+        if let Some(registry) = &self.registry {
+            registry
+                .forget(self.chat_id, self.user_id, &self.operation)
+                .await;
+        }
+    }
+}
+
+impl CommandReplyTarget {
+    /// Register the operation of the target's user, so that `/cancel` can abort it
+    /// The keyboard of the given message is removed on cancellation.
+    /// Without the operation registry the returned handle is never cancelled.
+    pub async fn start_operation(
+        &self,
+        name: impl Into<String>,
+        message_id: Option<MessageId>,
+    ) -> OperationHandle {
+        match &self.operation_registry {
+            Some(registry) => {
+                registry
+                    .start(self.chat.id, self.user_id, name, message_id)
+                    .await
+            }
+            None => OperationHandle {
+                registry: None,
+                chat_id: self.chat.id,
+                user_id: self.user_id,
+                operation: PendingOperation {
+                    name: name.into(),
+                    message_id,
+                    started_at: SystemTime::now(),
+                },
+                receiver: None,
+                cancelled: false,
+            },
+        }
+    }
+
+    /// Abort the pending operation of the target's user: notify its handle, clear the pending wizard
+    /// and remove the operation's keyboard with its stored callback data
+    /// Returns the cancelled operation, `None` if there was none
+    pub async fn cancel_operation(&self) -> Option<PendingOperation> {
+        if let Some(command_wizard) = &self.command_wizard {
+            command_wizard.cancel(self.chat.id, self.user_id).await;
+        }
+        let operation = self
+            .operation_registry
+            .as_ref()?
+            .cancel(self.chat.id, self.user_id)
+            .await?;
+        if let Some(message_id) = operation.message_id {
+            // The keyboard may be already removed, e.g. by the operation itself
+            if let Err(err) = self
+                .send_request(self.bot.edit_message_reply_markup(self.chat.id, message_id))
+                .await
+            {
+                log::debug!(
+                    "Can't remove the keyboard of the cancelled {}: {}",
+                    operation.name,
+                    err
+                );
+            }
+            self.callback_data_storage
+                .clear_message_callbacks(message_id.0)
+                .await;
+        }
+        Some(operation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::data_store::in_mem::InMemStore;
+
+    #[tokio::test]
+    async fn test_operation_registry() {
+        let registry = OperationRegistry::new(Arc::new(InMemStore::new()));
+        let (chat_id, user_id) = (ChatId(1), Some(UserId(2)));
+
+        let mut export = registry.start(chat_id, user_id, "/export", None).await;
+        assert_eq!(
+            registry.current(chat_id, user_id).await.unwrap().name,
+            "/export"
+        );
+        assert!(registry.current(chat_id, None).await.is_none());
+        assert!(!export.is_cancelled());
+        assert_eq!(
+            registry.cancel(chat_id, user_id).await.unwrap().name,
+            "/export"
+        );
+        export.cancelled().await;
+        assert!(export.is_cancelled());
+        assert!(registry.cancel(chat_id, user_id).await.is_none());
+
+        // The replaced operation doesn't remove its successor
+        let import = registry.start(chat_id, user_id, "/import", None).await;
+        let wizard = registry
+            .start(chat_id, user_id, "/remind", Some(MessageId(5)))
+            .await;
+        import.finish().await;
+        assert_eq!(
+            registry.current(chat_id, user_id).await.as_ref(),
+            Some(wizard.operation())
+        );
+        assert!(!registry.finish(chat_id, user_id, "/import").await);
+        assert!(registry.finish(chat_id, user_id, "/remind").await);
+        assert!(registry.current(chat_id, user_id).await.is_none());
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_cancel_command() {
+        use std::time::Duration;
+
+        use crate::{api::app::bot_app::BotApp, markdown_string, testing::MockBotApi};
+
+        let api = MockBotApi::start().await;
+        let registry = OperationRegistry::new(Arc::new(InMemStore::new()));
+        let mut dispatcher = BotApp::new(api.bot(), ())
+            .configure_target(move |target| target.with_operation_registry(registry.clone()))
+            .command("export", "", |target, _, _| async move {
+                let mut operation = target.start_operation("/export", None).await;
+                tokio::select! {
+                    _ = tokio::time::sleep(Duration::from_secs(60)) => {}
+                    _ = operation.cancelled() => {
+                        target.markdown_message(markdown_string!("Export stopped")).await?;
+                    }
+                }
+                operation.finish().await;
+                Ok(())
+            })
+            .build();
+        let dispatcher_task = tokio::spawn(async move { dispatcher.dispatch().await });
+
+        api.send_text(ChatId(1), "/export").await;
+        api.send_text(ChatId(1), "/cancel").await;
+        let mut replies = Vec::new();
+        for _ in 0..2 {
+            let reply = api.next_request("sendMessage").await.unwrap();
+            replies.push(reply.str_param("text").unwrap().to_string());
+        }
+        replies.sort();
+        assert_eq!(replies, ["Cancelled /export", "Export stopped"]);
+
+        api.send_text(ChatId(1), "/cancel").await;
+        let reply = api.next_request("sendMessage").await.unwrap();
+        assert_eq!(reply.str_param("text"), Some("Nothing to cancel"));
+
+        dispatcher_task.abort();
+    }
+}
@@ -0,0 +1,208 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use serde::{Deserialize, Serialize};
+use teloxide::{
+    Bot,
+    prelude::{Requester, ResponseResult},
+    types::{ChatId, MaybeAnonymousUser, MessageId, Poll, PollAnswer},
+};
+
+use crate::api::data_store::data_store_trait::{DataStoreTrait, GLOBAL_NAMESPACE};
+
+/// Maximum length of the poll question allowed by Telegram Bot API
+/// See: https://core.telegram.org/bots/api#sendpoll
+pub(crate) const POLL_QUESTION_MAX_LENGTH: usize = 300;
+
+/// Maximum length of the poll option allowed by Telegram Bot API
+/// See: https://core.telegram.org/bots/api#inputpolloption
+pub(crate) const POLL_OPTION_MAX_LENGTH: usize = 100;
+
+/// Maximum number of the poll options allowed by Telegram Bot API
+/// See: https://core.telegram.org/bots/api#sendpoll
+pub(crate) const POLL_MAX_OPTIONS: usize = 10;
+
+/// Maximum length of the quiz explanation allowed by Telegram Bot API
+/// See: https://core.telegram.org/bots/api#sendpoll
+pub(crate) const POLL_EXPLANATION_MAX_LENGTH: usize = 200;
+
+/// Prefix of the keys of the poll records in the data store
+const POLL_KEY_PREFIX: &str = "poll_";
+
+/// Settings of a poll sent with [`CommandReplyTarget::send_poll`](crate::command::CommandReplyTarget::send_poll)
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PollSettings {
+    /// Show the voters' names, only public polls report the answers to the bot
+    pub public: bool,
+    /// Allow choosing several options, ignored in quiz mode
+    pub allows_multiple_answers: bool,
+    /// Index of the correct option, makes the poll a quiz
+    pub quiz_correct_option: Option<u8>,
+    /// Text shown when a user chooses an incorrect answer in a quiz
+    pub explanation: Option<String>,
+    /// Time in seconds the poll will be active after creation, 5-600
+    pub open_period: Option<u16>,
+}
+
+impl PollSettings {
+    /// Show the voters' names and report the answers to the bot
+    pub fn public(mut self) -> Self {
+        self.public = true;
+        self
+    }
+
+    /// Allow choosing several options
+    pub fn multiple_answers(mut self) -> Self {
+        self.allows_multiple_answers = true;
+        self
+    }
+
+    /// Make the poll a quiz with the given correct option
+    pub fn quiz(mut self, correct_option: u8) -> Self {
+        self.quiz_correct_option = Some(correct_option);
+        self
+    }
+
+    /// Set the text shown when a user chooses an incorrect answer in a quiz
+    pub fn explanation(mut self, explanation: impl Into<String>) -> Self {
+        self.explanation = Some(explanation.into());
+        self
+    }
+
+    /// Close the poll automatically after the given number of seconds
+    pub fn open_period(mut self, seconds: u16) -> Self {
+        self.open_period = Some(seconds);
+        self
+    }
+}
+
+/// Sent poll with the answers collected so far
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PollRecord {
+    /// The chat the poll was sent to
+    pub chat_id: ChatId,
+    /// The message containing the poll
+    pub message_id: MessageId,
+    /// The options of the poll as they were sent
+    pub options: Vec<String>,
+    /// The chosen options of each voter, keyed by the voter's user or chat id
+    pub answers: BTreeMap<String, Vec<u8>>,
+}
+
+impl PollRecord {
+    /// Count the votes for each option
+    pub fn votes(&self) -> Vec<u32> {
+        let mut votes = vec![0; self.options.len()];
+        for option_ids in self.answers.values() {
+            for &option_id in option_ids {
+                if let Some(count) = votes.get_mut(option_id as usize) {
+                    *count += 1;
+                }
+            }
+        }
+        votes
+    }
+}
+
+/// Tracker of the sent polls and their answers
+///
+/// Polls are kept by their ids outside of the chats' namespaces of the data store,
+/// because poll answer updates don't contain the chat.
+/// Telegram reports answers only for public polls.
+#[derive(Clone)]
+pub struct PollTracker {
+    store: Arc<dyn DataStoreTrait<PollRecord>>,
+}
+
+impl PollTracker {
+    /// Create a new PollTracker with the given DataStore
+    pub fn new(store: Arc<dyn DataStoreTrait<PollRecord>>) -> Self {
+        Self { store }
+    }
+
+    /// Get the record of the poll
+    pub async fn get(&self, poll_id: &str) -> Option<PollRecord> {
+        self.store.get(GLOBAL_NAMESPACE, &poll_key(poll_id)).await
+    }
+
+    /// Start tracking the sent poll
+    pub async fn track(&self, poll_id: &str, record: PollRecord) {
+        self.store.set(GLOBAL_NAMESPACE, &poll_key(poll_id), record).await
+    }
+
+    /// Stop tracking the poll, returns true if it was tracked
+    pub async fn forget(&self, poll_id: &str) -> bool {
+        self.store.remove(GLOBAL_NAMESPACE, &poll_key(poll_id)).await
+    }
+
+    /// Record the answer received in the poll answer update
+    /// Retracted votes remove the voter's answer, answers to untracked polls are ignored.
+    /// Returns the updated poll record
+    pub async fn record_answer(&self, answer: &PollAnswer) -> Option<PollRecord> {
+        let mut record = self.get(&answer.poll_id.0).await?;
+        let voter = match &answer.voter {
+            MaybeAnonymousUser::User(user) => user.id.to_string(),
+            MaybeAnonymousUser::Chat(chat) => chat.id.to_string(),
+        };
+        if answer.option_ids.is_empty() {
+            record.answers.remove(&voter);
+        } else {
+            record.answers.insert(voter, answer.option_ids.clone());
+        }
+        self.track(&answer.poll_id.0, record.clone()).await;
+        Some(record)
+    }
+
+    /// Close the tracked poll and stop tracking it
+    /// Returns the final state of the poll, or `None` if the poll isn't tracked
+    pub async fn stop_poll(&self, bot: &Bot, poll_id: &str) -> ResponseResult<Option<Poll>> {
+        let Some(record) = self.get(poll_id).await else {
+            return Ok(None);
+        };
+        let poll = bot.stop_poll(record.chat_id, record.message_id).await?;
+        self.forget(poll_id).await;
+        Ok(Some(poll))
+    }
+}
+
+/// Internal helper function to build the data store key of the poll
+fn poll_key(poll_id: &str) -> String {
+    format!("{}{}", POLL_KEY_PREFIX, poll_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::data_store::in_mem::InMemStore;
+
+    #[tokio::test]
+    async fn test_poll_tracker_answers() {
+        let tracker = PollTracker::new(Arc::new(InMemStore::new()));
+        let record = PollRecord {
+            chat_id: ChatId(12345),
+            message_id: MessageId(1),
+            options: vec!["yes".to_string(), "no".to_string()],
+            answers: BTreeMap::new(),
+        };
+        tracker.track("poll", record).await;
+
+        let answer = |user_id: u64, option_ids: Vec<u8>| -> PollAnswer {
+            let yaml = format!(
+                "{{poll_id: poll, user: {{id: {}, is_bot: false, first_name: User}}, option_ids: {:?}}}",
+                user_id, option_ids
+            );
+            serde_yaml::from_str(&yaml).unwrap()
+        };
+        tracker.record_answer(&answer(1, vec![0])).await;
+        tracker.record_answer(&answer(2, vec![1])).await;
+        let record = tracker.record_answer(&answer(3, vec![0])).await.unwrap();
+        assert_eq!(record.votes(), vec![2, 1]);
+
+        // Retracted vote
+        let record = tracker.record_answer(&answer(1, vec![])).await.unwrap();
+        assert_eq!(record.votes(), vec![1, 1]);
+
+        let mut unknown = answer(1, vec![0]);
+        unknown.poll_id.0 = "unknown".to_string();
+        assert!(tracker.record_answer(&unknown).await.is_none());
+    }
+}
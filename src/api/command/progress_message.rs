@@ -0,0 +1,220 @@
+use std::time::{Duration, Instant};
+
+use teloxide::{prelude::ResponseResult, types::Message};
+
+use crate::api::{
+    command::command_reply_target::CommandReplyTarget,
+    markdown::{string::MarkdownString, widgets::ProgressBar},
+};
+
+/// Default minimal interval between progress message edits
+/// Telegram starts rejecting edits with "Too Many Requests" if they are sent too often
+const DEFAULT_MIN_EDIT_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Message displaying the progress of a long-running operation
+///
+/// The message is created from a [`CommandReplyTarget`] and edited in place on each
+/// [`set`](Self::set) call. Edits are rate-limited: intermediate updates arriving
+/// faster than the minimal edit interval are postponed, the final 100% update is always sent.
+/// The last postponed update is sent by [`flush`](Self::flush) or when the message is dropped without
+/// [`finish`](Self::finish). If the progress message was dropped by a middleware, the updates are skipped.
+/// The progress is rendered with the default [`ProgressBar`], it can be replaced with [`with_bar`](Self::with_bar).
+///
+/// # Example
+/// ```ignore
+/// let mut progress = ProgressMessage::start(&target, "Starting…").await?;
+/// for (i, file) in files.iter().enumerate() {
+///     progress.set((i * 100 / files.len()) as u8, "downloading…").await?;
+///     download(file).await;
+/// }
+/// progress.finish(markdown_string!("Done\\!")).await?;
+/// ```
+pub struct ProgressMessage {
+    target: CommandReplyTarget,
+    min_edit_interval: Duration,
+    bar: ProgressBar,
+    last_edit: Option<Instant>,
+    last_state: Option<(u8, MarkdownString)>,
+    // The latest update skipped because the previous edit was too recent
+    pending: Option<(u8, MarkdownString)>,
+    // False if the message wasn't sent, so there is nothing to edit
+    active: bool,
+}
+
+impl ProgressMessage {
+    /// Start the progress message at 0% with the given label
+    /// The current message of the target is edited if present, otherwise a new message is sent
+    pub async fn start(
+        target: &CommandReplyTarget,
+        label: impl Into<MarkdownString>,
+    ) -> ResponseResult<Self> {
+        let label = label.into();
+        let bar = ProgressBar::new();
+        let msg = target
+            .render_markdown_message(render_progress(&bar, 0, &label))
+            .await?;
+        let mut target = target.clone();
+        // The inline messages are edited without a message id
+        let active = msg.is_some() || target.inline_message_id.is_some();
+        target.msg_id = msg.map(|msg| msg.id);
+        Ok(Self {
+            target,
+            min_edit_interval: DEFAULT_MIN_EDIT_INTERVAL,
+            bar,
+            last_edit: Some(Instant::now()),
+            last_state: Some((0, label)),
+            pending: None,
+            active,
+        })
+    }
+
+    /// Set the minimal interval between message edits
+    pub fn with_min_edit_interval(mut self, interval: Duration) -> Self {
+        self.min_edit_interval = interval;
+        self
+    }
+
+    /// Set the progress bar style used for the following updates, its label is replaced by the label of the update
+    pub fn with_bar(mut self, bar: ProgressBar) -> Self {
+        self.bar = bar;
+        self
+    }
+
+    /// Update the progress (0-100) and the label
+    /// The edit is skipped if nothing changed and postponed if the previous edit was too recent,
+    /// unless the progress reached 100%
+    pub async fn set(&mut self, percent: u8, label: impl Into<MarkdownString>) -> ResponseResult<()> {
+        let percent = percent.min(100);
+        let label = label.into();
+        if !self.active {
+            return Ok(());
+        }
+        if self.last_state.as_ref() == Some(&(percent, label.clone())) {
+            self.pending = None;
+            return Ok(());
+        }
+        let too_early = self
+            .last_edit
+            .is_some_and(|last_edit| last_edit.elapsed() < self.min_edit_interval);
+        if too_early && percent < 100 {
+            self.pending = Some((percent, label));
+            return Ok(());
+        }
+        self.edit(percent, label).await
+    }
+
+    /// Send the last update postponed because of the minimal edit interval
+    pub async fn flush(&mut self) -> ResponseResult<()> {
+        match self.pending.take() {
+            Some((percent, label)) if self.active => self.edit(percent, label).await,
+            _ => Ok(()),
+        }
+    }
+
+    /// Replace the progress bar with the final text
+    /// The text is sent as a new message if the progress message was dropped by a middleware.
+    /// Returns `None` if the text was dropped by a middleware of the target
+    pub async fn finish(mut self, text: MarkdownString) -> ResponseResult<Option<Message>> {
+        // The final text replaces the postponed update
+        self.pending = None;
+        self.target.render_markdown_message(text).await
+    }
+
+    /// Internal helper function to edit the message with the progress
+    async fn edit(&mut self, percent: u8, label: MarkdownString) -> ResponseResult<()> {
+        self.pending = None;
+        self.target
+            .render_markdown_message(render_progress(&self.bar, percent, &label))
+            .await?;
+        self.last_edit = Some(Instant::now());
+        self.last_state = Some((percent, label));
+        Ok(())
+    }
+}
+
+impl Drop for ProgressMessage {
+    fn drop(&mut self) {
+        // The postponed update is sent in the background, the message shouldn't stay behind the progress
+        let Some((percent, label)) = self.pending.take().filter(|_| self.active) else {
+            return;
+        };
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        let target = self.target.clone();
+        let text = render_progress(&self.bar, percent, &label);
+        runtime.spawn(async move {
+            if let Err(err) = target.render_markdown_message(text).await {
+                log::warn!("Can't send the last progress update in chat {}: {}", target.chat.id, err);
+            }
+        });
+    }
+}
+
+/// Render the progress bar followed by the percentage and the label
+fn render_progress(bar: &ProgressBar, percent: u8, label: &MarkdownString) -> MarkdownString {
+    bar.clone().label(label.clone()).render(percent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_progress() {
+        let label = MarkdownString::escape("downloading...");
+        assert_eq!(
+            render_progress(&ProgressBar::new(), 0, &label).as_str(),
+            "░░░░░░░░░░ 0%\ndownloading\\.\\.\\."
+        );
+        assert_eq!(
+            render_progress(&ProgressBar::new(), 42, &label).as_str(),
+            "████░░░░░░ 42%\ndownloading\\.\\.\\."
+        );
+        assert_eq!(
+            render_progress(&ProgressBar::new(), 100, &label).as_str(),
+            "██████████ 100%\ndownloading\\.\\.\\."
+        );
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_postponed_update() {
+        use teloxide::types::ChatId;
+
+        use crate::{api::app::bot_app::BotApp, markdown_string, testing::MockBotApi};
+
+        let api = MockBotApi::start().await;
+        let mut dispatcher = BotApp::new(api.bot(), ())
+            .with_middleware(|_, text: MarkdownString| (!text.as_str().contains("hidden")).then_some(text))
+            .command("copy", "", |target, _, _| async move {
+                let mut progress = ProgressMessage::start(&target, markdown_string!("copying"))
+                    .await?
+                    .with_min_edit_interval(Duration::from_secs(3600));
+                progress.set(50, markdown_string!("copying")).await?;
+                // The postponed update is sent when the progress message is dropped
+                drop(progress);
+                Ok(())
+            })
+            .command("hidden", "", |target, _, _| async move {
+                let mut progress = ProgressMessage::start(&target, markdown_string!("hidden")).await?;
+                progress.set(100, markdown_string!("done")).await?;
+                Ok(())
+            })
+            .build();
+        let dispatcher_task = tokio::spawn(async move { dispatcher.dispatch().await });
+
+        api.send_text(ChatId(1), "/copy").await;
+        let started = api.next_request("sendMessage").await.unwrap();
+        assert!(started.str_param("text").unwrap().contains("0%"));
+        let update = api.next_request("editMessageText").await.unwrap();
+        assert!(update.str_param("text").unwrap().contains("50%"));
+        assert_eq!(update.message_id, started.message_id);
+
+        // The progress message dropped by the middleware isn't updated with new messages
+        api.send_text(ChatId(1), "/hidden").await;
+        assert!(api.next_request("sendMessage").await.is_none());
+
+        dispatcher_task.abort();
+    }
+}
@@ -0,0 +1,334 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, LazyLock},
+    time::{Duration, SystemTime},
+};
+
+use serde::{Deserialize, Serialize};
+use teloxide::types::{ChatId, Message, MessageId, UserId};
+use tokio::sync::{Mutex, oneshot};
+
+use crate::api::data_store::data_store_trait::DataStoreTrait;
+
+/// The key under which the pending prompt is stored for each chat, followed by the user id if known
+const PENDING_PROMPT_KEY: &str = "pending_prompt";
+
+/// The number of the answers awaited in each chat by the running tasks of all registries,
+/// keyed by the token of the bot which asked the question
+/// Read synchronously by the update distribution of the [`BotApp`](crate::app::BotApp),
+/// which takes the answers out of the bot's chat queue blocked by the waiting handler
+static AWAITED_CHATS: LazyLock<std::sync::Mutex<HashMap<String, HashMap<ChatId, usize>>>> =
+    LazyLock::new(Default::default);
+
+/// Check if a task of this process is waiting for an answer to the bot's question in the chat,
+/// without the bot token checks the questions of all bots
+pub(crate) fn is_answer_awaited(bot_token: Option<&str>, chat_id: ChatId) -> bool {
+    let bots = AWAITED_CHATS.lock().unwrap_or_else(|err| err.into_inner());
+    match bot_token {
+        Some(bot_token) => bots
+            .get(bot_token)
+            .is_some_and(|chats| chats.contains_key(&chat_id)),
+        None => bots.values().any(|chats| chats.contains_key(&chat_id)),
+    }
+}
+
+/// Registration of the awaited answer in [`AWAITED_CHATS`], removed on drop,
+/// so the chat is released even if the waiting handler is cancelled
+struct AwaitedChat {
+    bot_token: String,
+    chat_id: ChatId,
+}
+
+impl AwaitedChat {
+    fn new(bot_token: &str, chat_id: ChatId) -> Self {
+        *AWAITED_CHATS
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .entry(bot_token.to_string())
+            .or_default()
+            .entry(chat_id)
+            .or_default() += 1;
+        Self {
+            bot_token: bot_token.to_string(),
+            chat_id,
+        }
+    }
+}
+
+impl Drop for AwaitedChat {
+    fn drop(&mut self) {
+        let mut bots = AWAITED_CHATS.lock().unwrap_or_else(|err| err.into_inner());
+        let Some(chats) = bots.get_mut(&self.bot_token) else {
+            return;
+        };
+        if let Some(count) = chats.get_mut(&self.chat_id) {
+            *count -= 1;
+            if *count == 0 {
+                chats.remove(&self.chat_id);
+            }
+        }
+        if chats.is_empty() {
+            bots.remove(&self.bot_token);
+        }
+    }
+}
+
+/// The chat and the user whose answer is awaited
+type PromptWaiterKey = (ChatId, Option<UserId>);
+
+/// Question waiting for the user's answer
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PendingPrompt {
+    /// The message with the question
+    pub question_id: MessageId,
+    /// The time after which the answer is not awaited anymore
+    pub expires_at: SystemTime,
+}
+
+/// Registry of the questions waiting for the user's answer
+///
+/// Used by [`CommandReplyTarget::prompt`](crate::command::CommandReplyTarget::prompt).
+/// The update handler should pass each incoming text message to [`resolve`](Self::resolve)
+/// before processing it as a command. There is at most one pending prompt per user in each chat,
+/// a new prompt replaces the previous one. In groups only the asked user answers the prompt.
+/// The answer must not wait in the same per-chat queue as the handler awaiting it,
+/// the [`BotApp`](crate::app::BotApp) handles the messages of such chats concurrently, only for the asking bot.
+///
+/// The pending prompts are persisted in the data store, so after a restart the bot can
+/// see that the message is an answer to a question, even though the waiting task is gone.
+#[derive(Clone)]
+pub struct PromptRegistry {
+    store: Arc<dyn DataStoreTrait<PendingPrompt>>,
+    waiters: Arc<Mutex<HashMap<PromptWaiterKey, oneshot::Sender<Message>>>>,
+}
+
+impl PromptRegistry {
+    /// Create a new PromptRegistry with the given DataStore
+    pub fn new(store: Arc<dyn DataStoreTrait<PendingPrompt>>) -> Self {
+        Self {
+            store,
+            waiters: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Get the pending prompt of the user in the chat, if it hasn't expired
+    pub async fn pending(&self, chat_id: ChatId, user_id: Option<UserId>) -> Option<PendingPrompt> {
+        let key = prompt_key(user_id);
+        let prompt = self.store.get(chat_id, &key).await?;
+        if prompt.expires_at < SystemTime::now() {
+            self.store.remove(chat_id, &key).await;
+            return None;
+        }
+        Some(prompt)
+    }
+
+    /// Pass the incoming message to the task waiting for the answer of its sender in its chat
+    /// Returns true if the message was consumed as an answer and shouldn't be processed further
+    pub async fn resolve(&self, msg: &Message) -> bool {
+        let user_id = msg.from.as_ref().map(|user| user.id);
+        if msg.text().is_none() || self.pending(msg.chat.id, user_id).await.is_none() {
+            return false;
+        }
+        self.store.remove(msg.chat.id, &prompt_key(user_id)).await;
+        match self.waiters.lock().await.remove(&(msg.chat.id, user_id)) {
+            Some(waiter) => waiter.send(msg.clone()).is_ok(),
+            None => false,
+        }
+    }
+
+    /// Register the prompt of the bot with the given token and wait for the user's answer until the timeout expires
+    pub(crate) async fn wait_answer(
+        &self,
+        bot_token: &str,
+        chat_id: ChatId,
+        user_id: Option<UserId>,
+        question_id: MessageId,
+        timeout: Duration,
+    ) -> Option<Message> {
+        let (sender, receiver) = oneshot::channel();
+        let _awaited = AwaitedChat::new(bot_token, chat_id);
+        self.waiters.lock().await.insert((chat_id, user_id), sender);
+        let prompt = PendingPrompt {
+            question_id,
+            expires_at: SystemTime::now() + timeout,
+        };
+        self.store.set(chat_id, &prompt_key(user_id), prompt).await;
+
+        let answer = tokio::time::timeout(timeout, receiver).await;
+        match answer {
+            Ok(Ok(answer)) => Some(answer),
+            // Replaced by a newer prompt
+            Ok(Err(_)) => None,
+            Err(_) => {
+                // The receiver is dropped at this point, so a closed sender is ours,
+                // not the one of a newer prompt
+                let mut waiters = self.waiters.lock().await;
+                if waiters.get(&(chat_id, user_id)).is_some_and(|waiter| waiter.is_closed()) {
+                    waiters.remove(&(chat_id, user_id));
+                    self.store.remove(chat_id, &prompt_key(user_id)).await;
+                }
+                None
+            }
+        }
+    }
+}
+
+/// Internal helper function to build the data store key of the user's pending prompt
+fn prompt_key(user_id: Option<UserId>) -> String {
+    match user_id {
+        Some(user_id) => format!("{}_{}", PENDING_PROMPT_KEY, user_id),
+        None => PENDING_PROMPT_KEY.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::data_store::in_mem::InMemStore;
+
+    const TEST_CHAT_ID: ChatId = ChatId(-12345);
+    const TEST_USER_ID: UserId = UserId(1);
+
+    fn text_message(chat_id: ChatId, user_id: UserId, text: &str) -> Message {
+        let yaml = format!(
+            "{{message_id: 2, date: 0, chat: {{id: {}, type: group, title: Group}}, \
+             from: {{id: {}, is_bot: false, first_name: User}}, text: '{}'}}",
+            chat_id, user_id, text
+        );
+        serde_yaml::from_str(&yaml).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_prompt_answer() {
+        let registry = PromptRegistry::new(Arc::new(InMemStore::new()));
+        assert!(!registry.resolve(&text_message(TEST_CHAT_ID, TEST_USER_ID, "ignored")).await);
+
+        let waiter = registry.clone();
+        let task = tokio::spawn(async move {
+            waiter
+                .wait_answer("token", TEST_CHAT_ID, Some(TEST_USER_ID), MessageId(1), Duration::from_secs(10))
+                .await
+        });
+        while registry.pending(TEST_CHAT_ID, Some(TEST_USER_ID)).await.is_none() {
+            tokio::task::yield_now().await;
+        }
+        assert!(!registry.resolve(&text_message(ChatId(67890), TEST_USER_ID, "other chat")).await);
+        // Another member of the group doesn't answer the user's prompt
+        assert!(!registry.resolve(&text_message(TEST_CHAT_ID, UserId(2), "other user")).await);
+        assert!(registry.resolve(&text_message(TEST_CHAT_ID, TEST_USER_ID, "answer")).await);
+
+        let answer = task.await.unwrap().unwrap();
+        assert_eq!(answer.text(), Some("answer"));
+        assert!(registry.pending(TEST_CHAT_ID, Some(TEST_USER_ID)).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_prompt_timeout() {
+        let registry = PromptRegistry::new(Arc::new(InMemStore::new()));
+        let answer = registry
+            .wait_answer("token", TEST_CHAT_ID, Some(TEST_USER_ID), MessageId(1), Duration::from_millis(10))
+            .await;
+        assert!(answer.is_none());
+        assert!(registry.pending(TEST_CHAT_ID, Some(TEST_USER_ID)).await.is_none());
+        assert!(!registry.resolve(&text_message(TEST_CHAT_ID, TEST_USER_ID, "late")).await);
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_prompt_in_bot_app() {
+        use crate::{api::app::bot_app::BotApp, markdown_format, markdown_string, testing::MockBotApi};
+
+        let api = MockBotApi::start().await;
+        let registry = PromptRegistry::new(Arc::new(InMemStore::new()));
+        let mut dispatcher = BotApp::new(api.bot(), ())
+            .configure_target(move |target| target.with_prompt_registry(registry.clone()))
+            .command("name", "", |target, _, _| async move {
+                let answer = target
+                    .prompt(markdown_string!("What is your name?"), Duration::from_secs(10))
+                    .await?;
+                let name = answer.as_ref().and_then(Message::text).unwrap_or("nobody");
+                target.markdown_message(markdown_format!("Hello, {}", name)).await?;
+                Ok(())
+            })
+            .build();
+        let dispatcher_task = tokio::spawn(async move { dispatcher.dispatch().await });
+        // The chat is not shared with the other tests, the awaited answers are global
+        let chat_id = ChatId(31);
+
+        api.send_text(chat_id, "/name").await;
+        let question = api.next_request("sendMessage").await.unwrap();
+        assert_eq!(question.str_param("text"), Some("What is your name?"));
+        let token = api.bot().token().to_string();
+        while !is_answer_awaited(Some(&token), chat_id) {
+            tokio::task::yield_now().await;
+        }
+        // The answer is not queued behind the handler waiting for it
+        api.send_text(chat_id, "Alice").await;
+        let reply = api.next_request("sendMessage").await.unwrap();
+        assert_eq!(reply.str_param("text"), Some("Hello, Alice"));
+        assert!(!is_answer_awaited(Some(&token), chat_id));
+
+        dispatcher_task.abort();
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_prompt_of_other_bot() {
+        use teloxide::Bot;
+
+        use crate::{api::app::bot_app::BotApp, markdown_string, testing::MockBotApi};
+
+        let (api, other_api) = (MockBotApi::start().await, MockBotApi::start().await);
+        let other_bot = Bot::new("other_token").set_api_url(other_api.bot().api_url());
+        let registries = [(); 2].map(|_| PromptRegistry::new(Arc::new(InMemStore::new())));
+        let dispatchers = BotApp::new(api.bot(), ())
+            .add_bot("other", other_bot)
+            .configure_target(move |target| {
+                let registry = registries[usize::from(target.bot_name.is_some())].clone();
+                target.with_prompt_registry(registry)
+            })
+            .command("name", "", |target, _, _| async move {
+                target
+                    .prompt(markdown_string!("What is your name?"), Duration::from_secs(10))
+                    .await?;
+                Ok(())
+            })
+            .command("slow", "", |target, _, _| async move {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                target.markdown_message(markdown_string!("slow")).await?;
+                Ok(())
+            })
+            .command("fast", "", |target, _, _| async move {
+                target.markdown_message(markdown_string!("fast")).await?;
+                Ok(())
+            })
+            .build_all();
+        let tasks: Vec<_> = dispatchers
+            .into_iter()
+            .map(|mut dispatcher| tokio::spawn(async move { dispatcher.dispatch().await }))
+            .collect();
+        // The chat is not shared with the other tests, the awaited answers are global
+        let chat_id = ChatId(32);
+
+        api.send_text(chat_id, "/name").await;
+        assert!(api.next_request("sendMessage").await.is_some());
+        let token = api.bot().token().to_string();
+        while !is_answer_awaited(Some(&token), chat_id) {
+            tokio::task::yield_now().await;
+        }
+        assert!(!is_answer_awaited(Some("other_token"), chat_id));
+        // The messages of the chat are still handled in order by the other bot
+        other_api.send_text(chat_id, "/slow").await;
+        other_api.send_text(chat_id, "/fast").await;
+        let replies: Vec<_> = [
+            other_api.next_request("sendMessage").await,
+            other_api.next_request("sendMessage").await,
+        ]
+        .into_iter()
+        .map(|request| request.unwrap().str_param("text").unwrap().to_string())
+        .collect();
+        assert_eq!(replies, ["slow", "fast"]);
+
+        tasks.iter().for_each(|task| task.abort());
+    }
+}
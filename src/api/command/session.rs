@@ -0,0 +1,222 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use serde::{Deserialize, Serialize};
+use teloxide::types::{ChatId, UserId};
+use tokio::sync::Mutex;
+
+use crate::api::{
+    command::command_reply_target::CommandReplyTarget,
+    data_store::data_store_trait::DataStoreTrait,
+};
+
+/// The key under which the session of the chat itself is stored
+const CHAT_SESSION_KEY: &str = "session";
+
+/// Typed state of the user in the chat, backed by a data store
+///
+/// The state is loaded on the first access and written back only if it was changed.
+/// Clones of the session share the state.
+#[derive(Clone)]
+pub struct Session<S> {
+    inner: Arc<SessionInner<S>>,
+}
+
+struct SessionInner<S> {
+    store: Arc<dyn DataStoreTrait<S>>,
+    chat_id: ChatId,
+    key: String,
+    state: Mutex<Option<S>>,
+    dirty: AtomicBool,
+}
+
+impl<S> Session<S>
+where
+    S: Serialize + for<'de> Deserialize<'de> + Default + Clone + Send + Sync + 'static,
+{
+    /// Create the session of the user in the chat, or of the chat itself if the user is `None`
+    pub fn new(store: Arc<dyn DataStoreTrait<S>>, chat_id: ChatId, user_id: Option<UserId>) -> Self {
+        Self {
+            inner: Arc::new(SessionInner {
+                store,
+                chat_id,
+                key: session_key(user_id),
+                state: Mutex::new(None),
+                dirty: AtomicBool::new(false),
+            }),
+        }
+    }
+
+    /// Get a copy of the state, the default state if nothing is stored
+    pub async fn get(&self) -> S {
+        let mut state = self.inner.state.lock().await;
+        self.load(&mut state).await.clone()
+    }
+
+    /// Modify the state, the change is written back on [`save`](Self::save)
+    pub async fn update<R>(&self, f: impl FnOnce(&mut S) -> R) -> R {
+        let mut state = self.inner.state.lock().await;
+        let result = f(self.load(&mut state).await);
+        self.inner.dirty.store(true, Ordering::SeqCst);
+        result
+    }
+
+    /// Reset the state to the default one and remove it from the store
+    pub async fn clear(&self) {
+        let mut state = self.inner.state.lock().await;
+        *state = Some(S::default());
+        self.inner.dirty.store(false, Ordering::SeqCst);
+        self.inner
+            .store
+            .remove(self.inner.chat_id, &self.inner.key)
+            .await;
+    }
+
+    /// Write the state back to the store if it was changed
+    /// Returns true if the state was written
+    pub async fn save(&self) -> bool {
+        let state = self.inner.state.lock().await;
+        if !self.inner.dirty.swap(false, Ordering::SeqCst) {
+            return false;
+        }
+        let Some(state) = state.as_ref() else {
+            return false;
+        };
+        self.inner
+            .store
+            .set(self.inner.chat_id, &self.inner.key, state.clone())
+            .await;
+        true
+    }
+
+    /// Internal helper function to load the state from the store on the first access
+    async fn load<'a>(&self, state: &'a mut Option<S>) -> &'a mut S {
+        if state.is_none() {
+            let stored = self.inner.store.get(self.inner.chat_id, &self.inner.key).await;
+            *state = Some(stored.unwrap_or_default());
+        }
+        state.get_or_insert_with(S::default)
+    }
+}
+
+/// Internal helper function to build the data store key of the session
+fn session_key(user_id: Option<UserId>) -> String {
+    match user_id {
+        Some(user_id) => format!("{}_{}", CHAT_SESSION_KEY, user_id),
+        None => CHAT_SESSION_KEY.to_string(),
+    }
+}
+
+/// Identity of the session: the chat and the user, if any
+type SessionId = (ChatId, Option<UserId>);
+
+/// Store of the sessions handed to the command handlers through the context
+///
+/// The sessions are shared by the handlers running concurrently for the same user and chat.
+/// Registered with [`BotApp::with_sessions`](crate::app::BotApp::with_sessions), the changed
+/// sessions are written back after each handler run.
+#[derive(Clone)]
+pub struct SessionStore<S> {
+    store: Arc<dyn DataStoreTrait<S>>,
+    open: Arc<Mutex<HashMap<SessionId, Session<S>>>>,
+}
+
+impl<S> SessionStore<S>
+where
+    S: Serialize + for<'de> Deserialize<'de> + Default + Clone + Send + Sync + 'static,
+{
+    /// Create a new SessionStore with the given DataStore
+    pub fn new(store: Arc<dyn DataStoreTrait<S>>) -> Self {
+        Self {
+            store,
+            open: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Get the session of the user who triggered the command in the target's chat
+    pub async fn session(&self, target: &CommandReplyTarget) -> Session<S> {
+        self.session_of(target.chat.id, target.user_id).await
+    }
+
+    /// Get the session of the user in the chat, or of the chat itself if the user is `None`
+    pub async fn session_of(&self, chat_id: ChatId, user_id: Option<UserId>) -> Session<S> {
+        self.open
+            .lock()
+            .await
+            .entry((chat_id, user_id))
+            .or_insert_with(|| Session::new(self.store.clone(), chat_id, user_id))
+            .clone()
+    }
+
+    /// Write back the changed session of the user in the chat
+    /// The session is closed if no handler holds it anymore
+    pub async fn write_back(&self, chat_id: ChatId, user_id: Option<UserId>) {
+        let mut open = self.open.lock().await;
+        let Some(session) = open.get(&(chat_id, user_id)) else {
+            return;
+        };
+        session.save().await;
+        if Arc::strong_count(&session.inner) == 1 {
+            open.remove(&(chat_id, user_id));
+        }
+    }
+}
+
+/// Sessions written back by the app after each handler run
+#[async_trait::async_trait]
+pub(crate) trait SessionWriteBack: Send + Sync {
+    async fn write_back(&self, chat_id: ChatId, user_id: Option<UserId>);
+}
+
+#[async_trait::async_trait]
+impl<S> SessionWriteBack for SessionStore<S>
+where
+    S: Serialize + for<'de> Deserialize<'de> + Default + Clone + Send + Sync + 'static,
+{
+    async fn write_back(&self, chat_id: ChatId, user_id: Option<UserId>) {
+        SessionStore::write_back(self, chat_id, user_id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::data_store::in_mem::InMemStore;
+
+    #[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+    struct Counter {
+        count: u32,
+    }
+
+    #[tokio::test]
+    async fn test_session_write_back() {
+        let store: Arc<dyn DataStoreTrait<Counter>> = Arc::new(InMemStore::new());
+        let sessions = SessionStore::new(store.clone());
+        let chat_id = ChatId(12345);
+        let user_id = Some(UserId(1));
+
+        let session = sessions.session_of(chat_id, user_id).await;
+        assert_eq!(session.get().await, Counter::default());
+        session.update(|counter| counter.count += 1).await;
+        // Shared with the concurrent handlers, not written yet
+        assert_eq!(sessions.session_of(chat_id, user_id).await.get().await.count, 1);
+        assert_eq!(store.get(chat_id, "session_1").await, None);
+
+        drop(session);
+        sessions.write_back(chat_id, user_id).await;
+        assert_eq!(store.get(chat_id, "session_1").await, Some(Counter { count: 1 }));
+
+        // Reloaded lazily from the store
+        let session = sessions.session_of(chat_id, user_id).await;
+        assert_eq!(session.get().await.count, 1);
+        assert!(!session.save().await);
+        session.clear().await;
+        assert_eq!(store.get(chat_id, "session_1").await, None);
+        assert_eq!(sessions.session_of(chat_id, None).await.get().await.count, 0);
+    }
+}
@@ -0,0 +1,349 @@
+use std::{future::Future, sync::Arc};
+
+use teloxide::prelude::ResponseResult;
+
+use crate::{
+    api::{
+        app::bot_app::BoxFuture,
+        command::{
+            command_registry::{reply_usage_at, run_authorized, usage},
+            command_reply_target::CommandReplyTarget,
+            command_trait::CommandTrait,
+        },
+        markdown::string::MarkdownString,
+    },
+    markdown_format,
+};
+
+/// Handler of the subcommand, receives the command path like `/settings notify` and the arguments after it
+type SubcommandHandler<Ctx> = Arc<
+    dyn Fn(CommandReplyTarget, Ctx, String, String) -> BoxFuture<ResponseResult<()>> + Send + Sync,
+>;
+
+/// Subcommand registered in the [`SubcommandRouter`]
+struct Subcommand<Ctx> {
+    name: String,
+    description: String,
+    /// The placeholders of the arguments shown in the help, e.g. `<on|off>`
+    placeholders: String,
+    handler: SubcommandHandler<Ctx>,
+}
+
+/// Command dispatching to the nested commands by its first argument, e.g. `/settings notify on`
+///
+/// The subcommands are registered like in the [`CommandRegistry`](crate::command::CommandRegistry):
+/// the [`CommandTrait`] types are registered under their [`NAME`](CommandTrait::NAME) and parse
+/// the arguments after it, the routers can be nested for the deeper hierarchies.
+/// If the subcommand is missing or unknown, the user gets the list of the available ones.
+/// Register the router with [`CommandRegistry::router`](crate::command::CommandRegistry::router).
+///
+/// # Example
+/// ```ignore
+/// let settings = SubcommandRouter::new("settings")
+///     .register::<NotifyCommand>("turn the notifications on or off")
+///     .register::<LanguageCommand>("change the language");
+/// let registry = CommandRegistry::new().router(settings, "change the settings");
+/// ```
+pub struct SubcommandRouter<Ctx = ()> {
+    name: String,
+    subcommands: Vec<Subcommand<Ctx>>,
+}
+
+impl<Ctx> SubcommandRouter<Ctx>
+where
+    Ctx: Clone + Send + Sync + 'static,
+{
+    /// Create an empty router of the command with the given name (without the leading slash)
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            subcommands: Vec::new(),
+        }
+    }
+
+    /// The name of the command
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Register the command type as the subcommand selected by its [`NAME`](CommandTrait::NAME)
+    /// The subcommand registered earlier with the same name is replaced.
+    pub fn register<C>(self, description: impl Into<String>) -> Self
+    where
+        C: CommandTrait<Context = Ctx> + 'static,
+    {
+        let placeholders = C::PLACEHOLDERS.join(" ");
+        self.add(
+            C::NAME,
+            description,
+            placeholders,
+            move |target, context, path, args| async move {
+                match C::parse_arguments(args) {
+                    Ok((command,)) => run_authorized(command, &target, context).await,
+                    Err(err) => reply_usage_at::<C>(&target, err, &path).await,
+                }
+            },
+        )
+    }
+
+    /// Register the handler of the subcommand with the given name
+    /// The handler receives the rest of the message after the subcommand name as the arguments.
+    pub fn command<F, Fut>(
+        self,
+        name: impl Into<String>,
+        description: impl Into<String>,
+        handler: F,
+    ) -> Self
+    where
+        F: Fn(CommandReplyTarget, Ctx, String) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ResponseResult<()>> + Send + 'static,
+    {
+        self.add(
+            name,
+            description,
+            String::new(),
+            move |target, context, _, args| handler(target, context, args),
+        )
+    }
+
+    /// Register the nested router as the subcommand selected by its name
+    pub fn nest(self, router: SubcommandRouter<Ctx>, description: impl Into<String>) -> Self {
+        let name = router.name.clone();
+        let router = Arc::new(router);
+        self.add(
+            name,
+            description,
+            String::new(),
+            move |target, context, path, args| {
+                let router = router.clone();
+                async move { router.run_at(&target, context, &path, &args).await }
+            },
+        )
+    }
+
+    /// The names and the descriptions of the subcommands
+    pub fn subcommands(&self) -> Vec<(&str, &str)> {
+        self.subcommands
+            .iter()
+            .map(|subcommand| (subcommand.name.as_str(), subcommand.description.as_str()))
+            .collect()
+    }
+
+    /// Render the list of the subcommands with their arguments and descriptions
+    pub fn help(&self) -> MarkdownString {
+        self.help_at(&format!("/{}", self.name))
+    }
+
+    /// Run the subcommand selected by the first argument, or reply with the list of the subcommands
+    pub async fn run(
+        &self,
+        target: &CommandReplyTarget,
+        context: Ctx,
+        args: &str,
+    ) -> ResponseResult<()> {
+        self.run_at(target, context, &format!("/{}", self.name), args)
+            .await
+    }
+
+    /// Internal helper function to register the subcommand's handler
+    fn add<F, Fut>(
+        mut self,
+        name: impl Into<String>,
+        description: impl Into<String>,
+        placeholders: String,
+        handler: F,
+    ) -> Self
+    where
+        F: Fn(CommandReplyTarget, Ctx, String, String) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ResponseResult<()>> + Send + 'static,
+    {
+        let name = name.into();
+        self.subcommands
+            .retain(|subcommand| subcommand.name != name);
+        self.subcommands.push(Subcommand {
+            name,
+            description: description.into(),
+            placeholders,
+            handler: Arc::new(move |target, context, path, args| {
+                Box::pin(handler(target, context, path, args))
+            }),
+        });
+        self
+    }
+
+    /// Internal helper function to run the subcommand of the command with the given path, e.g. `/settings`
+    async fn run_at(
+        &self,
+        target: &CommandReplyTarget,
+        context: Ctx,
+        path: &str,
+        args: &str,
+    ) -> ResponseResult<()> {
+        let args = args.trim_start();
+        let (selector, rest) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+        if selector.is_empty() {
+            target.markdown_message(self.help_at(path)).await?;
+            return Ok(());
+        }
+        let Some(subcommand) = self
+            .subcommands
+            .iter()
+            .find(|subcommand| subcommand.name == selector)
+        else {
+            let text = markdown_format!("Unknown subcommand {} of {}\n", selector, path)
+                + self.help_at(path);
+            target.markdown_message(text).await?;
+            return Ok(());
+        };
+        let path = format!("{} {}", path, subcommand.name);
+        (subcommand.handler)(target.clone(), context, path, rest.trim_start().to_string()).await
+    }
+
+    /// Internal helper function to render the list of the subcommands of the command with the given path
+    fn help_at(&self, path: &str) -> MarkdownString {
+        let list = self
+            .subcommands
+            .iter()
+            .map(|subcommand| {
+                let usage = usage(
+                    &format!("{} {}", path, subcommand.name),
+                    &subcommand.placeholders,
+                );
+                match subcommand.description.as_str() {
+                    "" => usage,
+                    description => format!("{} - {}", usage, description),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        markdown_format!("Subcommands of {}:\n{}", path, list)
+    }
+}
+
+#[cfg(all(test, feature = "testing"))]
+mod tests {
+    use teloxide::{dispatching::Dispatcher, types::ChatId};
+
+    use super::*;
+    use crate::{
+        api::command::{
+            command_arg::{EmptyArg, Req},
+            command_registry::CommandRegistry,
+        },
+        markdown_string,
+        testing::MockBotApi,
+    };
+
+    #[derive(Clone)]
+    struct LimitCommand(Option<Req<u32>>);
+
+    impl CommandTrait for LimitCommand {
+        type A = Req<u32>;
+        type B = EmptyArg;
+        type C = EmptyArg;
+        type D = EmptyArg;
+        type E = EmptyArg;
+        type F = EmptyArg;
+        type G = EmptyArg;
+        type H = EmptyArg;
+        type I = EmptyArg;
+        type FlagArgs = EmptyArg;
+        type Context = ();
+        const NAME: &'static str = "limit";
+        const PLACEHOLDERS: &[&'static str] = &["<n>"];
+
+        fn from_arguments(
+            a: Option<Req<u32>>,
+            _b: Option<EmptyArg>,
+            _c: Option<EmptyArg>,
+            _d: Option<EmptyArg>,
+            _e: Option<EmptyArg>,
+            _f: Option<EmptyArg>,
+            _g: Option<EmptyArg>,
+            _h: Option<EmptyArg>,
+            _i: Option<EmptyArg>,
+        ) -> Self {
+            Self(a)
+        }
+
+        fn param1(&self) -> Option<&Req<u32>> {
+            self.0.as_ref()
+        }
+
+        async fn run1(
+            &self,
+            target: &CommandReplyTarget,
+            _: (),
+            limit: &Req<u32>,
+        ) -> ResponseResult<()> {
+            target
+                .markdown_message(markdown_format!("Limit set to {}", limit.to_string()))
+                .await?;
+            Ok(())
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_subcommand_router() {
+        let api = MockBotApi::start().await;
+        let notify = SubcommandRouter::new("notify")
+            .command("on", "", |target, _, _| async move {
+                target
+                    .markdown_message(markdown_string!("Notifications on"))
+                    .await?;
+                Ok(())
+            })
+            .command("off", "", |target, _, _| async move {
+                target
+                    .markdown_message(markdown_string!("Notifications off"))
+                    .await?;
+                Ok(())
+            });
+        let settings = SubcommandRouter::new("settings")
+            .register::<LimitCommand>("set the limit")
+            .nest(notify, "turn the notifications on or off");
+        let registry = CommandRegistry::new().router(settings, "change the settings");
+        let mut dispatcher = Dispatcher::builder(api.bot(), registry.handler(())).build();
+        let dispatcher_task = tokio::spawn(async move { dispatcher.dispatch().await });
+        let chat_id = ChatId(1);
+
+        api.send_text(chat_id, "/settings").await;
+        let help = api.next_request("sendMessage").await.unwrap();
+        assert_eq!(
+            help.str_param("text"),
+            Some(
+                "Subcommands of /settings:\n/settings limit <n\\> \\- set the limit\n\
+                 /settings notify \\- turn the notifications on or off"
+            )
+        );
+
+        api.send_text(chat_id, "/settings notify on").await;
+        let reply = api.next_request("sendMessage").await.unwrap();
+        assert_eq!(reply.str_param("text"), Some("Notifications on"));
+
+        api.send_text(chat_id, "/settings notify loud").await;
+        let reply = api.next_request("sendMessage").await.unwrap();
+        assert_eq!(
+            reply.str_param("text"),
+            Some(
+                "Unknown subcommand loud of /settings notify\n\
+                 Subcommands of /settings notify:\n/settings notify on\n/settings notify off"
+            )
+        );
+
+        api.send_text(chat_id, "/settings limit many").await;
+        let reply = api.next_request("sendMessage").await.unwrap();
+        assert!(
+            reply
+                .str_param("text")
+                .unwrap()
+                .ends_with("\nUsage: /settings limit <n\\>")
+        );
+
+        api.send_text(chat_id, "/settings limit 5").await;
+        let reply = api.next_request("sendMessage").await.unwrap();
+        assert_eq!(reply.str_param("text"), Some("Limit set to 5"));
+
+        dispatcher_task.abort();
+    }
+}
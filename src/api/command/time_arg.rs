@@ -0,0 +1,239 @@
+use std::{fmt::Display, ops::Deref, time::Duration};
+
+#[cfg(feature = "chrono")]
+use chrono::{Days, NaiveDate, NaiveTime, Timelike, Utc};
+use teloxide::utils::command::ParseError;
+
+use crate::api::command::command_arg::{ParseCommandArg, invalid_input};
+
+/// Units of the durations with their lengths in milliseconds, from the largest
+const DURATION_UNITS: [(&str, u128); 6] = [
+    ("w", 604_800_000),
+    ("d", 86_400_000),
+    ("h", 3_600_000),
+    ("m", 60_000),
+    ("s", 1000),
+    ("ms", 1),
+];
+
+/// Formats of the dates accepted in addition to "today", "tomorrow" and "yesterday"
+#[cfg(feature = "chrono")]
+const DATE_FORMATS: [&str; 3] = ["%Y-%m-%d", "%d.%m.%Y", "%Y/%m/%d"];
+
+/// Duration argument like `15m`, `2h30m`, `1d` or `90s`, the number without the unit is in seconds
+///
+/// The units are `w`, `d`, `h`, `m`, `s` and `ms`, the longer names like `min` or `hours` are accepted too.
+/// Displayed in the canonical form, e.g. `1h30m` for `90m`.
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub struct DurationArg(pub Duration);
+
+impl Deref for DurationArg {
+    type Target = Duration;
+
+    fn deref(&self) -> &Duration {
+        &self.0
+    }
+}
+
+impl Display for DurationArg {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut millis = self.0.as_millis();
+        if millis == 0 {
+            return write!(f, "0s");
+        }
+        for (unit, length) in DURATION_UNITS {
+            let value = millis / length;
+            millis %= length;
+            if value > 0 {
+                write!(f, "{}{}", value, unit)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ParseCommandArg for DurationArg {
+    fn parse_command_arg(arg: &str) -> Result<Self, ParseError> {
+        let error = || invalid_input(format!("Invalid duration {}, expected e.g. 15m or 2h30m", arg));
+        let arg = arg.trim().to_lowercase();
+        if let Ok(seconds) = arg.parse::<u64>() {
+            return Ok(DurationArg(Duration::from_secs(seconds)));
+        }
+        if arg.is_empty() {
+            return Err(error());
+        }
+        let mut millis: u128 = 0;
+        let mut rest = arg.as_str();
+        while !rest.is_empty() {
+            let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+            let value: u128 = rest[..digits].parse().map_err(|_| error())?;
+            rest = &rest[digits..];
+            let letters = rest.find(|c: char| c.is_ascii_digit()).unwrap_or(rest.len());
+            let length = unit_length(&rest[..letters]).ok_or_else(error)?;
+            rest = &rest[letters..];
+            millis = value
+                .checked_mul(length)
+                .and_then(|value| millis.checked_add(value))
+                .ok_or_else(error)?;
+        }
+        let millis = u64::try_from(millis).map_err(|_| error())?;
+        Ok(DurationArg(Duration::from_millis(millis)))
+    }
+}
+
+/// Internal helper function to get the length of the duration unit in milliseconds
+fn unit_length(unit: &str) -> Option<u128> {
+    let unit = match unit {
+        "w" | "week" | "weeks" => "w",
+        "d" | "day" | "days" => "d",
+        "h" | "hr" | "hour" | "hours" => "h",
+        "m" | "min" | "mins" | "minute" | "minutes" => "m",
+        "s" | "sec" | "secs" | "second" | "seconds" => "s",
+        "ms" => "ms",
+        _ => return None,
+    };
+    DURATION_UNITS
+        .iter()
+        .find(|(name, _)| *name == unit)
+        .map(|(_, length)| *length)
+}
+
+/// Date argument like `2024-01-05`, `05.01.2024`, `today`, `tomorrow` or `yesterday`
+///
+/// The relative dates are resolved in UTC when the command is parsed.
+/// Displayed in the ISO 8601 form, e.g. `2024-01-05`.
+#[cfg(feature = "chrono")]
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub struct DateArg(pub NaiveDate);
+
+#[cfg(feature = "chrono")]
+impl Deref for DateArg {
+    type Target = NaiveDate;
+
+    fn deref(&self) -> &NaiveDate {
+        &self.0
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl Display for DateArg {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.format("%Y-%m-%d").fmt(f)
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl ParseCommandArg for DateArg {
+    fn parse_command_arg(arg: &str) -> Result<Self, ParseError> {
+        let today = Utc::now().date_naive();
+        let date = match arg.trim().to_lowercase().as_str() {
+            "today" => Some(today),
+            "tomorrow" => today.checked_add_days(Days::new(1)),
+            "yesterday" => today.checked_sub_days(Days::new(1)),
+            arg => DATE_FORMATS
+                .iter()
+                .find_map(|format| NaiveDate::parse_from_str(arg, format).ok()),
+        };
+        date.map(DateArg).ok_or_else(|| {
+            invalid_input(format!("Invalid date {}, expected e.g. 2024-01-05 or today", arg))
+        })
+    }
+}
+
+/// Time of the day argument like `15:30`, `15:30:45`, `3pm` or `9:15am`
+///
+/// Displayed in the 24-hour form, e.g. `15:30`, the seconds are shown only if they aren't zero.
+#[cfg(feature = "chrono")]
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub struct TimeArg(pub NaiveTime);
+
+#[cfg(feature = "chrono")]
+impl Deref for TimeArg {
+    type Target = NaiveTime;
+
+    fn deref(&self) -> &NaiveTime {
+        &self.0
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl Display for TimeArg {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.0.second() == 0 {
+            self.0.format("%H:%M").fmt(f)
+        } else {
+            self.0.format("%H:%M:%S").fmt(f)
+        }
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl ParseCommandArg for TimeArg {
+    fn parse_command_arg(arg: &str) -> Result<Self, ParseError> {
+        parse_time(&arg.trim().to_lowercase()).map(TimeArg).ok_or_else(|| {
+            invalid_input(format!("Invalid time {}, expected e.g. 15:30 or 3pm", arg))
+        })
+    }
+}
+
+/// Internal helper function to parse the time with the optional seconds and the am/pm suffix
+#[cfg(feature = "chrono")]
+fn parse_time(arg: &str) -> Option<NaiveTime> {
+    let (arg, pm) = match (arg.strip_suffix("am"), arg.strip_suffix("pm")) {
+        (Some(arg), _) => (arg.trim_end(), Some(false)),
+        (_, Some(arg)) => (arg.trim_end(), Some(true)),
+        _ => (arg, None),
+    };
+    let mut parts = arg.split(':').map(|part| part.parse::<u32>().ok());
+    let mut hour = parts.next()??;
+    let minute = parts.next().unwrap_or(Some(0))?;
+    let second = parts.next().unwrap_or(Some(0))?;
+    if parts.next().is_some() || (pm.is_none() && !arg.contains(':')) {
+        return None;
+    }
+    if let Some(pm) = pm {
+        if !(1..=12).contains(&hour) {
+            return None;
+        }
+        hour = hour % 12 + if pm { 12 } else { 0 };
+    }
+    NaiveTime::from_hms_opt(hour, minute, second)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_time_args() {
+        let duration = |arg| DurationArg::parse_command_arg(arg).map(|d| d.to_string()).ok();
+        assert_eq!(duration("15m").as_deref(), Some("15m"));
+        assert_eq!(duration("2h30m").as_deref(), Some("2h30m"));
+        assert_eq!(duration("90min").as_deref(), Some("1h30m"));
+        assert_eq!(duration("1d2H").as_deref(), Some("1d2h"));
+        assert_eq!(duration("45").as_deref(), Some("45s"));
+        assert_eq!(duration("1500ms").as_deref(), Some("1s500ms"));
+        assert_eq!(duration("0m").as_deref(), Some("0s"));
+        assert_eq!(duration("15x"), None);
+        assert_eq!(duration("h"), None);
+        assert_eq!(duration(""), None);
+
+        #[cfg(feature = "chrono")]
+        {
+            let date = |arg| DateArg::parse_command_arg(arg).map(|d| d.to_string()).ok();
+            assert_eq!(date("2024-01-05").as_deref(), Some("2024-01-05"));
+            assert_eq!(date("05.01.2024").as_deref(), Some("2024-01-05"));
+            assert_eq!(date("Today"), Some(Utc::now().date_naive().to_string()));
+            assert_eq!(date("2024-02-30"), None);
+
+            let time = |arg| TimeArg::parse_command_arg(arg).map(|t| t.to_string()).ok();
+            assert_eq!(time("15:30").as_deref(), Some("15:30"));
+            assert_eq!(time("9:05:07").as_deref(), Some("09:05:07"));
+            assert_eq!(time("3pm").as_deref(), Some("15:00"));
+            assert_eq!(time("12:15AM").as_deref(), Some("00:15"));
+            assert_eq!(time("15"), None);
+            assert_eq!(time("13pm"), None);
+            assert_eq!(time("25:00"), None);
+        }
+    }
+}
@@ -0,0 +1,263 @@
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use teloxide::{
+    prelude::ResponseResult,
+    types::{ChatId, UserId},
+    utils::command::ParseError,
+};
+
+use crate::{
+    api::{
+        command::{
+            command_arg::{mark_flag_candidates, split_flags}, command_registry::{reply_usage, run_authorized},
+            command_reply_target::CommandReplyTarget, command_trait::CommandTrait,
+        },
+        data_store::data_store_trait::DataStoreTrait,
+        parse::command_string::{screen_spaces, split_with_offsets},
+    },
+    markdown_format,
+};
+
+/// The key under which the pending wizard is stored for each user in the chat
+const PENDING_WIZARD_KEY: &str = "pending_wizard";
+
+/// Command waiting for the user to enter its next argument
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PendingWizard {
+    /// The command with the arguments entered so far, e.g. `/add 2`
+    pub command: String,
+    /// The placeholder of the requested argument
+    pub placeholder: String,
+    /// The requested argument takes the answer verbatim, see [`RestArg`](crate::command::RestArg)
+    pub rest: bool,
+}
+
+/// Guided input of the arguments missing in the command
+///
+/// Used by the commands registered with [`CommandRegistry::register_wizard`](crate::command::CommandRegistry::register_wizard)
+/// when the target has the wizard, see [`CommandReplyTarget::with_command_wizard`].
+/// If the command is sent with fewer arguments than its placeholders, the bot asks for each
+/// missing argument in sequence and runs the command when all of them are entered.
+/// Each answer is checked by the argument's [`ParseCommandArg`](crate::command::ParseCommandArg),
+/// the invalid one is asked again. Sending another command cancels the wizard.
+/// If the target has the [`OperationRegistry`](crate::command::OperationRegistry), the wizard is
+/// registered as the user's operation, so `/cancel` aborts it.
+///
+/// The update handler should pass each incoming text message to [`answer`](Self::answer)
+/// and run the returned command instead, [`BotApp`](crate::app::BotApp) does it.
+/// The entered arguments are persisted in the data store, so the wizard survives a restart.
+#[derive(Clone)]
+pub struct CommandWizard {
+    store: Arc<dyn DataStoreTrait<PendingWizard>>,
+}
+
+impl CommandWizard {
+    /// Create a new CommandWizard with the given DataStore
+    pub fn new(store: Arc<dyn DataStoreTrait<PendingWizard>>) -> Self {
+        Self { store }
+    }
+
+    /// Get the command waiting for the argument of the user in the chat
+    pub async fn pending(&self, chat_id: ChatId, user_id: Option<UserId>) -> Option<PendingWizard> {
+        self.store.get(chat_id, &wizard_key(user_id)).await
+    }
+
+    /// Stop waiting for the argument of the user in the chat
+    /// Returns true if there was a pending wizard
+    pub async fn cancel(&self, chat_id: ChatId, user_id: Option<UserId>) -> bool {
+        self.store.remove(chat_id, &wizard_key(user_id)).await
+    }
+
+    /// Append the text to the pending command of the target's user as the requested argument
+    /// Returns the command to run, or `None` if there is no pending wizard. The text starting
+    /// with a slash is another command, it cancels the wizard.
+    pub async fn answer(&self, target: &CommandReplyTarget, text: &str) -> Option<String> {
+        let pending = self.pending(target.chat.id, target.user_id).await?;
+        if text.starts_with('/') {
+            self.cancel(target.chat.id, target.user_id).await;
+            finish_operation(target, &pending.command).await;
+            return None;
+        }
+        let answer = if pending.rest {
+            text.to_string()
+        } else {
+            screen_spaces(&text.lines().collect::<Vec<_>>().join(" "))
+        };
+        Some(format!("{} {}", pending.command, answer))
+    }
+
+    /// Run the command if all its arguments are entered, otherwise ask for the next one
+    pub(crate) async fn run<C: CommandTrait>(
+        &self,
+        target: &CommandReplyTarget,
+        context: C::Context,
+        args: String,
+    ) -> ResponseResult<()> {
+        let pending = self
+            .pending(target.chat.id, target.user_id)
+            .await
+            .filter(|pending| pending.command.split(' ').next() == Some(format!("/{}", C::NAME).as_str()));
+        let tokens = split_with_offsets(&args);
+        let entered = split_flags(mark_flag_candidates::<C::FlagArgs>(&args, &tokens), None).0.len();
+        let command = format!("/{} {}", C::NAME, args).trim_end().to_string();
+        match C::parse_arguments(args) {
+            Ok((command,)) if entered >= C::PLACEHOLDERS.len() => {
+                self.cancel(target.chat.id, target.user_id).await;
+                finish_operation(target, &format!("/{}", C::NAME)).await;
+                run_authorized(command, target, context).await
+            }
+            // The answer is invalid, the pending command is kept
+            Err(err) if !is_missing_argument(&err) && pending.is_some() => {
+                let placeholder = pending.map(|pending| pending.placeholder).unwrap_or_default();
+                target
+                    .markdown_message(markdown_format!("{}\nEnter {}", err.to_string(), placeholder))
+                    .await?;
+                Ok(())
+            }
+            Err(err) if !is_missing_argument(&err) => reply_usage::<C>(target, err).await,
+            _ => {
+                let placeholder = C::PLACEHOLDERS[entered.min(C::PLACEHOLDERS.len() - 1)].to_string();
+                let question = markdown_format!("Enter {} for /{}", placeholder.clone(), C::NAME);
+                // Stored before asking, so that the quick answer finds it
+                let pending = PendingWizard {
+                    command,
+                    placeholder,
+                    rest: C::rest_slot() == Some(entered),
+                };
+                self.store
+                    .set(target.chat.id, &wizard_key(target.user_id), pending)
+                    .await;
+                target.start_operation(format!("/{}", C::NAME), None).await;
+                target.markdown_message(question).await?;
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Internal helper function to unregister the wizard's operation, named after its command
+async fn finish_operation(target: &CommandReplyTarget, command: &str) {
+    if let Some(operation_registry) = &target.operation_registry {
+        let name = command.split(' ').next().unwrap_or(command);
+        operation_registry.finish(target.chat.id, target.user_id, name).await;
+    }
+}
+
+/// Internal helper function to check if the parse error is caused by the missing argument
+fn is_missing_argument(err: &ParseError) -> bool {
+    matches!(err, ParseError::TooFewArguments { .. })
+}
+
+/// Internal helper function to build the data store key of the user's wizard
+fn wizard_key(user_id: Option<UserId>) -> String {
+    match user_id {
+        Some(user_id) => format!("{}_{}", PENDING_WIZARD_KEY, user_id),
+        None => PENDING_WIZARD_KEY.to_string(),
+    }
+}
+
+#[cfg(all(test, feature = "testing"))]
+mod tests {
+    use super::*;
+    use crate::{
+        api::{
+            app::bot_app::BotApp,
+            command::{
+                command_arg::{EmptyArg, RestArg},
+                command_registry::CommandRegistry,
+            },
+            data_store::in_mem::InMemStore,
+            testing::mock_bot_api::MOCK_USER_ID,
+        },
+        testing::MockBotApi,
+    };
+
+    #[derive(Clone)]
+    struct RemindCommand(Option<u32>, Option<RestArg>);
+
+    impl CommandTrait for RemindCommand {
+        type A = u32;
+        type B = RestArg;
+        type C = EmptyArg;
+        type D = EmptyArg;
+        type E = EmptyArg;
+        type F = EmptyArg;
+        type G = EmptyArg;
+        type H = EmptyArg;
+        type I = EmptyArg;
+        type FlagArgs = EmptyArg;
+        type Context = ();
+        const NAME: &'static str = "remind";
+        const PLACEHOLDERS: &[&'static str] = &["<minutes>", "<text>"];
+
+        fn from_arguments(
+            a: Option<u32>,
+            b: Option<RestArg>,
+            _c: Option<EmptyArg>,
+            _d: Option<EmptyArg>,
+            _e: Option<EmptyArg>,
+            _f: Option<EmptyArg>,
+            _g: Option<EmptyArg>,
+            _h: Option<EmptyArg>,
+            _i: Option<EmptyArg>,
+        ) -> Self {
+            Self(a, b)
+        }
+
+        fn param1(&self) -> Option<&u32> {
+            self.0.as_ref()
+        }
+
+        fn param2(&self) -> Option<&RestArg> {
+            self.1.as_ref()
+        }
+
+        async fn run2(
+            &self,
+            target: &CommandReplyTarget,
+            _: (),
+            minutes: &u32,
+            text: &RestArg,
+        ) -> ResponseResult<()> {
+            target
+                .markdown_message(markdown_format!("{} in {}m", text.to_string(), minutes.to_string()))
+                .await?;
+            Ok(())
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_command_wizard() {
+        let api = MockBotApi::start().await;
+        let wizard = CommandWizard::new(Arc::new(InMemStore::new()));
+        let app_wizard = wizard.clone();
+        let mut dispatcher = BotApp::new(api.bot(), ())
+            .configure_target(move |target| target.with_command_wizard(app_wizard.clone()))
+            .with_commands(CommandRegistry::new().register_wizard::<RemindCommand>("remind"))
+            .build();
+        let dispatcher_task = tokio::spawn(async move { dispatcher.dispatch().await });
+        let chat_id = ChatId(1);
+        let user_id = Some(UserId(MOCK_USER_ID));
+
+        api.send_text(chat_id, "/remind").await;
+        let question = api.next_request("sendMessage").await.unwrap();
+        assert_eq!(question.str_param("text"), Some("Enter <minutes\\> for /remind"));
+
+        api.send_text(chat_id, "soon").await;
+        let retry = api.next_request("sendMessage").await.unwrap();
+        assert!(retry.str_param("text").unwrap().ends_with("\nEnter <minutes\\>"));
+
+        api.send_text(chat_id, "15").await;
+        let question = api.next_request("sendMessage").await.unwrap();
+        assert_eq!(question.str_param("text"), Some("Enter <text\\> for /remind"));
+        assert_eq!(wizard.pending(chat_id, user_id).await.unwrap().command, "/remind 15");
+
+        api.send_text(chat_id, "buy \"milk\"").await;
+        let reply = api.next_request("sendMessage").await.unwrap();
+        assert_eq!(reply.str_param("text"), Some("buy \"milk\" in 15m"));
+        assert!(wizard.pending(chat_id, user_id).await.is_none());
+
+        dispatcher_task.abort();
+    }
+}
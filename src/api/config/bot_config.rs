@@ -0,0 +1,274 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use serde::{Deserialize, Serialize};
+use teloxide::{Bot, types::ChatId};
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::api::data_store::file_system_yaml::FilesystemYamlStore;
+use crate::api::data_store::{data_store_trait::DataStoreTrait, in_mem::InMemStore};
+
+/// Environment variable with the path of the config file
+pub const CONFIG_PATH_ENV: &str = "TELLURIDE_CONFIG";
+
+/// Environment variable with the bot token, the same as used by [`Bot::from_env`]
+pub const TOKEN_ENV: &str = "TELOXIDE_TOKEN";
+
+/// Environment variable with the directory of the filesystem storage,
+/// setting it switches the storage backend to the filesystem
+pub const STORAGE_PATH_ENV: &str = "TELLURIDE_STORAGE_PATH";
+
+/// Environment variable with the comma separated admin chat ids
+pub const ADMIN_CHAT_IDS_ENV: &str = "TELLURIDE_ADMIN_CHAT_IDS";
+
+/// Environment variable with the locale of the bot
+pub const LOCALE_ENV: &str = "TELLURIDE_LOCALE";
+
+/// Environment variable with the comma separated feature toggles,
+/// a name prefixed with `-` disables the feature, e.g. "stats,-polls"
+pub const FEATURES_ENV: &str = "TELLURIDE_FEATURES";
+
+/// Error loading the bot configuration
+#[derive(Debug)]
+pub enum ConfigError {
+    /// The config file can't be read
+    Io(PathBuf, std::io::Error),
+    /// The config file is not a valid YAML config
+    Parse(PathBuf, serde_yaml::Error),
+    /// The environment variable has an invalid value
+    InvalidValue(&'static str, String),
+    /// The bot token is set neither in the config file nor in the environment
+    MissingToken,
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(path, _) => write!(f, "can't read config {}", path.display()),
+            ConfigError::Parse(path, _) => write!(f, "can't parse config {}", path.display()),
+            ConfigError::InvalidValue(name, value) => {
+                write!(f, "invalid value of {}: {:?}", name, value)
+            }
+            ConfigError::MissingToken => write!(f, "bot token is not set, set {}", TOKEN_ENV),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConfigError::Io(_, err) => Some(err),
+            ConfigError::Parse(_, err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// Backend of the bot's data stores
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "backend")]
+pub enum StorageConfig {
+    /// Keep the data in memory, it's lost on restart
+    #[default]
+    Memory,
+    /// Keep the data in YAML files in the given directory, see [`FilesystemYamlStore`]
+    Filesystem { path: PathBuf },
+}
+
+/// Typed configuration of the bot
+///
+/// Loaded from the YAML file and/or the environment variables, the environment overrides the file.
+/// The file looks like:
+///
+/// ```yaml
+/// token: "123456:ABC"
+/// storage:
+///   backend: filesystem
+///   path: ./data
+/// admin_chat_ids: [12345]
+/// locale: en
+/// features:
+///   stats: true
+/// ```
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BotConfig {
+    /// The bot token
+    pub token: String,
+    /// The backend of the data stores
+    pub storage: StorageConfig,
+    /// The chats allowed to use the admin commands
+    pub admin_chat_ids: Vec<ChatId>,
+    /// The locale of the bot's messages
+    pub locale: Option<String>,
+    /// The feature toggles
+    pub features: BTreeMap<String, bool>,
+}
+
+impl fmt::Debug for BotConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Don't leak the token to the logs
+        f.debug_struct("BotConfig")
+            .field("token", &"***")
+            .field("storage", &self.storage)
+            .field("admin_chat_ids", &self.admin_chat_ids)
+            .field("locale", &self.locale)
+            .field("features", &self.features)
+            .finish()
+    }
+}
+
+impl BotConfig {
+    /// Load the config from the file at [`CONFIG_PATH_ENV`] if set,
+    /// then override it with the environment variables
+    pub fn load() -> Result<Self, ConfigError> {
+        let config = match std::env::var_os(CONFIG_PATH_ENV) {
+            Some(path) => Self::from_file(path)?,
+            None => Self::default(),
+        };
+        config.with_env_vars(std::env::vars())?.validated()
+    }
+
+    /// Load the config from the environment variables only
+    pub fn from_env() -> Result<Self, ConfigError> {
+        Self::default().with_env_vars(std::env::vars())?.validated()
+    }
+
+    /// Load the config from the YAML file, without applying the environment variables
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let content =
+            std::fs::read_to_string(path).map_err(|err| ConfigError::Io(path.to_path_buf(), err))?;
+        serde_yaml::from_str(&content).map_err(|err| ConfigError::Parse(path.to_path_buf(), err))
+    }
+
+    /// Override the config with the given environment variables, unknown variables are ignored
+    pub fn with_env_vars(
+        mut self,
+        vars: impl IntoIterator<Item = (String, String)>,
+    ) -> Result<Self, ConfigError> {
+        for (name, value) in vars {
+            match name.as_str() {
+                TOKEN_ENV => self.token = value,
+                STORAGE_PATH_ENV => {
+                    self.storage = StorageConfig::Filesystem { path: value.into() };
+                }
+                ADMIN_CHAT_IDS_ENV => {
+                    self.admin_chat_ids = value
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|id| !id.is_empty())
+                        .map(|id| id.parse().map(ChatId))
+                        .collect::<Result<_, _>>()
+                        .map_err(|_| ConfigError::InvalidValue(ADMIN_CHAT_IDS_ENV, value.clone()))?;
+                }
+                LOCALE_ENV => self.locale = Some(value),
+                FEATURES_ENV => {
+                    for feature in value.split(',').map(str::trim).filter(|f| !f.is_empty()) {
+                        match feature.strip_prefix('-') {
+                            Some(feature) => self.features.insert(feature.to_string(), false),
+                            None => self.features.insert(feature.to_string(), true),
+                        };
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(self)
+    }
+
+    /// Internal helper function to check that the required values are set
+    fn validated(self) -> Result<Self, ConfigError> {
+        if self.token.is_empty() {
+            return Err(ConfigError::MissingToken);
+        }
+        Ok(self)
+    }
+
+    /// Create the bot with the configured token
+    pub fn bot(&self) -> Bot {
+        Bot::new(&self.token)
+    }
+
+    /// Create the data store with the given name on the configured backend
+    /// The filesystem stores are kept in the subdirectories named after the stores,
+    /// on `wasm32` the memory stores are used instead
+    pub fn store<V>(&self, name: &str) -> Arc<dyn DataStoreTrait<V>>
+    where
+        V: Serialize + for<'de> Deserialize<'de> + Send + Sync + Clone + 'static,
+    {
+        match &self.storage {
+            StorageConfig::Memory => Arc::new(InMemStore::new()),
+            #[cfg(not(target_arch = "wasm32"))]
+            StorageConfig::Filesystem { path } => Arc::new(FilesystemYamlStore::new(path.join(name))),
+            #[cfg(target_arch = "wasm32")]
+            StorageConfig::Filesystem { .. } => {
+                log::warn!("Filesystem storage is not available on wasm32, keeping {} in memory", name);
+                Arc::new(InMemStore::new())
+            }
+        }
+    }
+
+    /// Check if the chat is allowed to use the admin commands
+    pub fn is_admin(&self, chat_id: ChatId) -> bool {
+        self.admin_chat_ids.contains(&chat_id)
+    }
+
+    /// Check if the feature is enabled, unknown features are disabled
+    pub fn feature(&self, name: &str) -> bool {
+        self.features.get(name).copied().unwrap_or(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(vars: &[(&str, &str)]) -> Vec<(String, String)> {
+        vars.iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_config_env_overrides_file() {
+        let config: BotConfig = serde_yaml::from_str(
+            "{token: file_token, storage: {backend: filesystem, path: data}, features: {stats: true, polls: true}}",
+        )
+        .unwrap();
+        assert_eq!(
+            config.storage,
+            StorageConfig::Filesystem { path: "data".into() }
+        );
+
+        let config = config
+            .with_env_vars(vars(&[
+                (TOKEN_ENV, "env_token"),
+                (ADMIN_CHAT_IDS_ENV, "1, -100200"),
+                (FEATURES_ENV, "-polls,quiz"),
+                ("UNRELATED", "value"),
+            ]))
+            .unwrap()
+            .validated()
+            .unwrap();
+        assert_eq!(config.token, "env_token");
+        assert!(config.is_admin(ChatId(-100200)));
+        assert!(!config.is_admin(ChatId(2)));
+        assert!(config.feature("stats"));
+        assert!(!config.feature("polls"));
+        assert!(config.feature("quiz"));
+        assert!(!config.feature("unknown"));
+        assert!(!format!("{:?}", config).contains("env_token"));
+
+        let invalid = BotConfig::default().with_env_vars(vars(&[(ADMIN_CHAT_IDS_ENV, "admin")]));
+        assert!(matches!(invalid, Err(ConfigError::InvalidValue(ADMIN_CHAT_IDS_ENV, _))));
+        assert!(matches!(
+            BotConfig::default().validated(),
+            Err(ConfigError::MissingToken)
+        ));
+    }
+}
@@ -0,0 +1 @@
+pub(crate) mod bot_config;
@@ -1,6 +1,10 @@
 use serde::{Deserialize, Serialize};
 use teloxide::types::ChatId;
 
+/// Chat namespace of the data which is not bound to a specific chat
+/// Telegram never uses zero as a chat id, so it can't clash with real chats' data
+pub(crate) const GLOBAL_NAMESPACE: ChatId = ChatId(0);
+
 /// Trait for key-value data storage with serializable values
 /// Storage is organized per-chat, with each chat having its own key-value namespace
 #[async_trait::async_trait]
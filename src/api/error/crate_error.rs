@@ -0,0 +1,318 @@
+use std::fmt::{self, Display};
+#[cfg(feature = "teloxide")]
+use std::sync::Arc;
+
+#[cfg(feature = "teloxide")]
+use teloxide::{RequestError, utils::command::ParseError};
+
+#[cfg(feature = "teloxide")]
+use crate::api::{config::bot_config::ConfigError, markdown::template::TemplateError};
+#[cfg(feature = "fluent")]
+use crate::api::i18n::localizer::I18nError;
+use crate::api::markdown::validate::MarkdownError;
+
+/// Result type with [`Error`] as the default error
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Error of the telluride APIs
+///
+/// Consolidates the errors of the different modules, so that the application can use `?`
+/// across them. The context describing what was being done can be attached with
+/// [`ResultExt::context`], the original error is kept as the source.
+/// The error is displayed without its sources, use the alternate format `{:#}`
+/// to display the whole chain, e.g. "starting bot: loading store: i/o error: missing".
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// Telegram request failed
+    #[cfg(feature = "teloxide")]
+    Request(RequestError),
+    /// Command arguments can't be parsed
+    #[cfg(feature = "teloxide")]
+    Parse(ParseError),
+    /// Bot configuration can't be loaded
+    #[cfg(feature = "teloxide")]
+    Config(ConfigError),
+    /// Stored value can't be serialized or deserialized
+    #[cfg(feature = "teloxide")]
+    Serialization(serde_yaml::Error),
+    /// Text loaded at runtime is not valid MarkdownV2
+    Markdown(MarkdownError),
+    /// Message template can't be loaded or formatted
+    #[cfg(feature = "teloxide")]
+    Template(TemplateError),
+    /// Localized message can't be loaded or formatted
+    #[cfg(feature = "fluent")]
+    I18n(I18nError),
+    /// File operation failed, e.g. in the filesystem store
+    Io(std::io::Error),
+    /// Custom error of the application, e.g. returned by a command handler, see [`Error::other`]
+    Other(Box<dyn std::error::Error + Send + Sync>),
+    /// Error with the description of the failed operation
+    Context {
+        /// What was being done when the error happened
+        context: String,
+        /// The original error
+        source: Box<Error>,
+    },
+}
+
+impl Error {
+    /// Wrap the custom error of the application
+    pub fn other(err: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> Self {
+        Error::Other(err.into())
+    }
+
+    /// Wrap the error with the description of the failed operation
+    pub fn context(self, context: impl Into<String>) -> Self {
+        Error::Context {
+            context: context.into(),
+            source: Box::new(self),
+        }
+    }
+
+    /// Get the error without the context wrappers
+    pub fn root(&self) -> &Error {
+        match self {
+            Error::Context { source, .. } => source.root(),
+            err => err,
+        }
+    }
+
+    /// Get the custom error of the given type, e.g. the one returned by a command handler
+    /// The custom errors passed as `RequestError::Io` are found too
+    pub fn downcast_ref<E: std::error::Error + 'static>(&self) -> Option<&E> {
+        let inner = match self.root() {
+            Error::Other(err) => return err.downcast_ref(),
+            #[cfg(feature = "teloxide")]
+            Error::Request(RequestError::Io(err)) => err.get_ref()?,
+            Error::Io(err) => err.get_ref()?,
+            _ => return None,
+        };
+        inner
+            .downcast_ref()
+            .or_else(|| inner.downcast_ref::<Error>()?.downcast_ref())
+    }
+
+    /// Get the Telegram request error, if it's the cause of the error
+    #[cfg(feature = "teloxide")]
+    pub fn request_error(&self) -> Option<&RequestError> {
+        match self.root() {
+            Error::Request(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(feature = "teloxide")]
+            Error::Request(_) => write!(f, "telegram request failed")?,
+            #[cfg(feature = "teloxide")]
+            Error::Parse(_) => write!(f, "can't parse command arguments")?,
+            #[cfg(feature = "teloxide")]
+            Error::Config(err) => write!(f, "{}", err)?,
+            #[cfg(feature = "teloxide")]
+            Error::Serialization(_) => write!(f, "serialization failed")?,
+            Error::Markdown(_) => write!(f, "invalid markdown")?,
+            #[cfg(feature = "teloxide")]
+            Error::Template(err) => write!(f, "{}", err)?,
+            #[cfg(feature = "fluent")]
+            Error::I18n(err) => write!(f, "{}", err)?,
+            Error::Io(_) => write!(f, "i/o error")?,
+            Error::Other(err) => write!(f, "{}", err)?,
+            Error::Context { context, .. } => write!(f, "{}", context)?,
+        }
+        if f.alternate() {
+            let mut source = std::error::Error::source(self);
+            while let Some(err) = source {
+                write!(f, ": {}", err)?;
+                source = err.source();
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The wrappers of the module errors are transparent: they are displayed as the wrapped error
+/// and their source is the source of the wrapped error
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            #[cfg(feature = "teloxide")]
+            Error::Request(err) => Some(err),
+            #[cfg(feature = "teloxide")]
+            Error::Parse(err) => Some(err),
+            #[cfg(feature = "teloxide")]
+            Error::Config(err) => err.source(),
+            #[cfg(feature = "teloxide")]
+            Error::Serialization(err) => Some(err),
+            Error::Markdown(err) => Some(err),
+            #[cfg(feature = "teloxide")]
+            Error::Template(err) => err.source(),
+            #[cfg(feature = "fluent")]
+            Error::I18n(err) => err.source(),
+            Error::Io(err) => Some(err),
+            Error::Other(err) => err.source(),
+            Error::Context { source, .. } => Some(source.as_ref()),
+        }
+    }
+}
+
+#[cfg(feature = "teloxide")]
+impl From<RequestError> for Error {
+    fn from(err: RequestError) -> Self {
+        Error::Request(err)
+    }
+}
+
+#[cfg(feature = "teloxide")]
+impl From<ParseError> for Error {
+    fn from(err: ParseError) -> Self {
+        Error::Parse(err)
+    }
+}
+
+#[cfg(feature = "teloxide")]
+impl From<ConfigError> for Error {
+    fn from(err: ConfigError) -> Self {
+        Error::Config(err)
+    }
+}
+
+#[cfg(feature = "teloxide")]
+impl From<serde_yaml::Error> for Error {
+    fn from(err: serde_yaml::Error) -> Self {
+        Error::Serialization(err)
+    }
+}
+
+#[cfg(feature = "teloxide")]
+impl From<TemplateError> for Error {
+    fn from(err: TemplateError) -> Self {
+        Error::Template(err)
+    }
+}
+
+#[cfg(feature = "fluent")]
+impl From<I18nError> for Error {
+    fn from(err: I18nError) -> Self {
+        Error::I18n(err)
+    }
+}
+
+impl From<MarkdownError> for Error {
+    fn from(err: MarkdownError) -> Self {
+        Error::Markdown(err)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<Box<dyn std::error::Error + Send + Sync>> for Error {
+    fn from(err: Box<dyn std::error::Error + Send + Sync>) -> Self {
+        Error::Other(err)
+    }
+}
+
+/// The Telegram request errors are unwrapped, the other errors are passed as `RequestError::Io`,
+/// so that the handlers returning [`ResponseResult`](teloxide::prelude::ResponseResult) can use `?` with them
+#[cfg(feature = "teloxide")]
+impl From<Error> for RequestError {
+    fn from(err: Error) -> Self {
+        match err {
+            Error::Request(err) => err,
+            err => RequestError::Io(Arc::new(std::io::Error::other(err))),
+        }
+    }
+}
+
+/// Extension of the results with the errors convertible to [`Error`] to attach the context
+///
+/// # Example
+///
+/// ```rust
+/// use telluride::{Result, ResultExt};
+///
+/// fn read_template(path: &str) -> Result<String> {
+///     std::fs::read_to_string(path).context(format!("reading template {}", path))
+/// }
+///
+/// let err = read_template("/nonexistent").unwrap_err();
+/// assert_eq!(err.to_string(), "reading template /nonexistent");
+/// assert!(format!("{:#}", err).starts_with("reading template /nonexistent: i/o error: "));
+/// ```
+pub trait ResultExt<T> {
+    /// Convert the error to [`Error`] with the given context
+    fn context(self, context: impl Into<String>) -> Result<T>;
+
+    /// Convert the error to [`Error`] with the context evaluated only on error
+    fn with_context<C: Into<String>>(self, context: impl FnOnce() -> C) -> Result<T>;
+}
+
+impl<T, E: Into<Error>> ResultExt<T> for std::result::Result<T, E> {
+    fn context(self, context: impl Into<String>) -> Result<T> {
+        self.map_err(|err| err.into().context(context))
+    }
+
+    fn with_context<C: Into<String>>(self, context: impl FnOnce() -> C) -> Result<T> {
+        self.map_err(|err| err.into().context(context()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_context() {
+        let io_error = std::io::Error::new(std::io::ErrorKind::NotFound, "missing");
+        let result: std::result::Result<(), _> = Err(io_error);
+        let err = result
+            .context("loading store")
+            .with_context(|| "starting bot")
+            .unwrap_err();
+        assert_eq!(err.to_string(), "starting bot");
+        assert_eq!(format!("{:#}", err), "starting bot: loading store: i/o error: missing");
+        assert!(matches!(err.root(), Error::Io(_)));
+        assert!(std::error::Error::source(&err).is_some());
+    }
+
+    #[test]
+    fn test_transparent_error() {
+        let err = Error::other("quota exceeded").context("reading quota");
+        assert_eq!(format!("{:#}", err), "reading quota: quota exceeded");
+        let markdown_error =
+            crate::api::markdown::validate::check_markdownv2_format("*bold", false).unwrap_err();
+        let err = Error::from(markdown_error);
+        assert_eq!(format!("{:#}", err), format!("invalid markdown: {}", markdown_error));
+    }
+
+    #[cfg(feature = "teloxide")]
+    #[test]
+    fn test_request_error() {
+        let err: Error = RequestError::RetryAfter(teloxide::types::Seconds::from_seconds(1)).into();
+        let err = err.context("sending message");
+        assert!(matches!(err.request_error(), Some(RequestError::RetryAfter(_))));
+    }
+
+    #[cfg(feature = "teloxide")]
+    #[test]
+    fn test_custom_error() {
+        let parse_error = "x".parse::<i32>().unwrap_err();
+        let err = Error::other(parse_error.clone()).context("reading quota");
+        assert_eq!(err.downcast_ref::<std::num::ParseIntError>(), Some(&parse_error));
+        assert!(err.downcast_ref::<std::fmt::Error>().is_none());
+        // The custom error survives the round trip through the request error
+        let err = Error::from(RequestError::from(err));
+        assert_eq!(err.downcast_ref::<std::num::ParseIntError>(), Some(&parse_error));
+        let retry_after = RequestError::RetryAfter(teloxide::types::Seconds::from_seconds(1));
+        let request_error = RequestError::from(Error::from(retry_after));
+        assert!(matches!(request_error, RequestError::RetryAfter(_)));
+    }
+}
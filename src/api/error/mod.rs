@@ -0,0 +1 @@
+pub(crate) mod crate_error;
@@ -0,0 +1,131 @@
+/// Creates an HtmlString with compile-time validation of the format string.
+#[macro_export]
+macro_rules! html_string {
+    ($format_str:expr) => {{
+        // Compile-time validation for Telegram HTML format compatibility
+        const _: () = $crate::html::validate_html_format($format_str);
+        $crate::html::HtmlString::from_validated_string($format_str)
+    }};
+}
+
+/// Helper macro to process arguments in any order, handling @code, @raw, and regular arguments.
+///
+/// This uses incremental TT munching to process one argument at a time.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! html_process_args {
+    // Base case: no more arguments, return accumulated vector
+    (@munch [] -> [$($processed:tt)*]) => {
+        vec![$($processed)*]
+    };
+
+    // Process @code with language - must come before @raw to match correctly
+    (@munch [@code $lang:literal $code_content:expr $(, $($tail:tt)*)?] -> [$($processed:tt)*]) => {
+        $crate::html_process_args!(@munch [$($($tail)*)?] -> [
+            $($processed)*
+            {
+                let content: $crate::html::HtmlString = $code_content.into();
+                format!("<pre><code class=\"language-{}\">{}</code></pre>", $lang, content.as_str())
+            },
+        ])
+    };
+
+    // Process @code without language
+    (@munch [@code $code_content:expr $(, $($tail:tt)*)?] -> [$($processed:tt)*]) => {
+        $crate::html_process_args!(@munch [$($($tail)*)?] -> [
+            $($processed)*
+            {
+                let content: $crate::html::HtmlString = $code_content.into();
+                format!("<pre>{}</pre>", content.as_str())
+            },
+        ])
+    };
+
+    // Process @raw argument
+    (@munch [@raw $raw_arg:expr $(, $($tail:tt)*)?] -> [$($processed:tt)*]) => {
+        $crate::html_process_args!(@munch [$($($tail)*)?] -> [
+            $($processed)*
+            {
+                let html: $crate::html::HtmlString = $raw_arg;
+                html.as_str().to_string()
+            },
+        ])
+    };
+
+    // Process regular argument
+    (@munch [$arg:expr $(, $($tail:tt)*)?] -> [$($processed:tt)*]) => {
+        $crate::html_process_args!(@munch [$($($tail)*)?] -> [
+            $($processed)*
+            {
+                let arg_html: $crate::html::HtmlString = $arg.into();
+                arg_html.as_str().to_string()
+            },
+        ])
+    };
+
+    // Entry point
+    ($($args:tt)*) => {
+        $crate::html_process_args!(@munch [$($args)*] -> [])
+    };
+}
+
+/// Formats an HtmlString using either a &str literal (with compile-time validation) or an HtmlString as the template.
+///
+/// If a &str literal is provided, it will be validated at compile-time using `html_string!`.
+/// Arguments must be types that implement `Into<HtmlString>`, the strings are escaped.
+///
+/// # Special Argument Modifiers
+///
+/// - `@raw`: Pass an HtmlString without re-escaping (for pre-formatted HTML)
+/// - `@code`: Wrap the escaped content in a `<pre>` block
+/// - `@code "lang"`: Wrap the escaped content in a language-specific `<pre><code>` block
+///
+/// # Examples
+/// ```rust
+/// use telluride::{html_format, html_string};
+///
+/// let user = "<Alice>";
+/// let html = html_format!("Hello <b>{}</b>! {}", user, @raw html_string!("<i>Welcome</i>"));
+/// assert_eq!(html.as_str(), "Hello <b>&lt;Alice&gt;</b>! <i>Welcome</i>");
+/// ```
+#[macro_export]
+macro_rules! html_format {
+    // String literal with no arguments
+    ($format_str:literal) => {
+        $crate::html_string!($format_str)
+    };
+
+    // String literal with arguments - delegate to HtmlString version
+    ($format_str:literal, $($args:tt)*) => {
+        $crate::html_format!($crate::html_string!($format_str), $($args)*)
+    };
+
+    // HtmlString with no arguments
+    ($format_html:expr) => {{
+        let html_string: $crate::html::HtmlString = $format_html;
+        html_string
+    }};
+
+    // HtmlString with arguments
+    ($format_html:expr, $($args:tt)*) => {{
+        let html_string: $crate::html::HtmlString = $format_html;
+        let format_str = html_string.as_str();
+
+        // Process all arguments using the helper macro
+        let escaped_args: Vec<String> = $crate::html_process_args!($($args)*);
+
+        // Replace placeholders with converted arguments
+        let mut result = String::with_capacity(format_str.len());
+        let mut rest = format_str;
+        for escaped_arg in escaped_args {
+            if let Some(placeholder_pos) = rest.find("{}") {
+                result.push_str(&rest[..placeholder_pos]);
+                result.push_str(&escaped_arg);
+                rest = &rest[placeholder_pos + 2..];
+            }
+        }
+        result.push_str(rest);
+
+        $crate::html::HtmlString::from_validated_string(result)
+    }};
+}
@@ -0,0 +1,3 @@
+pub(crate) mod macros;
+pub(crate) mod string;
+pub(crate) mod validate;
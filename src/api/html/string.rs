@@ -0,0 +1,279 @@
+use std::{fmt, ops::Add};
+
+#[cfg(feature = "teloxide")]
+use teloxide::{
+    payloads::{EditMessageTextInlineSetters, EditMessageTextSetters, SendMessageSetters},
+    prelude::Requester,
+    types::{MessageId, ParseMode::Html, Recipient},
+};
+
+/// A wrapper around [`String`] that ensures safe HTML formatting for Telegram messages.
+///
+/// The HTML counterpart of [`MarkdownString`](crate::markdown::MarkdownString), useful when the
+/// formatting is deeply nested. It can only be constructed through safe methods:
+/// 1. [`html_string!`](crate::html_string!) macro - statically validates the format string at compile time
+/// 2. [`escape`](Self::escape) constructor - automatically escapes the HTML special characters in the input
+/// 3. [`new`](Self::new) constructor - creates an empty HtmlString
+/// 4. [`From`]/[`Into`] trait - automatically escapes the input for safety
+///
+/// # HTML Support
+///
+/// This type is designed to work with Telegram's [HTML](https://core.telegram.org/bots/api#html-style) format.
+/// Use with [`HtmlStringMessage::send_html_message`](crate::html::HtmlStringMessage::send_html_message)
+/// to send messages with proper formatting.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct HtmlString(String);
+
+/// Internal helper function to escape the HTML special characters,
+/// the quotes are escaped too to make the text safe in the attribute values
+pub(crate) fn escape_html(input: &str) -> String {
+    let mut escaped = String::with_capacity(input.len());
+    for c in input.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+impl HtmlString {
+    /// Creates an HtmlString by escaping all HTML special characters in the input.
+    ///
+    /// # Example
+    /// ```rust
+    /// use telluride::html::HtmlString;
+    ///
+    /// let html = HtmlString::escape("<b>Tom & Jerry</b>");
+    /// assert_eq!(html.as_str(), "&lt;b&gt;Tom &amp; Jerry&lt;/b&gt;");
+    /// ```
+    pub fn escape<T: Into<String>>(input: T) -> Self {
+        HtmlString(escape_html(&input.into()))
+    }
+
+    /// Creates an empty HtmlString.
+    pub fn new() -> Self {
+        HtmlString::default()
+    }
+
+    /// Private constructor for use by the html_string! macro after compile-time validation.
+    /// This should only be called by trusted code that has already validated the input.
+    #[doc(hidden)]
+    pub fn from_validated_string(s: impl Into<String>) -> Self {
+        HtmlString(s.into())
+    }
+
+    /// Returns the inner string value
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Consumes the HtmlString and returns the inner String
+    pub fn into_string(self) -> String {
+        self.0
+    }
+
+    /// Adds other HtmlString to self
+    pub fn push(&mut self, other: &HtmlString) {
+        self.0.push_str(&other.0);
+    }
+}
+
+impl fmt::Display for HtmlString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl AsRef<str> for HtmlString {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<HtmlString> for String {
+    fn from(html: HtmlString) -> String {
+        html.0
+    }
+}
+
+impl From<String> for HtmlString {
+    fn from(s: String) -> Self {
+        HtmlString::escape(s)
+    }
+}
+
+impl From<&String> for HtmlString {
+    fn from(s: &String) -> Self {
+        HtmlString::escape(s)
+    }
+}
+
+impl From<&str> for HtmlString {
+    fn from(s: &str) -> Self {
+        HtmlString::escape(s)
+    }
+}
+
+// Implement From for common numeric types
+impl From<i32> for HtmlString {
+    fn from(n: i32) -> Self {
+        HtmlString::escape(n.to_string())
+    }
+}
+
+impl From<i64> for HtmlString {
+    fn from(n: i64) -> Self {
+        HtmlString::escape(n.to_string())
+    }
+}
+
+impl From<f32> for HtmlString {
+    fn from(n: f32) -> Self {
+        HtmlString::escape(n.to_string())
+    }
+}
+
+impl From<f64> for HtmlString {
+    fn from(n: f64) -> Self {
+        HtmlString::escape(n.to_string())
+    }
+}
+
+impl From<usize> for HtmlString {
+    fn from(n: usize) -> Self {
+        HtmlString::escape(n.to_string())
+    }
+}
+
+impl From<isize> for HtmlString {
+    fn from(n: isize) -> Self {
+        HtmlString::escape(n.to_string())
+    }
+}
+
+impl Add for HtmlString {
+    type Output = HtmlString;
+
+    fn add(self, other: HtmlString) -> HtmlString {
+        let mut result = self;
+        result.push(&other);
+        result
+    }
+}
+
+impl Add<&HtmlString> for HtmlString {
+    type Output = HtmlString;
+
+    fn add(self, other: &HtmlString) -> HtmlString {
+        let mut result = self;
+        result.push(other);
+        result
+    }
+}
+
+/// Trait for sending HTML messages with [teloxide Bot](https://docs.rs/teloxide/latest/teloxide/struct.Bot.html)
+///
+/// The HTML counterpart of [`MarkdownStringMessage`](crate::markdown::MarkdownStringMessage),
+/// automatically setting the parse mode to `HTML`.
+///
+/// # Example
+///
+/// ```rust
+/// use telluride::{html::HtmlStringMessage, html_format};
+/// use teloxide::{Bot, types::ChatId};
+///
+/// async fn send_html_example(bot: Bot, chat_id: ChatId, name: &str) {
+///     bot.send_html_message(chat_id, html_format!("Hello <b>{}</b>!", name))
+///         .await
+///         .unwrap();
+/// }
+/// ```
+#[allow(async_fn_in_trait)]
+#[cfg(feature = "teloxide")]
+pub trait HtmlStringMessage: Requester {
+    /// This method replaces [teloxide Bot::send_message](https://docs.rs/teloxide/latest/teloxide/struct.Bot.html#method.send_message) for `HtmlString`
+    fn send_html_message<C>(&self, chat_id: C, text: HtmlString) -> <Self as Requester>::SendMessage
+    where
+        C: Into<Recipient>;
+
+    /// This method replaces [teloxide Bot::edit_message_text](https://docs.rs/teloxide/latest/teloxide/struct.Bot.html#method.edit_message_text) for `HtmlString`
+    fn edit_html_message_text<C>(
+        &self,
+        chat_id: C,
+        message_id: MessageId,
+        text: HtmlString,
+    ) -> <Self as Requester>::EditMessageText
+    where
+        C: Into<Recipient>;
+
+    /// This method replaces [teloxide Bot::edit_message_text_inline](https://docs.rs/teloxide/latest/teloxide/struct.Bot.html#method.edit_message_text_inline) for `HtmlString`
+    fn edit_html_message_text_inline(
+        &self,
+        inline_message_id: &str,
+        text: HtmlString,
+    ) -> <Self as Requester>::EditMessageTextInline;
+}
+
+/// Implementation of `HtmlStringMessage` for teloxide `Bot` and the adaptors wrapping it
+#[cfg(feature = "teloxide")]
+impl<R: Requester> HtmlStringMessage for R {
+    fn send_html_message<C>(&self, chat_id: C, text: HtmlString) -> <Self as Requester>::SendMessage
+    where
+        C: Into<Recipient>,
+    {
+        self.send_message(chat_id, text).parse_mode(Html)
+    }
+
+    fn edit_html_message_text<C>(
+        &self,
+        chat_id: C,
+        message_id: MessageId,
+        text: HtmlString,
+    ) -> <Self as Requester>::EditMessageText
+    where
+        C: Into<Recipient>,
+    {
+        self.edit_message_text(chat_id, message_id, text)
+            .parse_mode(Html)
+    }
+
+    fn edit_html_message_text_inline(
+        &self,
+        inline_message_id: &str,
+        text: HtmlString,
+    ) -> <Self as Requester>::EditMessageTextInline {
+        self.edit_message_text_inline(inline_message_id, text)
+            .parse_mode(Html)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{html_format, html_string};
+
+    #[test]
+    fn test_html_format() {
+        let html = html_string!("<b>Hello</b>, world!");
+        assert_eq!(html.as_str(), "<b>Hello</b>, world!");
+
+        let name = "<Tom & \"Jerry\">";
+        let html = html_format!("Hello <b>{}</b>!", name);
+        assert_eq!(html.as_str(), "Hello <b>&lt;Tom &amp; &quot;Jerry&quot;&gt;</b>!");
+
+        let bold = html_string!("<b>bold</b>");
+        let html = html_format!("{} {} {}", @raw bold, 42, @code "rust" "a < b");
+        assert_eq!(
+            html.as_str(),
+            "<b>bold</b> 42 <pre><code class=\"language-rust\">a &lt; b</code></pre>"
+        );
+        assert_eq!(html_format!("{}", @code "x&y").as_str(), "<pre>x&amp;y</pre>");
+
+        let combined = html_string!("<i>a</i>") + HtmlString::escape(" & b");
+        assert_eq!(combined.to_string(), "<i>a</i> &amp; b");
+    }
+}
@@ -0,0 +1,182 @@
+/// HTML tags supported by Telegram
+/// See: https://core.telegram.org/bots/api#html-style
+const SUPPORTED_TAGS: [&str; 16] = [
+    "b", "strong", "i", "em", "u", "ins", "s", "strike", "del", "span", "tg-spoiler", "a", "code",
+    "pre", "blockquote", "tg-emoji",
+];
+
+/// HTML entities supported by Telegram, numeric entities are supported too
+/// See: https://core.telegram.org/bots/api#html-style
+const SUPPORTED_ENTITIES: [&str; 4] = ["lt", "gt", "amp", "quot"];
+
+/// Maximum nesting depth of the tags checked by the validator
+const MAX_TAG_DEPTH: usize = 16;
+
+/// Internal helper function to compare the byte range with the string in const context
+const fn range_eq(bytes: &[u8], start: usize, end: usize, expected: &str) -> bool {
+    let expected = expected.as_bytes();
+    if end - start != expected.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < expected.len() {
+        if bytes[start + i].to_ascii_lowercase() != expected[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+/// Internal helper function to find the index of the supported tag in const context
+const fn tag_index(bytes: &[u8], start: usize, end: usize) -> Option<usize> {
+    let mut i = 0;
+    while i < SUPPORTED_TAGS.len() {
+        if range_eq(bytes, start, end, SUPPORTED_TAGS[i]) {
+            return Some(i);
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Validates HTML format string at compile time.
+///
+/// This function validates that a format string follows the [Telegram HTML specification](https://core.telegram.org/bots/api#html-style).
+/// It performs compile-time validation to ensure:
+///
+/// - Only the tags supported by Telegram are used: `<b>`, `<i>`, `<u>`, `<s>`, `<tg-spoiler>`,
+///   `<a>`, `<code>`, `<pre>`, `<blockquote>`, `<tg-emoji>` and their aliases
+/// - The tags are properly closed and nested
+/// - `<`, `>` and `&` are escaped outside of the tags as `&lt;`, `&gt;` and `&amp;`
+/// - Only the named entities `&lt;`, `&gt;`, `&amp;`, `&quot;` and the numeric entities are used
+pub const fn validate_html_format(format_str: &str) {
+    let bytes = format_str.as_bytes();
+    let mut stack = [0usize; MAX_TAG_DEPTH];
+    let mut depth = 0;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'<' => {
+                let closing = i + 1 < bytes.len() && bytes[i + 1] == b'/';
+                let name_start = if closing { i + 2 } else { i + 1 };
+                let mut name_end = name_start;
+                while name_end < bytes.len()
+                    && (bytes[name_end].is_ascii_alphanumeric() || bytes[name_end] == b'-')
+                {
+                    name_end += 1;
+                }
+                assert!(
+                    name_end > name_start,
+                    "Unescaped '<' in HTML format string. Use &lt; to escape it."
+                );
+                let Some(tag) = tag_index(bytes, name_start, name_end) else {
+                    panic!("Unsupported tag in HTML format string. See https://core.telegram.org/bots/api#html-style for the supported tags.");
+                };
+
+                // Skip the attributes up to the end of the tag, '>' is allowed in the quoted values
+                let mut j = name_end;
+                let mut quote = 0u8;
+                while j < bytes.len() && (quote != 0 || bytes[j] != b'>') {
+                    if quote == 0 && (bytes[j] == b'"' || bytes[j] == b'\'') {
+                        quote = bytes[j];
+                    } else if quote != 0 && bytes[j] == quote {
+                        quote = 0;
+                    }
+                    j += 1;
+                }
+                assert!(j < bytes.len(), "Unclosed tag in HTML format string");
+
+                if closing {
+                    assert!(
+                        depth > 0 && stack[depth - 1] == tag,
+                        "Unmatched closing tag in HTML format string - tags must be properly nested"
+                    );
+                    depth -= 1;
+                } else {
+                    assert!(depth < MAX_TAG_DEPTH, "Tags are nested too deep in HTML format string");
+                    stack[depth] = tag;
+                    depth += 1;
+                }
+                i = j;
+            }
+            b'>' => panic!("Unescaped '>' in HTML format string. Use &gt; to escape it."),
+            b'&' => {
+                let mut j = i + 1;
+                while j < bytes.len() && (bytes[j].is_ascii_alphanumeric() || bytes[j] == b'#') {
+                    j += 1;
+                }
+                assert!(
+                    j < bytes.len() && bytes[j] == b';',
+                    "Unescaped '&' in HTML format string. Use &amp; to escape it."
+                );
+                let numeric = j > i + 2 && bytes[i + 1] == b'#';
+                let mut known = numeric;
+                let mut k = 0;
+                while k < SUPPORTED_ENTITIES.len() {
+                    known = known || range_eq(bytes, i + 1, j, SUPPORTED_ENTITIES[k]);
+                    k += 1;
+                }
+                assert!(
+                    known,
+                    "Unsupported entity in HTML format string. Use &lt;, &gt;, &amp;, &quot; or numeric entities."
+                );
+                i = j;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+
+    assert!(depth == 0, "Unclosed tag in HTML format string");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_html_format_patterns() {
+        const _: () = validate_html_format("Hello <b>{}</b> &amp; <i>welcome</i>&#33;");
+        let valid_patterns = [
+            "Simple text",
+            "<b>bold</b> <strong>bold</strong> <i>italic</i> <em>italic</em>",
+            "<u>underline</u> <s>strike</s> <del>del</del> <tg-spoiler>spoiler</tg-spoiler>",
+            "<span class=\"tg-spoiler\">spoiler</span>",
+            "<a href=\"http://example.com/?a=1&b=2\">link</a>",
+            "<a href='tg://user?id=123'>mention</a>",
+            "<b>bold <i>italic bold</i></b>",
+            "<pre><code class=\"language-rust\">fn main() {}</code></pre>",
+            "<blockquote expandable>quote</blockquote>",
+            "<tg-emoji emoji-id=\"5368324170671202286\">👍</tg-emoji>",
+            "1 &lt; 2 &gt; 0 &quot;quoted&quot; &#x1F600;",
+            "Format placeholder: {}",
+        ];
+        for pattern in valid_patterns {
+            validate_html_format(pattern);
+        }
+    }
+
+    #[test]
+    fn test_invalid_html_format_patterns() {
+        let invalid_patterns = [
+            "1 < 2",
+            "2 > 1",
+            "Tom & Jerry",
+            "&nbsp;",
+            "<div>block</div>",
+            "<b>unclosed",
+            "</b>",
+            "<b><i>crossed</b></i>",
+            "<a href=\"url\"",
+        ];
+        for pattern in invalid_patterns {
+            assert!(
+                std::panic::catch_unwind(|| validate_html_format(pattern)).is_err(),
+                "Pattern '{}' should be rejected",
+                pattern
+            );
+        }
+    }
+}
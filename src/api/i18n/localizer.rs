@@ -0,0 +1,378 @@
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fmt::{self, Display},
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use fluent_bundle::{FluentArgs, FluentResource, FluentValue, concurrent::FluentBundle};
+use teloxide::types::ChatId;
+use unic_langid::LanguageIdentifier;
+
+use crate::api::{
+    data_store::data_store_trait::DataStoreTrait,
+    markdown::{
+        string::{MarkdownString, escape_markdown},
+        validate::{MarkdownError, check_markdownv2_format},
+    },
+};
+
+/// Key of the chat language in the store passed to [`Localizer::chat_language`]
+const CHAT_LANGUAGE_KEY: &str = "language";
+
+/// Localizer used by the [`t!`](crate::t!) macro
+static GLOBAL: OnceLock<Localizer> = OnceLock::new();
+
+/// Error loading or formatting the localized messages
+#[derive(Debug)]
+pub enum I18nError {
+    /// The `.ftl` file or the directory can't be read
+    Io(PathBuf, std::io::Error),
+    /// The language identifier is invalid or there are no messages for it
+    InvalidLanguage(String),
+    /// The Fluent resource has syntax errors or duplicate messages
+    Syntax(String, String),
+    /// There is no message with the key in the language and in the default language
+    NotFound(String),
+    /// The message can't be resolved, e.g. an argument is missing
+    Format(String, String),
+    /// The resolved message is not valid MarkdownV2
+    Markdown(String, MarkdownError),
+}
+
+impl Display for I18nError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            I18nError::Io(path, _) => write!(f, "can't read {}", path.display()),
+            I18nError::InvalidLanguage(lang) => write!(f, "invalid language {:?}", lang),
+            I18nError::Syntax(lang, details) => {
+                write!(f, "invalid fluent resource for {:?}: {}", lang, details)
+            }
+            I18nError::NotFound(key) => write!(f, "message {:?} not found", key),
+            I18nError::Format(key, details) => {
+                write!(f, "can't format message {:?}: {}", key, details)
+            }
+            I18nError::Markdown(key, _) => write!(f, "invalid markdown in message {:?}", key),
+        }
+    }
+}
+
+impl std::error::Error for I18nError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            I18nError::Io(_, err) => Some(err),
+            I18nError::Markdown(_, err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// Argument of the localized message, the text is escaped, the number is formatted by Fluent
+#[derive(Debug, Clone)]
+pub struct I18nArg(FluentValue<'static>);
+
+impl From<MarkdownString> for I18nArg {
+    fn from(text: MarkdownString) -> Self {
+        I18nArg(FluentValue::String(Cow::Owned(text.into_string())))
+    }
+}
+
+impl From<&str> for I18nArg {
+    fn from(text: &str) -> Self {
+        MarkdownString::escape(text).into()
+    }
+}
+
+impl From<String> for I18nArg {
+    fn from(text: String) -> Self {
+        MarkdownString::escape(text).into()
+    }
+}
+
+impl From<&String> for I18nArg {
+    fn from(text: &String) -> Self {
+        MarkdownString::escape(text).into()
+    }
+}
+
+macro_rules! i18n_arg_from_number {
+    ($($t:ty),*) => {
+        $(impl From<$t> for I18nArg {
+            fn from(n: $t) -> Self {
+                I18nArg(FluentValue::from(n))
+            }
+        })*
+    };
+}
+
+i18n_arg_from_number!(i32, i64, u32, u64, usize, isize, f32, f64);
+
+/// Localized MarkdownV2 messages loaded from the [Fluent](https://projectfluent.org) `.ftl` resources
+///
+/// The messages are written in MarkdownV2, the reserved characters in the text must be escaped
+/// and each resolved message is validated. The language is chosen by the exact tag, then by the
+/// primary language subtag (`en` for `en-US`), then the default language is used. The language
+/// of the chat can be kept in a store with [`set_chat_language`](Self::set_chat_language).
+///
+/// # Example
+/// ```rust
+/// use telluride::i18n::{I18nArg, Localizer};
+///
+/// let mut localizer = Localizer::new("en").unwrap();
+/// localizer.add_resource("en", "hello = Hello, *{ $name }*\\!").unwrap();
+/// localizer.add_resource("de", "hello = Hallo, *{ $name }*\\!").unwrap();
+/// let text = localizer.format("de-AT", "hello", &[("name", I18nArg::from("J. Doe"))]).unwrap();
+/// assert_eq!(text.as_str(), "Hallo, *J\\. Doe*\\!");
+/// ```
+pub struct Localizer {
+    default_language: String,
+    bundles: HashMap<String, FluentBundle<FluentResource>>,
+}
+
+impl fmt::Debug for Localizer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Localizer")
+            .field("default_language", &self.default_language)
+            .field("languages", &self.languages())
+            .finish()
+    }
+}
+
+impl Localizer {
+    /// Create the localizer without messages with the default language, e.g. [`BotConfig::locale`](crate::config::BotConfig::locale)
+    pub fn new(default_language: &str) -> Result<Self, I18nError> {
+        Ok(Self {
+            default_language: normalize_language(default_language)?,
+            bundles: HashMap::new(),
+        })
+    }
+
+    /// Load the messages from the directory with `<lang>.ftl` files or `<lang>` subdirectories of `.ftl` files
+    pub fn load_dir(dir: impl AsRef<Path>, default_language: &str) -> Result<Self, I18nError> {
+        let mut localizer = Self::new(default_language)?;
+        for (lang, path) in ftl_files(dir.as_ref())? {
+            let source =
+                std::fs::read_to_string(&path).map_err(|err| I18nError::Io(path.clone(), err))?;
+            localizer.add_resource(&lang, &source)?;
+        }
+        Ok(localizer)
+    }
+
+    /// Add the messages in the Fluent syntax for the language
+    pub fn add_resource(&mut self, language: &str, source: &str) -> Result<(), I18nError> {
+        let lang = normalize_language(language)?;
+        let resource = FluentResource::try_new(source.to_string()).map_err(|(_, errors)| {
+            I18nError::Syntax(lang.clone(), format!("{:?}", errors))
+        })?;
+        let bundle = self.bundles.entry(lang.clone()).or_insert_with(|| {
+            let langid: LanguageIdentifier = lang.parse().unwrap_or_default();
+            let mut bundle = FluentBundle::new_concurrent(vec![langid]);
+            // The Unicode isolation marks around the arguments are not needed in the messages
+            bundle.set_use_isolating(false);
+            bundle.set_formatter(Some(escape_number));
+            bundle
+        });
+        bundle
+            .add_resource(resource)
+            .map_err(|errors| I18nError::Syntax(lang, format!("{:?}", errors)))
+    }
+
+    /// Languages with the loaded messages, sorted
+    pub fn languages(&self) -> Vec<String> {
+        let mut languages: Vec<String> = self.bundles.keys().cloned().collect();
+        languages.sort();
+        languages
+    }
+
+    /// Get the loaded language matching the requested one, the default language if there is none
+    pub fn resolve_language(&self, language: &str) -> &str {
+        let Ok(lang) = normalize_language(language) else {
+            return &self.default_language;
+        };
+        let primary = lang.split('-').next().unwrap_or_default();
+        [lang.as_str(), primary]
+            .into_iter()
+            .find_map(|lang| self.bundles.get_key_value(lang).map(|(lang, _)| lang.as_str()))
+            .unwrap_or(&self.default_language)
+    }
+
+    /// Format the message by the key, `message.attribute` keys refer to the attributes
+    /// The message is looked up in the default language if it's missing in the requested one.
+    pub fn format(
+        &self,
+        language: &str,
+        key: &str,
+        args: &[(&str, I18nArg)],
+    ) -> Result<MarkdownString, I18nError> {
+        let lang = self.resolve_language(language);
+        let (id, attribute) = match key.split_once('.') {
+            Some((id, attribute)) => (id, Some(attribute)),
+            None => (key, None),
+        };
+        let (bundle, pattern) = [lang, self.default_language.as_str()]
+            .into_iter()
+            .filter_map(|lang| self.bundles.get(lang))
+            .find_map(|bundle| {
+                let message = bundle.get_message(id)?;
+                let pattern = match attribute {
+                    Some(attribute) => message.get_attribute(attribute)?.value(),
+                    None => message.value()?,
+                };
+                Some((bundle, pattern))
+            })
+            .ok_or_else(|| I18nError::NotFound(key.to_string()))?;
+        let mut fluent_args = FluentArgs::with_capacity(args.len());
+        for (name, value) in args {
+            fluent_args.set(*name, value.0.clone());
+        }
+        let mut errors = Vec::new();
+        let text = bundle.format_pattern(pattern, Some(&fluent_args), &mut errors);
+        if !errors.is_empty() {
+            return Err(I18nError::Format(key.to_string(), format!("{:?}", errors)));
+        }
+        check_markdownv2_format(&text, false).map_err(|err| I18nError::Markdown(key.to_string(), err))?;
+        Ok(MarkdownString::from_validated_string(text.into_owned()))
+    }
+
+    /// Format the message, on error log it and return the escaped key
+    pub fn translate(&self, language: &str, key: &str, args: &[(&str, I18nArg)]) -> MarkdownString {
+        self.format(language, key, args).unwrap_or_else(|err| {
+            log::warn!("Can't translate to {}: {:#}", language, crate::Error::from(err));
+            MarkdownString::escape(key)
+        })
+    }
+
+    /// Set the localizer used by the [`t!`](crate::t!) macro, it can be set only once
+    pub fn set_global(localizer: Localizer) -> Result<(), Localizer> {
+        GLOBAL.set(localizer)
+    }
+
+    /// Get the localizer used by the [`t!`](crate::t!) macro
+    pub fn global() -> Option<&'static Localizer> {
+        GLOBAL.get()
+    }
+
+    /// Translate the message with the global localizer, used by the [`t!`](crate::t!) macro
+    pub fn translate_global(language: &str, key: &str, args: &[(&str, I18nArg)]) -> MarkdownString {
+        match Self::global() {
+            Some(localizer) => localizer.translate(language, key, args),
+            None => {
+                log::warn!("The global localizer is not set, can't translate {}", key);
+                MarkdownString::escape(key)
+            }
+        }
+    }
+
+    /// Get the language of the chat from the store, the default language if it's not set
+    pub async fn chat_language(&self, store: &dyn DataStoreTrait<String>, chat_id: ChatId) -> String {
+        match store.get(chat_id, CHAT_LANGUAGE_KEY).await {
+            Some(language) => self.resolve_language(&language).to_string(),
+            None => self.default_language.clone(),
+        }
+    }
+
+    /// Keep the language of the chat in the store, fails if there are no messages for the language
+    pub async fn set_chat_language(
+        &self,
+        store: &dyn DataStoreTrait<String>,
+        chat_id: ChatId,
+        language: &str,
+    ) -> Result<(), I18nError> {
+        let lang = normalize_language(language)?;
+        if !self.bundles.contains_key(&lang) {
+            return Err(I18nError::InvalidLanguage(language.to_string()));
+        }
+        store.set(chat_id, CHAT_LANGUAGE_KEY, lang).await;
+        Ok(())
+    }
+}
+
+/// Internal helper function to escape the numbers formatted by Fluent, the text arguments are escaped beforehand
+fn escape_number<M>(value: &FluentValue, _memoizer: &M) -> Option<String> {
+    match value {
+        FluentValue::Number(number) => Some(escape_markdown(&number.as_string())),
+        _ => None,
+    }
+}
+
+/// Internal helper function to get the canonical form of the language tag, e.g. `en-US` for `en_us`
+fn normalize_language(language: &str) -> Result<String, I18nError> {
+    language
+        .replace('_', "-")
+        .parse::<LanguageIdentifier>()
+        .map(|langid| langid.to_string())
+        .map_err(|_| I18nError::InvalidLanguage(language.to_string()))
+}
+
+/// Internal helper function to list the `.ftl` files of the directory with their languages
+fn ftl_files(dir: &Path) -> Result<Vec<(String, PathBuf)>, I18nError> {
+    let read_dir = |dir: &Path| -> Result<Vec<PathBuf>, I18nError> {
+        let entries = std::fs::read_dir(dir).map_err(|err| I18nError::Io(dir.to_path_buf(), err))?;
+        let mut paths = entries
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| I18nError::Io(dir.to_path_buf(), err))?;
+        paths.sort();
+        Ok(paths)
+    };
+    let is_ftl = |path: &Path| path.extension().is_some_and(|ext| ext == "ftl");
+    let mut files = Vec::new();
+    for path in read_dir(dir)? {
+        let Some(stem) = path.file_stem().and_then(|stem| stem.to_str()) else {
+            continue;
+        };
+        if path.is_dir() {
+            let lang = stem.to_string();
+            for file in read_dir(&path)?.into_iter().filter(|file| is_ftl(file)) {
+                files.push((lang.clone(), file));
+            }
+        } else if is_ftl(&path) {
+            files.push((stem.to_string(), path));
+        }
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::data_store::in_mem::InMemStore;
+
+    const EN: &str = "
+items = { $count ->
+    [one] *{ $count }* item
+   *[other] *{ $count }* items
+}
+greeting = Hi, { $name }\\!
+    .title = Greeting
+broken = Hi!
+";
+
+    #[tokio::test]
+    async fn test_localizer() {
+        let mut localizer = Localizer::new("en").unwrap();
+        localizer.add_resource("en", EN).unwrap();
+        localizer.add_resource("ru_RU", "greeting = Привет, { $name }\\!").unwrap();
+        assert_eq!(localizer.languages(), ["en", "ru-RU"]);
+
+        let text = localizer.format("en-GB", "items", &[("count", I18nArg::from(-1.5))]).unwrap();
+        assert_eq!(text.as_str(), "*\\-1\\.5* items");
+        let text = localizer.format("en", "items", &[("count", 1.into())]).unwrap();
+        assert_eq!(text.as_str(), "*1* item");
+        let text = localizer.format("ru-RU", "greeting", &[("name", "A.B".into())]).unwrap();
+        assert_eq!(text.as_str(), "Привет, A\\.B\\!");
+        assert_eq!(localizer.format("ru", "greeting.title", &[]).unwrap().as_str(), "Greeting");
+        assert!(matches!(localizer.format("en", "greeting", &[]), Err(I18nError::Format(..))));
+        assert!(matches!(localizer.format("en", "broken", &[]), Err(I18nError::Markdown(..))));
+        assert!(matches!(localizer.format("en", "missing", &[]), Err(I18nError::NotFound(_))));
+        assert_eq!(localizer.translate("en", "missing.key", &[]).as_str(), "missing\\.key");
+
+        let store = InMemStore::<String>::new();
+        let chat_id = ChatId(1);
+        assert_eq!(localizer.chat_language(&store, chat_id).await, "en");
+        localizer.set_chat_language(&store, chat_id, "ru-ru").await.unwrap();
+        assert_eq!(localizer.chat_language(&store, chat_id).await, "ru-RU");
+        assert!(localizer.set_chat_language(&store, chat_id, "fr").await.is_err());
+    }
+}
@@ -0,0 +1,22 @@
+/// Translates the message by the key with the global [`Localizer`](crate::i18n::Localizer)
+///
+/// The arguments are given as `name = value`, the text values are escaped, the numbers are
+/// formatted and escaped by Fluent, so they can be used in the selectors. If the message can't be
+/// translated the escaped key is returned and the warning is logged, see
+/// [`Localizer::translate`](crate::i18n::Localizer::translate).
+///
+/// # Examples
+/// ```ignore
+/// let lang = localizer.chat_language(&store, chat_id).await;
+/// let text = t!(&lang, "welcome", name = user.first_name, count = 3);
+/// ```
+#[macro_export]
+macro_rules! t {
+    ($lang:expr, $key:expr $(, $name:ident = $value:expr)* $(,)?) => {
+        $crate::i18n::Localizer::translate_global(
+            $lang,
+            $key,
+            &[$((stringify!($name), $crate::i18n::I18nArg::from($value))),*],
+        )
+    };
+}
@@ -0,0 +1,2 @@
+pub(crate) mod localizer;
+pub(crate) mod macros;
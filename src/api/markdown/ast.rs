@@ -0,0 +1,773 @@
+use crate::api::{
+    html::string::{HtmlString, escape_html},
+    markdown::{
+        string::{MarkdownString, escape_markdown},
+        validate::check_markdownv2_format,
+    },
+};
+
+/// Node of the MarkdownV2 document tree
+///
+/// The text content is stored unescaped, the escaping is applied when the tree is rendered
+/// back with [`render`], so any tree built or modified by hand renders to valid MarkdownV2.
+/// The entities which can't be represented there, e.g. a blockquote inside a spoiler,
+/// a link inside a link or a link with an invalid URL, are rendered as their content.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Node {
+    /// Plain text
+    Text(String),
+    /// `*bold*`
+    Bold(Vec<Node>),
+    /// `_italic_`
+    Italic(Vec<Node>),
+    /// `__underline__`
+    Underline(Vec<Node>),
+    /// `~strikethrough~`
+    Strikethrough(Vec<Node>),
+    /// `||spoiler||`
+    Spoiler(Vec<Node>),
+    /// `` `inline code` ``
+    Code(String),
+    /// Pre-formatted code block with the optional language
+    Pre {
+        language: Option<String>,
+        code: String,
+    },
+    /// `[text](url)`, including the user mentions with `tg://user?id=` URLs
+    Link { url: String, children: Vec<Node> },
+    /// `![👍](tg://emoji?id=5368324170671202286)`
+    CustomEmoji { id: String, emoji: String },
+    /// Lines starting with `>`
+    Blockquote(Vec<Node>),
+    /// Blockquote collapsed by default, starting with `**>` and ending with `||`
+    ExpandableBlockquote(Vec<Node>),
+}
+
+impl Node {
+    /// The child nodes of the formatting node, empty for the text and code nodes
+    pub fn children(&self) -> &[Node] {
+        match self {
+            Node::Bold(children)
+            | Node::Italic(children)
+            | Node::Underline(children)
+            | Node::Strikethrough(children)
+            | Node::Spoiler(children)
+            | Node::Blockquote(children)
+            | Node::ExpandableBlockquote(children)
+            | Node::Link { children, .. } => children,
+            _ => &[],
+        }
+    }
+
+    /// The mutable child nodes of the formatting node, `None` for the text and code nodes
+    pub fn children_mut(&mut self) -> Option<&mut Vec<Node>> {
+        match self {
+            Node::Bold(children)
+            | Node::Italic(children)
+            | Node::Underline(children)
+            | Node::Strikethrough(children)
+            | Node::Spoiler(children)
+            | Node::Blockquote(children)
+            | Node::ExpandableBlockquote(children)
+            | Node::Link { children, .. } => Some(children),
+            _ => None,
+        }
+    }
+
+    /// The text of the node without formatting
+    pub fn to_plain_text(&self) -> String {
+        match self {
+            Node::Text(text) | Node::Code(text) | Node::Pre { code: text, .. } => text.clone(),
+            Node::CustomEmoji { emoji, .. } => emoji.clone(),
+            node => node.children().iter().map(Node::to_plain_text).collect(),
+        }
+    }
+}
+
+/// Replace each link in the tree with its text
+///
+/// # Example
+/// ```rust
+/// use telluride::{markdown::ast, markdown_string};
+///
+/// let mut nodes = ast::parse(&markdown_string!("See *[docs](http://example\\.com)*"));
+/// ast::strip_links(&mut nodes);
+/// assert_eq!(ast::render(&nodes).as_str(), "See *docs*");
+/// ```
+pub fn strip_links(nodes: &mut Vec<Node>) {
+    let mut result = Vec::with_capacity(nodes.len());
+    for mut node in nodes.drain(..) {
+        match node {
+            Node::Link { mut children, .. } => {
+                strip_links(&mut children);
+                result.extend(children);
+            }
+            _ => {
+                if let Some(children) = node.children_mut() {
+                    strip_links(children);
+                }
+                result.push(node);
+            }
+        }
+    }
+    *nodes = result;
+}
+
+/// Stop condition of the parsed sequence of nodes
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Stop {
+    End,
+    Marker(&'static str),
+    Newline,
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    // Set inside the expandable blockquote until its closing `||`
+    expandable: bool,
+}
+
+impl Parser {
+    fn starts_with(&self, marker: &str) -> bool {
+        (self.pos..)
+            .zip(marker.chars())
+            .all(|(pos, c)| self.chars.get(pos) == Some(&c))
+    }
+
+    fn at_line_start(&self) -> bool {
+        self.pos == 0 || self.chars[self.pos - 1] == '\n'
+    }
+
+    /// Read the verbatim content up to the closing marker, unescaping the escaped characters
+    fn read_until(&mut self, closing: &str) -> String {
+        let mut content = String::new();
+        while self.pos < self.chars.len() {
+            if self.starts_with(closing) {
+                self.pos += closing.chars().count();
+                break;
+            }
+            let c = self.chars[self.pos];
+            self.pos += 1;
+            if c == '\\' && self.pos < self.chars.len() {
+                content.push(self.chars[self.pos]);
+                self.pos += 1;
+            } else {
+                content.push(c);
+            }
+        }
+        content
+    }
+
+    /// Read the consecutive quoted lines forming one blockquote, starting at the first quote marker
+    /// The lines of the expandable blockquote end at its closing marker
+    fn parse_quote_lines(&mut self) -> Vec<Node> {
+        let expandable = self.expandable;
+        let mut children = Vec::new();
+        loop {
+            self.pos += 1;
+            children.extend(self.parse_nodes(Stop::Newline));
+            if (expandable && !self.expandable) || !self.starts_with("\n>") {
+                break;
+            }
+            self.pos += 1;
+            children.push(Node::Text("\n".to_string()));
+        }
+        children
+    }
+
+    fn parse_nodes(&mut self, stop: Stop) -> Vec<Node> {
+        const STYLES: [&str; 6] = ["||", "__", "*", "_", "~", "["];
+        let mut nodes = Vec::new();
+        let mut text = String::new();
+        'outer: while self.pos < self.chars.len() {
+            let c = self.chars[self.pos];
+            if stop == Stop::Newline && c == '\n' {
+                break;
+            }
+            // The expandable blockquote is closed by "||" at the end of its last line
+            if stop == Stop::Newline
+                && self.expandable
+                && self.starts_with("||")
+                && matches!(self.chars.get(self.pos + 2), None | Some('\n'))
+            {
+                self.pos += 2;
+                self.expandable = false;
+                break;
+            }
+            // "__" is always treated greedily as the underline marker
+            if let Stop::Marker(marker) = stop
+                && self.starts_with(marker)
+                && !(marker == "_" && self.starts_with("__"))
+            {
+                self.pos += marker.chars().count();
+                break;
+            }
+            let node = match c {
+                '\\' => {
+                    self.pos += 1;
+                    if let Some(&escaped) = self.chars.get(self.pos) {
+                        text.push(escaped);
+                        self.pos += 1;
+                    }
+                    continue;
+                }
+                // Ignored by Telegram, used to separate the italic and underline markers
+                '\r' => {
+                    self.pos += 1;
+                    continue;
+                }
+                '`' if self.starts_with("```") => {
+                    self.pos += 3;
+                    let mut language = None;
+                    let rest = &self.chars[self.pos..];
+                    if let Some(newline) = rest.iter().position(|&c| c == '\n')
+                        && !rest[..newline].contains(&'`')
+                    {
+                        let tag: String = rest[..newline].iter().collect();
+                        language = Some(tag).filter(|tag| !tag.is_empty());
+                        self.pos += newline + 1;
+                    }
+                    let mut code = self.read_until("```");
+                    if code.ends_with('\n') {
+                        code.pop();
+                    }
+                    Node::Pre { language, code }
+                }
+                '`' => {
+                    self.pos += 1;
+                    Node::Code(self.read_until("`"))
+                }
+                '!' if self.starts_with("![") => {
+                    self.pos += 2;
+                    let emoji: String = self
+                        .parse_nodes(Stop::Marker("]"))
+                        .iter()
+                        .map(Node::to_plain_text)
+                        .collect();
+                    let url = if self.starts_with("(") {
+                        self.pos += 1;
+                        self.read_until(")")
+                    } else {
+                        String::new()
+                    };
+                    match url.strip_prefix("tg://emoji?id=") {
+                        Some(id) => Node::CustomEmoji {
+                            id: id.to_string(),
+                            emoji,
+                        },
+                        None => Node::Text(emoji),
+                    }
+                }
+                '>' if stop == Stop::End && self.at_line_start() => {
+                    Node::Blockquote(self.parse_quote_lines())
+                }
+                '*' if stop == Stop::End && self.at_line_start() && self.starts_with("**>") => {
+                    // The empty bold marks the expandable blockquote
+                    self.pos += 2;
+                    self.expandable = true;
+                    let children = self.parse_quote_lines();
+                    if std::mem::take(&mut self.expandable) {
+                        // Not closed by "||", so it's a regular blockquote
+                        Node::Blockquote(children)
+                    } else {
+                        Node::ExpandableBlockquote(children)
+                    }
+                }
+                _ => {
+                    for style in STYLES {
+                        if self.starts_with(style) {
+                            self.pos += style.chars().count();
+                            let closing = if style == "[" { "]" } else { style };
+                            let children = self.parse_nodes(Stop::Marker(closing));
+                            let node = match style {
+                                "||" => Node::Spoiler(children),
+                                "__" => Node::Underline(children),
+                                "*" => Node::Bold(children),
+                                "_" => Node::Italic(children),
+                                "~" => Node::Strikethrough(children),
+                                _ if self.starts_with("(") => {
+                                    self.pos += 1;
+                                    let url = self.read_until(")");
+                                    Node::Link { url, children }
+                                }
+                                _ => {
+                                    flush_text(&mut nodes, &mut text);
+                                    nodes.extend(children);
+                                    continue 'outer;
+                                }
+                            };
+                            flush_text(&mut nodes, &mut text);
+                            nodes.push(node);
+                            continue 'outer;
+                        }
+                    }
+                    text.push(c);
+                    self.pos += 1;
+                    continue;
+                }
+            };
+            flush_text(&mut nodes, &mut text);
+            nodes.push(node);
+        }
+        flush_text(&mut nodes, &mut text);
+        nodes
+    }
+}
+
+/// Internal helper function to move the accumulated text into a node
+fn flush_text(nodes: &mut Vec<Node>, text: &mut String) {
+    if !text.is_empty() {
+        nodes.push(Node::Text(std::mem::take(text)));
+    }
+}
+
+/// Parse the MarkdownString into the tree of nodes
+///
+/// # Example
+/// ```rust
+/// use telluride::{markdown::ast::{self, Node}, markdown_string};
+///
+/// let nodes = ast::parse(&markdown_string!("*Hello* world\\!"));
+/// assert_eq!(
+///     nodes,
+///     vec![
+///         Node::Bold(vec![Node::Text("Hello".to_string())]),
+///         Node::Text(" world!".to_string()),
+///     ]
+/// );
+/// ```
+pub fn parse(markdown: &MarkdownString) -> Vec<Node> {
+    let mut parser = Parser {
+        chars: markdown.as_str().chars().collect(),
+        pos: 0,
+        expandable: false,
+    };
+    parser.parse_nodes(Stop::End)
+}
+
+/// Internal helper function to escape the content of the code entities and of the link URLs
+fn escape_verbatim(input: &str, special: char) -> String {
+    let mut escaped = String::with_capacity(input.len());
+    for c in input.chars() {
+        if c == '\\' || c == special {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Internal helper function to check if the link URL is accepted by the MarkdownV2 validator
+fn is_valid_url(url: &str) -> bool {
+    check_markdownv2_format(&format!("[a]({})", escape_verbatim(url, ')')), false).is_ok()
+}
+
+/// Internal helper function to render the nodes, the open styles are not repeated in the nested nodes
+/// The open links are marked with "[" and the open blockquotes with ">", `quote_end` is set after
+/// the blockquote, which ends at the end of the line
+fn render_nodes(nodes: &[Node], open: &mut Vec<&'static str>, quote_end: &mut bool, out: &mut String) {
+    for node in nodes {
+        if std::mem::take(quote_end) && !matches!(node, Node::Text(text) if text.starts_with('\n')) {
+            out.push('\n');
+        }
+        let marker = match node {
+            Node::Bold(_) => "*",
+            Node::Italic(_) => "_",
+            Node::Underline(_) => "__",
+            Node::Strikethrough(_) => "~",
+            Node::Spoiler(_) => "||",
+            _ => "",
+        };
+        match node {
+            Node::Text(text) => {
+                let escaped = escape_markdown(text);
+                // The quote marker is recognized only at the beginning of a line
+                if open.contains(&">") {
+                    // The spoiler marker at the end of the line would close the expandable blockquote
+                    if escaped.starts_with('\n') && out.ends_with("||") {
+                        out.push('\r');
+                    }
+                    out.push_str(&escaped.replace('\n', "\n>"));
+                } else {
+                    out.push_str(&escaped);
+                }
+            }
+            Node::Code(code) if !code.is_empty() => {
+                out.push('`');
+                out.push_str(&escape_verbatim(code, '`'));
+                out.push('`');
+            }
+            Node::Pre { language, code } => {
+                let language = language.as_deref().unwrap_or_default();
+                out.push_str("```");
+                // The language tag can't break the block
+                out.extend(
+                    language
+                        .chars()
+                        .filter(|c| c.is_alphanumeric() || "+-#_.".contains(*c)),
+                );
+                out.push('\n');
+                out.push_str(&escape_verbatim(code, '`'));
+                out.push_str("\n```");
+            }
+            Node::Link { url, children } if !open.contains(&"[") && is_valid_url(url) => {
+                out.push('[');
+                open.push("[");
+                render_nodes(children, open, quote_end, out);
+                open.pop();
+                out.push_str("](");
+                out.push_str(&escape_verbatim(url, ')'));
+                out.push(')');
+            }
+            Node::CustomEmoji { id, emoji }
+                if open.contains(&"[") || id.is_empty() || !id.chars().all(|c| c.is_ascii_digit()) =>
+            {
+                out.push_str(&escape_markdown(emoji));
+            }
+            Node::CustomEmoji { id, emoji } => {
+                out.push_str("![");
+                out.push_str(&escape_markdown(emoji));
+                out.push_str("](tg://emoji?id=");
+                out.push_str(id);
+                out.push(')');
+            }
+            // The blockquote can't be inside the other entities
+            Node::Blockquote(children) | Node::ExpandableBlockquote(children) if !open.is_empty() => {
+                render_nodes(children, open, quote_end, out);
+            }
+            Node::Blockquote(children) | Node::ExpandableBlockquote(children) => {
+                if !out.is_empty() && !out.ends_with('\n') {
+                    out.push('\n');
+                }
+                let expandable = matches!(node, Node::ExpandableBlockquote(_));
+                out.push_str(if expandable { "**>" } else { ">" });
+                open.push(">");
+                render_nodes(children, open, quote_end, out);
+                open.pop();
+                if expandable {
+                    out.push_str("||");
+                }
+                *quote_end = true;
+            }
+            _ if marker.is_empty() || open.contains(&marker) => {
+                // The same style can't be nested, its content is rendered as is
+                render_nodes(node.children(), open, quote_end, out);
+            }
+            _ => {
+                let start = out.len();
+                push_marker(out, marker);
+                let content_start = out.len();
+                open.push(marker);
+                render_nodes(node.children(), open, quote_end, out);
+                open.pop();
+                if out.len() == content_start {
+                    // Empty entities are not allowed
+                    out.truncate(start);
+                    continue;
+                }
+                push_marker(out, marker);
+            }
+        }
+    }
+}
+
+/// Internal helper function to append the style marker,
+/// separating the adjacent italic and underline markers with '\r' which is ignored by Telegram
+/// See: https://core.telegram.org/bots/api#markdownv2-style
+fn push_marker(out: &mut String, marker: &str) {
+    if marker.starts_with('_') && out.ends_with('_') {
+        // The underscore is a marker if it's not escaped by an odd number of backslashes
+        let backslashes = out[..out.len() - 1]
+            .chars()
+            .rev()
+            .take_while(|&c| c == '\\')
+            .count();
+        if backslashes % 2 == 0 {
+            out.push('\r');
+        }
+    }
+    out.push_str(marker);
+}
+
+/// Render the tree of nodes to a valid MarkdownString
+/// The entities which can't be represented in MarkdownV2 are rendered as their content, see [`Node`]
+pub fn render(nodes: &[Node]) -> MarkdownString {
+    let mut out = String::new();
+    render_nodes(nodes, &mut Vec::new(), &mut false, &mut out);
+    MarkdownString::from_validated_string(out)
+}
+
+/// Internal helper function to render the nodes with Telegram's HTML tags
+fn render_html_nodes(nodes: &[Node], out: &mut String) {
+    for node in nodes {
+        let tag = match node {
+            Node::Bold(_) => "b",
+            Node::Italic(_) => "i",
+            Node::Underline(_) => "u",
+            Node::Strikethrough(_) => "s",
+            Node::Spoiler(_) => "tg-spoiler",
+            Node::Blockquote(_) => "blockquote",
+            Node::ExpandableBlockquote(_) => "blockquote expandable",
+            _ => "",
+        };
+        match node {
+            Node::Text(text) => out.push_str(&escape_html(text)),
+            Node::Code(code) => {
+                out.push_str("<code>");
+                out.push_str(&escape_html(code));
+                out.push_str("</code>");
+            }
+            Node::Pre { language: Some(language), code } => {
+                out.push_str("<pre><code class=\"language-");
+                out.push_str(&escape_html(language));
+                out.push_str("\">");
+                out.push_str(&escape_html(code));
+                out.push_str("</code></pre>");
+            }
+            Node::Pre { language: None, code } => {
+                out.push_str("<pre>");
+                out.push_str(&escape_html(code));
+                out.push_str("</pre>");
+            }
+            Node::Link { url, children } => {
+                out.push_str("<a href=\"");
+                out.push_str(&escape_html(url));
+                out.push_str("\">");
+                render_html_nodes(children, out);
+                out.push_str("</a>");
+            }
+            Node::CustomEmoji { id, emoji } => {
+                out.push_str("<tg-emoji emoji-id=\"");
+                out.push_str(&escape_html(id));
+                out.push_str("\">");
+                out.push_str(&escape_html(emoji));
+                out.push_str("</tg-emoji>");
+            }
+            _ => {
+                out.push('<');
+                out.push_str(tag);
+                out.push('>');
+                render_html_nodes(node.children(), out);
+                out.push_str("</");
+                out.push_str(tag.split(' ').next().unwrap_or(tag));
+                out.push('>');
+            }
+        }
+    }
+}
+
+/// Render the tree of nodes to the equivalent [Telegram HTML](https://core.telegram.org/bots/api#html-style)
+///
+/// # Example
+/// ```rust
+/// use telluride::{markdown::ast, markdown_string};
+///
+/// let nodes = ast::parse(&markdown_string!("*1 < 2* [docs](http://a\\.b/?x=1&y=2)"));
+/// assert_eq!(
+///     ast::render_html(&nodes).as_str(),
+///     "<b>1 &lt; 2</b> <a href=\"http://a.b/?x=1&amp;y=2\">docs</a>"
+/// );
+/// ```
+pub fn render_html(nodes: &[Node]) -> HtmlString {
+    let mut out = String::new();
+    render_html_nodes(nodes, &mut out);
+    HtmlString::from_validated_string(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_render() {
+        let markdown = MarkdownString::from_validated_string(
+            "*bold _italic_* __under__ ~strike~ ||spoiler|| `co\\`de` [link *bold*](http://a\\.b/\\)) ![👍](tg://emoji?id=123)\n>quote *one*\n>two\n```rust\nlet a = 1;\n```\n**>more||",
+        );
+        let nodes = parse(&markdown);
+        assert_eq!(
+            nodes[0],
+            Node::Bold(vec![
+                Node::Text("bold ".to_string()),
+                Node::Italic(vec![Node::Text("italic".to_string())]),
+            ])
+        );
+        assert!(nodes.contains(&Node::Code("co`de".to_string())));
+        assert!(nodes.contains(&Node::Link {
+            url: "http://a.b/)".to_string(),
+            children: vec![
+                Node::Text("link ".to_string()),
+                Node::Bold(vec![Node::Text("bold".to_string())]),
+            ],
+        }));
+        assert!(nodes.contains(&Node::CustomEmoji {
+            id: "123".to_string(),
+            emoji: "👍".to_string(),
+        }));
+        assert!(nodes.contains(&Node::Pre {
+            language: Some("rust".to_string()),
+            code: "let a = 1;".to_string(),
+        }));
+        assert!(
+            nodes
+                .iter()
+                .any(|node| matches!(node, Node::Blockquote(children) if children.len() == 4))
+        );
+        assert_eq!(
+            nodes.last(),
+            Some(&Node::ExpandableBlockquote(vec![Node::Text("more".to_string())]))
+        );
+        // The tree survives the round trip
+        assert_eq!(parse(&render(&nodes)), nodes);
+        assert_eq!(render(&nodes).to_plain_text(), markdown.to_plain_text());
+        assert!(MarkdownString::try_from_raw(render(&nodes).as_str()).is_ok());
+    }
+
+    #[test]
+    fn test_render_valid() {
+        let text = |text: &str| Node::Text(text.to_string());
+        let quote = Node::ExpandableBlockquote(vec![text("a\nb")]);
+        let link = |url: &str, children| Node::Link {
+            url: url.to_string(),
+            children,
+        };
+        let emoji = |id: &str| Node::CustomEmoji {
+            id: id.to_string(),
+            emoji: "👍".to_string(),
+        };
+        let cases = [
+            (link("http://a.b/(x)\\y", vec![text("a")]), "[a](http://a.b/(x\\)\\\\y)"),
+            (link("javascript:alert(1)", vec![text("a")]), "a"),
+            (emoji("123"), "![👍](tg://emoji?id=123)"),
+            (emoji("1)"), "👍"),
+            (Node::Spoiler(vec![quote.clone()]), "||a\nb||"),
+            (link("tg://user?id=1", vec![quote.clone()]), "[a\nb](tg://user?id=1)"),
+            (link("http://a", vec![link("http://b", vec![text("b")]), emoji("1")]), "[b👍](http://a)"),
+            (Node::Blockquote(vec![text("a "), Node::Blockquote(vec![text("b")])]), ">a b"),
+            (quote.clone(), "**>a\n>b||"),
+            (
+                Node::Italic(vec![text("a"), Node::Underline(vec![text("b")])]),
+                "_a__b__\r_",
+            ),
+            (Node::Code("a`b\\".to_string()), "`a\\`b\\\\`"),
+            (
+                Node::Pre {
+                    language: Some("c++`".to_string()),
+                    code: "```".to_string(),
+                },
+                "```c++\n\\`\\`\\`\n```",
+            ),
+        ];
+        for (node, expected) in cases {
+            let rendered = render(std::slice::from_ref(&node));
+            assert_eq!(rendered.as_str(), expected, "node: {:?}", node);
+            assert!(MarkdownString::try_from_raw(rendered.as_str()).is_ok(), "node: {:?}", node);
+            assert_eq!(render(&parse(&rendered)), rendered);
+        }
+    }
+
+    #[test]
+    fn test_render_random_trees() {
+        // Linear congruential generator, so that the failures are reproducible
+        let mut seed = 7u64;
+        let mut next = move |n: u64| {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (seed >> 33) % n
+        };
+        fn tree(next: &mut dyn FnMut(u64) -> u64, depth: u32) -> Vec<Node> {
+            let texts = ["a", "*_[]()\\>!", "\n", "b\nc", "", "||"];
+            (0..next(4))
+                .map(|_| {
+                    let kind = if depth == 0 { next(4) } else { next(14) };
+                    let children = |next: &mut dyn FnMut(u64) -> u64| tree(next, depth - 1);
+                    match kind {
+                        0 => Node::Text(texts[next(6) as usize].to_string()),
+                        1 => Node::Code(texts[next(6) as usize].to_string()),
+                        2 => Node::Pre {
+                            language: (next(2) == 0).then(|| "rust".to_string()),
+                            code: texts[next(6) as usize].to_string(),
+                        },
+                        3 => Node::CustomEmoji {
+                            id: ["1", "", "x"][next(3) as usize].to_string(),
+                            emoji: "👍".to_string(),
+                        },
+                        4 => Node::Bold(children(next)),
+                        5 => Node::Italic(children(next)),
+                        6 => Node::Underline(children(next)),
+                        7 => Node::Strikethrough(children(next)),
+                        8 => Node::Spoiler(children(next)),
+                        9 | 10 => Node::Link {
+                            url: ["http://a.b/)", "tg://user?id=1", "a b"][next(3) as usize].to_string(),
+                            children: children(next),
+                        },
+                        11 => Node::Blockquote(children(next)),
+                        _ => Node::ExpandableBlockquote(children(next)),
+                    }
+                })
+                .collect()
+        }
+        for _ in 0..3000 {
+            let nodes = tree(&mut next, 3);
+            let rendered = render(&nodes);
+            assert!(
+                MarkdownString::try_from_raw(rendered.as_str()).is_ok(),
+                "nodes: {:?}, rendered: {:?}",
+                nodes,
+                rendered.as_str()
+            );
+        }
+    }
+
+    #[test]
+    fn test_render_html() {
+        let markdown = MarkdownString::from_validated_string(
+            "*b _i_* __u__ ~s~ ||p|| `a<b` ```rust\nx && y\n``` ```\nplain\n```\n>quote \"q\"\n![👍](tg://emoji?id=5368324170671202286)",
+        );
+        let html = render_html(&parse(&markdown));
+        assert_eq!(
+            html.as_str(),
+            "<b>b <i>i</i></b> <u>u</u> <s>s</s> <tg-spoiler>p</tg-spoiler> <code>a&lt;b</code> \
+             <pre><code class=\"language-rust\">x &amp;&amp; y</code></pre> <pre>plain</pre>\n\
+             <blockquote>quote &quot;q&quot;</blockquote>\n<tg-emoji emoji-id=\"5368324170671202286\">👍</tg-emoji>"
+        );
+        let nodes = parse(&MarkdownString::from_validated_string("**>a\n>b||\n**>c"));
+        assert_eq!(
+            nodes,
+            vec![
+                Node::ExpandableBlockquote(vec![
+                    Node::Text("a".to_string()),
+                    Node::Text("\n".to_string()),
+                    Node::Text("b".to_string()),
+                ]),
+                Node::Text("\n".to_string()),
+                // Not closed by "||"
+                Node::Blockquote(vec![Node::Text("c".to_string())]),
+            ]
+        );
+        assert_eq!(render(&nodes).as_str(), "**>a\n>b||\n>c");
+        assert_eq!(
+            render_html(&nodes).as_str(),
+            "<blockquote expandable>a\nb</blockquote>\n<blockquote>c</blockquote>"
+        );
+    }
+
+    #[test]
+    fn test_render_is_valid() {
+        let nodes = vec![
+            Node::Bold(vec![
+                Node::Text("1+1=2!".to_string()),
+                Node::Bold(vec![Node::Text("nested".to_string())]),
+            ]),
+            Node::Underline(vec![Node::Italic(vec![Node::Text("both".to_string())])]),
+            Node::Italic(vec![]),
+            Node::Pre {
+                language: Some("rust```\nx".to_string()),
+                code: "```".to_string(),
+            },
+        ];
+        assert_eq!(
+            render(&nodes).as_str(),
+            "*1\\+1\\=2\\!nested*__\r_both_\r__```rustx\n\\`\\`\\`\n```"
+        );
+    }
+}
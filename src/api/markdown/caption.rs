@@ -0,0 +1,98 @@
+use std::fmt;
+
+use crate::api::markdown::string::{MarkdownString, TELEGRAM_MAX_CAPTION_LENGTH};
+
+/// MarkdownString limited to Telegram's 1024 characters caption limit
+///
+/// The longer strings are truncated the same way as the messages, see
+/// [`MarkdownStringMessage`](crate::markdown::MarkdownStringMessage).
+///
+/// # Example
+/// ```rust
+/// use telluride::markdown::{MarkdownCaption, MarkdownString};
+///
+/// let caption = MarkdownCaption::from(MarkdownString::escape("x".repeat(2000)));
+/// assert!(caption.is_truncated());
+/// assert!(caption.as_markdown().len_utf16() <= 1024);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MarkdownCaption(MarkdownString);
+
+impl MarkdownCaption {
+    /// Returns the inner string value
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+
+    /// Returns the caption as the MarkdownString
+    pub fn as_markdown(&self) -> &MarkdownString {
+        &self.0
+    }
+
+    /// Converts the caption into the MarkdownString
+    pub fn into_markdown(self) -> MarkdownString {
+        self.0
+    }
+
+    /// Returns true if the caption was truncated to fit the limit
+    pub fn is_truncated(&self) -> bool {
+        self.0.is_truncated()
+    }
+}
+
+impl From<MarkdownString> for MarkdownCaption {
+    fn from(markdown: MarkdownString) -> Self {
+        MarkdownCaption(markdown.limit_length(TELEGRAM_MAX_CAPTION_LENGTH))
+    }
+}
+
+impl From<&str> for MarkdownCaption {
+    fn from(text: &str) -> Self {
+        MarkdownString::from(text).into()
+    }
+}
+
+impl From<String> for MarkdownCaption {
+    fn from(text: String) -> Self {
+        MarkdownString::from(text).into()
+    }
+}
+
+impl From<MarkdownCaption> for String {
+    fn from(caption: MarkdownCaption) -> String {
+        caption.0.into()
+    }
+}
+
+impl fmt::Display for MarkdownCaption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl AsRef<str> for MarkdownCaption {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::markdown_string;
+
+    #[test]
+    fn test_caption_limit() {
+        let caption = MarkdownCaption::from(markdown_string!("*short*"));
+        assert_eq!(caption.as_str(), "*short*");
+        assert!(!caption.is_truncated());
+
+        // The message limit is 4096, the caption is cut at 1024
+        let long = MarkdownString::escape("word. ".repeat(300));
+        assert!(long.len_utf16() < 4096);
+        let caption = MarkdownCaption::from(long);
+        assert!(caption.is_truncated());
+        assert!(caption.as_markdown().len_utf16() <= TELEGRAM_MAX_CAPTION_LENGTH);
+        assert!(caption.as_str().ends_with("\\.\\.\\."));
+    }
+}
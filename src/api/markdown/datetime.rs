@@ -0,0 +1,57 @@
+use std::fmt::Display;
+
+use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+
+use crate::api::markdown::string::MarkdownString;
+
+impl MarkdownString {
+    /// Formats the date and time with the [chrono format string](chrono::format::strftime)
+    /// and escapes the result
+    ///
+    /// Panics if the format string is invalid, like `DateTime::format(...).to_string()`.
+    ///
+    /// # Example
+    /// ```rust
+    /// use chrono::{TimeZone, Utc};
+    /// use telluride::markdown::MarkdownString;
+    ///
+    /// let dt = Utc.with_ymd_and_hms(2024, 3, 1, 12, 30, 0).unwrap();
+    /// let text = MarkdownString::datetime(&dt, "%d.%m.%Y %H:%M");
+    /// assert_eq!(text.as_str(), "01\\.03\\.2024 12:30");
+    /// ```
+    pub fn datetime<Tz: TimeZone>(dt: &DateTime<Tz>, format: &str) -> Self
+    where
+        Tz::Offset: Display,
+    {
+        MarkdownString::escape(dt.format(format).to_string())
+    }
+}
+
+/// Escaped ISO 8601 date, e.g. `2024\-03\-01`
+impl From<NaiveDate> for MarkdownString {
+    fn from(date: NaiveDate) -> Self {
+        MarkdownString::escape(date.to_string())
+    }
+}
+
+/// Escaped date and time in UTC, e.g. `2024\-03\-01 12:30:00 UTC`
+impl From<DateTime<Utc>> for MarkdownString {
+    fn from(dt: DateTime<Utc>) -> Self {
+        MarkdownString::escape(dt.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::markdown_format;
+
+    #[test]
+    fn test_chrono_conversions() {
+        let date = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+        let dt = Utc.with_ymd_and_hms(2024, 3, 1, 12, 30, 0).unwrap();
+        let text = markdown_format!("{} / {}", date, dt);
+        assert_eq!(text.as_str(), "2024\\-03\\-01 / 2024\\-03\\-01 12:30:00 UTC");
+        assert_eq!(MarkdownString::datetime(&dt, "%b %-d").as_str(), "Mar 1");
+    }
+}
@@ -0,0 +1,177 @@
+use std::fmt::{self, Display};
+
+use crate::api::markdown::{
+    ast::{self, Node},
+    string::MarkdownString,
+};
+
+/// Maximum length of the encoded start parameter
+/// See: https://core.telegram.org/api/links#bot-links
+const MAX_PAYLOAD_LENGTH: usize = 64;
+
+/// Alphabet of the base64url encoding, the same as the characters allowed in the start parameter
+/// See: https://datatracker.ietf.org/doc/html/rfc4648#section-5
+const BASE64URL: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// Error building the deep link
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeepLinkError {
+    /// The bot username is not 5-32 characters of letters, digits and underscores
+    InvalidUsername(String),
+    /// The encoded payload is longer than 64 characters
+    PayloadTooLong(usize),
+}
+
+impl Display for DeepLinkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeepLinkError::InvalidUsername(username) => {
+                write!(f, "invalid bot username {:?}", username)
+            }
+            DeepLinkError::PayloadTooLong(length) => write!(
+                f,
+                "encoded payload is {} characters long, the limit is {}",
+                length, MAX_PAYLOAD_LENGTH
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DeepLinkError {}
+
+/// Where the deep link starts the bot
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeepLinkKind {
+    /// The private chat with the bot, `?start=`
+    Start,
+    /// Adding the bot to a group, `?startgroup=`
+    StartGroup,
+}
+
+/// Encode the payload with base64url without padding, as required for the start parameter
+pub fn encode_payload(payload: impl AsRef<[u8]>) -> String {
+    let bytes = payload.as_ref();
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &byte)| n | ((byte as u32) << (16 - 8 * i)));
+        // 3 bytes give 4 characters, the incomplete chunk gives one character more than its bytes
+        for i in 0..=chunk.len() {
+            encoded.push(BASE64URL[((n >> (18 - 6 * i)) & 0x3f) as usize] as char);
+        }
+    }
+    encoded
+}
+
+/// Decode the base64url payload, `None` if it's not valid base64url without padding
+pub fn decode_payload(encoded: &str) -> Option<Vec<u8>> {
+    if encoded.len() % 4 == 1 {
+        return None;
+    }
+    let mut decoded = Vec::with_capacity(encoded.len() * 3 / 4);
+    for chunk in encoded.as_bytes().chunks(4) {
+        let mut n = 0u32;
+        for (i, c) in chunk.iter().enumerate() {
+            let value = BASE64URL.iter().position(|b| b == c)? as u32;
+            n |= value << (18 - 6 * i);
+        }
+        for i in 0..chunk.len() - 1 {
+            decoded.push((n >> (16 - 8 * i)) as u8);
+        }
+    }
+    Some(decoded)
+}
+
+/// Build the `https://t.me/<bot>?start=<payload>` URL with the encoded payload
+pub fn deeplink_url(
+    bot_username: &str,
+    kind: DeepLinkKind,
+    payload: impl AsRef<[u8]>,
+) -> Result<String, DeepLinkError> {
+    let username = bot_username.trim_start_matches('@');
+    let valid_username = (5..=32).contains(&username.len())
+        && username.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid_username {
+        return Err(DeepLinkError::InvalidUsername(bot_username.to_string()));
+    }
+    let encoded = encode_payload(payload);
+    if encoded.len() > MAX_PAYLOAD_LENGTH {
+        return Err(DeepLinkError::PayloadTooLong(encoded.len()));
+    }
+    let parameter = match kind {
+        DeepLinkKind::Start => "start",
+        DeepLinkKind::StartGroup => "startgroup",
+    };
+    Ok(format!("https://t.me/{}?{}={}", username, parameter, encoded))
+}
+
+/// Build the link with the text to the deep link URL, see [`deeplink_url`]
+///
+/// # Example
+/// ```rust
+/// use telluride::markdown::deeplink::{self, DeepLinkKind};
+///
+/// let link = deeplink::deeplink("my_bot", DeepLinkKind::Start, "ref=42", "Join!").unwrap();
+/// assert_eq!(link.as_str(), "[Join\\!](https://t.me/my_bot?start=cmVmPTQy)");
+/// assert_eq!(deeplink::decode_start("/start cmVmPTQy").unwrap(), b"ref=42");
+/// ```
+pub fn deeplink(
+    bot_username: &str,
+    kind: DeepLinkKind,
+    payload: impl AsRef<[u8]>,
+    text: impl Into<MarkdownString>,
+) -> Result<MarkdownString, DeepLinkError> {
+    let url = deeplink_url(bot_username, kind, payload)?;
+    let text: MarkdownString = text.into();
+    let mut children = ast::parse(&text);
+    ast::strip_links(&mut children);
+    Ok(ast::render(&[Node::Link { url, children }]))
+}
+
+/// Decode the payload of the `/start` command, the text may be the whole message
+/// like `/start@my_bot <payload>` or the command argument only
+pub fn decode_start(text: &str) -> Option<Vec<u8>> {
+    let text = text.trim();
+    let payload = match text.strip_prefix("/start") {
+        Some(rest) if rest.starts_with(['@', ' ']) => rest.split_once(' ')?.1.trim(),
+        Some("") => return None,
+        _ => text,
+    };
+    decode_payload(payload)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deeplink() {
+        for payload in ["", "a", "ab", "abc", "abcd", "\u{1F600} ünïcode/+?"] {
+            let encoded = encode_payload(payload);
+            assert!(encoded.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+            assert_eq!(decode_payload(&encoded).unwrap(), payload.as_bytes());
+        }
+        assert_eq!(encode_payload([0xfb, 0xff]), "-_8");
+        assert_eq!(decode_payload("a"), None);
+        assert_eq!(decode_payload("a+b="), None);
+
+        assert_eq!(
+            deeplink_url("@my_bot", DeepLinkKind::StartGroup, [1, 2, 3]).unwrap(),
+            "https://t.me/my_bot?startgroup=AQID"
+        );
+        assert_eq!(
+            deeplink_url("bot", DeepLinkKind::Start, "x"),
+            Err(DeepLinkError::InvalidUsername("bot".to_string()))
+        );
+        assert_eq!(
+            deeplink_url("my_bot", DeepLinkKind::Start, [0; 49]),
+            Err(DeepLinkError::PayloadTooLong(66))
+        );
+
+        assert_eq!(decode_start("/start@my_bot AQID").unwrap(), [1, 2, 3]);
+        assert_eq!(decode_start("AQID").unwrap(), [1, 2, 3]);
+        assert_eq!(decode_start("/start"), None);
+    }
+}
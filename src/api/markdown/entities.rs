@@ -0,0 +1,140 @@
+use teloxide::types::{CustomEmojiId, MessageEntity, MessageEntityKind};
+
+use crate::api::markdown::{
+    ast::{self, Node},
+    string::MarkdownString,
+};
+
+/// Plain text with the entities being built, the offsets are counted in UTF-16 code units
+/// See: https://core.telegram.org/api/entities#entity-length
+#[derive(Default)]
+struct EntitiesBuilder {
+    text: String,
+    offset: usize,
+    entities: Vec<MessageEntity>,
+}
+
+impl EntitiesBuilder {
+    fn push_str(&mut self, text: &str) {
+        self.text.push_str(text);
+        self.offset += text.encode_utf16().count();
+    }
+
+    /// Add the entity started at the offset, empty entities are dropped
+    fn close(&mut self, offset: usize, kind: MessageEntityKind) {
+        if self.offset > offset {
+            self.entities
+                .push(MessageEntity::new(kind, offset, self.offset - offset));
+        }
+    }
+
+    fn add_nodes(&mut self, nodes: &[Node]) {
+        for node in nodes {
+            let offset = self.offset;
+            let kind = match node {
+                Node::Text(text) => {
+                    self.push_str(text);
+                    continue;
+                }
+                Node::Code(code) => {
+                    self.push_str(code);
+                    MessageEntityKind::Code
+                }
+                Node::Pre { language, code } => {
+                    self.push_str(code);
+                    MessageEntityKind::Pre {
+                        language: language.clone(),
+                    }
+                }
+                Node::CustomEmoji { id, emoji } => {
+                    self.push_str(emoji);
+                    MessageEntityKind::CustomEmoji {
+                        custom_emoji_id: CustomEmojiId(id.clone()),
+                    }
+                }
+                Node::Link { url, children } => {
+                    self.add_nodes(children);
+                    match url.parse() {
+                        Ok(url) => MessageEntityKind::TextLink { url },
+                        Err(_) => continue,
+                    }
+                }
+                Node::Bold(children) => {
+                    self.add_nodes(children);
+                    MessageEntityKind::Bold
+                }
+                Node::Italic(children) => {
+                    self.add_nodes(children);
+                    MessageEntityKind::Italic
+                }
+                Node::Underline(children) => {
+                    self.add_nodes(children);
+                    MessageEntityKind::Underline
+                }
+                Node::Strikethrough(children) => {
+                    self.add_nodes(children);
+                    MessageEntityKind::Strikethrough
+                }
+                Node::Spoiler(children) => {
+                    self.add_nodes(children);
+                    MessageEntityKind::Spoiler
+                }
+                Node::Blockquote(children) => {
+                    self.add_nodes(children);
+                    MessageEntityKind::Blockquote
+                }
+                Node::ExpandableBlockquote(children) => {
+                    self.add_nodes(children);
+                    MessageEntityKind::ExpandableBlockquote
+                }
+            };
+            self.close(offset, kind);
+        }
+    }
+}
+
+/// Convert the MarkdownV2 text into the plain text and the list of the message entities
+pub(crate) fn markdown_to_entities(markdown: &MarkdownString) -> (String, Vec<MessageEntity>) {
+    let mut builder = EntitiesBuilder::default();
+    builder.add_nodes(&ast::parse(markdown));
+    builder.entities.sort_by_key(|entity| entity.offset);
+    (builder.text, builder.entities)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::markdown_string;
+
+    #[test]
+    fn test_markdown_to_entities() {
+        let (text, entities) = markdown_to_entities(&markdown_string!(
+            "*Hello* _wörld_ 👍 __u__\\! [link](http://example\\.com/)"
+        ));
+        assert_eq!(text, "Hello wörld 👍 u! link");
+        assert_eq!(
+            entities,
+            vec![
+                MessageEntity::bold(0, 5),
+                MessageEntity::italic(6, 5),
+                // The emoji takes two UTF-16 code units
+                MessageEntity::underline(15, 1),
+                MessageEntity::text_link("http://example.com/".parse().unwrap(), 18, 4),
+            ]
+        );
+
+        let (text, entities) = markdown_to_entities(&markdown_string!(
+            "Run `cargo \\` test`:\n```rust\nfn main() {}\n```\n>quoted\n>lines\nend\n**>more||"
+        ));
+        assert_eq!(text, "Run cargo ` test:\nfn main() {}\nquoted\nlines\nend\nmore");
+        assert_eq!(
+            entities,
+            vec![
+                MessageEntity::code(4, 12),
+                MessageEntity::pre(Some("rust".to_string()), 18, 12),
+                MessageEntity::new(MessageEntityKind::Blockquote, 31, 12),
+                MessageEntity::new(MessageEntityKind::ExpandableBlockquote, 48, 4),
+            ]
+        );
+    }
+}
@@ -0,0 +1,73 @@
+use std::fmt::{self, Display, Write};
+
+use crate::api::markdown::string::{ESCAPE_CHARS, MarkdownString};
+
+/// Wrapper displaying the value with the MarkdownV2 special characters escaped
+///
+/// It allows to embed any [`Display`] value into the markdown produced by `write!` or `format!`
+/// without the intermediate string, e.g. for the APIs taking [`fmt::Arguments`].
+/// The formatting flags like the width are not applied to the value.
+///
+/// # Example
+/// ```rust
+/// use telluride::markdown::{EscapedDisplay, MarkdownString};
+///
+/// let price = 9.99;
+/// let text = format!("*Price:* {}", EscapedDisplay(price));
+/// assert_eq!(text, "*Price:* 9\\.99");
+/// assert_eq!(MarkdownString::escaper("a-b").to_string(), "a\\-b");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EscapedDisplay<T>(pub T);
+
+impl<T: Display> Display for EscapedDisplay<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(EscapingWriter(f), "{}", self.0)
+    }
+}
+
+impl<T: Display> From<EscapedDisplay<T>> for MarkdownString {
+    fn from(value: EscapedDisplay<T>) -> Self {
+        MarkdownString::from_validated_string(value.to_string())
+    }
+}
+
+impl MarkdownString {
+    /// Wrap the value to display it escaped, see [`EscapedDisplay`]
+    pub fn escaper<T: Display>(value: T) -> EscapedDisplay<T> {
+        EscapedDisplay(value)
+    }
+}
+
+/// Writer escaping the MarkdownV2 special characters on the fly
+struct EscapingWriter<'a, 'b>(&'a mut fmt::Formatter<'b>);
+
+impl Write for EscapingWriter<'_, '_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        // Write the runs of the plain characters at once
+        let mut start = 0;
+        for (i, c) in s.char_indices() {
+            if ESCAPE_CHARS.contains(&c) {
+                self.0.write_str(&s[start..i])?;
+                self.0.write_char('\\')?;
+                start = i;
+            }
+        }
+        self.0.write_str(&s[start..])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::markdown_format;
+
+    #[test]
+    fn test_escaped_display() {
+        let value = "[a](b) *c* 1.5!";
+        assert_eq!(EscapedDisplay(value).to_string(), MarkdownString::escape(value).as_str());
+        assert_eq!(EscapedDisplay("").to_string(), "");
+        let text = markdown_format!("*{}*", EscapedDisplay(-42));
+        assert_eq!(text.as_str(), "*\\-42*");
+    }
+}
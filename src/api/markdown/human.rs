@@ -0,0 +1,116 @@
+use std::time::Duration;
+
+use crate::api::markdown::string::MarkdownString;
+
+/// Units of the byte sizes, each is 1024 times the previous one
+const BYTE_UNITS: [&str; 7] = ["B", "KB", "MB", "GB", "TB", "PB", "EB"];
+
+/// Suffixes of the counts, each is 1000 times the previous one
+const COUNT_SUFFIXES: [&str; 7] = ["", "K", "M", "B", "T", "Q", "Qi"];
+
+/// Units of the durations with their lengths in seconds, from the largest
+const DURATION_UNITS: [(&str, u64); 4] = [("d", 86400), ("h", 3600), ("m", 60), ("s", 1)];
+
+/// Internal helper function to scale the value down by the base and format it with the unit,
+/// with one decimal digit for the values less than 10
+fn scale(value: u64, base: f64, units: &[&str], separator: &str) -> String {
+    if value < base as u64 {
+        return format!("{}{}{}", value, separator, units[0]);
+    }
+    let mut scaled = value as f64;
+    let mut unit = 0;
+    while scaled >= base && unit + 1 < units.len() {
+        scaled /= base;
+        unit += 1;
+    }
+    if scaled < 10.0 {
+        format!("{:.1}{}{}", scaled, separator, units[unit])
+    } else {
+        format!("{:.0}{}{}", scaled, separator, units[unit])
+    }
+}
+
+/// Format the size in bytes with the binary units, e.g. "1\.2 GB"
+///
+/// # Example
+/// ```rust
+/// use telluride::markdown::human::human_bytes;
+///
+/// assert_eq!(human_bytes(512).as_str(), "512 B");
+/// assert_eq!(human_bytes(1_288_490_189).as_str(), "1\\.2 GB");
+/// ```
+pub fn human_bytes(bytes: u64) -> MarkdownString {
+    MarkdownString::escape(scale(bytes, 1024.0, &BYTE_UNITS, " "))
+}
+
+/// Format the count with the K, M, B suffixes, e.g. "15K" or "3\.4M"
+///
+/// # Example
+/// ```rust
+/// use telluride::markdown::human::human_count;
+///
+/// assert_eq!(human_count(999).as_str(), "999");
+/// assert_eq!(human_count(3_400_000).as_str(), "3\\.4M");
+/// ```
+pub fn human_count(count: u64) -> MarkdownString {
+    MarkdownString::escape(scale(count, 1000.0, &COUNT_SUFFIXES, ""))
+}
+
+/// Format the duration with the two largest units, e.g. "3h 5m" or "2d 4h",
+/// the durations shorter than a second are formatted in milliseconds
+///
+/// # Example
+/// ```rust
+/// use std::time::Duration;
+/// use telluride::markdown::human::human_duration;
+///
+/// assert_eq!(human_duration(Duration::from_secs(3 * 3600 + 5 * 60 + 7)).as_str(), "3h 5m");
+/// assert_eq!(human_duration(Duration::from_millis(250)).as_str(), "250ms");
+/// ```
+pub fn human_duration(duration: Duration) -> MarkdownString {
+    let mut seconds = duration.as_secs();
+    if seconds == 0 {
+        return MarkdownString::escape(format!("{}ms", duration.as_millis()));
+    }
+    let mut parts = Vec::new();
+    for (unit, length) in DURATION_UNITS {
+        let value = seconds / length;
+        seconds %= length;
+        if value > 0 || !parts.is_empty() {
+            parts.push((value, unit));
+        }
+        if parts.len() == 2 {
+            break;
+        }
+    }
+    let text = parts
+        .iter()
+        .filter(|(value, _)| *value > 0)
+        .map(|(value, unit)| format!("{}{}", value, unit))
+        .collect::<Vec<_>>()
+        .join(" ");
+    MarkdownString::escape(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_human_formatting() {
+        assert_eq!(human_bytes(0).as_str(), "0 B");
+        assert_eq!(human_bytes(1536).as_str(), "1\\.5 KB");
+        assert_eq!(human_bytes(20 * 1024 * 1024).as_str(), "20 MB");
+        assert_eq!(human_bytes(u64::MAX).as_str(), "16 EB");
+
+        assert_eq!(human_count(1000).as_str(), "1\\.0K");
+        assert_eq!(human_count(15_300).as_str(), "15K");
+        assert_eq!(human_count(2_000_000_000).as_str(), "2\\.0B");
+
+        assert_eq!(human_duration(Duration::ZERO).as_str(), "0ms");
+        assert_eq!(human_duration(Duration::from_secs(59)).as_str(), "59s");
+        assert_eq!(human_duration(Duration::from_secs(3600)).as_str(), "1h");
+        assert_eq!(human_duration(Duration::from_secs(3601)).as_str(), "1h");
+        assert_eq!(human_duration(Duration::from_secs(2 * 86400 + 4 * 3600 + 1)).as_str(), "2d 4h");
+    }
+}
@@ -0,0 +1,185 @@
+use crate::{
+    api::markdown::string::{MarkdownString, TELEGRAM_MAX_MESSAGE_LENGTH, TRUNCATION_MARKER},
+    markdown_string, markdown_write, markdown_writeln,
+};
+
+/// Indentation of the nested list per level
+const INDENT: &str = "  ";
+
+/// Marker of the list items
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ListStyle {
+    /// `- item`
+    #[default]
+    Dash,
+    /// `• item`
+    Bullet,
+    /// `1. item`
+    Numbered,
+}
+
+#[derive(Debug, Clone)]
+struct ListItem {
+    text: MarkdownString,
+    sublist: Option<ListBuilder>,
+}
+
+/// Builder of the bulleted and numbered lists
+///
+/// The items can be truncated to the maximal length each, and the whole list is limited to
+/// [Telegram's message length limit](https://core.telegram.org/bots/api#sendmessage) by default:
+/// the items which don't fit are replaced by the "..." line.
+///
+/// # Example
+/// ```rust
+/// use telluride::{markdown::{ListBuilder, ListStyle}, markdown_string};
+///
+/// let list = ListBuilder::new(ListStyle::Numbered)
+///     .item(markdown_string!("*Fruits*"))
+///     .sublist(ListBuilder::new(ListStyle::Bullet).items(["apple", "pear"]))
+///     .item("Vegetables...")
+///     .build();
+/// assert_eq!(list.as_str(), "1\\. *Fruits*\n  • apple\n  • pear\n2\\. Vegetables\\.\\.\\.");
+/// ```
+#[derive(Debug, Clone)]
+pub struct ListBuilder {
+    style: ListStyle,
+    items: Vec<ListItem>,
+    max_item_length: Option<usize>,
+    max_length: usize,
+}
+
+impl Default for ListBuilder {
+    fn default() -> Self {
+        Self::new(ListStyle::default())
+    }
+}
+
+impl ListBuilder {
+    /// Create the empty list with the given item marker
+    pub fn new(style: ListStyle) -> Self {
+        Self {
+            style,
+            items: Vec::new(),
+            max_item_length: None,
+            max_length: TELEGRAM_MAX_MESSAGE_LENGTH,
+        }
+    }
+
+    /// Add the item
+    pub fn item(mut self, text: impl Into<MarkdownString>) -> Self {
+        self.push_item(text);
+        self
+    }
+
+    /// Add the items
+    pub fn items<T: Into<MarkdownString>>(mut self, items: impl IntoIterator<Item = T>) -> Self {
+        for item in items {
+            self.push_item(item);
+        }
+        self
+    }
+
+    /// Add the item without consuming the builder
+    pub fn push_item(&mut self, text: impl Into<MarkdownString>) {
+        self.items.push(ListItem {
+            text: text.into(),
+            sublist: None,
+        });
+    }
+
+    /// Nest the list under the last item, it's ignored if there are no items
+    pub fn sublist(mut self, sublist: ListBuilder) -> Self {
+        if let Some(item) = self.items.last_mut() {
+            item.sublist = Some(sublist);
+        }
+        self
+    }
+
+    /// Truncate the items longer than the limit, including the nested ones, the marker is not counted
+    pub fn max_item_length(mut self, max_item_length: usize) -> Self {
+        self.max_item_length = Some(max_item_length);
+        self
+    }
+
+    /// Set the limit of the whole list length, the message length limit by default
+    pub fn max_length(mut self, max_length: usize) -> Self {
+        self.max_length = max_length;
+        self
+    }
+
+    /// Build the list as the MarkdownString, one item per line
+    pub fn build(&self) -> MarkdownString {
+        let mut lines = Vec::new();
+        self.render_lines(0, self.max_item_length, &mut lines);
+        let truncation_marker = markdown_string!(TRUNCATION_MARKER);
+        let mut result = MarkdownString::new();
+        let mut length = 0;
+        for (i, line) in lines.iter().enumerate() {
+            let separator = usize::from(i > 0);
+            let line_length = line.len_utf16();
+            // The last line doesn't need the room for the truncation marker
+            let reserved = if i + 1 < lines.len() {
+                1 + truncation_marker.len_utf16()
+            } else {
+                0
+            };
+            if length + separator + line_length + reserved > self.max_length {
+                if i > 0 {
+                    markdown_writeln!(result);
+                }
+                result.push(&truncation_marker);
+                break;
+            }
+            if i > 0 {
+                markdown_writeln!(result);
+            }
+            result.push(line);
+            length += separator + line_length;
+        }
+        result
+    }
+
+    /// Render the items with their markers and the nested lists to the separate lines
+    fn render_lines(&self, depth: usize, max_item_length: Option<usize>, lines: &mut Vec<MarkdownString>) {
+        for (i, item) in self.items.iter().enumerate() {
+            let mut line = MarkdownString::escape(INDENT.repeat(depth));
+            match self.style {
+                ListStyle::Dash => line.push(&markdown_string!("\\- ")),
+                ListStyle::Bullet => line.push(&markdown_string!("• ")),
+                ListStyle::Numbered => markdown_write!(line, "{}\\. ", (i + 1).to_string()),
+            }
+            match max_item_length {
+                Some(max_length) => line.push(&item.text.clone().limit_length(max_length)),
+                None => line.push(&item.text),
+            }
+            lines.push(line);
+            if let Some(sublist) = &item.sublist {
+                sublist.render_lines(depth + 1, max_item_length, lines);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_list_builder() {
+        let list = ListBuilder::default()
+            .items(["a-b", "long item"])
+            .sublist(ListBuilder::new(ListStyle::Numbered).item("x").item("y"))
+            .max_item_length(7)
+            .build();
+        assert_eq!(list.as_str(), "\\- a\\-b\n\\- long\\.\\.\\.\n  1\\. x\n  2\\. y");
+
+        let list = ListBuilder::new(ListStyle::Bullet)
+            .items((1..=10).map(|i| i.to_string()))
+            .max_length(20)
+            .build();
+        assert_eq!(list.as_str(), "• 1\n• 2\n• 3\n• 4\n\\.\\.\\.");
+        assert!(list.len_utf16() <= 20);
+        assert_eq!(ListBuilder::default().build().as_str(), "");
+    }
+}
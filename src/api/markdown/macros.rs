@@ -1,6 +1,12 @@
 /// Creates a MarkdownString with compile-time validation of the format string.
+///
+/// With the `macros` feature the errors in the string literals point at the offending character.
 #[macro_export]
 macro_rules! markdown_string {
+    ($format_str:literal) => {{
+        $crate::markdown_check_literal!($format_str);
+        $crate::markdown::MarkdownString::from_validated_string($format_str)
+    }};
     ($format_str:expr) => {{
         // Compile-time validation for Telegram MarkdownV2 format compatibility
         const _: () = $crate::markdown::validate_markdownv2_format($format_str);
@@ -8,6 +14,26 @@ macro_rules! markdown_string {
     }};
 }
 
+/// Helper macro to validate the string literal, by the procedural macro if available
+#[cfg(feature = "macros")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! markdown_check_literal {
+    ($format_str:literal) => {
+        $crate::__check_markdown_literal!($format_str)
+    };
+}
+
+/// Helper macro to validate the string literal, by the procedural macro if available
+#[cfg(not(feature = "macros"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! markdown_check_literal {
+    ($format_str:literal) => {
+        const _: () = $crate::markdown::validate_markdownv2_format($format_str);
+    };
+}
+
 /// Helper macro to process arguments in any order, handling @code, @raw, and regular arguments.
 ///
 /// This uses incremental TT munching to process one argument at a time.
@@ -24,6 +50,7 @@ macro_rules! md_process_args {
         $crate::md_process_args!(@munch [$($($tail)*)?] -> [
             $($processed)*
             {
+                const _: () = $crate::markdown::validate_code_language($lang);
                 let content: String = $code_content.into();
                 format!("```{}\n{}\n```", $lang, content)
             },
@@ -41,6 +68,95 @@ macro_rules! md_process_args {
         ])
     };
 
+    // Process @url argument
+    (@munch [@url $url_arg:expr $(, $($tail:tt)*)?] -> [$($processed:tt)*]) => {
+        $crate::md_process_args!(@munch [$($($tail)*)?] -> [
+            $($processed)*
+            {
+                let url = $crate::markdown::MarkdownString::escape_url($url_arg);
+                url.as_str().to_string()
+            },
+        ])
+    };
+
+    // Process @bold argument
+    (@munch [@bold $arg:expr $(, $($tail:tt)*)?] -> [$($processed:tt)*]) => {
+        $crate::md_process_args!(@munch [$($($tail)*)?] -> [
+            $($processed)*
+            {
+                let arg_markdown: $crate::markdown::MarkdownString = $arg.into();
+                format!("*{}*", arg_markdown.as_str())
+            },
+        ])
+    };
+
+    // Process @italic argument
+    (@munch [@italic $arg:expr $(, $($tail:tt)*)?] -> [$($processed:tt)*]) => {
+        $crate::md_process_args!(@munch [$($($tail)*)?] -> [
+            $($processed)*
+            {
+                let arg_markdown: $crate::markdown::MarkdownString = $arg.into();
+                format!("_{}_", arg_markdown.as_str())
+            },
+        ])
+    };
+
+    // Process @underline argument
+    (@munch [@underline $arg:expr $(, $($tail:tt)*)?] -> [$($processed:tt)*]) => {
+        $crate::md_process_args!(@munch [$($($tail)*)?] -> [
+            $($processed)*
+            {
+                let arg_markdown: $crate::markdown::MarkdownString = $arg.into();
+                format!("__{}__", arg_markdown.as_str())
+            },
+        ])
+    };
+
+    // Process @strike argument
+    (@munch [@strike $arg:expr $(, $($tail:tt)*)?] -> [$($processed:tt)*]) => {
+        $crate::md_process_args!(@munch [$($($tail)*)?] -> [
+            $($processed)*
+            {
+                let arg_markdown: $crate::markdown::MarkdownString = $arg.into();
+                format!("~{}~", arg_markdown.as_str())
+            },
+        ])
+    };
+
+    // Process @spoiler argument
+    (@munch [@spoiler $arg:expr $(, $($tail:tt)*)?] -> [$($processed:tt)*]) => {
+        $crate::md_process_args!(@munch [$($($tail)*)?] -> [
+            $($processed)*
+            {
+                let spoiler = $crate::markdown::MarkdownString::spoiler($arg);
+                spoiler.as_str().to_string()
+            },
+        ])
+    };
+
+    // Process @link argument with the URL
+    (@munch [@link $url:tt $text:expr $(, $($tail:tt)*)?] -> [$($processed:tt)*]) => {
+        $crate::md_process_args!(@munch [$($($tail)*)?] -> [
+            $($processed)*
+            {
+                let text: $crate::markdown::MarkdownString = $text.into();
+                let url = $crate::markdown::MarkdownString::escape_url($url);
+                format!("[{}]({})", text.as_str(), url.as_str())
+            },
+        ])
+    };
+
+    // Process @mention argument with the user id
+    (@munch [@mention $user_id:tt $name:expr $(, $($tail:tt)*)?] -> [$($processed:tt)*]) => {
+        $crate::md_process_args!(@munch [$($($tail)*)?] -> [
+            $($processed)*
+            {
+                let mention = $crate::markdown::MarkdownString::mention($name, $user_id);
+                mention.as_str().to_string()
+            },
+        ])
+    };
+
     // Process @raw argument
     (@munch [@raw $raw_arg:expr $(, $($tail:tt)*)?] -> [$($processed:tt)*]) => {
         $crate::md_process_args!(@munch [$($($tail)*)?] -> [
@@ -74,11 +190,22 @@ macro_rules! md_process_args {
 /// If a &str literal is provided, it will be validated at compile-time using `markdown_string!`.
 /// Arguments must be types that implement `Into<MarkdownString>`.
 ///
+/// The `{}` placeholders take the arguments in order, the `{0}`, `{1}`, ... placeholders take
+/// the argument by its index, so the same argument can be used several times, like in [`format!`].
+///
 /// # Special Argument Modifiers
 ///
 /// - `@raw`: Pass a MarkdownString without re-escaping (for pre-formatted markdown)
 /// - `@code`: Wrap content in a code block (```). Content is not escaped.
-/// - `@code "lang"`: Wrap content in a language-specific code block (```lang)
+/// - `@code "lang"`: Wrap content in a language-specific code block (```lang), the language is validated
+///   at compile time, see [`validate_code_language`](crate::markdown::validate_code_language)
+/// - `@bold`, `@italic`, `@underline`, `@strike`: Escape the argument and wrap it in the entity
+/// - `@spoiler`: Hide the argument in the spoiler, see [`MarkdownString::spoiler`](crate::markdown::MarkdownString::spoiler)
+/// - `@link url`: Escape the argument and make it the text of the link to the URL, the URL must be
+///   a single token tree, e.g. a literal, a variable or an expression in parentheses
+/// - `@mention user_id`: Escape the argument and make it the mention of the user, see
+///   [`MarkdownString::mention`](crate::markdown::MarkdownString::mention), the id must be a single token tree
+/// - `@url`: Percent-encode the argument for the URL part of the link, see [`MarkdownString::escape_url`](crate::markdown::MarkdownString::escape_url)
 ///
 /// You can mix these modifiers and regular arguments in any order.
 ///
@@ -95,6 +222,24 @@ macro_rules! md_process_args {
 /// // Using @code with language
 /// let code = "fn main() { println!(\"Hello\"); }";
 /// let result = markdown_format!("Example:\n{}", @code "rust" code);
+///
+/// // Using the entity modifiers
+/// let result = markdown_format!("{} is {}", @bold "Alice", @link "https://example.com/status" "online");
+///
+/// // Mentioning the user without the username
+/// let result = markdown_format!("Hi, {}", @mention (user.id) user.first_name);
+///
+/// // Reusing the argument by the index
+/// let result = markdown_format!("[{0}]({1}) \\({1}\\)", "docs", @url "https://example.com");
+///
+/// // Using @url for the link target
+/// let result = markdown_format!("[Search]({})", @url "https://example.com/?q=a b");
+/// ```
+///
+/// The invalid code block language fails to compile:
+/// ```compile_fail
+/// use telluride::markdown_format;
+/// let code = markdown_format!("{}", @code "rust," "fn main() {}");
 /// ```
 #[macro_export]
 macro_rules! markdown_format {
@@ -117,19 +262,43 @@ macro_rules! markdown_format {
     // MarkdownString with arguments
     ($format_markdown:expr, $($args:tt)*) => {{
         let markdown_string: $crate::markdown::MarkdownString = $format_markdown;
-        let format_str = markdown_string.as_str();
 
         // Process all arguments using the helper macro
         let escaped_args: Vec<String> = $crate::md_process_args!($($args)*);
 
         // Replace placeholders with converted arguments
-        let mut result = format_str.to_string();
-        for escaped_arg in escaped_args {
-            if let Some(placeholder_pos) = result.find("{}") {
-                result.replace_range(placeholder_pos..placeholder_pos + 2, &escaped_arg);
-            }
-        }
-
-        $crate::markdown::MarkdownString::from_validated_string(result)
+        $crate::markdown::MarkdownString::format_with_args(&markdown_string, &escaped_args)
     }};
 }
+
+/// Appends the formatted text to the MarkdownString, like [`write!`] for the strings.
+/// Accepts the same arguments as [`markdown_format!`].
+///
+/// # Examples
+/// ```rust
+/// use telluride::{markdown_string, markdown_write, markdown_writeln};
+///
+/// let mut report = markdown_string!("*Report*");
+/// markdown_writeln!(report);
+/// markdown_write!(report, "Total: {}", @bold "1.5");
+/// markdown_writeln!(report, " \\({}\\)", "approx.");
+/// assert_eq!(report.as_str(), "*Report*\nTotal: *1\\.5* \\(approx\\.\\)\n");
+/// ```
+#[macro_export]
+macro_rules! markdown_write {
+    ($buf:expr, $($args:tt)*) => {
+        $buf.push(&$crate::markdown_format!($($args)*))
+    };
+}
+
+/// Appends the formatted text followed by the line break to the MarkdownString, like [`writeln!`] for the strings.
+/// Accepts the same arguments as [`markdown_format!`], without arguments appends only the line break.
+#[macro_export]
+macro_rules! markdown_writeln {
+    ($buf:expr) => {
+        $buf.push_line(&$crate::markdown::MarkdownString::new())
+    };
+    ($buf:expr, $($args:tt)*) => {
+        $buf.push_line(&$crate::markdown_format!($($args)*))
+    };
+}
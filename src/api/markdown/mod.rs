@@ -1,3 +1,22 @@
+pub(crate) mod ast;
+pub(crate) mod caption;
+#[cfg(feature = "chrono")]
+pub(crate) mod datetime;
+pub(crate) mod deeplink;
+#[cfg(feature = "teloxide")]
+pub(crate) mod entities;
+pub(crate) mod escaped;
+pub(crate) mod human;
+pub(crate) mod list;
 pub(crate) mod macros;
+#[cfg(feature = "teloxide")]
+pub(crate) mod send_options;
+pub(crate) mod split;
+#[cfg(feature = "teloxide")]
+pub(crate) mod stream;
 pub(crate) mod string;
+pub(crate) mod table;
+#[cfg(feature = "teloxide")]
+pub(crate) mod template;
 pub(crate) mod validate;
+pub(crate) mod widgets;
@@ -0,0 +1,119 @@
+use teloxide::{
+    payloads::SendMessageSetters,
+    types::{LinkPreviewOptions, MessageId, ReplyParameters},
+};
+
+/// Options of the message sent with
+/// [`send_markdown_message_with`](crate::markdown::MarkdownStringMessage::send_markdown_message_with)
+///
+/// The options are applied to the request after the MarkdownV2 parse mode is set, so unlike the raw
+/// teloxide setters they can't be used to replace it.
+///
+/// # Example
+/// ```rust
+/// use telluride::markdown::MarkdownSendOptions;
+/// use teloxide::types::MessageId;
+///
+/// let options = MarkdownSendOptions::default()
+///     .disable_link_preview()
+///     .silent()
+///     .reply_to(MessageId(42));
+/// assert!(options.disable_notification);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MarkdownSendOptions {
+    /// Link preview generation options
+    pub link_preview_options: Option<LinkPreviewOptions>,
+    /// Send the message silently, the users receive a notification with no sound
+    pub disable_notification: bool,
+    /// Protect the message from forwarding and saving
+    pub protect_content: bool,
+    /// Description of the message to reply to
+    pub reply_parameters: Option<ReplyParameters>,
+}
+
+impl MarkdownSendOptions {
+    /// Set the link preview options
+    pub fn link_preview(mut self, options: LinkPreviewOptions) -> Self {
+        self.link_preview_options = Some(options);
+        self
+    }
+
+    /// Don't show the link preview
+    pub fn disable_link_preview(self) -> Self {
+        self.link_preview(LinkPreviewOptions {
+            is_disabled: true,
+            url: None,
+            prefer_small_media: false,
+            prefer_large_media: false,
+            show_above_text: false,
+        })
+    }
+
+    /// Send the message without the notification sound
+    pub fn silent(mut self) -> Self {
+        self.disable_notification = true;
+        self
+    }
+
+    /// Protect the message from forwarding and saving
+    pub fn protect_content(mut self) -> Self {
+        self.protect_content = true;
+        self
+    }
+
+    /// Reply to the message in the same chat
+    pub fn reply_to(self, message_id: MessageId) -> Self {
+        self.reply_parameters(ReplyParameters::new(message_id))
+    }
+
+    /// Set the description of the message to reply to, e.g. in another chat or with a quote
+    pub fn reply_parameters(mut self, parameters: ReplyParameters) -> Self {
+        self.reply_parameters = Some(parameters);
+        self
+    }
+
+    /// Internal helper function to set the options on the request
+    pub(crate) fn apply<R: SendMessageSetters>(&self, mut request: R) -> R {
+        if let Some(options) = &self.link_preview_options {
+            request = request.link_preview_options(options.clone());
+        }
+        if self.disable_notification {
+            request = request.disable_notification(true);
+        }
+        if self.protect_content {
+            request = request.protect_content(true);
+        }
+        if let Some(parameters) = &self.reply_parameters {
+            request = request.reply_parameters(parameters.clone());
+        }
+        request
+    }
+}
+
+#[cfg(all(test, feature = "testing"))]
+mod tests {
+    use teloxide::types::ChatId;
+
+    use super::*;
+    use crate::{markdown::MarkdownStringMessage, markdown_string, testing::MockBotApi};
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_send_options() {
+        let api = MockBotApi::start().await;
+        let options = MarkdownSendOptions::default()
+            .disable_link_preview()
+            .silent()
+            .reply_to(MessageId(7));
+        api.bot()
+            .send_markdown_message_with(ChatId(1), markdown_string!("*Hi*"), &options)
+            .await
+            .unwrap();
+        let request = api.next_request("sendMessage").await.unwrap();
+        assert_eq!(request.str_param("parse_mode"), Some("MarkdownV2"));
+        assert_eq!(request.params["disable_notification"], true);
+        assert_eq!(request.params["link_preview_options"]["is_disabled"], true);
+        assert_eq!(request.params["reply_parameters"]["message_id"], 7);
+        assert!(request.params.get("protect_content").is_none());
+    }
+}
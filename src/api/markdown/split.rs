@@ -0,0 +1,340 @@
+/// Formatting entity open at some position of the MarkdownV2 text
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Marker {
+    /// Bold, italic, underline, strikethrough or spoiler, the marker is both the opening and the closing one
+    Style(&'static str),
+    /// Inline code
+    Code,
+    /// Pre-formatted code block with the opening line, e.g. "```rust\n"
+    Pre(String),
+}
+
+impl Marker {
+    fn opening(&self) -> &str {
+        match self {
+            Marker::Style(marker) => marker,
+            Marker::Code => "`",
+            Marker::Pre(opening) => opening,
+        }
+    }
+
+    fn closing(&self) -> &str {
+        match self {
+            Marker::Style(marker) => marker,
+            Marker::Code => "`",
+            Marker::Pre(_) => "\n```",
+        }
+    }
+}
+
+/// Preference of the split position, the higher the better
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum BoundaryKind {
+    /// Any character boundary inside the formatting entities
+    Inside,
+    /// Any character boundary outside of the formatting entities
+    Char,
+    /// Space inside the formatting entities
+    InsideSpace,
+    /// Space outside of the formatting entities
+    Space,
+    /// Line break inside the formatting entities
+    InsideNewline,
+    /// Line break outside of the formatting entities
+    Newline,
+}
+
+/// Position where the text can be split, with the entities open at it
+struct Boundary {
+    pos: usize,
+    kind: BoundaryKind,
+    open: Vec<Marker>,
+}
+
+impl Boundary {
+    /// Number of the separator bytes dropped at the split, the line break or the space
+    fn skip(&self) -> usize {
+        match self.kind {
+            BoundaryKind::Newline
+            | BoundaryKind::InsideNewline
+            | BoundaryKind::Space
+            | BoundaryKind::InsideSpace => 1,
+            _ => 0,
+        }
+    }
+}
+
+/// Internal helper function to toggle the style marker on the stack of the open entities
+fn toggle(open: &mut Vec<Marker>, marker: &'static str) {
+    if open.last() == Some(&Marker::Style(marker)) {
+        open.pop();
+    } else {
+        open.push(Marker::Style(marker));
+    }
+}
+
+/// Internal helper function to get the length of the character at the position in UTF-16 code units
+/// Zero in the middle of a character, so that each character is counted once
+fn utf16_len_at(text: &str, pos: usize) -> usize {
+    if !text.is_char_boundary(pos) {
+        return 0;
+    }
+    text[pos..].chars().next().map_or(0, char::len_utf16)
+}
+
+/// Internal helper function to check if the '>' at the position is the blockquote marker,
+/// i.e. it starts the line, alone or after the "**" of the expandable blockquote
+fn is_quote_marker(bytes: &[u8], pos: usize) -> bool {
+    let line_start = |pos: usize| pos == 0 || bytes[pos - 1] == b'\n';
+    line_start(pos) || (pos >= 2 && &bytes[pos - 2..pos] == b"**" && line_start(pos - 2))
+}
+
+/// Internal helper function to find the positions where the text can be split
+/// Escape sequences, links and the language tags of the code blocks are never split
+///
+/// Also returns the visible length of the text before each byte position, i.e. the length
+/// of the plain text without the formatting in UTF-16 code units, as Telegram counts it.
+fn boundaries(text: &str) -> (Vec<Boundary>, Vec<usize>) {
+    let bytes = text.as_bytes();
+    let mut result = Vec::new();
+    let mut visible = Vec::with_capacity(bytes.len() + 1);
+    let mut visible_len = 0;
+    let mut open: Vec<Marker> = Vec::new();
+    let mut link_depth = 0usize;
+    let mut i = 0;
+    while i < bytes.len() {
+        visible.resize(i + 1, visible_len);
+        if link_depth == 0 && text.is_char_boundary(i) && i > 0 {
+            let in_entity = !open.is_empty();
+            let kind = match (bytes[i], in_entity) {
+                (b'\n', false) => BoundaryKind::Newline,
+                (b' ', false) => BoundaryKind::Space,
+                (_, false) => BoundaryKind::Char,
+                (b'\n', true) => BoundaryKind::InsideNewline,
+                (b' ', true) => BoundaryKind::InsideSpace,
+                (_, true) => BoundaryKind::Inside,
+            };
+            result.push(Boundary {
+                pos: i,
+                kind,
+                open: open.clone(),
+            });
+        }
+        let in_pre = matches!(open.last(), Some(Marker::Pre(_)));
+        let in_code = open.last() == Some(&Marker::Code);
+        match bytes[i] {
+            // The escaped character is kept together with the backslash
+            b'\\' => {
+                i += 1;
+                if i < bytes.len() {
+                    visible_len += utf16_len_at(text, i);
+                }
+                while i + 1 < bytes.len() && !text.is_char_boundary(i + 1) {
+                    i += 1;
+                }
+            }
+            b'`' if bytes[i..].starts_with(b"```") && !in_code => {
+                if in_pre {
+                    open.pop();
+                    i += 2;
+                } else {
+                    // The language tag up to the end of the line belongs to the opening marker
+                    let end = text[i + 3..]
+                        .find('\n')
+                        .map_or(bytes.len(), |newline| i + 3 + newline + 1);
+                    open.push(Marker::Pre(text[i..end].to_string()));
+                    i = end - 1;
+                }
+            }
+            _ if in_pre => visible_len += utf16_len_at(text, i),
+            b'`' => {
+                if in_code {
+                    open.pop();
+                } else {
+                    open.push(Marker::Code);
+                }
+            }
+            _ if in_code => visible_len += utf16_len_at(text, i),
+            b'*' => toggle(&mut open, "*"),
+            b'~' => toggle(&mut open, "~"),
+            b'_' if bytes[i..].starts_with(b"__") => {
+                toggle(&mut open, "__");
+                i += 1;
+            }
+            b'_' => toggle(&mut open, "_"),
+            b'|' if bytes[i..].starts_with(b"||") => {
+                toggle(&mut open, "||");
+                i += 1;
+            }
+            b'>' if is_quote_marker(bytes, i) => {}
+            // The custom emoji is shown as its text
+            b'!' if bytes.get(i + 1) == Some(&b'[') => {}
+            b'[' => link_depth += 1,
+            b']' if link_depth > 0 => {
+                // Skip the link URL, which may contain escaped ')' and '\'
+                if bytes.get(i + 1) == Some(&b'(') {
+                    i += 2;
+                    while i < bytes.len() && bytes[i] != b')' {
+                        if bytes[i] == b'\\' {
+                            i += 1;
+                        }
+                        i += 1;
+                    }
+                }
+                link_depth -= 1;
+            }
+            _ if text.is_char_boundary(i) => visible_len += utf16_len_at(text, i),
+            _ => {}
+        }
+        i += 1;
+    }
+    visible.resize(bytes.len() + 1, visible_len);
+    (result, visible)
+}
+
+/// Internal helper function to find the position where the part starting at `start` ends
+///
+/// The furthest position of the most preferred kind in the second half of the part,
+/// so that the parts are not too short, or the first position after the limit
+fn next_split<'a>(
+    boundaries: &'a [Boundary],
+    visible: &[usize],
+    start: usize,
+    max_length: usize,
+) -> Option<&'a Boundary> {
+    let fits = |boundary: &&Boundary| {
+        boundary.pos > start && visible[boundary.pos] - visible[start] <= max_length
+    };
+    let half = max_length / 2;
+    boundaries
+        .iter()
+        .filter(fits)
+        .max_by_key(|boundary| {
+            let len = visible[boundary.pos] - visible[start];
+            (len >= half, boundary.kind, boundary.pos)
+        })
+        .or_else(|| boundaries.iter().find(|boundary| boundary.pos > start))
+}
+
+/// Split the MarkdownV2 text into parts with the visible text not longer than the given length
+/// in UTF-16 code units, see [`boundaries`]
+///
+/// The text is split at the line breaks if possible, then at the spaces, preferably outside of
+/// the formatting entities and not earlier than in the middle of the part. If an entity has to be
+/// split, it's closed at the end of the part and reopened at the beginning of the next one.
+/// The pieces which can't be split at all, e.g. very long links, are returned as is, longer than the limit.
+pub(crate) fn split_markdown(text: &str, max_length: usize) -> Vec<String> {
+    let (boundaries, visible) = boundaries(text);
+    if visible[text.len()] <= max_length {
+        return vec![text.to_string()];
+    }
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut reopen: Vec<Marker> = Vec::new();
+    loop {
+        let prefix: String = reopen.iter().map(Marker::opening).collect();
+        if visible[text.len()] - visible[start] <= max_length {
+            parts.push(format!("{}{}", prefix, &text[start..]));
+            break;
+        }
+        let Some(split) = next_split(&boundaries, &visible, start, max_length) else {
+            parts.push(format!("{}{}", prefix, &text[start..]));
+            break;
+        };
+        let closing: String = split.open.iter().rev().map(Marker::closing).collect();
+        let part = format!("{}{}{}", prefix, &text[start..split.pos], closing);
+        parts.push(part);
+        start = split.pos + split.skip();
+        reopen = split.open.clone();
+        if start >= text.len() {
+            break;
+        }
+    }
+    parts
+}
+
+/// Split off the first part of the MarkdownV2 text like [`split_markdown`], returning it and the rest
+/// of the text with the split entities reopened, `None` if the whole text fits.
+/// The first part is longer than the limit if the text can't be split within it.
+pub(crate) fn split_markdown_first(text: &str, max_length: usize) -> (String, Option<String>) {
+    let (boundaries, visible) = boundaries(text);
+    if visible[text.len()] <= max_length {
+        return (text.to_string(), None);
+    }
+    let Some(split) = next_split(&boundaries, &visible, 0, max_length) else {
+        return (text.to_string(), None);
+    };
+    let closing: String = split.open.iter().rev().map(Marker::closing).collect();
+    let reopen: String = split.open.iter().map(Marker::opening).collect();
+    let first = format!("{}{}", &text[..split.pos], closing);
+    let start = split.pos + split.skip();
+    let rest = (start < text.len()).then(|| format!("{}{}", reopen, &text[start..]));
+    (first, rest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_markdown() {
+        assert_eq!(split_markdown("short", 10), vec!["short"]);
+        assert_eq!(
+            split_markdown("first line\nsecond line", 15),
+            vec!["first line", "second line"]
+        );
+        assert_eq!(
+            split_markdown("one two three four", 10),
+            vec!["one two", "three four"]
+        );
+        // Escape sequences are never split and the backslashes are not counted
+        assert_eq!(split_markdown("abcd\\.efgh", 5), vec!["abcd\\.", "efgh"]);
+        // Entities are closed and reopened
+        assert_eq!(
+            split_markdown("*bold text here*", 6),
+            vec!["*bold*", "*text*", "*here*"]
+        );
+        assert_eq!(
+            split_markdown("```rust\nline one\nline two\n```", 12),
+            vec!["```rust\nline one\n```", "```rust\nline two\n```"]
+        );
+        // Links are kept whole
+        assert_eq!(
+            split_markdown("see [the link](http://a\\.b) now", 10),
+            vec!["see", "[the link](http://a\\.b)", "now"]
+        );
+        // The blockquote markers are not counted
+        assert_eq!(split_markdown(">abc\n>def", 7), vec![">abc\n>def"]);
+        // Emoji take two UTF-16 code units
+        assert_eq!(split_markdown("👍👍 👍👍", 4), vec!["👍👍", "👍👍"]);
+
+        assert_eq!(
+            split_markdown_first("*bold text here*", 10),
+            ("*bold text*".to_string(), Some("*here*".to_string()))
+        );
+        assert_eq!(split_markdown_first("short", 10), ("short".to_string(), None));
+    }
+
+    #[test]
+    fn test_split_multibyte_code() {
+        assert_eq!(
+            split_markdown("`привет мир` `ещё`", 10),
+            vec!["`привет мир`", "`ещё`"]
+        );
+        assert_eq!(
+            split_markdown("```
+привет
+мир 👍
+```", 8),
+            vec!["```
+привет
+```", "```
+мир 👍
+```"]
+        );
+        assert_eq!(
+            split_markdown_first("`привет` мир", 6),
+            ("`привет`".to_string(), Some("мир".to_string()))
+        );
+    }
+}
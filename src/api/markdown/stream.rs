@@ -0,0 +1,133 @@
+use teloxide::{
+    Bot,
+    prelude::ResponseResult,
+    types::{Message, Recipient},
+};
+
+use crate::{
+    api::markdown::string::{MarkdownString, MarkdownStringMessage, TELEGRAM_MAX_MESSAGE_LENGTH},
+    markdown_string,
+};
+
+/// Writer of the unbounded output to the chat, sending the messages as they fill up
+///
+/// The pushed texts are accumulated until the next one doesn't fit into
+/// [Telegram's message length limit](https://core.telegram.org/bots/api#sendmessage),
+/// then the accumulated text is sent as a separate message. The texts longer than the limit are
+/// split with [`split_for_sending`](MarkdownString::split_for_sending), so nothing is truncated.
+/// The rest is sent by [`flush`](Self::flush).
+///
+/// # Example
+/// ```ignore
+/// let mut stream = MarkdownMessageStream::new(bot.clone(), chat_id);
+/// for entry in log_entries {
+///     stream.push_line(&MarkdownString::escape(entry)).await?;
+/// }
+/// stream.flush().await?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct MarkdownMessageStream {
+    bot: Bot,
+    chat_id: Recipient,
+    max_length: usize,
+    buffer: MarkdownString,
+    messages: Vec<Message>,
+}
+
+impl MarkdownMessageStream {
+    /// Create the stream writing to the chat
+    pub fn new(bot: Bot, chat_id: impl Into<Recipient>) -> Self {
+        Self {
+            bot,
+            chat_id: chat_id.into(),
+            max_length: TELEGRAM_MAX_MESSAGE_LENGTH,
+            buffer: MarkdownString::new(),
+            messages: Vec::new(),
+        }
+    }
+
+    /// Set the maximal length of the messages, it can't exceed the message length limit
+    pub fn with_max_length(mut self, max_length: usize) -> Self {
+        self.max_length = max_length.clamp(1, TELEGRAM_MAX_MESSAGE_LENGTH);
+        self
+    }
+
+    /// Append the text, sending the accumulated text first if the text doesn't fit into the same message
+    pub async fn push(&mut self, text: &MarkdownString) -> ResponseResult<()> {
+        let length = text.len_utf16();
+        if self.buffer.len_utf16() + length <= self.max_length {
+            self.buffer.push(text);
+            return Ok(());
+        }
+        self.flush().await?;
+        if length <= self.max_length {
+            self.buffer.push(text);
+            return Ok(());
+        }
+        let mut parts = text.split(self.max_length);
+        // The last part may be continued by the next texts
+        if let Some(last) = parts.pop() {
+            for part in parts {
+                self.send(part).await?;
+            }
+            self.buffer = last;
+        }
+        Ok(())
+    }
+
+    /// Append the text followed by the line break
+    pub async fn push_line(&mut self, line: &MarkdownString) -> ResponseResult<()> {
+        self.push(&(line + markdown_string!("\n"))).await
+    }
+
+    /// Send the accumulated text if there is any
+    pub async fn flush(&mut self) -> ResponseResult<()> {
+        let text = std::mem::take(&mut self.buffer);
+        // Telegram rejects the messages consisting of the whitespace only
+        if !text.to_plain_text().trim().is_empty() {
+            self.send(text).await?;
+        }
+        Ok(())
+    }
+
+    /// Messages sent so far
+    pub fn messages(&self) -> &[Message] {
+        &self.messages
+    }
+
+    /// Internal helper function to send the message fitting into the limit
+    async fn send(&mut self, text: MarkdownString) -> ResponseResult<()> {
+        let message = self.bot.send_markdown_message(self.chat_id.clone(), text).await?;
+        self.messages.push(message);
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "testing"))]
+mod tests {
+    use teloxide::types::ChatId;
+
+    use super::*;
+    use crate::testing::MockBotApi;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_markdown_message_stream() {
+        let api = MockBotApi::start().await;
+        let mut stream = MarkdownMessageStream::new(api.bot(), ChatId(1)).with_max_length(10);
+        for line in ["one", "two", "three"] {
+            stream.push_line(&MarkdownString::escape(line)).await.unwrap();
+        }
+        stream.push(&MarkdownString::escape("a".repeat(25))).await.unwrap();
+        stream.flush().await.unwrap();
+        stream.flush().await.unwrap();
+        assert_eq!(stream.messages().len(), 5);
+
+        let mut texts = Vec::new();
+        for _ in 0..5 {
+            let request = api.next_request("sendMessage").await.unwrap();
+            texts.push(request.str_param("text").unwrap().to_string());
+        }
+        let expected = ["one\ntwo\n", "three\n", "aaaaaaaaaa", "aaaaaaaaaa", "aaaaa"];
+        assert_eq!(texts, expected);
+    }
+}
@@ -1,20 +1,33 @@
-use std::{fmt, ops::Add};
+use std::{borrow::Cow, fmt, ops::Add};
 
+#[cfg(feature = "teloxide")]
 use teloxide::{
-    Bot,
+    RequestError,
     payloads::{
-        EditMessageTextInlineSetters, EditMessageTextSetters, SendMessage, SendMessageSetters,
+        EditMessageTextInlineSetters, EditMessageTextSetters, SendDocumentSetters,
+        SendMessageSetters, SendPhotoSetters, SendVideoSetters,
     },
     prelude::Requester,
-    requests::JsonRequest,
     types::{
-        MessageId,
+        InputFile, Message, MessageEntity, MessageId,
         ParseMode::{self, MarkdownV2},
-        Recipient,
+        Recipient, UserId,
     },
 };
 
-use crate::markdown_string;
+#[cfg(feature = "teloxide")]
+use crate::api::markdown::{
+    caption::MarkdownCaption, entities::markdown_to_entities, send_options::MarkdownSendOptions,
+};
+use crate::{
+    api::html::string::HtmlString,
+    api::markdown::{
+        ast::{self, Node},
+        split::{split_markdown, split_markdown_first},
+        validate::{MarkdownError, MarkdownErrorKind, check_markdownv2_format, placeholder_len},
+    },
+    markdown_string,
+};
 
 /// A wrapper around [`String`] that ensures safe MarkdownV2 formatting for Telegram messages.
 ///
@@ -31,15 +44,118 @@ use crate::markdown_string;
 /// This type is designed to work with Telegram's [MarkdownV2](https://core.telegram.org/bots/api#markdownv2-style) format.
 /// Use with [`MarkdownStringMessage::send_markdown_message`](crate::markdown::MarkdownStringMessage::send_markdown_message)
 /// to send messages with proper formatting.
+///
+/// The strings created from the literals borrow them without allocation until they are modified.
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
-pub struct MarkdownString(String, bool);
+pub struct MarkdownString(Cow<'static, str>, bool, String);
+
+pub(crate) const TRUNCATION_MARKER: &str = "\\.\\.\\.";
+
+/// Characters which must be escaped in MarkdownV2 text
+/// See: https://core.telegram.org/bots/api#markdownv2-style
+pub(crate) const ESCAPE_CHARS: [char; 19] = [
+    '\\', '_', '*', '[', ']', '(', ')', '~', '`', '>', '#', '+', '-', '=', '|', '{', '}', '.', '!',
+];
+
+/// Characters kept as is by [`MarkdownString::escape_url`] in addition to the alphanumeric ones,
+/// the brackets are encoded as they are special in the MarkdownV2 links
+/// See: https://datatracker.ietf.org/doc/html/rfc3986#section-2.2
+const URL_CHARS: &[u8] = b"-._~:/?#@!$&'*+,;=";
+
+/// Internal helper function to get the length of the URL at the start of the text for
+/// [`MarkdownString::escape_linkified`], 0 if there is nothing after the scheme
+fn linkified_url_len(text: &str) -> usize {
+    let end = text.find(char::is_whitespace).unwrap_or(text.len());
+    let scheme_len = if text.starts_with("https://") { 8 } else { 7 };
+    let mut url = &text[..end];
+    loop {
+        let trimmed = url.trim_end_matches(['.', ',', ';', ':', '!', '?', '\'', '"', '>', ']']);
+        // Keep the closing parenthesis only if it's balanced, e.g. in Wikipedia URLs
+        let trimmed = match trimmed.strip_suffix(')') {
+            Some(stripped) if stripped.matches('(').count() <= stripped.matches(')').count() => stripped,
+            _ => trimmed,
+        };
+        if trimmed.len() == url.len() {
+            break;
+        }
+        url = trimmed;
+    }
+    if url.len() > scheme_len { url.len() } else { 0 }
+}
+
+/// Lookup table of the bytes of [`ESCAPE_CHARS`], all of them are ASCII,
+/// so the bytes of the multibyte UTF-8 characters never match
+const ESCAPE_TABLE: [bool; 256] = {
+    let mut table = [false; 256];
+    let mut i = 0;
+    while i < ESCAPE_CHARS.len() {
+        table[ESCAPE_CHARS[i] as usize] = true;
+        i += 1;
+    }
+    table
+};
+
+/// Internal helper function to check if the text has any MarkdownV2 special characters
+fn needs_escaping(input: &str) -> bool {
+    input.bytes().any(|byte| ESCAPE_TABLE[byte as usize])
+}
+
+/// Internal helper function to escape all MarkdownV2 special characters
+/// The bytes are scanned in a single pass, the runs of the plain text are copied as a whole.
+pub(crate) fn escape_markdown(input: &str) -> String {
+    let mut escaped = String::with_capacity(input.len() + input.len() / 8);
+    let mut start = 0;
+    for (i, &byte) in input.as_bytes().iter().enumerate() {
+        if ESCAPE_TABLE[byte as usize] {
+            escaped.push_str(&input[start..i]);
+            escaped.push('\\');
+            start = i;
+        }
+    }
+    escaped.push_str(&input[start..]);
+    escaped
+}
 
-const TRUNCATION_MARKER: &str = "\\.\\.\\.";
+/// Internal helper function to check if the text starts with a blockquote, which has to start a line
+fn starts_with_quote(text: &str) -> bool {
+    text.starts_with('>') || text.starts_with("**>")
+}
+
+/// Internal helper function to render the quote of [`MarkdownString::quote_message`]
+#[cfg(feature = "teloxide")]
+fn quote_preview(sender: Option<String>, text: &str, max_len: usize) -> MarkdownString {
+    let mut preview = String::new();
+    if text.encode_utf16().count() <= max_len {
+        preview.push_str(text);
+    } else if max_len > 0 {
+        let mut length = 0;
+        for c in text.chars() {
+            length += c.len_utf16();
+            if length > max_len - 1 {
+                break;
+            }
+            preview.push(c);
+        }
+        preview.truncate(preview.trim_end().len());
+        preview.push('…');
+    }
+    let mut children = Vec::new();
+    if let Some(sender) = sender {
+        children.push(Node::Bold(vec![Node::Text(sender)]));
+        if !preview.is_empty() {
+            children.push(Node::Text("\n".to_string()));
+        }
+    }
+    if !preview.is_empty() {
+        children.push(Node::Text(preview));
+    }
+    ast::render(&[Node::Blockquote(children)])
+}
 
 impl MarkdownString {
     /// Creates a MarkdownString by escaping all markdown special characters in the input.
     /// This is safe to use with any string content as all special characters will be escaped.
-    /// Uses [teloxide's markdown escape function](https://docs.rs/teloxide/latest/teloxide/utils/markdown/fn.escape.html).
+    /// Escapes the same characters as [teloxide's markdown escape function](https://docs.rs/teloxide/latest/teloxide/utils/markdown/fn.escape.html).
     ///
     /// # Example
     /// ```rust
@@ -49,11 +165,142 @@ impl MarkdownString {
     /// // Result: "Hello\\! This has special chars: \\*bold\\* \\_italic\\_"
     /// ```
     pub fn escape<T: Into<String>>(input: T) -> Self {
-        let input_string = input.into();
-        let escaped = teloxide::utils::markdown::escape(&input_string);
-        let mut result = MarkdownString::default();
-        result.push(&MarkdownString::from_validated_string(escaped));
-        result
+        let input: String = input.into();
+        if needs_escaping(&input) {
+            MarkdownString::from_validated_string(escape_markdown(&input))
+        } else {
+            // Nothing to escape, the string is taken as is
+            MarkdownString::from_validated_string(input)
+        }
+    }
+
+    /// Creates a spoiler hiding the content, the strings are escaped
+    ///
+    /// The spoilers inside the content are removed, as they would close the outer spoiler
+    /// instead of being nested in it.
+    ///
+    /// # Example
+    /// ```rust
+    /// use telluride::{markdown::MarkdownString, markdown_string};
+    ///
+    /// assert_eq!(MarkdownString::spoiler("a||b").as_str(), "||a\\|\\|b||");
+    /// let content = markdown_string!("*x* ||y||");
+    /// assert_eq!(MarkdownString::spoiler(content).as_str(), "||*x* y||");
+    /// ```
+    pub fn spoiler(content: impl Into<MarkdownString>) -> Self {
+        let content: MarkdownString = content.into();
+        ast::render(&[Node::Spoiler(ast::parse(&content))])
+    }
+
+    /// Escapes the text like [`escape`](Self::escape), but keeps the http and https URLs clickable
+    /// by turning them into the links. The trailing punctuation and the unbalanced closing
+    /// parenthesis are not considered the part of the URL.
+    ///
+    /// # Example
+    /// ```rust
+    /// use telluride::markdown::MarkdownString;
+    ///
+    /// let text = MarkdownString::escape_linkified("See https://example.com/a_b.");
+    /// assert_eq!(text.as_str(), "See [https://example\\.com/a\\_b](https://example.com/a_b)\\.");
+    /// ```
+    pub fn escape_linkified(text: &str) -> Self {
+        let mut nodes = Vec::new();
+        let mut plain_start = 0;
+        let mut pos = 0;
+        while pos < text.len() {
+            let rest = &text[pos..];
+            let at_word_start = text[..pos]
+                .chars()
+                .next_back()
+                .is_none_or(|c| c.is_whitespace() || "([<\"'".contains(c));
+            if !(at_word_start && (rest.starts_with("http://") || rest.starts_with("https://"))) {
+                pos += rest.chars().next().map_or(1, char::len_utf8);
+                continue;
+            }
+            let url_len = linkified_url_len(rest);
+            if url_len == 0 {
+                pos += 1;
+                continue;
+            }
+            if plain_start < pos {
+                nodes.push(Node::Text(text[plain_start..pos].to_string()));
+            }
+            let url = &rest[..url_len];
+            nodes.push(Node::Link {
+                url: url.to_string(),
+                children: vec![Node::Text(url.to_string())],
+            });
+            pos += url_len;
+            plain_start = pos;
+        }
+        if plain_start < text.len() {
+            nodes.push(Node::Text(text[plain_start..].to_string()));
+        }
+        ast::render(&nodes)
+    }
+
+    /// Creates the mention of the user by the id, which works also for the users without the username
+    /// The display name is escaped, the links in it are replaced by their text. It's used by the
+    /// `@mention` modifier of [`markdown_format!`](crate::markdown_format!).
+    ///
+    /// # Example
+    /// ```rust
+    /// use teloxide::types::UserId;
+    /// use telluride::markdown::MarkdownString;
+    ///
+    /// let mention = MarkdownString::mention("John D.", UserId(123));
+    /// assert_eq!(mention.as_str(), "[John D\\.](tg://user?id=123)");
+    /// ```
+    #[cfg(feature = "teloxide")]
+    pub fn mention(display_name: impl Into<MarkdownString>, user_id: UserId) -> Self {
+        let display_name: MarkdownString = display_name.into();
+        let mut children = ast::parse(&display_name);
+        ast::strip_links(&mut children);
+        ast::render(&[Node::Link {
+            url: format!("tg://user?id={}", user_id.0),
+            children,
+        }])
+    }
+
+    /// Creates the blockquote with the bold sender name and the preview of the message text or caption,
+    /// the common "replying to" header. The preview is trimmed to `max_len` UTF-16 code units with `…`.
+    #[cfg(feature = "teloxide")]
+    pub fn quote_message(message: &Message, max_len: usize) -> Self {
+        let sender = message
+            .from
+            .as_ref()
+            .map(|user| user.full_name())
+            .or_else(|| message.sender_chat.as_ref().and_then(|chat| chat.title()).map(str::to_string));
+        let text = message.text().or_else(|| message.caption()).unwrap_or_default();
+        quote_preview(sender, text, max_len)
+    }
+
+    /// Creates a MarkdownString for the URL part of the link by percent-encoding the characters
+    /// not allowed in the URLs, the URL structure (`/`, `?`, `&`, etc.) and the already encoded
+    /// `%XX` sequences are kept. The result is valid only inside the `(...)` part of the link,
+    /// it's used by the `@url` modifier of [`markdown_format!`](crate::markdown_format!).
+    ///
+    /// # Example
+    /// ```rust
+    /// use telluride::markdown::MarkdownString;
+    ///
+    /// let url = MarkdownString::escape_url("https://example.com/a (b)?q=привет");
+    /// assert_eq!(url.as_str(), "https://example.com/a%20%28b%29?q=%D0%BF%D1%80%D0%B8%D0%B2%D0%B5%D1%82");
+    /// ```
+    pub fn escape_url(input: impl AsRef<str>) -> Self {
+        let bytes = input.as_ref().as_bytes();
+        let mut escaped = String::with_capacity(bytes.len());
+        for (i, &byte) in bytes.iter().enumerate() {
+            let is_encoded = byte == b'%'
+                && bytes.get(i + 1).is_some_and(u8::is_ascii_hexdigit)
+                && bytes.get(i + 2).is_some_and(u8::is_ascii_hexdigit);
+            if is_encoded || byte.is_ascii_alphanumeric() || URL_CHARS.contains(&byte) {
+                escaped.push(byte as char);
+            } else {
+                escaped.push_str(&format!("%{:02X}", byte));
+            }
+        }
+        MarkdownString::from_validated_string(escaped)
     }
 
     /// Creates an empty MarkdownString.
@@ -73,25 +320,144 @@ impl MarkdownString {
     /// Private constructor for use by the markdown_string! macro after compile-time validation.
     /// This should only be called by trusted code that has already validated the input.
     #[doc(hidden)]
-    pub fn from_validated_string(s: impl Into<String>) -> Self {
-        let s: String = s.into();
-        if s.len() > TELEGRAM_MAX_MESSAGE_LENGTH {
-            // Truncate, escape and mark as truncated
-            let safe_length = TELEGRAM_MAX_MESSAGE_LENGTH - 100; // additional space for escaping
-            let truncated_str = s[..safe_length].to_string();
-            let mut escaped_truncated_str = MarkdownString::escape(truncated_str);
-            let truncation_marker = markdown_string!(TRUNCATION_MARKER);
-            escaped_truncated_str.push(&truncation_marker);
-            return MarkdownString(escaped_truncated_str.0, true);
+    pub fn from_validated_string(s: impl Into<Cow<'static, str>>) -> Self {
+        MarkdownString(s.into(), false, String::new())
+    }
+
+    /// Validate the MarkdownV2 text at runtime, e.g. loaded from the config file or the database
+    ///
+    /// Unlike the format strings of [`markdown_string!`] the text can't contain `{}` placeholders,
+    /// the braces must be escaped.
+    ///
+    /// # Example
+    /// ```rust
+    /// use telluride::markdown::{MarkdownErrorKind, MarkdownString};
+    ///
+    /// let markdown = MarkdownString::try_from_raw("*Hello* world\\!").unwrap();
+    /// assert_eq!(markdown.as_str(), "*Hello* world\\!");
+    ///
+    /// let err = MarkdownString::try_from_raw("Hello world!").unwrap_err();
+    /// assert_eq!(err.offset(), 11);
+    /// assert_eq!(err.kind(), MarkdownErrorKind::Unescaped('!'));
+    /// ```
+    pub fn try_from_raw(s: &str) -> Result<Self, MarkdownError> {
+        check_markdownv2_format(s, false)?;
+        Ok(MarkdownString(s.to_string().into(), false, String::new()))
+    }
+
+    /// Convert the almost valid MarkdownV2 text, e.g. pasted by the user, keeping its formatting
+    ///
+    /// Unlike [`From<&str>`](#impl-From<%26str>-for-MarkdownString) which escapes everything,
+    /// only the characters which break the parsing are escaped. The unterminated entities
+    /// and code blocks are closed at the end of the text, the broken links are left as the plain text.
+    /// The result always passes [`try_from_raw`](Self::try_from_raw), the text which can't be repaired
+    /// is escaped entirely.
+    ///
+    /// # Example
+    /// ```rust
+    /// use telluride::markdown::MarkdownString;
+    ///
+    /// let markdown = MarkdownString::repair("*Total:* 1.5 _EUR");
+    /// assert_eq!(markdown.as_str(), "*Total:* 1\\.5 _EUR_");
+    /// ```
+    pub fn repair(s: &str) -> Self {
+        let mut text = s.to_string();
+        // The trailing backslash would escape the closing markers added below
+        let mut escaped = false;
+        for c in text.chars() {
+            escaped = c == '\\' && !escaped;
+        }
+        if escaped {
+            text.push('\\');
+        }
+        // Each fix either escapes the reported character or closes the innermost entity,
+        // but the closing marker may merge with the text, e.g. "__a_" closed as "__a___",
+        // and be reported again, so the number of fixes is limited
+        let max_fixes = 3 * text.len() + 16;
+        for _ in 0..max_fixes {
+            let Err(err) = check_markdownv2_format(&text, false) else {
+                return MarkdownString(text.into(), false, String::new());
+            };
+            let pos = err.offset();
+            match err.kind() {
+                MarkdownErrorKind::Unescaped(_)
+                | MarkdownErrorKind::UnmatchedClosingBracket
+                | MarkdownErrorKind::UnclosedLinkText
+                | MarkdownErrorKind::CrossingEntities
+                | MarkdownErrorKind::NestedLink
+                | MarkdownErrorKind::UnclosedLinkUrl => text.insert(pos, '\\'),
+                // Reported at the start of the URL, escape the opening parenthesis
+                MarkdownErrorKind::InvalidLinkUrl => text.insert(pos - 1, '\\'),
+                MarkdownErrorKind::UnclosedCode => text.push('`'),
+                MarkdownErrorKind::UnclosedPre => text.push_str("```"),
+                MarkdownErrorKind::UnmatchedAsterisk
+                | MarkdownErrorKind::UnmatchedUnderscore
+                | MarkdownErrorKind::UnmatchedTilde
+                | MarkdownErrorKind::UnmatchedPipe => {
+                    let marker = if text[pos..].starts_with("__") || text[pos..].starts_with("||") {
+                        &text[pos..pos + 2]
+                    } else {
+                        &text[pos..pos + 1]
+                    };
+                    if pos + marker.len() == text.len() {
+                        // Nothing to format, the marker is the plain text
+                        text.insert(pos, '\\');
+                    } else {
+                        let marker = marker.to_string();
+                        text.push_str(&marker);
+                    }
+                }
+                // The validator is a separate crate, its future problems are escaped like the reserved characters
+                _ => text.insert(pos, '\\'),
+            }
+        }
+        MarkdownString::escape(s)
+    }
+
+    /// Substitutes the `{}` and `{N}` placeholders of the template with the already escaped arguments,
+    /// used by the [`markdown_format!`](crate::markdown_format!) macro.
+    /// The `{}` placeholders take the arguments in order regardless of the indexed ones,
+    /// the placeholders without the argument are left as is.
+    #[doc(hidden)]
+    pub fn format_with_args(template: &MarkdownString, args: &[String]) -> Self {
+        let template = template.as_str();
+        let bytes = template.as_bytes();
+        let mut result = String::with_capacity(template.len() + args.iter().map(String::len).sum::<usize>());
+        let mut next_arg = 0;
+        let mut copied = 0;
+        let mut i = 0;
+        while i < bytes.len() {
+            if bytes[i] == b'\\' {
+                i += 2;
+                continue;
+            }
+            let len = placeholder_len(bytes, i);
+            if len == 0 {
+                i += 1;
+                continue;
+            }
+            let index = if len == 2 {
+                next_arg += 1;
+                Some(next_arg - 1)
+            } else {
+                template[i + 1..i + len - 1].parse::<usize>().ok()
+            };
+            if let Some(arg) = index.and_then(|index| args.get(index)) {
+                result.push_str(&template[copied..i]);
+                result.push_str(arg);
+                copied = i + len;
+            }
+            i += len;
         }
-        MarkdownString(s, false)
+        result.push_str(&template[copied..]);
+        MarkdownString(result.into(), false, String::new())
     }
 
     /// Test-only constructor for creating templates in tests.
     /// This bypasses safety checks and should only be used in tests.
     #[cfg(test)]
     pub(crate) fn test_template(s: &str) -> Self {
-        MarkdownString(s.to_string(), false)
+        MarkdownString(s.to_string().into(), false, String::new())
     }
 
     /// Returns the inner string value
@@ -101,7 +467,7 @@ impl MarkdownString {
 
     /// Consumes the MarkdownString and returns the inner String
     pub fn into_string(self) -> String {
-        self.0
+        self.0.into_owned()
     }
 
     /// Check if the MarkdownString has been truncated due to length limits
@@ -109,27 +475,312 @@ impl MarkdownString {
         self.1
     }
 
-    /// Adds other MarkdownString to self, returning a new combined MarkdownString
-    /// Internally doesn't allow to overflow [Telegram's message length limit](https://core.telegram.org/bots/api#sendmessage).
-    /// If the result exceeds the limit minus truncation indicator length,
-    /// it adds the truncation indicator "..." at the end and sets the flag
-    /// to prevent further additions.
+    /// Adds other MarkdownString to self
+    /// The string may grow beyond [Telegram's message length limit](https://core.telegram.org/bots/api#sendmessage),
+    /// it's truncated when sent, or split with [`split_for_sending`](Self::split_for_sending).
+    /// Nothing is added to the string which was already truncated, the text is kept in the [`overflow`](Self::overflow).
+    /// The blockquote is started on a new line, the line break is added if needed.
     pub fn push(&mut self, other: &MarkdownString) {
+        let quote = starts_with_quote(other.as_str());
         if self.1 {
-            // Already truncated, do nothing
+            if quote && !self.2.is_empty() && !self.2.ends_with('\n') {
+                self.2.push('\n');
+            }
+            self.2.push_str(other.as_str());
             return;
         }
+        if self.0.is_empty() {
+            // Keep the borrowed literal without copying
+            self.0 = other.0.clone();
+            return;
+        }
+        if quote && !self.0.ends_with('\n') {
+            self.push_raw("\n");
+        }
+        self.push_raw(other.as_str());
+    }
+
+    /// Adds as much of other MarkdownString as fits into Telegram's message length limit and returns the rest,
+    /// so that it can be sent as the next message. The text is split like in [`split_for_sending`](Self::split_for_sending).
+    /// Nothing is added to the string which was already truncated, the whole other string is returned.
+    ///
+    /// # Example
+    /// ```rust
+    /// use telluride::markdown::MarkdownString;
+    ///
+    /// let mut message = MarkdownString::escape("a".repeat(4090));
+    /// let rest = message.push_or_overflow(&MarkdownString::escape("b c d e f"));
+    /// assert_eq!(message.len_utf16(), 4095);
+    /// assert_eq!(rest.unwrap().as_str(), "e f");
+    /// ```
+    pub fn push_or_overflow(&mut self, other: &MarkdownString) -> Option<MarkdownString> {
+        let available = TELEGRAM_MAX_MESSAGE_LENGTH.saturating_sub(self.len_utf16());
+        if self.1 || available == 0 {
+            return Some(other.clone());
+        }
+        // The blockquote is started on a new line like in `push`
+        let text = if starts_with_quote(other.as_str()) && !self.0.is_empty() && !self.0.ends_with('\n') {
+            format!("\n{}", other.as_str())
+        } else {
+            other.as_str().to_string()
+        };
+        let (first, rest) = split_markdown_first(&text, available);
+        if MarkdownString::from_validated_string(first.clone()).len_utf16() > available {
+            // The beginning of the other string can't be split to fit
+            return Some(other.clone());
+        }
+        self.push_raw(&first);
+        rest.map(MarkdownString::from_validated_string)
+    }
+
+    /// The text dropped by [`push`](Self::push) and by the truncation, `None` if nothing was dropped.
+    /// It can be sent as the follow-up message.
+    pub fn overflow(&self) -> Option<MarkdownString> {
+        (!self.2.is_empty()).then(|| MarkdownString::from_validated_string(self.2.clone()))
+    }
+
+    /// Internal helper function to append the validated text,
+    /// the borrowed string is copied once into the buffer of the final size
+    fn push_raw(&mut self, text: &str) {
+        match &mut self.0 {
+            Cow::Borrowed(current) => {
+                let mut owned = String::with_capacity(current.len() + text.len());
+                owned.push_str(current);
+                owned.push_str(text);
+                self.0 = Cow::Owned(owned);
+            }
+            Cow::Owned(current) => current.push_str(text),
+        }
+    }
+
+    /// Adds other MarkdownString followed by the line break, see also [`markdown_writeln!`](crate::markdown_writeln!)
+    pub fn push_line(&mut self, line: &MarkdownString) {
+        self.push(line);
+        self.push(&markdown_string!("\n"));
+    }
+
+    /// Adds the plain text, escaping all markdown special characters
+    ///
+    /// # Example
+    /// ```rust
+    /// use telluride::{markdown::MarkdownString, markdown_string};
+    ///
+    /// let mut report = markdown_string!("*Total:* ");
+    /// report.push_str_escaped("1.5 + 2");
+    /// assert_eq!(report.as_str(), "*Total:* 1\\.5 \\+ 2");
+    /// ```
+    pub fn push_str_escaped(&mut self, text: &str) {
+        self.push(&MarkdownString::from_validated_string(escape_markdown(text)));
+    }
+
+    /// Concatenates the MarkdownStrings, placing the separator between them
+    /// Like with [`push`](Self::push), nothing is added after the truncated string.
+    ///
+    /// # Example
+    /// ```rust
+    /// use telluride::{markdown::MarkdownString, markdown_string};
+    ///
+    /// let names = ["Alice", "Bob"].into_iter().map(MarkdownString::escape);
+    /// let list = MarkdownString::join(names, &markdown_string!(", "));
+    /// assert_eq!(list.as_str(), "Alice, Bob");
+    /// ```
+    pub fn join<I: IntoIterator<Item = MarkdownString>>(iter: I, separator: &MarkdownString) -> Self {
+        let mut result = MarkdownString::new();
+        for (i, item) in iter.into_iter().enumerate() {
+            if i > 0 {
+                result.push(separator);
+            }
+            result.push(&item);
+        }
+        result
+    }
+
+    /// Splits the MarkdownString into the messages fitting into Telegram's message length limit
+    /// The text is split preferably at the line breaks and the spaces, never inside an escape sequence
+    /// or a link. The formatting entities which have to be split are closed at the end of a message
+    /// and reopened in the next one.
+    ///
+    /// # Example
+    /// ```rust
+    /// use telluride::markdown::MarkdownString;
+    ///
+    /// let long = MarkdownString::escape("line\n".repeat(1000));
+    /// let parts = long.split_for_sending();
+    /// assert_eq!(parts.len(), 2);
+    /// assert!(parts.iter().all(|part| part.len_utf16() <= 4096));
+    /// ```
+    pub fn split_for_sending(&self) -> Vec<MarkdownString> {
+        self.split(TELEGRAM_MAX_MESSAGE_LENGTH)
+    }
+
+    /// Converts the MarkdownString to the plain text and the list of the formatting entities,
+    /// which can be sent with `entities` or `caption_entities` instead of the parse mode,
+    /// e.g. in `copyMessage` or in the inline query results.
+    /// The offsets and the lengths of the entities are counted in UTF-16 code units, as Telegram requires.
+    ///
+    /// # Example
+    /// ```rust
+    /// use telluride::markdown_string;
+    /// use teloxide::types::MessageEntity;
+    ///
+    /// let (text, entities) = markdown_string!("*Hello* world\\!").to_entities();
+    /// assert_eq!(text, "Hello world!");
+    /// assert_eq!(entities, vec![MessageEntity::bold(0, 5)]);
+    /// ```
+    #[cfg(feature = "teloxide")]
+    pub fn to_entities(&self) -> (String, Vec<MessageEntity>) {
+        markdown_to_entities(self)
+    }
+
+    /// Internal helper function to split the MarkdownString into parts not longer than the given length
+    pub(crate) fn split(&self, max_length: usize) -> Vec<MarkdownString> {
+        split_markdown(&self.0, max_length)
+            .into_iter()
+            .map(|part| MarkdownString(part.into(), false, String::new()).limit_length(max_length))
+            .collect()
+    }
+
+    /// Length of the visible text in UTF-16 code units, which Telegram's length limits are counted in.
+    /// The formatting characters, the escape backslashes and the link URLs are not counted.
+    ///
+    /// # Example
+    /// ```rust
+    /// use telluride::markdown_string;
+    ///
+    /// let markdown = markdown_string!("*Hello* 👋\\!");
+    /// assert_eq!(markdown.as_str().len(), 14);
+    /// assert_eq!(markdown.len_utf16(), 9);
+    /// ```
+    pub fn len_utf16(&self) -> usize {
+        self.to_plain_text().encode_utf16().count()
+    }
+
+    /// Limits the MarkdownString to a length smaller than Telegram's message length limit,
+    /// e.g. to [`TELEGRAM_MAX_CAPTION_LENGTH`] for media captions, see [`len_utf16`](Self::len_utf16).
+    /// If the string is longer, it's cut at the last suitable line break or space, like in
+    /// [`split_for_sending`](Self::split_for_sending), the entities open at the cut are closed
+    /// and the result is marked with "..." at the end. The text which can't be cut this way, e.g. a very long link,
+    /// is truncated as the plain text without the formatting.
+    pub(crate) fn limit_length(self, max_length: usize) -> MarkdownString {
+        if self.len_utf16() <= max_length {
+            return self;
+        }
         let truncation_marker = markdown_string!(TRUNCATION_MARKER);
-        let combined_length = self.0.len() + other.0.len() + truncation_marker.as_str().len();
-        if combined_length > TELEGRAM_MAX_MESSAGE_LENGTH {
-            if self.0.len() + truncation_marker.as_str().len() <= TELEGRAM_MAX_MESSAGE_LENGTH {
-                // Can fit truncation marker
-                self.0.push_str(truncation_marker.as_str());
+        let max_length = max_length.saturating_sub(truncation_marker.len_utf16());
+        if let (first, Some(rest)) = split_markdown_first(self.as_str(), max_length) {
+            let first = MarkdownString::from_validated_string(first);
+            if first.len_utf16() <= max_length {
+                return MarkdownString((first.into_string() + TRUNCATION_MARKER).into(), true, rest + &self.2);
             }
-            self.1 = true; // Mark as truncated
-        } else {
-            self.0.push_str(other.as_str());
         }
+        let mut truncated = MarkdownString::default();
+        let mut length = 0;
+        let plain_text = self.to_plain_text();
+        let mut rest = plain_text.as_str();
+        for c in plain_text.chars() {
+            if length + c.len_utf16() > max_length {
+                break;
+            }
+            length += c.len_utf16();
+            rest = &rest[c.len_utf8()..];
+            truncated.0.to_mut().push_str(&escape_markdown(c.encode_utf8(&mut [0; 4])));
+        }
+        truncated.0.to_mut().push_str(truncation_marker.as_str());
+        truncated.1 = true;
+        truncated.2 = escape_markdown(rest) + &self.2;
+        truncated
+    }
+
+    /// Converts the MarkdownString to the equivalent [Telegram HTML](https://core.telegram.org/bots/api#html-style),
+    /// e.g. to show the messages on a web page, see [`ast::render_html`](crate::markdown::ast::render_html)
+    ///
+    /// # Example
+    /// ```rust
+    /// use telluride::markdown_string;
+    ///
+    /// let markdown = markdown_string!("*Hello* _<world\\>_\\!");
+    /// assert_eq!(markdown.to_html().as_str(), "<b>Hello</b> <i>&lt;world&gt;</i>!");
+    /// ```
+    pub fn to_html(&self) -> HtmlString {
+        ast::render_html(&ast::parse(self))
+    }
+
+    /// Converts the MarkdownString to plain text by removing formatting characters,
+    /// link URLs, code block language tags, blockquote markers and escape backslashes.
+    /// Useful for places where Telegram doesn't support formatting, e.g. callback query answers.
+    ///
+    /// # Example
+    /// ```rust
+    /// use telluride::{markdown::MarkdownString, markdown_string};
+    ///
+    /// let markdown = markdown_string!("*Hello* [world](http://example\\.com)\\!");
+    /// assert_eq!(markdown.to_plain_text(), "Hello world!");
+    /// ```
+    pub fn to_plain_text(&self) -> String {
+        let mut result = String::with_capacity(self.0.len());
+        let mut chars = self.0.chars().peekable();
+        let mut in_code = false;
+        let mut in_pre = false;
+        // The blockquote markers '>' and '**>' are at the start of the line
+        let mut line_start = true;
+        while let Some(c) = chars.next() {
+            let at_line_start = line_start;
+            line_start = c == '\n' || (line_start && c == '*');
+            match c {
+                '\\' => {
+                    if let Some(next_c) = chars.next() {
+                        result.push(next_c);
+                    }
+                }
+                '`' => {
+                    if !in_code && chars.peek() == Some(&'`') {
+                        chars.next();
+                        if chars.peek() == Some(&'`') {
+                            chars.next();
+                        }
+                        in_pre = !in_pre;
+                        if in_pre {
+                            // Skip the language tag up to the end of the line
+                            let rest: String = chars.clone().collect();
+                            if let Some(newline_pos) = rest.find('\n')
+                                && !rest[..newline_pos].contains('`')
+                            {
+                                for _ in 0..=rest[..newline_pos].chars().count() {
+                                    chars.next();
+                                }
+                            }
+                        } else if result.ends_with('\n') {
+                            result.pop();
+                        }
+                    } else if !in_pre {
+                        in_code = !in_code;
+                    } else {
+                        result.push(c);
+                    }
+                }
+                _ if in_code || in_pre => result.push(c),
+                '>' if at_line_start => {}
+                // The custom emoji is shown as its text
+                '!' if chars.peek() == Some(&'[') => {}
+                '*' | '_' | '~' | '|' | '[' => {}
+                ']' => {
+                    if chars.peek() == Some(&'(') {
+                        // Skip the link URL, which may contain escaped ')' and '\'
+                        while let Some(url_c) = chars.next() {
+                            match url_c {
+                                '\\' => {
+                                    chars.next();
+                                }
+                                ')' => break,
+                                _ => {}
+                            }
+                        }
+                    }
+                }
+                _ => result.push(c),
+            }
+        }
+        result
     }
 }
 
@@ -147,7 +798,7 @@ impl AsRef<str> for MarkdownString {
 
 impl From<MarkdownString> for String {
     fn from(markdown: MarkdownString) -> String {
-        markdown.0
+        markdown.0.into_owned()
     }
 }
 
@@ -247,9 +898,37 @@ impl Add<&MarkdownString> for &MarkdownString {
     }
 }
 
+impl Extend<MarkdownString> for MarkdownString {
+    fn extend<I: IntoIterator<Item = MarkdownString>>(&mut self, iter: I) {
+        for item in iter {
+            self.push(&item);
+        }
+    }
+}
+
+impl<'a> Extend<&'a MarkdownString> for MarkdownString {
+    fn extend<I: IntoIterator<Item = &'a MarkdownString>>(&mut self, iter: I) {
+        for item in iter {
+            self.push(item);
+        }
+    }
+}
+
+impl FromIterator<MarkdownString> for MarkdownString {
+    fn from_iter<I: IntoIterator<Item = MarkdownString>>(iter: I) -> Self {
+        let mut result = MarkdownString::new();
+        result.extend(iter);
+        result
+    }
+}
+
 /// Maximum message length allowed by Telegram Bot API
 /// See: https://core.telegram.org/bots/api#sendmessage
-const TELEGRAM_MAX_MESSAGE_LENGTH: usize = 4096;
+pub(crate) const TELEGRAM_MAX_MESSAGE_LENGTH: usize = 4096;
+
+/// Maximum media caption length allowed by Telegram Bot API
+/// See: https://core.telegram.org/bots/api#senddocument
+pub(crate) const TELEGRAM_MAX_CAPTION_LENGTH: usize = 1024;
 
 /// Trait for sending markdown messages with [teloxide Bot](https://docs.rs/teloxide/latest/teloxide/struct.Bot.html)
 ///
@@ -257,7 +936,8 @@ const TELEGRAM_MAX_MESSAGE_LENGTH: usize = 4096;
 /// using [teloxide Bot](https://docs.rs/teloxide/latest/teloxide/struct.Bot.html), automatically setting the parse mode to `MarkdownV2`.
 ///
 /// All methods automatically validate message length and truncate with "..."
-/// if the message exceeds Telegram's 4096 character limit.
+/// if the message exceeds Telegram's 4096 character limit,
+/// except [`send_markdown_message_paged`](Self::send_markdown_message_paged) which sends the long text in several messages.
 ///
 /// # Example
 ///
@@ -279,13 +959,70 @@ const TELEGRAM_MAX_MESSAGE_LENGTH: usize = 4096;
 /// while automatically applying the correct parse mode, making it safer and more
 /// convenient than manually setting the parse mode each time.
 #[allow(async_fn_in_trait)]
+#[cfg(feature = "teloxide")]
 pub trait MarkdownStringMessage: Requester {
     /// This method replaces [teloxide Bot::send_message](https://docs.rs/teloxide/latest/teloxide/struct.Bot.html#method.send_message) for `MarkdownString`
     fn send_markdown_message<C>(
         &self,
         chat_id: C,
         text: MarkdownString,
-    ) -> JsonRequest<SendMessage>
+    ) -> <Self as Requester>::SendMessage
+    where
+        C: Into<Recipient>;
+
+    /// Send the message with the options like the link preview or the reply, see [`MarkdownSendOptions`]
+    fn send_markdown_message_with<C>(
+        &self,
+        chat_id: C,
+        text: MarkdownString,
+        options: &MarkdownSendOptions,
+    ) -> <Self as Requester>::SendMessage
+    where
+        C: Into<Recipient>;
+
+    /// Send the text as plain text with the explicit formatting entities instead of the parse mode,
+    /// see [`MarkdownString::to_entities`]
+    fn send_entities_message<C>(&self, chat_id: C, text: MarkdownString) -> <Self as Requester>::SendMessage
+    where
+        C: Into<Recipient>;
+
+    /// Send the text split into as many messages as needed to fit Telegram's length limit,
+    /// see [`MarkdownString::split_for_sending`]. Returns the sent messages.
+    async fn send_markdown_message_paged<C>(
+        &self,
+        chat_id: C,
+        text: MarkdownString,
+    ) -> Result<Vec<Message>, <Self as Requester>::Err>
+    where
+        C: Into<Recipient>;
+
+    /// Send the photo with the markdown caption, limited to Telegram's 1024 characters caption limit
+    fn send_markdown_photo<C>(
+        &self,
+        chat_id: C,
+        photo: InputFile,
+        caption: impl Into<MarkdownCaption>,
+    ) -> <Self as Requester>::SendPhoto
+    where
+        C: Into<Recipient>;
+
+    /// Send the document with the markdown caption, limited to Telegram's 1024 characters caption limit
+    fn send_markdown_document<C>(
+        &self,
+        chat_id: C,
+        document: InputFile,
+        caption: impl Into<MarkdownCaption>,
+    ) -> <Self as Requester>::SendDocument
+    where
+        C: Into<Recipient>;
+
+    /// Send the video with the markdown caption, limited to Telegram's 1024 characters caption limit
+    fn send_markdown_video<C>(
+        &self,
+        chat_id: C,
+        video: InputFile,
+        caption: impl Into<MarkdownCaption>,
+    ) -> <Self as Requester>::SendVideo
     where
         C: Into<Recipient>;
 
@@ -305,18 +1042,102 @@ pub trait MarkdownStringMessage: Requester {
         inline_message_id: &str,
         text: MarkdownString,
     ) -> <Self as Requester>::EditMessageTextInline;
+
 }
 
-/// Implementation of `MarkdownStringMessage` for teloxide `Bot`
-impl MarkdownStringMessage for Bot {
-    fn send_markdown_message<C>(&self, chat_id: C, text: MarkdownString) -> JsonRequest<SendMessage>
+/// Implementation of `MarkdownStringMessage` for teloxide `Bot` and the adaptors wrapping it,
+/// e.g. `Throttle<Bot>`, `CacheMe<Bot>` or `DefaultParseMode<Bot>`
+#[cfg(feature = "teloxide")]
+impl<R> MarkdownStringMessage for R
+where
+    R: Requester<Err = RequestError>,
+{
+    fn send_markdown_message<C>(&self, chat_id: C, text: MarkdownString) -> <Self as Requester>::SendMessage
     where
         C: Into<Recipient>,
     {
-        self.send_message(chat_id, text)
+        self.send_message(chat_id, text.limit_length(TELEGRAM_MAX_MESSAGE_LENGTH))
             .parse_mode(ParseMode::MarkdownV2)
     }
 
+    fn send_markdown_message_with<C>(
+        &self,
+        chat_id: C,
+        text: MarkdownString,
+        options: &MarkdownSendOptions,
+    ) -> <Self as Requester>::SendMessage
+    where
+        C: Into<Recipient>,
+    {
+        options.apply(self.send_markdown_message(chat_id, text))
+    }
+
+    fn send_entities_message<C>(&self, chat_id: C, text: MarkdownString) -> <Self as Requester>::SendMessage
+    where
+        C: Into<Recipient>,
+    {
+        let (text, entities) = text.limit_length(TELEGRAM_MAX_MESSAGE_LENGTH).to_entities();
+        self.send_message(chat_id, text).entities(entities)
+    }
+
+    async fn send_markdown_message_paged<C>(
+        &self,
+        chat_id: C,
+        text: MarkdownString,
+    ) -> Result<Vec<Message>, <Self as Requester>::Err>
+    where
+        C: Into<Recipient>,
+    {
+        let chat_id = chat_id.into();
+        let mut messages = Vec::new();
+        for part in text.split_for_sending() {
+            messages.push(self.send_markdown_message(chat_id.clone(), part).await?);
+        }
+        Ok(messages)
+    }
+
+    fn send_markdown_photo<C>(
+        &self,
+        chat_id: C,
+        photo: InputFile,
+        caption: impl Into<MarkdownCaption>,
+    ) -> <Self as Requester>::SendPhoto
+    where
+        C: Into<Recipient>,
+    {
+        self.send_photo(chat_id, photo)
+            .caption(caption.into())
+            .parse_mode(MarkdownV2)
+    }
+
+    fn send_markdown_document<C>(
+        &self,
+        chat_id: C,
+        document: InputFile,
+        caption: impl Into<MarkdownCaption>,
+    ) -> <Self as Requester>::SendDocument
+    where
+        C: Into<Recipient>,
+    {
+        self.send_document(chat_id, document)
+            .caption(caption.into())
+            .parse_mode(MarkdownV2)
+    }
+
+    fn send_markdown_video<C>(
+        &self,
+        chat_id: C,
+        video: InputFile,
+        caption: impl Into<MarkdownCaption>,
+    ) -> <Self as Requester>::SendVideo
+    where
+        C: Into<Recipient>,
+    {
+        self.send_video(chat_id, video)
+            .caption(caption.into())
+            .parse_mode(MarkdownV2)
+    }
+
     fn edit_markdown_message_text<C>(
         &self,
         chat_id: C,
@@ -326,7 +1147,7 @@ impl MarkdownStringMessage for Bot {
     where
         C: Into<Recipient>,
     {
-        self.edit_message_text(chat_id, message_id, text)
+        self.edit_message_text(chat_id, message_id, text.limit_length(TELEGRAM_MAX_MESSAGE_LENGTH))
             .parse_mode(MarkdownV2)
     }
 
@@ -335,7 +1156,7 @@ impl MarkdownStringMessage for Bot {
         inline_message_id: &str,
         text: MarkdownString,
     ) -> <Self as Requester>::EditMessageTextInline {
-        self.edit_message_text_inline(inline_message_id, text)
+        self.edit_message_text_inline(inline_message_id, text.limit_length(TELEGRAM_MAX_MESSAGE_LENGTH))
             .parse_mode(MarkdownV2)
     }
 }
@@ -343,7 +1164,44 @@ impl MarkdownStringMessage for Bot {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{markdown_format, markdown_string};
+    use crate::{markdown_format, markdown_string, markdown_write, markdown_writeln};
+
+    #[test]
+    fn test_escape_matches_teloxide() {
+        let input = "\\_*[]()~`>#+-=|{}.! plain text";
+        assert_eq!(escape_markdown(input), "\\\\\\_\\*\\[\\]\\(\\)\\~\\`\\>\\#\\+\\-\\=\\|\\{\\}\\.\\! plain text");
+        #[cfg(feature = "teloxide")]
+        assert_eq!(escape_markdown(input), teloxide::utils::markdown::escape(input));
+    }
+
+    #[cfg(feature = "teloxide")]
+    #[test]
+    fn test_quote_message() {
+        let message: Message = serde_yaml::from_str(
+            "message_id: 1\ndate: 0\nchat: {id: 1, type: private, first_name: A}\n\
+             from: {id: 1, is_bot: false, first_name: John, last_name: D.}\ntext: \"Hi! How are you?\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            MarkdownString::quote_message(&message, 100).as_str(),
+            ">*John D\\.*\n>Hi\\! How are you?"
+        );
+        assert_eq!(MarkdownString::quote_message(&message, 5).as_str(), ">*John D\\.*\n>Hi\\!…");
+        assert_eq!(quote_preview(None, "line 1\nline 2", 20).as_str(), ">line 1\n>line 2");
+        // The quote passes the validation of the raw MarkdownV2
+        let quote = MarkdownString::quote_message(&message, 100);
+        assert!(MarkdownString::try_from_raw(quote.as_str()).is_ok());
+    }
+
+    #[test]
+    fn test_literal_not_copied() {
+        let mut text = MarkdownString::new();
+        text.push(&markdown_string!("*literal*"));
+        assert!(matches!(text.0, Cow::Borrowed(_)));
+        text.push_str_escaped("1.5");
+        assert!(matches!(text.0, Cow::Owned(_)));
+        assert_eq!(text.as_str(), "*literal*1\\.5");
+    }
 
     #[test]
     fn test_escape_constructor() {
@@ -672,6 +1530,149 @@ mod tests {
         assert_eq!(result.as_str(), "Simple message without placeholders\\.");
     }
 
+    #[test]
+    fn test_markdown_format_url() {
+        let result = markdown_format!(
+            "[{}]({}) [search](https://example.com/?q={})",
+            "a (link)",
+            @url "http://example.com/a\\b)",
+            @url "rust & go"
+        );
+        assert_eq!(
+            result.as_str(),
+            "[a \\(link\\)](http://example.com/a%5Cb%29) [search](https://example.com/?q=rust%20&%20go)"
+        );
+        assert!(MarkdownString::try_from_raw(result.as_str()).is_ok());
+    }
+
+    #[test]
+    fn test_markdown_format_entity_modifiers() {
+        let url = "http://example.com/a b";
+        let result = markdown_format!(
+            "{} {} {} {} {}\\! {}",
+            @bold "1+1",
+            @italic "a_b",
+            @underline String::from("u"),
+            @strike markdown_string!("*s*"),
+            @link url "the (link)",
+            @spoiler "x||y"
+        );
+        assert_eq!(
+            result.as_str(),
+            "*1\\+1* _a\\_b_ __u__ ~*s*~ [the \\(link\\)](http://example.com/a%20b)\\! ||x\\|\\|y||"
+        );
+        assert!(MarkdownString::try_from_raw(result.as_str()).is_ok());
+    }
+
+    #[cfg(feature = "teloxide")]
+    #[test]
+    fn test_markdown_format_mention() {
+        let user_id = UserId(42);
+        let text = markdown_format!("Hi, {}\\!", @mention user_id "*Bob* [x](http://a.b)");
+        assert_eq!(text.as_str(), "Hi, [\\*Bob\\* \\[x\\]\\(http://a\\.b\\)](tg://user?id=42)\\!");
+        let text = markdown_format!("{}", @mention (UserId(7)) markdown_string!("*Bob* [x](http://a.b)"));
+        assert_eq!(text.as_str(), "[*Bob* x](tg://user?id=7)");
+    }
+
+    #[test]
+    fn test_markdown_format_positional() {
+        let text = markdown_format!("{0} \\+ {0} \\= {1}, {}", "x.y", @bold "2x.y");
+        assert_eq!(text.as_str(), "x\\.y \\+ x\\.y \\= *2x\\.y*, x\\.y");
+        let text = markdown_format!("[{0}]({1}) `{0}`", "a", @url "https://a.b/?q=1 2");
+        assert_eq!(text.as_str(), "[a](https://a.b/?q=1%202) `a`");
+        // The placeholders in the substituted arguments are not replaced
+        let text = markdown_format!("{} {}", @code "{}", "b");
+        assert_eq!(text.as_str(), "```\n{}\n``` b");
+    }
+
+    #[test]
+    fn test_escape_linkified() {
+        let text = MarkdownString::escape_linkified("Read (https://en.wikipedia.org/wiki/Rust_(language)), http://a.b!");
+        assert_eq!(
+            text.as_str(),
+            "Read \\([https://en\\.wikipedia\\.org/wiki/Rust\\_\\(language\\)](https://en.wikipedia.org/wiki/Rust_(language\\))\\), [http://a\\.b](http://a.b)\\!"
+        );
+        // No links inside the words and without the host
+        let text = "xhttp://a.b https:// http://.";
+        assert_eq!(MarkdownString::escape_linkified(text), MarkdownString::escape(text));
+    }
+
+    #[test]
+    fn test_markdown_repair() {
+        let cases = [
+            ("*bold* and 1.5!", "*bold* and 1\\.5\\!"),
+            ("*bold _italic", "*bold _italic_*"),
+            ("__under", "__under__"),
+            ("a *", "a \\*"),
+            ("*a _b* c_", "*a _b\\* c_*"),
+            ("x] [y", "x\\] \\[y"),
+            ("[a](b) [c](https://c.d)", "[a]\\(b) [c](https://c.d)"),
+            ("`code", "`code`"),
+            ("```\nfn", "```\nfn```"),
+            ("end\\", "end\\\\"),
+            ("{} || |", "\\{\\} || \\|||"),
+        ];
+        for (input, expected) in cases {
+            let repaired = MarkdownString::repair(input);
+            assert_eq!(repaired.as_str(), expected, "input: {:?}", input);
+            assert!(MarkdownString::try_from_raw(repaired.as_str()).is_ok());
+        }
+        // The closing markers merging with the text are escaped entirely
+        assert_eq!(MarkdownString::repair("__a_["), MarkdownString::escape("__a_["));
+        assert_eq!(MarkdownString::repair("__\\*_["), MarkdownString::escape("__\\*_["));
+    }
+
+    #[test]
+    fn test_markdown_repair_random() {
+        let alphabet: Vec<char> = "*_~|`[]()\\>#+-=.!{}a \n".chars().collect();
+        // Linear congruential generator, so that the failures are reproducible
+        let mut seed = 42u64;
+        for _ in 0..5000 {
+            let mut input = String::new();
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            for _ in 0..(seed >> 33) % 16 {
+                seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+                input.push(alphabet[(seed >> 33) as usize % alphabet.len()]);
+            }
+            let repaired = MarkdownString::repair(&input);
+            assert!(
+                MarkdownString::try_from_raw(repaired.as_str()).is_ok(),
+                "input: {:?}, repaired: {:?}",
+                input,
+                repaired.as_str()
+            );
+        }
+    }
+
+    #[test]
+    fn test_markdown_join() {
+        let items = vec![markdown_string!("*a*"), MarkdownString::escape("b.c")];
+        let joined = MarkdownString::join(items.clone(), &markdown_string!("\n"));
+        assert_eq!(joined.as_str(), "*a*\nb\\.c");
+        assert_eq!(MarkdownString::join(Vec::new(), &markdown_string!(", ")).as_str(), "");
+
+        let collected: MarkdownString = items.iter().cloned().collect();
+        assert_eq!(collected.as_str(), "*a*b\\.c");
+
+        let mut truncated = MarkdownString::escape("x".repeat(5000)).limit_length(10);
+        truncated.extend(&items);
+        assert!(truncated.is_truncated());
+        assert!(!truncated.as_str().contains("*a*"));
+    }
+
+    #[test]
+    fn test_markdown_writeln() {
+        let mut report = markdown_string!("*Report*");
+        markdown_writeln!(report);
+        for (name, value) in [("a.b", 1), ("c", 2)] {
+            markdown_write!(report, "{}: ", @italic name);
+            markdown_writeln!(report, "{}", value.to_string());
+        }
+        report.push_line(&markdown_string!("\\-\\-"));
+        report.push_str_escaped("done.");
+        assert_eq!(report.as_str(), "*Report*\n_a\\.b_: 1\n_c_: 2\n\\-\\-\ndone\\.");
+    }
+
     #[test]
     fn test_markdown_format_macro_multiple_same_placeholder() {
         let template = MarkdownString::test_template("Hello {}\\! Nice to meet you, {}\\.");
@@ -887,6 +1888,7 @@ mod tests {
     // Note: We can't easily test the MarkdownStringSendMessage trait without
     // setting up a real Bot instance, but we can test that the types are correct
     #[test]
+    #[cfg(feature = "teloxide")]
     fn test_markdown_string_send_message_trait_exists() {
         // This test ensures the trait is properly defined and accessible
         use crate::api::markdown::string::MarkdownStringMessage;
@@ -894,6 +1896,10 @@ mod tests {
         // If this compiles, the trait is properly defined
         fn _test_trait_bound<T: MarkdownStringMessage>(_bot: T) {}
 
+        // The trait is implemented for the adaptors too
+        use teloxide::{Bot, requests::RequesterExt};
+        _test_trait_bound(Bot::new("token").parse_mode(ParseMode::Html));
+
         // Test that MarkdownString can be created for the trait method
         let _message = MarkdownString::escape("Test message");
     }
@@ -989,4 +1995,108 @@ mod tests {
             "*Important*: ```\nName   Value\nTest     123\n```"
         );
     }
+
+    #[test]
+    fn test_to_plain_text() {
+        let markdown = markdown_string!("*Bold* _italic_ __underline__ ~strike~ ||spoiler||");
+        assert_eq!(markdown.to_plain_text(), "Bold italic underline strike spoiler");
+
+        let markdown = MarkdownString::escape("Special chars: *bold* _italic_ 1.5!");
+        assert_eq!(markdown.to_plain_text(), "Special chars: *bold* _italic_ 1.5!");
+
+        let markdown = markdown_string!("See [the docs](http://example\\.com/a\\)b) now");
+        assert_eq!(markdown.to_plain_text(), "See the docs now");
+
+        let markdown = markdown_string!(">quote\n**>expandable\n>a\\>b||\n1 \\> 0");
+        assert_eq!(markdown.to_plain_text(), "quote\nexpandable\na>b\n1 > 0");
+        #[cfg(feature = "teloxide")]
+        assert_eq!(markdown.len_utf16(), markdown.to_entities().0.encode_utf16().count());
+
+        let markdown = markdown_string!("![👍](tg://emoji?id=1) ok\\!");
+        assert_eq!(markdown.to_plain_text(), "👍 ok!");
+    }
+
+    #[test]
+    fn test_push_quote() {
+        let quote = markdown_string!(">quote");
+        let mut text = markdown_string!("a");
+        text.push(&quote);
+        assert_eq!(text.as_str(), "a\n>quote");
+        text.push(&markdown_string!("\n"));
+        text.push(&markdown_string!("**>more||"));
+        assert_eq!(text.as_str(), "a\n>quote\n**>more||");
+        assert!(MarkdownString::try_from_raw(text.as_str()).is_ok());
+        assert_eq!((markdown_string!("a") + &quote).as_str(), "a\n>quote");
+        let joined = MarkdownString::join([quote.clone(), quote.clone()], &markdown_string!(" "));
+        assert_eq!(joined.as_str(), ">quote \n>quote");
+        let mut message = markdown_string!("a");
+        assert_eq!(message.push_or_overflow(&quote), None);
+        assert_eq!(message.as_str(), "a\n>quote");
+    }
+
+    #[test]
+    fn test_limit_length() {
+        let short = markdown_string!("*short*");
+        assert_eq!(short.clone().limit_length(10), short);
+
+        // The length is counted in UTF-16 code units of the visible text
+        let long = markdown_string!("*bold* text\\!");
+        assert_eq!(long.clone().limit_length(10), long);
+        let mut limited = long.limit_length(8);
+        assert_eq!(limited.as_str(), "*bold*\\.\\.\\.");
+        assert!(limited.is_truncated());
+        // The dropped text is kept in the overflow
+        assert_eq!(limited.overflow().unwrap().as_str(), "text\\!");
+        limited.push(&markdown_string!(" *more*"));
+        assert_eq!(limited.overflow().unwrap().as_str(), "text\\! *more*");
+        assert_eq!(short.overflow(), None);
+
+        // The entities open at the cut are closed before the marker
+        let limited = markdown_string!("*bold _text_ here*").limit_length(12);
+        assert_eq!(limited.as_str(), "*bold _text_*\\.\\.\\.");
+        assert!(check_markdownv2_format(limited.as_str(), false).is_ok());
+        assert_eq!(limited.overflow().unwrap().as_str(), "*here*");
+
+        let emoji = MarkdownString::escape("👍".repeat(5));
+        assert_eq!(emoji.len_utf16(), 10);
+        assert_eq!(emoji.limit_length(8).as_str(), "👍👍\\.\\.\\.");
+    }
+
+    #[test]
+    fn test_push_or_overflow() {
+        let mut message = MarkdownString::escape("a".repeat(4090));
+        assert_eq!(message.push_or_overflow(&markdown_string!(" b")), None);
+        assert_eq!(message.len_utf16(), 4092);
+        // The text which can't be split is returned whole
+        let mut message = MarkdownString::escape("a".repeat(4095));
+        let emoji = MarkdownString::escape("👍");
+        assert_eq!(message.push_or_overflow(&emoji), Some(emoji));
+        assert_eq!(message.len_utf16(), 4095);
+    }
+
+    #[test]
+    fn test_split_for_sending() {
+        let mut long = markdown_string!("*Report*\n");
+        for i in 0..1000 {
+            long.push(&markdown_format!("Line {}: _{}_\n", i, "value."));
+        }
+        assert!(!long.is_truncated());
+        let parts = long.split_for_sending();
+        assert!(parts.len() > 1);
+        for part in &parts {
+            assert!(part.len_utf16() <= TELEGRAM_MAX_MESSAGE_LENGTH);
+            assert!(!part.is_truncated());
+        }
+        let joined: Vec<String> = parts.iter().map(MarkdownString::to_plain_text).collect();
+        assert_eq!(joined.join("\n").trim_end(), long.to_plain_text().trim_end());
+    }
+
+    #[test]
+    fn test_to_plain_text_code() {
+        let markdown = MarkdownString::test_template("Inline `a*b_c` code");
+        assert_eq!(markdown.to_plain_text(), "Inline a*b_c code");
+
+        let markdown = markdown_format!("Report:\n{}", @code "rust" "let x = 1;");
+        assert_eq!(markdown.to_plain_text(), "Report:\nlet x = 1;");
+    }
 }
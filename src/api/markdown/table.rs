@@ -0,0 +1,259 @@
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use crate::api::markdown::{
+    ast::{Node, render},
+    string::MarkdownString,
+};
+
+/// Character appended to the cells cut to the column width
+const ELLIPSIS: char = '…';
+
+/// Alignment of the cell content inside the column
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Alignment {
+    #[default]
+    Left,
+    Right,
+    Center,
+}
+
+#[derive(Debug, Clone)]
+struct Column {
+    title: String,
+    alignment: Alignment,
+    max_width: Option<usize>,
+}
+
+/// Builder of the monospace tables rendered as MarkdownV2 code blocks
+///
+/// The column widths are measured in the terminal cells, so the wide characters
+/// like CJK or emoji are accounted as two cells. The cells longer than the column
+/// limit are cut with the ellipsis.
+///
+/// # Example
+/// ```rust
+/// use telluride::markdown::table::{Alignment, TableBuilder};
+///
+/// let table = TableBuilder::new()
+///     .column("Name", Alignment::Left)
+///     .column("Score", Alignment::Right)
+///     .row(["Alice", "10"])
+///     .row(["Bob", "7"])
+///     .build();
+/// assert_eq!(
+///     table.as_str(),
+///     "```\nName  | Score\n------+------\nAlice |    10\nBob   |     7\n```"
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct TableBuilder {
+    columns: Vec<Column>,
+    rows: Vec<Vec<String>>,
+    header: bool,
+    max_width: Option<usize>,
+}
+
+impl Default for TableBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TableBuilder {
+    pub fn new() -> Self {
+        Self {
+            columns: Vec::new(),
+            rows: Vec::new(),
+            header: true,
+            max_width: None,
+        }
+    }
+
+    /// Add the column with the title shown in the header
+    pub fn column(mut self, title: impl Into<String>, alignment: Alignment) -> Self {
+        self.columns.push(Column {
+            title: title.into(),
+            alignment,
+            max_width: None,
+        });
+        self
+    }
+
+    /// Limit the width of the last added column
+    pub fn max_column_width(mut self, width: usize) -> Self {
+        if let Some(column) = self.columns.last_mut() {
+            column.max_width = Some(width.max(1));
+        }
+        self
+    }
+
+    /// Limit the width of the whole table including the separators,
+    /// the widest columns are narrowed first
+    pub fn max_width(mut self, width: usize) -> Self {
+        self.max_width = Some(width);
+        self
+    }
+
+    /// Show or hide the header with the column titles, shown by default
+    pub fn header(mut self, header: bool) -> Self {
+        self.header = header;
+        self
+    }
+
+    /// Add the row, the missing cells are left empty and the extra ones are ignored
+    pub fn row<I>(mut self, cells: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: ToString,
+    {
+        self.push_row(cells);
+        self
+    }
+
+    /// Add the row to the existing builder, e.g. in a loop
+    pub fn push_row<I>(&mut self, cells: I)
+    where
+        I: IntoIterator,
+        I::Item: ToString,
+    {
+        self.rows
+            .push(cells.into_iter().map(|cell| cell.to_string()).collect());
+    }
+
+    /// Internal helper function to calculate the column widths with the limits applied
+    fn widths(&self) -> Vec<usize> {
+        let mut widths: Vec<usize> = self
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(i, column)| {
+                let title = if self.header { column.title.width() } else { 0 };
+                let content = self
+                    .rows
+                    .iter()
+                    .filter_map(|row| row.get(i))
+                    .map(|cell| cell.width())
+                    .max()
+                    .unwrap_or(0);
+                let width = title.max(content).max(1);
+                column.max_width.map_or(width, |max| width.min(max))
+            })
+            .collect();
+        if let Some(max_width) = self.max_width {
+            let separators = 3 * widths.len().saturating_sub(1);
+            while widths.iter().sum::<usize>() + separators > max_width {
+                let widest = widths.iter_mut().max().unwrap();
+                if *widest <= 1 {
+                    break;
+                }
+                *widest -= 1;
+            }
+        }
+        widths
+    }
+
+    /// Render the table as plain text without the code block
+    pub fn to_plain_text(&self) -> String {
+        let widths = self.widths();
+        let mut lines = Vec::new();
+        if self.header {
+            let titles = self.columns.iter().map(|column| column.title.as_str());
+            lines.push(self.render_line(titles, &widths));
+            let rule: Vec<String> = widths.iter().map(|width| "-".repeat(*width)).collect();
+            lines.push(rule.join("-+-"));
+        }
+        for row in &self.rows {
+            let cells = (0..self.columns.len()).map(|i| row.get(i).map_or("", String::as_str));
+            lines.push(self.render_line(cells, &widths));
+        }
+        lines.join("\n")
+    }
+
+    /// Internal helper function to render a single line of the table
+    fn render_line<'a>(&self, cells: impl Iterator<Item = &'a str>, widths: &[usize]) -> String {
+        let cells: Vec<String> = cells
+            .zip(&self.columns)
+            .zip(widths)
+            .map(|((cell, column), width)| align(&fit(cell, *width), *width, column.alignment))
+            .collect();
+        cells.join(" | ").trim_end().to_string()
+    }
+
+    /// Render the table as the code block
+    pub fn build(&self) -> MarkdownString {
+        render(&[Node::Pre {
+            language: None,
+            code: self.to_plain_text(),
+        }])
+    }
+}
+
+/// Internal helper function to cut the text to the given display width
+fn fit(text: &str, width: usize) -> String {
+    let text = text.replace(['\n', '\r', '\t'], " ");
+    if text.width() <= width {
+        return text;
+    }
+    let mut result = String::new();
+    let mut used = 0;
+    for c in text.chars() {
+        let char_width = c.width().unwrap_or(0);
+        if used + char_width + 1 > width {
+            break;
+        }
+        result.push(c);
+        used += char_width;
+    }
+    result.push(ELLIPSIS);
+    result
+}
+
+/// Internal helper function to pad the text to the given display width
+fn align(text: &str, width: usize, alignment: Alignment) -> String {
+    let padding = width.saturating_sub(text.width());
+    let (left, right) = match alignment {
+        Alignment::Left => (0, padding),
+        Alignment::Right => (padding, 0),
+        Alignment::Center => (padding / 2, padding - padding / 2),
+    };
+    format!("{}{}{}", " ".repeat(left), text, " ".repeat(right))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_table_builder() {
+        let table = TableBuilder::new()
+            .column("Item", Alignment::Left)
+            .column("Qty", Alignment::Center)
+            .column("Price", Alignment::Right)
+            .row(["Apple", "3", "1.50"])
+            .row(["日本茶", "10", "12.00"])
+            .row(["Back`tick\\", "1"]);
+        assert_eq!(
+            table.to_plain_text(),
+            "Item       | Qty | Price\n\
+             -----------+-----+------\n\
+             Apple      |  3  |  1.50\n\
+             日本茶     | 10  | 12.00\n\
+             Back`tick\\ |  1  |"
+        );
+        // The code block content is escaped
+        assert!(table.build().as_str().contains("Back\\`tick\\\\ |"));
+
+        // The widest columns are narrowed first to fit the table width
+        let narrow = table.clone().header(false).max_width(18);
+        assert_eq!(
+            narrow.to_plain_text(),
+            "Apple | 3  |  1.50\n日本… | 10 | 12.00\nBack… | 1  |"
+        );
+
+        let limited = TableBuilder::new()
+            .column("Description", Alignment::Left)
+            .max_column_width(6)
+            .row(["A very long text"]);
+        assert_eq!(limited.to_plain_text(), "Descr…\n------\nA ver…");
+    }
+}
@@ -0,0 +1,213 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display},
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+    time::{Duration, SystemTime},
+};
+
+use crate::api::markdown::{
+    string::MarkdownString,
+    validate::{MarkdownError, check_markdownv2_format},
+};
+
+/// Error loading or formatting the message templates
+#[derive(Debug)]
+pub enum TemplateError {
+    /// The templates file can't be read
+    Io(PathBuf, std::io::Error),
+    /// The templates file is not a YAML map of the names to the texts
+    Parse(PathBuf, serde_yaml::Error),
+    /// The template is not valid MarkdownV2
+    Invalid(String, MarkdownError),
+    /// There is no template with the name
+    NotFound(String),
+    /// The formatted template is not valid MarkdownV2, e.g. some placeholders have no arguments
+    Format(String, MarkdownError),
+}
+
+impl Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TemplateError::Io(path, _) => write!(f, "can't read templates {}", path.display()),
+            TemplateError::Parse(path, _) => write!(f, "can't parse templates {}", path.display()),
+            TemplateError::Invalid(name, _) => write!(f, "invalid template {:?}", name),
+            TemplateError::NotFound(name) => write!(f, "template {:?} not found", name),
+            TemplateError::Format(name, _) => write!(f, "can't format template {:?}", name),
+        }
+    }
+}
+
+impl std::error::Error for TemplateError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TemplateError::Io(_, err) => Some(err),
+            TemplateError::Parse(_, err) => Some(err),
+            TemplateError::Invalid(_, err) | TemplateError::Format(_, err) => Some(err),
+            TemplateError::NotFound(_) => None,
+        }
+    }
+}
+
+/// Named message templates loaded at runtime, so that the texts can be edited without rebuilding the bot
+///
+/// The templates are loaded from the YAML map of the names to the MarkdownV2 texts with the
+/// `{}` and `{0}` placeholders, like the format strings of [`markdown_format!`](crate::markdown_format!).
+/// Each template is validated at load time, the invalid file is rejected as a whole.
+/// The registry loaded from the file can be reloaded with [`reload`](Self::reload) or
+/// [`watch`](Self::watch), on errors the previous templates are kept.
+///
+/// # Example
+/// ```rust
+/// use telluride::markdown::TemplateRegistry;
+///
+/// let registry = TemplateRegistry::from_yaml("welcome: \"Hello, *{}*\\\\!\"").unwrap();
+/// let text = registry.format("welcome", ["John D."]).unwrap();
+/// assert_eq!(text.as_str(), "Hello, *John D\\.*\\!");
+/// assert!(registry.format("goodbye", ["John"]).is_err());
+/// ```
+#[derive(Debug, Default)]
+pub struct TemplateRegistry {
+    path: Option<PathBuf>,
+    templates: RwLock<BTreeMap<String, MarkdownString>>,
+}
+
+impl TemplateRegistry {
+    /// Load the templates from the YAML file
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, TemplateError> {
+        let path = path.as_ref().to_path_buf();
+        let templates = load_file(&path)?;
+        Ok(Self {
+            path: Some(path),
+            templates: RwLock::new(templates),
+        })
+    }
+
+    /// Load the templates from the YAML text, such registry can't be reloaded
+    pub fn from_yaml(yaml: &str) -> Result<Self, TemplateError> {
+        let templates = parse_templates(yaml, Path::new("<yaml>"))?;
+        Ok(Self {
+            path: None,
+            templates: RwLock::new(templates),
+        })
+    }
+
+    /// Reload the templates from the file, the previous templates are kept on error
+    pub fn reload(&self) -> Result<(), TemplateError> {
+        if let Some(path) = &self.path {
+            let templates = load_file(path)?;
+            *self.templates.write().unwrap() = templates;
+        }
+        Ok(())
+    }
+
+    /// Reload the templates when the modification time of the file changes, checking it with the interval
+    /// The errors are logged, the task runs until it's aborted.
+    pub fn watch(self: &Arc<Self>, interval: Duration) -> tokio::task::JoinHandle<()> {
+        let registry = self.clone();
+        tokio::spawn(async move {
+            let Some(path) = registry.path.clone() else {
+                return;
+            };
+            let mut last_modified = modified_time(&path);
+            loop {
+                tokio::time::sleep(interval).await;
+                let current = modified_time(&path);
+                if current == last_modified {
+                    continue;
+                }
+                last_modified = current;
+                match registry.reload() {
+                    Ok(()) => log::info!("Templates reloaded from {}", path.display()),
+                    Err(err) => {
+                        log::warn!("Keeping the previous templates: {:#}", crate::Error::from(err))
+                    }
+                }
+            }
+        })
+    }
+
+    /// Get the template by the name
+    pub fn get(&self, name: &str) -> Option<MarkdownString> {
+        self.templates.read().unwrap().get(name).cloned()
+    }
+
+    /// Names of the loaded templates in alphabetical order
+    pub fn names(&self) -> Vec<String> {
+        self.templates.read().unwrap().keys().cloned().collect()
+    }
+
+    /// Substitute the placeholders of the template with the escaped arguments
+    /// Fails if there is no such template or if some placeholders are left without the arguments.
+    pub fn format<T: Into<MarkdownString>>(
+        &self,
+        name: &str,
+        args: impl IntoIterator<Item = T>,
+    ) -> Result<MarkdownString, TemplateError> {
+        let template = self
+            .get(name)
+            .ok_or_else(|| TemplateError::NotFound(name.to_string()))?;
+        let args: Vec<String> = args
+            .into_iter()
+            .map(|arg| arg.into().into_string())
+            .collect();
+        let result = MarkdownString::format_with_args(&template, &args);
+        check_markdownv2_format(result.as_str(), false)
+            .map_err(|err| TemplateError::Format(name.to_string(), err))?;
+        Ok(result)
+    }
+}
+
+/// Internal helper function to read and validate the templates file
+fn load_file(path: &Path) -> Result<BTreeMap<String, MarkdownString>, TemplateError> {
+    let content =
+        std::fs::read_to_string(path).map_err(|err| TemplateError::Io(path.to_path_buf(), err))?;
+    parse_templates(&content, path)
+}
+
+/// Internal helper function to get the modification time of the file
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).ok()?.modified().ok()
+}
+
+/// Internal helper function to parse and validate the templates
+fn parse_templates(
+    yaml: &str,
+    path: &Path,
+) -> Result<BTreeMap<String, MarkdownString>, TemplateError> {
+    let texts: BTreeMap<String, String> =
+        serde_yaml::from_str(yaml).map_err(|err| TemplateError::Parse(path.to_path_buf(), err))?;
+    texts
+        .into_iter()
+        .map(|(name, text)| match check_markdownv2_format(&text, true) {
+            Ok(()) => Ok((name, MarkdownString::from_validated_string(text))),
+            Err(err) => Err(TemplateError::Invalid(name, err)),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_template_registry() {
+        let path = std::env::temp_dir().join(format!("telluride_templates_{}.yaml", std::process::id()));
+        std::fs::write(&path, "welcome: \"Hi, {0}\\\\! Bye, {0}\\\\.\"\nlist: \"{} and {}\"\n").unwrap();
+        let registry = TemplateRegistry::from_file(&path).unwrap();
+        assert_eq!(registry.names(), ["list", "welcome"]);
+        assert_eq!(registry.format("welcome", ["A.B"]).unwrap().as_str(), "Hi, A\\.B\\! Bye, A\\.B\\.");
+        assert!(matches!(registry.format("list", ["a"]), Err(TemplateError::Format(..))));
+        assert!(matches!(registry.format("missing", ["a"]), Err(TemplateError::NotFound(_))));
+
+        // The invalid file is rejected and the previous templates are kept
+        std::fs::write(&path, "welcome: \"Hi!\"\n").unwrap();
+        assert!(matches!(registry.reload(), Err(TemplateError::Invalid(name, _)) if name == "welcome"));
+        assert!(registry.get("list").is_some());
+
+        std::fs::write(&path, "welcome: \"Hi\\\\!\"\n").unwrap();
+        registry.reload().unwrap();
+        assert_eq!(registry.names(), ["welcome"]);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,109 @@
+use crate::{api::markdown::string::MarkdownString, markdown_write};
+
+/// Progress bar rendered as the line of the filled and empty characters
+///
+/// The bar is configured once and rendered on each update, e.g. by editing the message with
+/// [`edit_markdown_message_text`](crate::markdown::MarkdownStringMessage::edit_markdown_message_text),
+/// see also [`ProgressMessage`](crate::command::ProgressMessage) which rate-limits the edits.
+///
+/// # Example
+/// ```rust
+/// use telluride::markdown::widgets::ProgressBar;
+///
+/// let bar = ProgressBar::new().width(5).chars('▰', '▱').label("Uploading...");
+/// assert_eq!(bar.render(40).as_str(), "▰▰▱▱▱ 40%\nUploading\\.\\.\\.");
+/// assert_eq!(bar.render_ratio(1, 4).as_str(), "▰▱▱▱▱ 25%\nUploading\\.\\.\\.");
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProgressBar {
+    width: usize,
+    filled: char,
+    empty: char,
+    percentage: bool,
+    label: Option<MarkdownString>,
+}
+
+impl Default for ProgressBar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProgressBar {
+    /// Create the bar 10 characters wide with the percentage and without the label
+    pub fn new() -> Self {
+        Self {
+            width: 10,
+            filled: '█',
+            empty: '░',
+            percentage: true,
+            label: None,
+        }
+    }
+
+    /// Set the width of the bar in characters
+    pub fn width(mut self, width: usize) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// Set the characters of the filled and the empty parts of the bar
+    pub fn chars(mut self, filled: char, empty: char) -> Self {
+        self.filled = filled;
+        self.empty = empty;
+        self
+    }
+
+    /// Show or hide the percentage after the bar
+    pub fn percentage(mut self, percentage: bool) -> Self {
+        self.percentage = percentage;
+        self
+    }
+
+    /// Set the label displayed on the line below the bar
+    pub fn label(mut self, label: impl Into<MarkdownString>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    /// Render the bar for the progress in percents, the values above 100 are shown as 100%
+    pub fn render(&self, percent: u8) -> MarkdownString {
+        let percent = percent.min(100);
+        let filled = percent as usize * self.width / 100;
+        let bar: String = std::iter::repeat_n(self.filled, filled)
+            .chain(std::iter::repeat_n(self.empty, self.width - filled))
+            .collect();
+        let mut result = MarkdownString::escape(bar);
+        if self.percentage {
+            markdown_write!(result, " {}%", percent.to_string());
+        }
+        if let Some(label) = &self.label {
+            result.push_line(&MarkdownString::new());
+            result.push(label);
+        }
+        result
+    }
+
+    /// Render the bar for `current` of `total` steps done, the zero total is shown as 100%
+    pub fn render_ratio(&self, current: usize, total: usize) -> MarkdownString {
+        let percent = (current.min(total) * 100).checked_div(total).unwrap_or(100);
+        self.render(percent as u8)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::markdown_format;
+
+    #[test]
+    fn test_progress_bar() {
+        let bar = ProgressBar::new();
+        assert_eq!(bar.render(0).as_str(), "░░░░░░░░░░ 0%");
+        assert_eq!(bar.render(42).as_str(), "████░░░░░░ 42%");
+        assert_eq!(bar.render(200).as_str(), "██████████ 100%");
+        let bar = bar.width(4).chars('=', ' ').percentage(false).label(markdown_format!("*{}*", "a.b"));
+        assert_eq!(bar.render_ratio(3, 4).as_str(), "\\=\\=\\= \n*a\\.b*");
+        assert_eq!(bar.render_ratio(0, 0).as_str(), "\\=\\=\\=\\=\n*a\\.b*");
+    }
+}
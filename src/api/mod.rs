@@ -1,3 +1,24 @@
 pub(crate) mod markdown;
+pub(crate) mod html;
+pub(crate) mod parse;
+pub(crate) mod error;
+#[cfg(feature = "teloxide")]
 pub(crate) mod command;
-pub(crate) mod data_store;
\ No newline at end of file
+#[cfg(feature = "teloxide")]
+pub(crate) mod data_store;
+#[cfg(feature = "teloxide")]
+pub(crate) mod retry;
+#[cfg(feature = "teloxide")]
+pub(crate) mod rate_limit;
+#[cfg(feature = "teloxide")]
+pub(crate) mod schedule;
+#[cfg(feature = "teloxide")]
+pub(crate) mod app;
+#[cfg(feature = "teloxide")]
+pub(crate) mod config;
+#[cfg(feature = "fluent")]
+pub(crate) mod i18n;
+#[cfg(feature = "webhook")]
+pub(crate) mod webhook;
+#[cfg(feature = "testing")]
+pub(crate) mod testing;
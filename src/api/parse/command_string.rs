@@ -0,0 +1,111 @@
+/// Split the command message into the command name and the arguments
+/// The bot name suffix of the command, e.g. "/start@my_bot", is removed without checking it,
+/// see [`is_command_to_other_bot`]
+pub fn split_command(text: &str) -> Option<(&str, &str)> {
+    let text = text.strip_prefix('/')?;
+    let (command, args) = text
+        .split_once(char::is_whitespace)
+        .unwrap_or((text, ""));
+    let name = command.split('@').next().unwrap_or(command);
+    Some((name, args.trim_start()))
+}
+
+/// Check if the command is addressed to another bot by its suffix, e.g. "/start@other_bot" in a group
+/// The usernames are compared case-insensitively, the commands without the suffix are for all bots
+pub fn is_command_to_other_bot(text: &str, bot_username: &str) -> bool {
+    let Some(text) = text.strip_prefix('/') else {
+        return false;
+    };
+    let command = text.split(char::is_whitespace).next().unwrap_or(text);
+    command
+        .split_once('@')
+        .is_some_and(|(_, username)| !username.eq_ignore_ascii_case(bot_username))
+}
+
+/// Split the command arguments by spaces, the spaces inside the double quotes or escaped with a backslash
+/// are kept in the argument, e.g. `"two words"` or `two\ words`
+/// The quotes and the backslashes are escaped with a backslash, the unclosed quote lasts to the end of the line.
+/// Only the first line of the text is used
+pub fn split_with_screened_spaces(arg: &str) -> Vec<String> {
+    split_with_offsets(arg).into_iter().map(|(arg, _)| arg).collect()
+}
+
+/// Split the command arguments like [`split_with_screened_spaces`], returning each argument
+/// with the byte offset of its start in the text
+pub(crate) fn split_with_offsets(arg: &str) -> Vec<(String, usize)> {
+    let mut args = Vec::new();
+    // The quoted empty argument is kept, so the argument is started by any character except a space
+    let mut current: Option<(String, usize)> = None;
+    let mut quoted = false;
+    let mut chars = arg.lines().next().unwrap_or("").char_indices().peekable();
+    while let Some((pos, c)) = chars.next() {
+        if c == ' ' && !quoted {
+            args.extend(current.take());
+            continue;
+        }
+        let (token, _) = current.get_or_insert_with(|| (String::new(), pos));
+        match c {
+            '"' => quoted = !quoted,
+            '\\' => match chars.peek() {
+                Some(&(_, next @ ('\\' | '"' | ' '))) => {
+                    token.push(next);
+                    chars.next();
+                }
+                _ => token.push('\\'),
+            },
+            _ => token.push(c),
+        }
+    }
+    args.extend(current);
+    args
+}
+
+/// Escape the backslashes and the quotes in the argument and quote it if it has spaces, is empty
+/// or starts with `-`, so that it's not taken for a flag, the reverse of [`split_with_screened_spaces`]
+pub fn screen_spaces(s: &str) -> String {
+    let escaped = s.replace('\\', "\\\\").replace('"', "\\\"");
+    if s.is_empty() || s.contains(' ') || s.starts_with('-') {
+        format!("\"{}\"", escaped)
+    } else {
+        escaped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_command() {
+        assert_eq!(split_command("/start"), Some(("start", "")));
+        assert_eq!(split_command("/start@my_bot"), Some(("start", "")));
+        assert_eq!(split_command("/add 1  2"), Some(("add", "1  2")));
+        assert_eq!(split_command("/add@my_bot 1 2"), Some(("add", "1 2")));
+        assert_eq!(split_command("hello"), None);
+        assert!(is_command_to_other_bot("/start@other_bot", "my_bot"));
+        assert!(is_command_to_other_bot("/add@other_bot 1 2", "my_bot"));
+        assert!(!is_command_to_other_bot("/start@My_Bot", "my_bot"));
+        assert!(!is_command_to_other_bot("/add 1 a@other_bot", "my_bot"));
+        assert!(!is_command_to_other_bot("hello@other_bot", "my_bot"));
+    }
+
+    #[test]
+    fn test_screened_spaces_round_trip() {
+        let args = ["plain", "with space", "back\\slash", "both\\ here", "say \"hi\"", "", "--dry-run"];
+        let command = args.map(screen_spaces).join(" ");
+        assert_eq!(split_with_screened_spaces(&command), args);
+        assert_eq!(
+            command,
+            r#"plain "with space" back\\slash "both\\ here" "say \"hi\"" "" "--dry-run""#
+        );
+        assert_eq!(split_with_screened_spaces("a  b\nsecond line"), ["a", "b"]);
+        assert_eq!(
+            split_with_screened_spaces(r#""two words" two\ words --title="a b" 5\" "open quote"#),
+            ["two words", "two words", "--title=a b", "5\"", "open quote"]
+        );
+        assert_eq!(
+            split_with_offsets(" a\\ b  c"),
+            [("a b".to_string(), 1), ("c".to_string(), 7)]
+        );
+    }
+}
@@ -0,0 +1 @@
+pub(crate) mod rate_limiter;
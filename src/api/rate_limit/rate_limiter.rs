@@ -0,0 +1,199 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use teloxide::types::ChatId;
+use tokio::sync::Mutex;
+
+/// Number of tracked chats after which the chats without pending limits are forgotten
+const CHATS_CLEANUP_THRESHOLD: usize = 1000;
+
+/// Limiter of the outgoing messages rate following [Telegram's limits](https://core.telegram.org/bots/faq#my-bot-is-hitting-limits-how-do-i-avoid-this)
+///
+/// By default it allows:
+/// - one message per second in a private chat
+/// - twenty messages per minute in a group
+/// - thirty messages per second in total
+///
+/// The limiter is cheap to clone, all clones share the same state,
+/// so the same instance should be used for all requests of a bot.
+///
+/// # Example
+///
+/// ```rust
+/// use telluride::rate_limit::RateLimiter;
+/// use teloxide::types::ChatId;
+///
+/// async fn send_burst(limiter: RateLimiter, chat_id: ChatId) {
+///     for _ in 0..10 {
+///         limiter.acquire(chat_id).await;
+///         // send the message here
+///     }
+/// }
+/// ```
+#[derive(Clone)]
+pub struct RateLimiter {
+    /// Minimal interval between messages in a private chat
+    pub chat_interval: Duration,
+    /// Minimal interval between messages in a group, a supergroup or a channel
+    pub group_interval: Duration,
+    /// Maximum number of messages sent in total during one second
+    pub global_per_second: usize,
+    state: Arc<Mutex<RateLimiterState>>,
+}
+
+#[derive(Default)]
+struct RateLimiterState {
+    // The earliest time the next message can be sent to the chat
+    chat_next_slot: HashMap<ChatId, Instant>,
+    // Times of the messages sent during the last second
+    global_sent: VecDeque<Instant>,
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(1), Duration::from_secs(3), 30)
+    }
+}
+
+impl RateLimiter {
+    /// Create a limiter with the given per-chat intervals and the global limit
+    pub fn new(chat_interval: Duration, group_interval: Duration, global_per_second: usize) -> Self {
+        Self {
+            chat_interval,
+            group_interval,
+            global_per_second,
+            state: Arc::new(Mutex::new(RateLimiterState::default())),
+        }
+    }
+
+    /// Wait until a message can be sent to the chat and reserve the slot for it
+    pub async fn acquire(&self, chat_id: ChatId) {
+        loop {
+            let wait = {
+                let mut state = self.state.lock().await;
+                match self.try_reserve(&mut state, chat_id, Instant::now()) {
+                    None => return,
+                    Some(wait) => wait,
+                }
+            };
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Reserve the slot for a message if limits allow, otherwise return the time to wait
+    fn try_reserve(
+        &self,
+        state: &mut RateLimiterState,
+        chat_id: ChatId,
+        now: Instant,
+    ) -> Option<Duration> {
+        let window = Duration::from_secs(1);
+        while state
+            .global_sent
+            .front()
+            .is_some_and(|&sent| now.duration_since(sent) >= window)
+        {
+            state.global_sent.pop_front();
+        }
+
+        let chat_wait = state
+            .chat_next_slot
+            .get(&chat_id)
+            .map(|&next_slot| next_slot.saturating_duration_since(now))
+            .unwrap_or_default();
+        let global_wait = if state.global_sent.len() >= self.global_per_second {
+            state
+                .global_sent
+                .front()
+                .map(|&sent| (sent + window).saturating_duration_since(now))
+                .unwrap_or_default()
+        } else {
+            Duration::ZERO
+        };
+        let wait = chat_wait.max(global_wait);
+        if !wait.is_zero() {
+            return Some(wait);
+        }
+
+        if state.chat_next_slot.len() > CHATS_CLEANUP_THRESHOLD {
+            state.chat_next_slot.retain(|_, next_slot| *next_slot > now);
+        }
+        // Supergroups and channels share the identifiers range, so both are limited as groups
+        let interval = if chat_id.is_user() {
+            self.chat_interval
+        } else {
+            self.group_interval
+        };
+        state.chat_next_slot.insert(chat_id, now + interval);
+        state.global_sent.push_back(now);
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const USER_CHAT_ID: ChatId = ChatId(12345);
+    const OTHER_USER_CHAT_ID: ChatId = ChatId(67890);
+    const GROUP_CHAT_ID: ChatId = ChatId(-12345);
+
+    #[test]
+    fn test_per_chat_interval() {
+        let limiter = RateLimiter::default();
+        let mut state = RateLimiterState::default();
+        let now = Instant::now();
+
+        assert_eq!(limiter.try_reserve(&mut state, USER_CHAT_ID, now), None);
+        assert_eq!(
+            limiter.try_reserve(&mut state, USER_CHAT_ID, now),
+            Some(Duration::from_secs(1))
+        );
+        // Other chats are not affected
+        assert_eq!(limiter.try_reserve(&mut state, OTHER_USER_CHAT_ID, now), None);
+        // The slot is available after the interval
+        let later = now + Duration::from_secs(1);
+        assert_eq!(limiter.try_reserve(&mut state, USER_CHAT_ID, later), None);
+    }
+
+    #[test]
+    fn test_group_interval() {
+        let limiter = RateLimiter::default();
+        let mut state = RateLimiterState::default();
+        let now = Instant::now();
+
+        assert_eq!(limiter.try_reserve(&mut state, GROUP_CHAT_ID, now), None);
+        assert_eq!(
+            limiter.try_reserve(&mut state, GROUP_CHAT_ID, now),
+            Some(Duration::from_secs(3))
+        );
+    }
+
+    #[test]
+    fn test_global_limit() {
+        let limiter = RateLimiter::new(Duration::ZERO, Duration::ZERO, 2);
+        let mut state = RateLimiterState::default();
+        let now = Instant::now();
+
+        assert_eq!(limiter.try_reserve(&mut state, USER_CHAT_ID, now), None);
+        assert_eq!(limiter.try_reserve(&mut state, OTHER_USER_CHAT_ID, now), None);
+        assert_eq!(
+            limiter.try_reserve(&mut state, GROUP_CHAT_ID, now),
+            Some(Duration::from_secs(1))
+        );
+        let later = now + Duration::from_secs(1);
+        assert_eq!(limiter.try_reserve(&mut state, GROUP_CHAT_ID, later), None);
+    }
+
+    #[tokio::test]
+    async fn test_acquire_waits() {
+        let limiter = RateLimiter::new(Duration::from_millis(50), Duration::from_millis(50), 30);
+        let start = Instant::now();
+        limiter.acquire(USER_CHAT_ID).await;
+        limiter.acquire(USER_CHAT_ID).await;
+        assert!(start.elapsed() >= Duration::from_millis(50));
+    }
+}
@@ -0,0 +1,297 @@
+use std::{
+    collections::hash_map::RandomState,
+    future::Future,
+    hash::{BuildHasher, Hasher},
+    time::Duration,
+};
+
+use teloxide::{
+    ApiError, RequestError,
+    requests::{Output, Request},
+};
+
+/// Descriptions of the Telegram server errors (5xx) worth retrying
+const TRANSIENT_API_ERRORS: [&str; 4] = [
+    "Internal Server Error",
+    "Bad Gateway",
+    "Service Unavailable",
+    "Gateway Timeout",
+];
+
+/// Policy for retrying Telegram requests rejected by flood control or failed by transient errors
+///
+/// When Telegram responds with [`RequestError::RetryAfter`], the request is repeated
+/// after the indicated delay plus a random jitter, so that concurrent requests
+/// don't hit the limit again at the same moment.
+/// The transient errors, i.e. the network errors and Telegram's server errors, are not retried
+/// by default: the failed request may have reached Telegram, so its retry can duplicate the message.
+/// Enable them with [`with_transient_retries`](Self::with_transient_retries) if the rare duplicate
+/// is better than the lost message, they are retried with the exponential backoff plus the jitter,
+/// see [`is_transient`](Self::is_transient).
+/// Other errors, and any error after the last retry, are returned to the caller.
+///
+/// [`CommandReplyTarget`](crate::command::CommandReplyTarget) sends all its requests with its policy,
+/// the requests built by [`MarkdownStringMessage`](crate::markdown::MarkdownStringMessage)
+/// are sent with [`send`](Self::send).
+///
+/// # Example
+///
+/// ```rust
+/// use telluride::{markdown::MarkdownStringMessage, markdown_string, retry::RetryPolicy};
+/// use teloxide::{Bot, types::ChatId};
+///
+/// async fn send_with_retry(bot: Bot, chat_id: ChatId) {
+///     let policy = RetryPolicy::default();
+///     policy
+///         .send(bot.send_markdown_message(chat_id, markdown_string!("Hello\\!")))
+///         .await
+///         .unwrap();
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Maximum number of retries after the first attempt
+    pub max_retries: u32,
+    /// Maximum random delay added to the delay requested by Telegram or to the backoff
+    pub max_jitter: Duration,
+    /// Retry the network errors and Telegram's server errors, disabled by default
+    /// as the retried message may be duplicated
+    pub retry_transient: bool,
+    /// Delay before the first retry of the transient error, doubled for each next retry
+    pub initial_backoff: Duration,
+    /// Maximum delay between the retries of the transient error
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            max_jitter: Duration::from_millis(500),
+            retry_transient: false,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Create a policy with the given maximum number of retries and the default jitter
+    pub fn new(max_retries: u32) -> Self {
+        Self {
+            max_retries,
+            ..Default::default()
+        }
+    }
+
+    /// Set the maximum random delay added to the delay requested by Telegram
+    pub fn with_max_jitter(mut self, max_jitter: Duration) -> Self {
+        self.max_jitter = max_jitter;
+        self
+    }
+
+    /// Set the delay before the first retry of the transient error and the maximum delay
+    pub fn with_backoff(mut self, initial_backoff: Duration, max_backoff: Duration) -> Self {
+        self.initial_backoff = initial_backoff;
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    /// Enable or disable the retries of the network errors and Telegram's server errors
+    /// The request may have been processed by Telegram before the error, e.g. when the connection
+    /// is lost while waiting for the response, so the retried message may be sent twice.
+    pub fn with_transient_retries(mut self, retry_transient: bool) -> Self {
+        self.retry_transient = retry_transient;
+        self
+    }
+
+    /// Check if the error is transient: the network error, the server error page
+    /// which isn't a valid response, or Telegram's server error
+    pub fn is_transient(err: &RequestError) -> bool {
+        match err {
+            RequestError::Network(_) | RequestError::InvalidJson { .. } => true,
+            RequestError::Api(ApiError::Unknown(description)) => TRANSIENT_API_ERRORS
+                .iter()
+                .any(|transient| description.starts_with(transient)),
+            _ => false,
+        }
+    }
+
+    /// Send the request, retrying it according to the policy
+    pub async fn send<R>(&self, request: R) -> Result<Output<R>, RequestError>
+    where
+        R: Request<Err = RequestError>,
+    {
+        self.run(|| request.send_ref()).await
+    }
+
+    /// Run the operation, repeating it according to the policy
+    /// The operation is called again each time to produce a new attempt
+    pub async fn run<T, F, Fut>(&self, mut operation: F) -> Result<T, RequestError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, RequestError>>,
+    {
+        let mut attempt = 0;
+        loop {
+            match operation().await {
+                Err(RequestError::RetryAfter(seconds)) if attempt < self.max_retries => {
+                    attempt += 1;
+                    let delay = seconds.duration() + self.jitter();
+                    log::warn!(
+                        "Telegram flood control, retrying in {:?} (attempt {} of {})",
+                        delay,
+                        attempt,
+                        self.max_retries
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                Err(err)
+                    if self.retry_transient
+                        && attempt < self.max_retries
+                        && Self::is_transient(&err) =>
+                {
+                    attempt += 1;
+                    let delay = self.backoff(attempt) + self.jitter();
+                    log::warn!(
+                        "Telegram request failed: {}, retrying in {:?} (attempt {} of {})",
+                        err,
+                        delay,
+                        attempt,
+                        self.max_retries
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Delay before the given retry of the transient error, doubled for each retry up to max_backoff
+    fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 1u32.checked_shl(attempt.saturating_sub(1)).unwrap_or(u32::MAX);
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+
+    /// Random delay in range from zero to max_jitter
+    fn jitter(&self) -> Duration {
+        let max_jitter_ms = self.max_jitter.as_millis() as u64;
+        if max_jitter_ms == 0 {
+            return Duration::ZERO;
+        }
+        // RandomState is seeded randomly for each instance, which is enough for jitter
+        let random = RandomState::new().build_hasher().finish();
+        Duration::from_millis(random % (max_jitter_ms + 1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use teloxide::types::Seconds;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_retry_after_is_retried() {
+        let policy = RetryPolicy::new(2).with_max_jitter(Duration::ZERO);
+        let attempts = AtomicU32::new(0);
+        let result = policy
+            .run(|| async {
+                if attempts.fetch_add(1, Ordering::SeqCst) == 0 {
+                    Err(RequestError::RetryAfter(Seconds::from_seconds(0)))
+                } else {
+                    Ok(42)
+                }
+            })
+            .await;
+        assert_eq!(result.unwrap(), 42);
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_persistent_retry_after_is_returned() {
+        let policy = RetryPolicy::new(2).with_max_jitter(Duration::ZERO);
+        let attempts = AtomicU32::new(0);
+        let result: Result<(), _> = policy
+            .run(|| async {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                Err(RequestError::RetryAfter(Seconds::from_seconds(0)))
+            })
+            .await;
+        assert!(matches!(result, Err(RequestError::RetryAfter(_))));
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_transient_errors_are_retried() {
+        let policy = RetryPolicy::new(2)
+            .with_max_jitter(Duration::ZERO)
+            .with_backoff(Duration::ZERO, Duration::ZERO)
+            .with_transient_retries(true);
+        let attempts = AtomicU32::new(0);
+        let result = policy
+            .run(|| async {
+                if attempts.fetch_add(1, Ordering::SeqCst) < 2 {
+                    Err(RequestError::Api(ApiError::Unknown("Bad Gateway".to_string())))
+                } else {
+                    Ok(42)
+                }
+            })
+            .await;
+        assert_eq!(result.unwrap(), 42);
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+
+        let policy = policy.with_transient_retries(false);
+        let result: Result<(), _> = policy
+            .run(|| async { Err(RequestError::Api(ApiError::Unknown("Bad Gateway".to_string()))) })
+            .await;
+        assert!(matches!(result, Err(RequestError::Api(_))));
+
+        let policy = RetryPolicy::default().with_backoff(Duration::from_secs(1), Duration::from_secs(5));
+        let delays: Vec<_> = (1..=5).map(|attempt| policy.backoff(attempt).as_secs()).collect();
+        assert_eq!(delays, [1, 2, 4, 5, 5]);
+        assert_eq!(policy.backoff(100), Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn test_transient_errors_are_not_retried_by_default() {
+        let policy = RetryPolicy::default().with_backoff(Duration::ZERO, Duration::ZERO);
+        let attempts = AtomicU32::new(0);
+        let result: Result<(), _> = policy
+            .run(|| async {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                Err(RequestError::Api(ApiError::Unknown("Bad Gateway".to_string())))
+            })
+            .await;
+        assert!(matches!(result, Err(RequestError::Api(_))));
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_other_errors_are_not_retried() {
+        let policy = RetryPolicy::default();
+        let attempts = AtomicU32::new(0);
+        let result: Result<(), _> = policy
+            .run(|| async {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                Err(RequestError::Api(teloxide::ApiError::MessageNotModified))
+            })
+            .await;
+        assert!(matches!(result, Err(RequestError::Api(_))));
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_jitter_is_bounded() {
+        let policy = RetryPolicy::default().with_max_jitter(Duration::from_millis(10));
+        for _ in 0..100 {
+            assert!(policy.jitter() <= Duration::from_millis(10));
+        }
+        let policy = RetryPolicy::default().with_max_jitter(Duration::ZERO);
+        assert_eq!(policy.jitter(), Duration::ZERO);
+    }
+}
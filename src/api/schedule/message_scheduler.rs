@@ -0,0 +1,299 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, SystemTime},
+};
+
+use serde::{Deserialize, Serialize};
+use teloxide::{
+    ApiError, Bot, RequestError,
+    prelude::Requester,
+    requests::Request,
+    types::{ChatId, MessageId},
+};
+use tokio::task::JoinHandle;
+
+use crate::api::{
+    data_store::data_store_trait::{DataStoreTrait, GLOBAL_NAMESPACE},
+    markdown::string::{MarkdownString, MarkdownStringMessage},
+};
+
+/// Default interval between checks for due messages
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Message waiting to be sent, or deleted, at the specified time
+// Untagged to keep reading the messages stored before the deletions were added
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ScheduledMessage {
+    /// The message to send, see [`MessageScheduler::schedule_message`]
+    Send {
+        /// The chat to send the message to
+        chat_id: ChatId,
+        /// The time when the message should be sent
+        at: SystemTime,
+        /// The markdown text of the message
+        text: String,
+    },
+    /// The message to delete, see [`MessageScheduler::schedule_deletion`]
+    Delete {
+        /// The chat of the message
+        chat_id: ChatId,
+        /// The time when the message should be deleted
+        at: SystemTime,
+        /// The message to delete
+        message_id: MessageId,
+    },
+}
+
+impl ScheduledMessage {
+    /// The chat of the scheduled message
+    pub fn chat_id(&self) -> ChatId {
+        match self {
+            ScheduledMessage::Send { chat_id, .. } | ScheduledMessage::Delete { chat_id, .. } => *chat_id,
+        }
+    }
+
+    /// The time when the message should be sent or deleted
+    pub fn at(&self) -> SystemTime {
+        match self {
+            ScheduledMessage::Send { at, .. } | ScheduledMessage::Delete { at, .. } => *at,
+        }
+    }
+}
+
+/// Scheduler of messages to be sent at a specified time
+///
+/// The scheduled messages are persisted in the provided data store, so if a persistent
+/// store is used, reminders and digests survive restarts of the bot. Messages which
+/// became due while the bot was down are sent as soon as the dispatcher is started again.
+/// The scheduler also deletes the messages at the specified time, e.g. the ones sent by
+/// [`CommandReplyTarget::send_ephemeral`](crate::command::CommandReplyTarget::send_ephemeral).
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use std::{sync::Arc, time::{Duration, SystemTime}};
+/// use telluride::{data_store::FilesystemYamlStore, markdown_string, schedule::MessageScheduler};
+/// use teloxide::{Bot, types::ChatId};
+///
+/// async fn remind(bot: Bot, chat_id: ChatId) {
+///     let scheduler = MessageScheduler::new(bot, Arc::new(FilesystemYamlStore::new("schedule".into())));
+///     scheduler.spawn();
+///     let at = SystemTime::now() + Duration::from_secs(3600);
+///     scheduler
+///         .schedule_message(chat_id, at, markdown_string!("Time to stretch\\!"))
+///         .await;
+/// }
+/// ```
+#[derive(Clone)]
+pub struct MessageScheduler {
+    bot: Bot,
+    store: Arc<dyn DataStoreTrait<ScheduledMessage>>,
+    poll_interval: Duration,
+}
+
+impl MessageScheduler {
+    /// Create a scheduler keeping the scheduled messages in the given DataStore
+    pub fn new(bot: Bot, store: Arc<dyn DataStoreTrait<ScheduledMessage>>) -> Self {
+        Self {
+            bot,
+            store,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+        }
+    }
+
+    /// Set the interval between checks for due messages
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Schedule the message to be sent to the chat at the given time
+    /// Returns the id of the scheduled message which can be used to cancel it
+    pub async fn schedule_message(
+        &self,
+        chat_id: ChatId,
+        at: SystemTime,
+        text: MarkdownString,
+    ) -> String {
+        let id = new_schedule_id(chat_id);
+        let message = ScheduledMessage::Send {
+            chat_id,
+            at,
+            text: text.into_string(),
+        };
+        self.store.set(GLOBAL_NAMESPACE, &id, message).await;
+        id
+    }
+
+    /// Schedule the deletion of the message in the chat at the given time, e.g. of a one-time password
+    /// Returns the id of the scheduled deletion which can be used to cancel it
+    pub async fn schedule_deletion(&self, chat_id: ChatId, message_id: MessageId, at: SystemTime) -> String {
+        let id = new_schedule_id(chat_id);
+        let message = ScheduledMessage::Delete {
+            chat_id,
+            at,
+            message_id,
+        };
+        self.store.set(GLOBAL_NAMESPACE, &id, message).await;
+        id
+    }
+
+    /// Cancel the scheduled message, returns true if it was still pending
+    pub async fn cancel(&self, id: &str) -> bool {
+        self.store.remove(GLOBAL_NAMESPACE, id).await
+    }
+
+    /// List the pending scheduled messages with their ids
+    pub async fn pending(&self) -> Vec<(String, ScheduledMessage)> {
+        let mut pending = Vec::new();
+        for id in self.store.keys(GLOBAL_NAMESPACE).await {
+            if let Some(message) = self.store.get(GLOBAL_NAMESPACE, &id).await {
+                pending.push((id, message));
+            }
+        }
+        pending.sort_by_key(|(_, message)| message.at());
+        pending
+    }
+
+    /// Start the background task sending the messages when they become due
+    pub fn spawn(&self) -> JoinHandle<()> {
+        let scheduler = self.clone();
+        tokio::spawn(async move {
+            loop {
+                scheduler.dispatch_due(SystemTime::now()).await;
+                tokio::time::sleep(scheduler.poll_interval).await;
+            }
+        })
+    }
+
+    /// Send all messages due at the given time and delete the messages scheduled for deletion
+    /// Messages failed due to flood control or network problems are kept for the next attempt,
+    /// messages rejected by Telegram (e.g. the bot was blocked) are dropped
+    async fn dispatch_due(&self, now: SystemTime) {
+        for (id, message) in self.due(now).await {
+            let result = match message {
+                ScheduledMessage::Send { chat_id, text, .. } => {
+                    let text = MarkdownString::from_validated_string(text);
+                    self.bot
+                        .send_markdown_message(chat_id, text)
+                        .send()
+                        .await
+                        .map(|_| ())
+                }
+                ScheduledMessage::Delete {
+                    chat_id,
+                    message_id,
+                    ..
+                } => self
+                    .bot
+                    .delete_message(chat_id, message_id)
+                    .send()
+                    .await
+                    .map(|_| ()),
+            };
+            match result {
+                Ok(()) => {}
+                // The message was already deleted by the user
+                Err(RequestError::Api(ApiError::MessageToDeleteNotFound)) => {}
+                Err(err @ (RequestError::RetryAfter(_) | RequestError::Network(_))) => {
+                    log::warn!("Failed to send scheduled message {}, will retry: {}", id, err);
+                    continue;
+                }
+                Err(err) => {
+                    log::error!("Failed to send scheduled message {}, dropping it: {}", id, err);
+                }
+            }
+            self.store.remove(GLOBAL_NAMESPACE, &id).await;
+        }
+    }
+
+    /// Internal helper function to list the messages due at the given time
+    async fn due(&self, now: SystemTime) -> Vec<(String, ScheduledMessage)> {
+        self.pending()
+            .await
+            .into_iter()
+            .filter(|(_, message)| message.at() <= now)
+            .collect()
+    }
+}
+
+/// Generate a unique id for a scheduled message
+fn new_schedule_id(chat_id: ChatId) -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let nanos = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    format!("{}_{}_{}", chat_id, nanos, COUNTER.fetch_add(1, Ordering::Relaxed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{api::data_store::in_mem::InMemStore, markdown_string};
+
+    const TEST_CHAT_ID: ChatId = ChatId(12345);
+
+    #[tokio::test]
+    async fn test_schedule_and_cancel() {
+        let scheduler = MessageScheduler::new(Bot::new("token"), Arc::new(InMemStore::new()));
+        let now = SystemTime::now();
+        let later = scheduler
+            .schedule_message(TEST_CHAT_ID, now + Duration::from_secs(60), markdown_string!("later"))
+            .await;
+        let soon = scheduler
+            .schedule_message(TEST_CHAT_ID, now, markdown_string!("soon"))
+            .await;
+        assert_ne!(later, soon);
+
+        let pending = scheduler.pending().await;
+        assert_eq!(pending.len(), 2);
+        assert_eq!(pending[0].0, soon);
+        assert!(matches!(&pending[1].1, ScheduledMessage::Send { text, .. } if text == "later"));
+
+        let due = scheduler.due(now).await;
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].0, soon);
+
+        assert!(scheduler.cancel(&soon).await);
+        assert!(!scheduler.cancel(&soon).await);
+        assert!(scheduler.due(now).await.is_empty());
+
+        let deletion = scheduler
+            .schedule_deletion(TEST_CHAT_ID, MessageId(7), now)
+            .await;
+        let due = scheduler.due(now).await;
+        assert_eq!(due[0].0, deletion);
+        assert_eq!(
+            due[0].1,
+            ScheduledMessage::Delete {
+                chat_id: TEST_CHAT_ID,
+                at: now,
+                message_id: MessageId(7),
+            }
+        );
+    }
+
+    #[test]
+    fn test_scheduled_message_format() {
+        // The messages stored before the deletions were added are still read
+        let message: ScheduledMessage = serde_yaml::from_str(
+            "chat_id: 12345\nat: {secs_since_epoch: 60, nanos_since_epoch: 0}\ntext: hi\n",
+        )
+        .unwrap();
+        assert_eq!(message.chat_id(), TEST_CHAT_ID);
+        assert_eq!(message.at(), SystemTime::UNIX_EPOCH + Duration::from_secs(60));
+        assert!(matches!(message, ScheduledMessage::Send { text, .. } if text == "hi"));
+        let deletion = ScheduledMessage::Delete {
+            chat_id: TEST_CHAT_ID,
+            at: SystemTime::UNIX_EPOCH,
+            message_id: MessageId(7),
+        };
+        let yaml = serde_yaml::to_string(&deletion).unwrap();
+        assert_eq!(serde_yaml::from_str::<ScheduledMessage>(&yaml).unwrap(), deletion);
+    }
+}
@@ -0,0 +1 @@
+pub(crate) mod message_scheduler;
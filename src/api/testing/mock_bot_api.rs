@@ -0,0 +1,382 @@
+use std::{
+    collections::VecDeque,
+    net::SocketAddr,
+    sync::{
+        Arc,
+        atomic::{AtomicI32, Ordering},
+    },
+    time::Duration,
+};
+
+use axum::{Router, body::Bytes, extract::State, http::Uri, response::IntoResponse};
+use serde_json::{Value, json};
+use teloxide::{
+    Bot,
+    types::{ChatId, MessageId},
+};
+use tokio::{
+    sync::{Mutex, Notify},
+    task::JoinHandle,
+};
+
+/// The token of the bot connected to the mock server
+const MOCK_BOT_TOKEN: &str = "1234567:MOCK";
+
+/// The user id of the bot connected to the mock server
+const MOCK_BOT_ID: u64 = 1234567;
+
+/// The user id of the user sending the updates
+pub(crate) const MOCK_USER_ID: u64 = 7654321;
+
+/// The date of the mock messages, zero date marks the inaccessible messages
+const MOCK_DATE: i64 = 1700000000;
+
+/// Maximum time the mock server holds the getUpdates request if there are no updates
+const MOCK_POLLING_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Default time to wait for the bot's request in [`MockBotApi::next_request`]
+const MOCK_REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Request received by the mock Bot API server
+#[derive(Clone, Debug)]
+pub struct MockRequest {
+    /// The name of the Bot API method, e.g. "sendMessage"
+    pub method: String,
+    /// The JSON parameters of the request, `Null` for the multipart requests
+    pub params: Value,
+    /// The id of the message sent or edited by the request
+    pub message_id: Option<MessageId>,
+}
+
+impl MockRequest {
+    /// Get the string parameter of the request
+    pub fn str_param(&self, name: &str) -> Option<&str> {
+        self.params.get(name)?.as_str()
+    }
+
+    /// Get the callback data of the inline keyboard buttons of the request, row by row
+    pub fn keyboard_callbacks(&self) -> Vec<Vec<String>> {
+        let Some(rows) = self.params["reply_markup"]["inline_keyboard"].as_array() else {
+            return Vec::new();
+        };
+        rows.iter()
+            .map(|row| {
+                row.as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|button| button["callback_data"].as_str())
+                    .map(str::to_string)
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// Internal state of the mock server shared with the request handler
+#[derive(Default)]
+struct MockState {
+    updates: Mutex<VecDeque<Value>>,
+    updates_notify: Notify,
+    requests: Mutex<VecDeque<MockRequest>>,
+    requests_notify: Notify,
+    next_update_id: AtomicI32,
+    next_message_id: AtomicI32,
+}
+
+/// In-process fake of the Telegram Bot API for end-to-end tests
+///
+/// The bot returned by [`bot`](Self::bot) sends its requests to the mock server,
+/// so the dispatcher can run in tests without a real token. The updates queued with
+/// [`send_text`](Self::send_text) and [`press_button`](Self::press_button) are delivered
+/// through getUpdates, the bot's requests are collected and can be checked with
+/// [`next_request`](Self::next_request). The methods returning a message respond with
+/// a message built from the request's parameters, all other methods respond with `true`.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use telluride::{app::BotApp, markdown_string, testing::MockBotApi};
+/// use teloxide::types::ChatId;
+///
+/// # async fn run() {
+/// let api = MockBotApi::start().await;
+/// let mut dispatcher = BotApp::new(api.bot(), ())
+///     .command("start", "start the bot", |target, _, _| async move {
+///         target.markdown_message(markdown_string!("Welcome\\!")).await?;
+///         Ok(())
+///     })
+///     .build();
+/// tokio::spawn(async move { dispatcher.dispatch().await });
+///
+/// api.send_text(ChatId(1), "/start").await;
+/// let request = api.next_request("sendMessage").await.unwrap();
+/// assert_eq!(request.str_param("text"), Some("Welcome\\!"));
+/// # }
+/// ```
+pub struct MockBotApi {
+    address: SocketAddr,
+    state: Arc<MockState>,
+    server: JoinHandle<()>,
+}
+
+impl MockBotApi {
+    /// Start the mock server on a random local port
+    pub async fn start() -> Self {
+        let state = Arc::new(MockState {
+            next_update_id: AtomicI32::new(1),
+            next_message_id: AtomicI32::new(1),
+            ..Default::default()
+        });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Couldn't bind the mock Bot API server");
+        let address = listener.local_addr().expect("Mock server has no address");
+        let app = Router::new().fallback(handle_request).with_state(state.clone());
+        let server = tokio::spawn(async move {
+            axum::serve(listener, app).await.expect("Mock Bot API server error");
+        });
+        Self {
+            address,
+            state,
+            server,
+        }
+    }
+
+    /// Create the bot connected to the mock server
+    pub fn bot(&self) -> Bot {
+        let url = format!("http://{}/", self.address)
+            .parse()
+            .expect("Invalid mock server url");
+        Bot::new(MOCK_BOT_TOKEN).set_api_url(url)
+    }
+
+    /// Queue the text message from the user in the private chat
+    pub async fn send_text(&self, chat_id: ChatId, text: &str) -> MessageId {
+        let message_id = self.state.next_message_id.fetch_add(1, Ordering::SeqCst);
+        let message = json!({
+            "message_id": message_id,
+            "date": MOCK_DATE,
+            "chat": private_chat(chat_id),
+            "from": user(),
+            "text": text,
+        });
+        self.push_update("message", message).await;
+        MessageId(message_id)
+    }
+
+    /// Queue the press of the inline keyboard button with the callback data
+    /// on the bot's message in the private chat
+    pub async fn press_button(&self, chat_id: ChatId, message_id: MessageId, data: &str) {
+        let query = json!({
+            "id": format!("query_{}", message_id.0),
+            "from": user(),
+            "chat_instance": chat_id.0.to_string(),
+            "message": {
+                "message_id": message_id.0,
+                "date": MOCK_DATE,
+                "chat": private_chat(chat_id),
+                "from": bot_user(),
+                "text": "",
+            },
+            "data": data,
+        });
+        self.push_update("callback_query", query).await;
+    }
+
+    /// Wait for the next request of the bot with the given method, skipping the other requests
+    /// Returns `None` if there is no such request during 5 seconds
+    pub async fn next_request(&self, method: &str) -> Option<MockRequest> {
+        tokio::time::timeout(MOCK_REQUEST_TIMEOUT, async {
+            loop {
+                let notified = self.state.requests_notify.notified();
+                {
+                    let mut requests = self.state.requests.lock().await;
+                    while let Some(request) = requests.pop_front() {
+                        if request.method == method {
+                            return request;
+                        }
+                    }
+                }
+                notified.await;
+            }
+        })
+        .await
+        .ok()
+    }
+
+    /// Internal helper function to queue the update of the given kind
+    async fn push_update(&self, kind: &str, payload: Value) {
+        let update_id = self.state.next_update_id.fetch_add(1, Ordering::SeqCst);
+        let mut update = json!({ "update_id": update_id });
+        update[kind] = payload;
+        self.state.updates.lock().await.push_back(update);
+        self.state.updates_notify.notify_waiters();
+    }
+}
+
+impl Drop for MockBotApi {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+/// Internal helper function to respond to the bot's request
+async fn handle_request(State(state): State<Arc<MockState>>, uri: Uri, body: Bytes) -> impl IntoResponse {
+    // Teloxide sends the method names capitalized, e.g. "SendMessage"
+    let method = uri.path().rsplit('/').next().unwrap_or_default();
+    let mut chars = method.chars();
+    let method: String = chars
+        .next()
+        .map(|first| first.to_ascii_lowercase())
+        .into_iter()
+        .chain(chars)
+        .collect();
+    let params: Value = serde_json::from_slice(&body).unwrap_or(Value::Null);
+    let result = match method.as_str() {
+        "getMe" => {
+            let mut me = bot_user();
+            me["can_join_groups"] = json!(true);
+            me["can_read_all_group_messages"] = json!(false);
+            me["supports_inline_queries"] = json!(false);
+            me["has_main_web_app"] = json!(false);
+            me
+        }
+        "getWebhookInfo" => json!({ "url": "", "has_custom_certificate": false, "pending_update_count": 0 }),
+        "getUpdates" => json!(take_updates(&state).await),
+        // Service requests are not recorded
+        "deleteWebhook" => json!(true),
+        _ => {
+            let result = response(&state, &method, &params);
+            let message_id = result["message_id"]
+                .as_i64()
+                .map(|message_id| MessageId(message_id as i32));
+            let request = MockRequest {
+                method,
+                params,
+                message_id,
+            };
+            state.requests.lock().await.push_back(request);
+            state.requests_notify.notify_waiters();
+            result
+        }
+    };
+    axum::Json(json!({ "ok": true, "result": result }))
+}
+
+/// Internal helper function to wait for the queued updates
+async fn take_updates(state: &MockState) -> Vec<Value> {
+    let notified = state.updates_notify.notified();
+    if state.updates.lock().await.is_empty() {
+        let _ = tokio::time::timeout(MOCK_POLLING_TIMEOUT, notified).await;
+    }
+    state.updates.lock().await.drain(..).collect()
+}
+
+/// Internal helper function to build the result of the recorded request
+fn response(state: &MockState, method: &str, params: &Value) -> Value {
+    let sends_message = method.starts_with("send") && method != "sendChatAction";
+    let edits_message = method.starts_with("edit") && params.get("inline_message_id").is_none();
+    if !sends_message && !edits_message {
+        return json!(true);
+    }
+    let message_id = match params.get("message_id") {
+        Some(message_id) if edits_message => message_id.clone(),
+        _ => json!(state.next_message_id.fetch_add(1, Ordering::SeqCst)),
+    };
+    let chat_id = ChatId(params["chat_id"].as_i64().unwrap_or_default());
+    let mut message = json!({
+        "message_id": message_id,
+        "date": MOCK_DATE,
+        "chat": private_chat(chat_id),
+        "from": bot_user(),
+    });
+    for field in ["text", "caption", "reply_markup"] {
+        if let Some(value) = params.get(field) {
+            message[field] = value.clone();
+        }
+    }
+    message
+}
+
+/// Internal helper function to build the private chat object
+fn private_chat(chat_id: ChatId) -> Value {
+    json!({ "id": chat_id.0, "type": "private", "first_name": "User" })
+}
+
+/// Internal helper function to build the user sending the updates
+fn user() -> Value {
+    json!({ "id": MOCK_USER_ID, "is_bot": false, "first_name": "User" })
+}
+
+/// Internal helper function to build the bot's user
+fn bot_user() -> Value {
+    json!({ "id": MOCK_BOT_ID, "is_bot": true, "first_name": "Mock", "username": "mock_bot" })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{api::app::bot_app::BotApp, markdown_format, markdown_string};
+
+    const TEST_CHAT_ID: ChatId = ChatId(12345);
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_mock_bot_api_menu_flow() {
+        let api = MockBotApi::start().await;
+        let mut dispatcher = BotApp::new(api.bot(), ())
+            .command("menu", "show menu", |target, _, _| async move {
+                let menu = vec![vec![("One", "/pressed 1"), ("Two", "/pressed 2")]];
+                target
+                    .markdown_message_with_menu(markdown_string!("Choose:"), menu)
+                    .await?;
+                Ok(())
+            })
+            .command("pressed", "handle button", |target, _, option| async move {
+                target
+                    .markdown_message(markdown_format!("Pressed {}", option))
+                    .await?;
+                Ok(())
+            })
+            .build();
+        let dispatcher_task = tokio::spawn(async move { dispatcher.dispatch().await });
+
+        api.send_text(TEST_CHAT_ID, "/menu").await;
+        let menu = api.next_request("sendMessage").await.unwrap();
+        assert_eq!(menu.str_param("text"), Some("Choose:"));
+        // The menu is attached after sending, its callbacks are keyed by the message id
+        let keyboard = api.next_request("editMessageReplyMarkup").await.unwrap();
+        let callbacks = keyboard.keyboard_callbacks();
+        assert_eq!(callbacks.len(), 1);
+        assert_eq!(callbacks[0].len(), 2);
+
+        let menu_id = menu.message_id.unwrap();
+        assert_eq!(keyboard.message_id, Some(menu_id));
+        api.press_button(TEST_CHAT_ID, menu_id, &callbacks[0][1]).await;
+        let edit = api.next_request("editMessageText").await.unwrap();
+        assert_eq!(edit.str_param("text"), Some("Pressed 2"));
+        assert_eq!(edit.params["message_id"], json!(menu_id.0));
+        assert!(api.next_request("answerCallbackQuery").await.is_some());
+
+        dispatcher_task.abort();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_command_to_other_bot() {
+        let api = MockBotApi::start().await;
+        let mut dispatcher = BotApp::new(api.bot(), ())
+            .command("echo", "", |target, _, args| async move {
+                target.markdown_message(markdown_format!("{}", args)).await?;
+                Ok(())
+            })
+            .build();
+        let dispatcher_task = tokio::spawn(async move { dispatcher.dispatch().await });
+
+        // The messages of the chat are handled in order, so the first reply is to the second command
+        api.send_text(TEST_CHAT_ID, "/echo@other_bot ignored").await;
+        api.send_text(TEST_CHAT_ID, "/echo@Mock_Bot handled").await;
+        let reply = api.next_request("sendMessage").await.unwrap();
+        assert_eq!(reply.str_param("text"), Some("handled"));
+
+        dispatcher_task.abort();
+    }
+}
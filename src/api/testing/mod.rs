@@ -0,0 +1 @@
+pub(crate) mod mock_bot_api;
@@ -0,0 +1,151 @@
+use std::{convert::Infallible, io, net::SocketAddr, sync::Arc};
+
+use teloxide::{
+    Bot, RequestError,
+    prelude::ResponseResult,
+    update_listeners::{UpdateListener, webhooks},
+};
+use url::Url;
+
+/// Maximum length of the webhook secret token allowed by Telegram Bot API
+/// See: https://core.telegram.org/bots/api#setwebhook
+pub(crate) const SECRET_TOKEN_MAX_LENGTH: usize = 256;
+
+/// Settings of the webhook served by [`webhook_listener`]
+#[derive(Clone, Debug)]
+pub struct WebhookConfig {
+    /// Local address the HTTP listener binds to
+    pub address: SocketAddr,
+    /// Public HTTPS url Telegram sends the updates to, it should be forwarded to the address
+    pub url: Url,
+    /// Local path of the webhook if it differs from the url's path, e.g. behind a reverse proxy
+    pub path: Option<String>,
+    /// Token Telegram sends in the "X-Telegram-Bot-Api-Secret-Token" header of each request,
+    /// requests without it are rejected. A random token is generated if not set
+    pub secret_token: Option<String>,
+    /// Maximum number of simultaneous connections for the update delivery, 1-100
+    pub max_connections: Option<u8>,
+    /// Drop the updates received while the webhook wasn't set
+    pub drop_pending_updates: bool,
+}
+
+impl WebhookConfig {
+    /// Create the config listening on the address for the updates sent to the url
+    pub fn new(address: SocketAddr, url: Url) -> Self {
+        Self {
+            address,
+            url,
+            path: None,
+            secret_token: None,
+            max_connections: None,
+            drop_pending_updates: false,
+        }
+    }
+
+    /// Serve the webhook on the given local path instead of the url's path
+    pub fn path(mut self, path: impl Into<String>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    /// Use the given secret token to verify the requests
+    /// The token should be 1-256 characters `A-Z`, `a-z`, `0-9`, `_` and `-`,
+    /// otherwise [`webhook_listener`] fails
+    pub fn secret_token(mut self, secret_token: impl Into<String>) -> Self {
+        self.secret_token = Some(secret_token.into());
+        self
+    }
+
+    /// Limit the number of simultaneous connections for the update delivery
+    pub fn max_connections(mut self, max_connections: u8) -> Self {
+        self.max_connections = Some(max_connections.clamp(1, 100));
+        self
+    }
+
+    /// Drop the updates received while the webhook wasn't set
+    pub fn drop_pending_updates(mut self) -> Self {
+        self.drop_pending_updates = true;
+        self
+    }
+
+    /// Internal helper function to convert the config to the teloxide webhook options
+    fn into_options(self) -> webhooks::Options {
+        let mut options = webhooks::Options::new(self.address, self.url);
+        if let Some(path) = self.path {
+            options = options.path(path);
+        }
+        if let Some(secret_token) = self.secret_token {
+            options = options.secret_token(secret_token);
+        }
+        if let Some(max_connections) = self.max_connections {
+            options = options.max_connections(max_connections);
+        }
+        if self.drop_pending_updates {
+            options = options.drop_pending_updates();
+        }
+        options
+    }
+}
+
+/// Set the webhook and start the HTTP listener feeding the updates into the dispatcher
+///
+/// Requests without the matching secret token header are rejected.
+/// The webhook is deleted when the listener is stopped, e.g. on Ctrl+C.
+/// The returned listener is passed to
+/// [`Dispatcher::dispatch_with_listener`](teloxide::dispatching::Dispatcher::dispatch_with_listener)
+/// or to [`BotApp::dispatch_with_webhook`](crate::app::BotApp::dispatch_with_webhook).
+///
+/// Fails if the secret token of the config is invalid.
+///
+/// # Panics
+///
+/// If binding to the address fails.
+pub async fn webhook_listener(
+    bot: Bot,
+    config: WebhookConfig,
+) -> ResponseResult<impl UpdateListener<Err = Infallible>> {
+    if let Some(secret_token) = &config.secret_token
+        && !is_valid_secret_token(secret_token)
+    {
+        return Err(RequestError::Io(Arc::new(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Invalid webhook secret token, it should be 1-256 characters A-Z, a-z, 0-9, _ and -",
+        ))));
+    }
+    log::info!(
+        "Setting webhook {}, listening on {}",
+        config.url,
+        config.address
+    );
+    webhooks::axum(bot, config.into_options()).await
+}
+
+/// Internal helper function to check the secret token against the Telegram Bot API restrictions
+fn is_valid_secret_token(token: &str) -> bool {
+    (1..=SECRET_TOKEN_MAX_LENGTH).contains(&token.len())
+        && token
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_secret_token() {
+        assert!(is_valid_secret_token("My_secret-token1"));
+        assert!(!is_valid_secret_token(""));
+        assert!(!is_valid_secret_token("with space"));
+        assert!(!is_valid_secret_token(&"a".repeat(SECRET_TOKEN_MAX_LENGTH + 1)));
+
+        let config = WebhookConfig::new(
+            "127.0.0.1:8443".parse().unwrap(),
+            "https://example.com/bot".parse().unwrap(),
+        );
+        assert_eq!(config.clone().secret_token("token").secret_token.as_deref(), Some("token"));
+        // The invalid token is reported before setting the webhook
+        let result = webhook_listener(Bot::new("token"), config.secret_token("bad token")).await;
+        assert!(matches!(result, Err(RequestError::Io(err)) if err.kind() == io::ErrorKind::InvalidInput));
+    }
+}
@@ -1,5 +1,13 @@
 mod api;
 
+// The derive macros refer to the crate by its name, also inside it
+extern crate self as telluride;
+
+pub use api::error::crate_error::{Error, Result, ResultExt};
+#[cfg(feature = "macros")]
+#[doc(hidden)]
+pub use telluride_macros::check_markdown_literal as __check_markdown_literal;
+
 /// The `markdown` module provides utilities for safe working with MarkdownV2 formatted strings.
 /// The goal is to make it impossible to create invalid MarkdownV2 strings that will cause runtime errors.
 /// 
@@ -25,32 +33,180 @@ mod api;
 /// The teloxide [Bot](https://docs.rs/teloxide/latest/teloxide/struct.Bot.html) type is extended with this trait implementation.
 pub mod markdown {
     pub use crate::api::markdown::{
-        string::{MarkdownString, MarkdownStringMessage},
-        validate::validate_markdownv2_format,
+        caption::MarkdownCaption,
+        escaped::EscapedDisplay,
+        list::{ListBuilder, ListStyle},
+        string::MarkdownString,
+        validate::{MarkdownError, MarkdownErrorKind, validate_code_language, validate_markdownv2_format},
     };
+    #[cfg(feature = "teloxide")]
+    pub use crate::api::markdown::string::MarkdownStringMessage;
+    #[cfg(feature = "teloxide")]
+    pub use crate::api::markdown::send_options::MarkdownSendOptions;
+    #[cfg(feature = "teloxide")]
+    pub use crate::api::markdown::stream::MarkdownMessageStream;
+    #[cfg(feature = "teloxide")]
+    pub use crate::api::markdown::template::{TemplateError, TemplateRegistry};
+
+    /// Tree of the MarkdownV2 formatting for post-processing the messages,
+    /// see [`parse`](ast::parse) and [`render`](ast::render)
+    pub mod ast {
+        pub use crate::api::markdown::ast::{Node, parse, render, render_html, strip_links};
+    }
+
+    /// Links starting the bot with the payload, see [`deeplink`](deeplink::deeplink)
+    pub mod deeplink {
+        pub use crate::api::markdown::deeplink::{
+            DeepLinkError, DeepLinkKind, decode_payload, decode_start, deeplink, deeplink_url,
+            encode_payload,
+        };
+    }
+
+    /// Human-readable sizes, counts and durations, see [`human_bytes`](human::human_bytes)
+    pub mod human {
+        pub use crate::api::markdown::human::{human_bytes, human_count, human_duration};
+    }
+
+    /// Aligned monospace tables, see [`TableBuilder`](table::TableBuilder)
+    pub mod table {
+        pub use crate::api::markdown::table::{Alignment, TableBuilder};
+    }
+
+    /// Reusable message elements, see [`ProgressBar`](widgets::ProgressBar)
+    pub mod widgets {
+        pub use crate::api::markdown::widgets::ProgressBar;
+    }
+}
+
+/// The `html` module is the counterpart of the [`markdown`] module for Telegram's
+/// [HTML](https://core.telegram.org/bots/api#html-style) parse mode, which handles nested formatting better.
+///
+/// The type [`HtmlString`](html::HtmlString) is created with the compile-time validated
+/// [`html_string!`] and [`html_format!`] macros, the latter escapes its arguments.
+/// The trait [`HtmlStringMessage`](html::HtmlStringMessage) extends the teloxide `Bot` with
+/// [`send_html_message`](html::HtmlStringMessage::send_html_message) and
+/// [`edit_html_message_text`](html::HtmlStringMessage::edit_html_message_text).
+pub mod html {
+    pub use crate::api::html::{string::HtmlString, validate::validate_html_format};
+    #[cfg(feature = "teloxide")]
+    pub use crate::api::html::string::HtmlStringMessage;
 }
 
+/// Command string parsing shared by the command handlers,
+/// available without the `teloxide` feature, e.g. in `wasm32` builds.
+pub mod parse {
+    pub use crate::api::parse::command_string::{
+        is_command_to_other_bot, screen_spaces, split_command, split_with_screened_spaces,
+    };
+}
+
+#[cfg(feature = "teloxide")]
 pub mod command {
     pub use crate::api::command::command_trait::{
         CommandTrait, NoopCommand,
     };
     pub use crate::api::command::command_arg::
     {
-        EmptyArg, ParseCommandArg
+        EmptyArg, EnumArgError, Flags, Opt, ParseCommandArg, ParseFlagArgs, Req, RestArg
     };
+    #[cfg(feature = "macros")]
+    pub use telluride_macros::CommandArgEnum;
+    pub use crate::api::command::time_arg::DurationArg;
+    #[cfg(feature = "chrono")]
+    pub use crate::api::command::time_arg::{DateArg, TimeArg};
     pub use crate::api::command::command_button::{
         CallbackData, CallbackDataStorage, CallbackDataStorageTrait,
         unpack_callback_data, pack_callback_data, ButtonData,
+        pack_command_button, parse_command_callback,
+        unpack_typed_callback_data, CallbackPayloadError,
     };
     pub use crate::api::command::command_reply_target::{
-        CommandReplyTarget,
+        CommandReplyTarget, EditFailurePolicy, RenderedMessage,
+    };
+    pub use crate::api::command::callback_reply_target::CallbackReplyTarget;
+    pub use crate::api::command::command_registry::CommandRegistry;
+    pub use crate::api::command::command_error::default_error_reply;
+    pub use crate::api::command::subcommand_router::SubcommandRouter;
+    pub use crate::api::command::paginator::{PaginationState, Paginator};
+    pub use crate::api::command::progress_message::ProgressMessage;
+    pub use crate::api::command::chat_action::ChatActionGuard;
+    pub use crate::api::command::last_message_tracker::LastMessageTracker;
+    pub use crate::api::command::live_message::{LiveMessage, LiveMessageStopHandle};
+    pub use crate::api::command::outgoing_middleware::OutgoingMiddleware;
+    pub use crate::api::command::poll::{PollRecord, PollSettings, PollTracker};
+    pub use crate::api::command::prompt::{PendingPrompt, PromptRegistry};
+    pub use crate::api::command::wizard::{CommandWizard, PendingWizard};
+    pub use crate::api::command::authorize::{DenyReason, UserAllowlist};
+    pub use crate::api::command::command_middleware::{CommandCall, CommandMiddleware};
+    pub use crate::api::command::command_metrics::{
+        CommandMetrics, CommandStats, StoreCommandMetrics, render_command_stats, LATENCY_BUCKETS_MS,
     };
+    pub use crate::api::command::confirm::{
+        ConfirmHandle, ConfirmRegistry, Confirmation, PendingConfirmation,
+    };
+    pub use crate::api::command::pending_operation::{
+        OperationHandle, OperationRegistry, PendingOperation,
+    };
+    pub use crate::api::command::session::{Session, SessionStore};
 }
 
+#[cfg(feature = "teloxide")]
 pub mod data_store {
     pub use crate::api::data_store::{
         data_store_trait::DataStoreTrait,
         in_mem::InMemStore,
-        file_system_yaml::FilesystemYamlStore,
+        namespaced::NamespacedStore,
+    };
+    #[cfg(not(target_arch = "wasm32"))]
+    pub use crate::api::data_store::file_system_yaml::FilesystemYamlStore;
+}
+
+#[cfg(feature = "teloxide")]
+pub mod retry {
+    pub use crate::api::retry::retry_policy::RetryPolicy;
+}
+
+#[cfg(feature = "teloxide")]
+pub mod rate_limit {
+    pub use crate::api::rate_limit::rate_limiter::RateLimiter;
+}
+
+#[cfg(feature = "teloxide")]
+pub mod schedule {
+    pub use crate::api::schedule::message_scheduler::{MessageScheduler, ScheduledMessage};
+}
+
+#[cfg(feature = "teloxide")]
+pub mod config {
+    pub use crate::api::config::bot_config::{
+        BotConfig, ConfigError, StorageConfig, ADMIN_CHAT_IDS_ENV, CONFIG_PATH_ENV, FEATURES_ENV,
+        LOCALE_ENV, STORAGE_PATH_ENV, TOKEN_ENV,
     };
-}
\ No newline at end of file
+}
+
+#[cfg(feature = "teloxide")]
+pub mod app {
+    pub use crate::api::app::admin_commands::{AdminCommands, ADMIN_COMMANDS};
+    pub use crate::api::app::bot_app::{BotApp, BoxFuture};
+    pub use crate::api::app::polling_supervisor::{
+        PollingHealth, PollingStatus, PollingSupervisor, SupervisedPolling,
+    };
+    pub use crate::api::app::update_dedup::UpdateDeduplicator;
+}
+
+/// Localization of the MarkdownV2 messages with [Fluent](https://projectfluent.org),
+/// see [`Localizer`](i18n::Localizer) and the [`t!`] macro.
+#[cfg(feature = "fluent")]
+pub mod i18n {
+    pub use crate::api::i18n::localizer::{I18nArg, I18nError, Localizer};
+}
+
+#[cfg(feature = "webhook")]
+pub mod webhook {
+    pub use crate::api::webhook::webhook_listener::{WebhookConfig, webhook_listener};
+}
+
+#[cfg(feature = "testing")]
+pub mod testing {
+    pub use crate::api::testing::mock_bot_api::{MockBotApi, MockRequest};
+}
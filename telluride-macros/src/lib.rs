@@ -0,0 +1,236 @@
+//! Procedural macros of the [telluride](https://docs.rs/telluride) crate, not intended to be used directly
+
+use std::ops::Range;
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{Data, DeriveInput, Fields, LitStr, parse_macro_input};
+use telluride_validate::{MarkdownErrorKind, check_markdownv2_format};
+
+/// Validate the MarkdownV2 string literal of the `markdown_string!` macro
+///
+/// Expands to nothing if the literal is valid, otherwise to the compile error pointing
+/// at the offending character inside the literal.
+#[proc_macro]
+pub fn check_markdown_literal(input: TokenStream) -> TokenStream {
+    let literal = parse_macro_input!(input as LitStr);
+    let token = literal.token();
+    match check_literal(&literal.value(), &token.to_string()) {
+        Ok(()) => TokenStream::new(),
+        Err((range, message)) => {
+            // Pointing inside the literal is supported only by the nightly compiler,
+            // the message shows the position anyway
+            let span = token.subspan(range).unwrap_or_else(|| literal.span());
+            syn::Error::new(span, message).to_compile_error().into()
+        }
+    }
+}
+
+/// Derive `FromStr` and `Display` of the fieldless enum used as the command argument,
+/// `ParseCommandArg` is implemented for it by the `FromStr`
+///
+/// The variants are matched case-insensitively by their kebab-case names, e.g. `in-progress`
+/// for `InProgress`. The name is replaced with `#[arg(name = "...")]`, the other accepted names
+/// are added with `#[arg(alias = "...")]`. The unknown value fails with the error listing the names.
+///
+/// # Example
+/// ```ignore
+/// #[derive(CommandArgEnum, Default, Clone, Copy)]
+/// enum Period {
+///     #[default]
+///     #[arg(alias = "d")]
+///     Daily,
+///     #[arg(name = "weekly", alias = "w")]
+///     EveryWeek,
+/// }
+/// ```
+#[proc_macro_derive(CommandArgEnum, attributes(arg))]
+pub fn derive_command_arg_enum(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    command_arg_enum(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Internal helper function to generate the `FromStr` and `Display` implementations of the enum
+fn command_arg_enum(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let Data::Enum(data) = &input.data else {
+        return Err(syn::Error::new_spanned(&input.ident, "CommandArgEnum supports only enums"));
+    };
+    let mut idents = Vec::new();
+    let mut names = Vec::new();
+    let mut patterns = Vec::new();
+    for variant in &data.variants {
+        if !matches!(variant.fields, Fields::Unit) {
+            return Err(syn::Error::new_spanned(variant, "CommandArgEnum variants can't have fields"));
+        }
+        let mut name = kebab_case(&variant.ident.to_string());
+        let mut aliases = Vec::new();
+        for attr in variant.attrs.iter().filter(|attr| attr.path().is_ident("arg")) {
+            attr.parse_nested_meta(|meta| {
+                let value = meta.value()?.parse::<LitStr>()?.value().to_lowercase();
+                if meta.path.is_ident("name") {
+                    name = value;
+                } else if meta.path.is_ident("alias") {
+                    aliases.push(value);
+                } else {
+                    return Err(meta.error("expected `name` or `alias`"));
+                }
+                Ok(())
+            })?;
+        }
+        aliases.insert(0, name.clone());
+        idents.push(&variant.ident);
+        names.push(name);
+        patterns.push(aliases);
+    }
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let patterns = patterns.iter().map(|aliases| quote! { #(#aliases)|* });
+    Ok(quote! {
+        impl #impl_generics ::std::str::FromStr for #ident #ty_generics #where_clause {
+            type Err = ::telluride::command::EnumArgError;
+
+            fn from_str(value: &str) -> ::std::result::Result<Self, Self::Err> {
+                match value.to_lowercase().as_str() {
+                    #(#patterns => ::std::result::Result::Ok(Self::#idents),)*
+                    _ => ::std::result::Result::Err(
+                        ::telluride::command::EnumArgError::new(value, &[#(#names),*])
+                    ),
+                }
+            }
+        }
+
+        impl #impl_generics ::std::fmt::Display for #ident #ty_generics #where_clause {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                f.write_str(match self {
+                    #(Self::#idents => #names,)*
+                })
+            }
+        }
+    })
+}
+
+/// Internal helper function to convert the variant name to kebab-case, e.g. `InProgress` to `in-progress`
+fn kebab_case(name: &str) -> String {
+    let mut result = String::new();
+    let mut previous = None;
+    for c in name.chars() {
+        if c.is_uppercase() && previous.is_some_and(|p: char| p.is_lowercase() || p.is_ascii_digit()) {
+            result.push('-');
+        }
+        result.extend(c.to_lowercase().map(|c| if c == '_' { '-' } else { c }));
+        previous = Some(c);
+    }
+    result
+}
+
+/// Internal helper function to validate the literal value and describe the error
+/// with the range of the offending character in the literal source
+fn check_literal(value: &str, source: &str) -> Result<(), (Range<usize>, String)> {
+    let Err(err) = check_markdownv2_format(value, true) else {
+        return Ok(());
+    };
+    let range = source_range(value, source, err.offset());
+    let line_start = source[..range.start].rfind('\n').map_or(0, |pos| pos + 1);
+    let line_end = source[range.start..]
+        .find('\n')
+        .map_or(source.len(), |pos| range.start + pos);
+    let column = source[line_start..range.start].chars().count();
+    let mut message = format!(
+        "{}\n  {}\n  {}^",
+        err.description(),
+        &source[line_start..line_end],
+        " ".repeat(column)
+    );
+    if let MarkdownErrorKind::Unescaped(c) = err.kind()
+        && !source.starts_with('r')
+    {
+        message.push_str(&format!(
+            "\nthe backslash must be escaped too in the string literal: \"\\\\{c}\", or use the raw string: r\"\\{c}\""
+        ));
+    }
+    Err((range, message))
+}
+
+/// Internal helper function to find the source range of the value character at the byte offset
+fn source_range(value: &str, source: &str, offset: usize) -> Range<usize> {
+    let char_len = value[offset..].chars().next().map_or(0, char::len_utf8);
+    // Raw strings are the same as the value after the `r#"` prefix
+    if source.starts_with('r') {
+        let start = source.find('"').map_or(0, |pos| pos + 1) + offset;
+        return start..start + char_len;
+    }
+    let bytes = source.as_bytes();
+    let mut pos = 1;
+    let mut value_pos = 0;
+    while pos < bytes.len() - 1 {
+        let (source_len, value_len) = match bytes[pos] {
+            b'\\' => match bytes[pos + 1] {
+                b'x' => (4, 1),
+                b'u' => {
+                    let end = source[pos..].find('}').map_or(source.len(), |end| pos + end + 1);
+                    let decoded = u32::from_str_radix(&source[pos + 3..end - 1], 16)
+                        .ok()
+                        .and_then(char::from_u32)
+                        .map_or(1, char::len_utf8);
+                    (end - pos, decoded)
+                }
+                // Line continuation skips the line break and the leading whitespace
+                b'\n' | b'\r' => {
+                    let rest = &source[pos + 1..];
+                    (1 + rest.len() - rest.trim_start().len(), 0)
+                }
+                _ => (2, 1),
+            },
+            _ => {
+                let len = source[pos..].chars().next().map_or(1, char::len_utf8);
+                (len, len)
+            }
+        };
+        if value_pos == offset && value_len > 0 {
+            return pos..pos + source_len;
+        }
+        pos += source_len;
+        value_pos += value_len;
+    }
+    0..source.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_literal() {
+        assert!(check_literal("*Hello* world\\!", r#""*Hello* world\\!""#).is_ok());
+
+        let (range, message) = check_literal("Hello world!", r#""Hello world!""#).unwrap_err();
+        assert_eq!(range, 12..13);
+        assert!(message.starts_with(
+            "Unescaped '!' in MarkdownV2 format string. Use \\! to escape it.\n  \"Hello world!\"\n              ^\n"
+        ));
+
+        // The escapes of the literal are accounted
+        let (range, _) = check_literal("a\n👍.", r#""a\n\u{1F44D}.""#).unwrap_err();
+        assert_eq!(range, 13..14);
+        let (range, message) = check_literal("ab.", "\"a\\\n    b.\"").unwrap_err();
+        assert_eq!(range, 9..10);
+        assert!(message.contains("\n      b.\"\n       ^"));
+
+        let (range, message) = check_literal("x.", r##"r#"x."#"##).unwrap_err();
+        assert_eq!(range, 4..5);
+        assert!(!message.contains("raw string"));
+
+        let (range, _) = check_literal("*bold", r#""*bold""#).unwrap_err();
+        assert_eq!(range, 1..2);
+    }
+
+    #[test]
+    fn test_kebab_case() {
+        assert_eq!(kebab_case("Daily"), "daily");
+        assert_eq!(kebab_case("InProgress"), "in-progress");
+        assert_eq!(kebab_case("Snake_Case"), "snake-case");
+        assert_eq!(kebab_case("HTTPServer"), "httpserver");
+    }
+}
@@ -0,0 +1,732 @@
+//! MarkdownV2 validator of the [telluride](https://docs.rs/telluride) crate, shared with its procedural macros,
+//! not intended to be used directly
+
+/// Validates MarkdownV2 format string at compile time.
+///
+/// This function validates that a format string follows the [Telegram MarkdownV2 specification](https://core.telegram.org/bots/api#markdownv2-style).
+/// It performs compile-time validation to ensure:
+///
+/// - Balanced formatting characters: \*, \_, \~, \|, \`, \[, \]
+/// - Properly escaped reserved characters: \!, \., \-, \+, \=, \>, \#, \{, \}
+/// - Correct nesting of the entities, e.g. no crossing `*bold _italic* text_` and no links inside links
+/// - Valid link syntax with matching parentheses and the http, https or tg URL (or the `{}` or `{0}` placeholder)
+///
+/// # MarkdownV2 Format Support
+///
+/// The validator supports the following MarkdownV2 elements:
+/// - **Bold**: `*bold text*` or `**bold text**`
+/// - _Italic_: `_italic text_` or `__italic text__`
+/// - `Code`: `` `code` `` or ``` ```code block``` ```
+/// - ~~Strikethrough~~: `~strikethrough~`
+/// - ||Spoiler||: `||spoiler||`
+/// - [Links](http://example.com): `[text](url)`
+/// - [User mentions](tg://user?id=123): `[name](tg://user?id=123)`
+/// - Custom emoji: `![👍](tg://emoji?id=5368324170671202286)`
+/// - Blockquotes: the lines starting with `>`, the expandable one starts with `**>` and ends with `||`
+pub const fn validate_markdownv2_format(format_str: &str) {
+    if let Err(err) = check_markdownv2_format(format_str, true) {
+        panic!("{}", err.description());
+    }
+}
+
+/// Validates the language of the code block at compile time, used by the `@code "lang"` modifier
+/// of `markdown_format!`. The language may contain only the alphanumeric
+/// ASCII characters, `+`, `-` and `#`, e.g. `rust`, `c++`, `c#`, `objective-c`.
+pub const fn validate_code_language(lang: &str) {
+    let bytes = lang.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        if !(bytes[i].is_ascii_alphanumeric() || matches!(bytes[i], b'+' | b'-' | b'#')) {
+            panic!(
+                "Invalid code block language - only alphanumeric characters, '+', '-' and '#' are allowed"
+            );
+        }
+        i += 1;
+    }
+}
+
+/// Problem found in the MarkdownV2 text, see [`MarkdownError`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum MarkdownErrorKind {
+    /// Reserved character which must be escaped with the backslash, e.g. `.` or `!`
+    Unescaped(char),
+    /// Bold formatting `*` is not closed
+    UnmatchedAsterisk,
+    /// Italic or underline formatting `_` is not closed
+    UnmatchedUnderscore,
+    /// Strikethrough formatting `~` is not closed
+    UnmatchedTilde,
+    /// Spoiler formatting `|` is not closed
+    UnmatchedPipe,
+    /// `]` without the opening `[`
+    UnmatchedClosingBracket,
+    /// Link text `[` is not closed
+    UnclosedLinkText,
+    /// Link URL `(` is not closed
+    UnclosedLinkUrl,
+    /// Link URL is not an http, https or tg URL
+    InvalidLinkUrl,
+    /// Inline code is not closed
+    UnclosedCode,
+    /// Pre-formatted code block is not closed
+    UnclosedPre,
+    /// Entity is closed before the entity nested in it, e.g. `*bold _italic* text_`
+    CrossingEntities,
+    /// Link inside the text of another link
+    NestedLink,
+}
+
+/// Error of the runtime MarkdownV2 validation, see `MarkdownString::try_from_raw`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MarkdownError {
+    offset: usize,
+    kind: MarkdownErrorKind,
+}
+
+impl MarkdownError {
+    const fn new(offset: usize, kind: MarkdownErrorKind) -> Self {
+        Self { offset, kind }
+    }
+
+    /// Byte offset of the problem in the text, for the unclosed formatting it's the offset of the opening character
+    pub const fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub const fn kind(&self) -> MarkdownErrorKind {
+        self.kind
+    }
+
+    /// Human readable description of the problem
+    pub const fn description(&self) -> &'static str {
+        match self.kind {
+            MarkdownErrorKind::Unescaped('!') => {
+                "Unescaped '!' in MarkdownV2 format string. Use \\! to escape it."
+            }
+            MarkdownErrorKind::Unescaped('.') => {
+                "Unescaped '.' in MarkdownV2 format string. Use \\. to escape it."
+            }
+            MarkdownErrorKind::Unescaped('-') => {
+                "Unescaped '-' in MarkdownV2 format string. Use \\- to escape it."
+            }
+            MarkdownErrorKind::Unescaped('+') => {
+                "Unescaped '+' in MarkdownV2 format string. Use \\+ to escape it."
+            }
+            MarkdownErrorKind::Unescaped('=') => {
+                "Unescaped '=' in MarkdownV2 format string. Use \\= to escape it."
+            }
+            MarkdownErrorKind::Unescaped('>') => {
+                "Unescaped '>' in MarkdownV2 format string. Use \\> to escape it."
+            }
+            MarkdownErrorKind::Unescaped('#') => {
+                "Unescaped '#' in MarkdownV2 format string. Use \\# to escape it."
+            }
+            MarkdownErrorKind::Unescaped('{') => {
+                "Unescaped '{' in MarkdownV2 format string. Use \\{ to escape it or use {} or {0} for format placeholders."
+            }
+            MarkdownErrorKind::Unescaped('|') => {
+                "Unescaped '|' in MarkdownV2 format string. Use \\| to escape it or || for spoilers."
+            }
+            MarkdownErrorKind::Unescaped('}') => {
+                "Unescaped '}' in MarkdownV2 format string. Use \\} to escape it."
+            }
+            MarkdownErrorKind::Unescaped(_) => "Unescaped reserved character in MarkdownV2 format string",
+            MarkdownErrorKind::UnmatchedAsterisk => {
+                "Unmatched asterisks (*) in MarkdownV2 format string - bold formatting must be balanced"
+            }
+            MarkdownErrorKind::UnmatchedUnderscore => {
+                "Unmatched underscores (_) in MarkdownV2 format string - italic formatting must be balanced"
+            }
+            MarkdownErrorKind::UnmatchedTilde => {
+                "Unmatched tildes (~) in MarkdownV2 format string - strikethrough formatting must be balanced"
+            }
+            MarkdownErrorKind::UnmatchedPipe => {
+                "Unmatched pipes (|) in MarkdownV2 format string - spoiler formatting must be balanced"
+            }
+            MarkdownErrorKind::UnmatchedClosingBracket => {
+                "Unmatched closing square bracket ']' in markdown format string"
+            }
+            MarkdownErrorKind::UnclosedLinkText => {
+                "Unmatched square brackets ([]) in MarkdownV2 format string - link text must be properly closed"
+            }
+            MarkdownErrorKind::UnclosedLinkUrl => {
+                "Unmatched parentheses in MarkdownV2 format string - link URLs must be properly closed"
+            }
+            MarkdownErrorKind::InvalidLinkUrl => {
+                "Invalid link URL in MarkdownV2 format string - only http://, https:// and tg:// URLs without spaces are allowed"
+            }
+            MarkdownErrorKind::UnclosedCode => "Unclosed code block in MarkdownV2 format string",
+            MarkdownErrorKind::UnclosedPre => {
+                "Unclosed pre-formatted code block in MarkdownV2 format string"
+            }
+            MarkdownErrorKind::CrossingEntities => {
+                "Crossing entities in MarkdownV2 format string - the nested entity must be closed first"
+            }
+            MarkdownErrorKind::NestedLink => {
+                "Nested link in MarkdownV2 format string - the link text can't contain links"
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for MarkdownError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} (at byte {})", self.description(), self.offset)
+    }
+}
+
+impl std::error::Error for MarkdownError {}
+
+/// Internal helper function to find the unescaped `)` closing the link URL, or the end of the text
+const fn link_url_end(bytes: &[u8], start: usize) -> usize {
+    let mut i = start;
+    while i < bytes.len() && bytes[i] != b')' {
+        if bytes[i] == b'\\' {
+            i += 1;
+        }
+        i += 1;
+    }
+    if i > bytes.len() { bytes.len() } else { i }
+}
+
+/// Internal helper function to check if the bytes at the position start with the prefix
+const fn starts_with_at(bytes: &[u8], start: usize, end: usize, prefix: &[u8]) -> bool {
+    if end - start < prefix.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < prefix.len() {
+        if bytes[start + i] != prefix[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+/// Internal helper function to get the length of the `{}` or `{N}` placeholder at the position, 0 if there is none
+pub const fn placeholder_len(bytes: &[u8], start: usize) -> usize {
+    if start >= bytes.len() || bytes[start] != b'{' {
+        return 0;
+    }
+    let mut i = start + 1;
+    while i < bytes.len() && bytes[i].is_ascii_digit() {
+        i += 1;
+    }
+    if i < bytes.len() && bytes[i] == b'}' { i + 1 - start } else { 0 }
+}
+
+/// Internal helper function to check the link URL: http, https or tg scheme with something after it
+/// and no whitespace, or the `{}` or `{N}` placeholder in the place of the whole URL or its beginning
+const fn is_valid_link_url(bytes: &[u8], start: usize, end: usize, placeholders: bool) -> bool {
+    let placeholder = if placeholders { placeholder_len(bytes, start) } else { 0 };
+    // The placeholder may be the whole URL, the scheme must be followed by something
+    let scheme_len = if placeholder > 0 && start + placeholder <= end {
+        0
+    } else if starts_with_at(bytes, start, end, b"https://") {
+        8
+    } else if starts_with_at(bytes, start, end, b"http://") {
+        7
+    } else if starts_with_at(bytes, start, end, b"tg://") {
+        5
+    } else {
+        return false;
+    };
+    if scheme_len > 0 && start + scheme_len == end {
+        return false;
+    }
+    let mut i = start;
+    while i < end {
+        if matches!(bytes[i], b' ' | b'\t' | b'\n' | b'\r') {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+/// Internal helper function to check the URL of the custom emoji: `tg://emoji?id=` with the numeric id
+const fn is_valid_emoji_url(bytes: &[u8], start: usize, end: usize) -> bool {
+    let prefix = b"tg://emoji?id=";
+    if !starts_with_at(bytes, start, end, prefix) || start + prefix.len() == end {
+        return false;
+    }
+    let mut i = start + prefix.len();
+    while i < end {
+        if !bytes[i].is_ascii_digit() {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+/// Formatting entity on the stack of the open entities
+#[derive(Clone, Copy)]
+enum Entity {
+    Bold,
+    Italic,
+    Underline,
+    Strikethrough,
+    Spoiler,
+    LinkText,
+    EmojiText,
+}
+
+impl Entity {
+    /// Error reported if the entity is not closed
+    const fn unclosed(self) -> MarkdownErrorKind {
+        match self {
+            Entity::Bold => MarkdownErrorKind::UnmatchedAsterisk,
+            Entity::Italic | Entity::Underline => MarkdownErrorKind::UnmatchedUnderscore,
+            Entity::Strikethrough => MarkdownErrorKind::UnmatchedTilde,
+            Entity::Spoiler => MarkdownErrorKind::UnmatchedPipe,
+            Entity::LinkText | Entity::EmojiText => MarkdownErrorKind::UnclosedLinkText,
+        }
+    }
+}
+
+/// Maximal number of the open entities, each kind of entity can be open only once
+const MAX_DEPTH: usize = 6;
+
+/// Internal helper function to find the entity on the stack of the open entities
+const fn is_open(stack: &[(Entity, usize); MAX_DEPTH], depth: usize, entity: Entity) -> bool {
+    let mut i = 0;
+    while i < depth {
+        if stack[i].0 as u8 == entity as u8 {
+            return true;
+        }
+        i += 1;
+    }
+    false
+}
+
+/// Internal helper function to check if the text of a link or of a custom emoji is open
+const fn is_link_open(stack: &[(Entity, usize); MAX_DEPTH], depth: usize) -> bool {
+    is_open(stack, depth, Entity::LinkText) || is_open(stack, depth, Entity::EmojiText)
+}
+
+/// Internal helper function implementing the MarkdownV2 validation
+///
+/// The open entities are tracked on the stack, so that the crossing entities like
+/// `*bold _italic* text_` are rejected. The content of the code and pre-formatted blocks
+/// is not parsed. The `>` is the quote marker only at the beginning of a line.
+/// The `{}` format placeholders are accepted only if `placeholders` is set,
+/// otherwise the braces must be escaped like the other reserved characters.
+/// See: https://core.telegram.org/bots/api#markdownv2-style
+pub const fn check_markdownv2_format(
+    format_str: &str,
+    placeholders: bool,
+) -> Result<(), MarkdownError> {
+    let bytes = format_str.as_bytes();
+    // Open entities with the offsets of their opening markers
+    let mut stack = [(Entity::Bold, 0usize); MAX_DEPTH];
+    let mut depth = 0;
+    // Offsets of the opening markers of the code and pre-formatted blocks
+    let mut code_pos = None;
+    let mut pre_pos = None;
+    // Set inside the expandable blockquote until its closing `||`
+    let mut expandable = false;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let current_char = bytes[i];
+        // The escaped character is never special
+        if current_char == b'\\' {
+            i += 2;
+            continue;
+        }
+        if pre_pos.is_some() {
+            if starts_with_at(bytes, i, bytes.len(), b"```") {
+                pre_pos = None;
+                i += 3;
+            } else {
+                i += 1;
+            }
+            continue;
+        }
+        if code_pos.is_some() {
+            if current_char == b'`' {
+                code_pos = None;
+            }
+            i += 1;
+            continue;
+        }
+        if i == 0 || bytes[i - 1] == b'\n' {
+            if current_char == b'>' {
+                i += 1;
+                continue;
+            }
+            // The empty bold before the quote marker starts the expandable blockquote
+            if starts_with_at(bytes, i, bytes.len(), b"**>") {
+                expandable = true;
+                i += 3;
+                continue;
+            }
+            expandable = false;
+        }
+        let next_char = if i + 1 < bytes.len() { bytes[i + 1] } else { 0 };
+        // The expandable blockquote is closed by "||" at the end of its last line
+        if expandable
+            && current_char == b'|'
+            && next_char == b'|'
+            && !is_open(&stack, depth, Entity::Spoiler)
+            && (i + 2 == bytes.len() || bytes[i + 2] == b'\n')
+        {
+            expandable = false;
+            i += 2;
+            continue;
+        }
+        let (entity, marker_len) = match current_char {
+            b'*' => (Entity::Bold, 1),
+            // Double underscore is always the underline, greedily from left to right
+            b'_' if next_char == b'_' => (Entity::Underline, 2),
+            b'_' => (Entity::Italic, 1),
+            b'~' => (Entity::Strikethrough, 1),
+            b'|' if next_char == b'|' => (Entity::Spoiler, 2),
+            b'`' => {
+                if starts_with_at(bytes, i, bytes.len(), b"```") {
+                    pre_pos = Some(i);
+                    i += 3;
+                } else {
+                    code_pos = Some(i);
+                    i += 1;
+                }
+                continue;
+            }
+            // The custom emoji is the link text after '!' with the tg://emoji URL
+            b'!' if next_char == b'[' => {
+                if is_link_open(&stack, depth) {
+                    return Err(MarkdownError::new(i, MarkdownErrorKind::NestedLink));
+                }
+                (Entity::EmojiText, 2)
+            }
+            b'[' => {
+                if is_link_open(&stack, depth) {
+                    return Err(MarkdownError::new(i, MarkdownErrorKind::NestedLink));
+                }
+                (Entity::LinkText, 1)
+            }
+            b']' => {
+                if depth == 0 || !matches!(stack[depth - 1].0, Entity::LinkText | Entity::EmojiText) {
+                    let kind = if is_link_open(&stack, depth) {
+                        MarkdownErrorKind::CrossingEntities
+                    } else {
+                        MarkdownErrorKind::UnmatchedClosingBracket
+                    };
+                    return Err(MarkdownError::new(i, kind));
+                }
+                depth -= 1;
+                let (entity, start) = stack[depth];
+                let emoji = matches!(entity, Entity::EmojiText);
+                // Link URL after the link text, the reserved characters don't need escaping there
+                if next_char == b'(' {
+                    let url_end = link_url_end(bytes, i + 2);
+                    if url_end == bytes.len() {
+                        return Err(MarkdownError::new(i + 1, MarkdownErrorKind::UnclosedLinkUrl));
+                    }
+                    let valid = if emoji {
+                        is_valid_emoji_url(bytes, i + 2, url_end)
+                    } else {
+                        is_valid_link_url(bytes, i + 2, url_end, placeholders)
+                    };
+                    if !valid {
+                        return Err(MarkdownError::new(i + 2, MarkdownErrorKind::InvalidLinkUrl));
+                    }
+                    i = url_end + 1;
+                } else if emoji {
+                    return Err(MarkdownError::new(start, MarkdownErrorKind::Unescaped('!')));
+                } else {
+                    i += 1;
+                }
+                continue;
+            }
+            // Reserved characters that should be escaped
+            b'!' | b'.' | b'-' | b'+' | b'=' | b'>' | b'#' | b'|' => {
+                return Err(MarkdownError::new(
+                    i,
+                    MarkdownErrorKind::Unescaped(current_char as char),
+                ));
+            }
+            // Allow format placeholders like {} and {0}
+            b'{' if placeholders && placeholder_len(bytes, i) > 0 => {
+                i += placeholder_len(bytes, i);
+                continue;
+            }
+            b'{' | b'}' => {
+                return Err(MarkdownError::new(
+                    i,
+                    MarkdownErrorKind::Unescaped(current_char as char),
+                ));
+            }
+            _ => {
+                i += 1;
+                continue;
+            }
+        };
+        // The style markers are both opening and closing ones,
+        // the entity can be closed only if it's the innermost one
+        if depth > 0 && stack[depth - 1].0 as u8 == entity as u8 {
+            depth -= 1;
+        } else if is_open(&stack, depth, entity) {
+            return Err(MarkdownError::new(i, MarkdownErrorKind::CrossingEntities));
+        } else {
+            stack[depth] = (entity, i);
+            depth += 1;
+        }
+        i += marker_len;
+    }
+
+    if let Some(pos) = pre_pos {
+        return Err(MarkdownError::new(pos, MarkdownErrorKind::UnclosedPre));
+    }
+    if let Some(pos) = code_pos {
+        return Err(MarkdownError::new(pos, MarkdownErrorKind::UnclosedCode));
+    }
+    if depth > 0 {
+        let (entity, pos) = stack[depth - 1];
+        return Err(MarkdownError::new(pos, entity.unclosed()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_compile_time_validation() {
+        // These should compile successfully
+        const _: () = {
+            let format_str = "Hello *{}*";
+            let format_str_bytes = format_str.as_bytes();
+            let mut asterisk_count = 0u8;
+            let mut i = 0;
+
+            while i < format_str_bytes.len() {
+                if format_str_bytes[i] == b'*' {
+                    asterisk_count = asterisk_count.wrapping_add(1);
+                }
+                i += 1;
+            }
+
+            assert!(
+                asterisk_count.is_multiple_of(2),
+                "Unmatched asterisks in markdown format string"
+            );
+        };
+    }
+
+    #[test]
+    #[allow(clippy::collapsible_match)]
+    fn test_markdownv2_format_patterns() {
+        // Test various valid MarkdownV2 patterns
+        let valid_patterns = [
+            "Simple text",
+            "With *bold* text",
+            "With _italic_ text",
+            "With `code` text",
+            "With ~strikethrough~ text",
+            "With ||spoiler|| text",
+            "*{}* and _{}_ and `{}`",
+            "**Bold** text",
+            "__Italic__ text",
+            "~~Strikethrough~~ text",
+            "Link: [text](url)",
+            "Code block: ```code```",
+            "Mixed: *bold* and `code`",
+            "Escaped \\! exclamation",
+            "Escaped \\. period",
+            "Escaped \\- dash",
+            "Escaped \\+ plus",
+            "Escaped \\= equals",
+            "Format placeholder: {}",
+        ];
+
+        // Test the enhanced validation logic for each pattern
+        for pattern in valid_patterns {
+            let format_str_bytes = pattern.as_bytes();
+            let mut i = 0;
+            let mut asterisk_count = 0u8;
+            let mut underscore_count = 0u8;
+            let mut backtick_count = 0u8;
+            let mut square_bracket_count = 0u8;
+            let mut paren_count = 0u8;
+            let mut tilde_count = 0u8;
+            let mut pipe_count = 0u8;
+            let mut prev_char = 0u8;
+
+            while i < format_str_bytes.len() {
+                let current_char = format_str_bytes[i];
+                let is_escaped = prev_char == b'\\';
+
+                if !is_escaped {
+                    match current_char {
+                        b'*' => asterisk_count = asterisk_count.wrapping_add(1),
+                        b'_' => underscore_count = underscore_count.wrapping_add(1),
+                        b'~' => tilde_count = tilde_count.wrapping_add(1),
+                        b'|' => pipe_count = pipe_count.wrapping_add(1),
+                        b'`' => backtick_count = backtick_count.wrapping_add(1),
+                        b'[' => square_bracket_count = square_bracket_count.wrapping_add(1),
+                        b']' => {
+                            if square_bracket_count > 0 {
+                                square_bracket_count = square_bracket_count.wrapping_sub(1);
+                            }
+                        }
+                        b'(' => {
+                            if prev_char == b']' {
+                                paren_count = paren_count.wrapping_add(1);
+                            }
+                        }
+                        b')' => {
+                            if paren_count > 0 {
+                                paren_count = paren_count.wrapping_sub(1);
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+
+                prev_char = current_char;
+                i += 1;
+            }
+
+            // Validate all formatting is balanced
+            assert!(
+                asterisk_count.is_multiple_of(2),
+                "Pattern '{}' has unmatched asterisks",
+                pattern
+            );
+            assert!(
+                underscore_count.is_multiple_of(2),
+                "Pattern '{}' has unmatched underscores",
+                pattern
+            );
+            assert!(
+                backtick_count.is_multiple_of(2),
+                "Pattern '{}' has unmatched backticks",
+                pattern
+            );
+            assert!(
+                tilde_count.is_multiple_of(2),
+                "Pattern '{}' has unmatched tildes",
+                pattern
+            );
+            assert!(
+                pipe_count.is_multiple_of(2),
+                "Pattern '{}' has unmatched pipes",
+                pattern
+            );
+            assert!(
+                square_bracket_count == 0,
+                "Pattern '{}' has unmatched square brackets",
+                pattern
+            );
+            assert!(
+                paren_count == 0,
+                "Pattern '{}' has unmatched parentheses",
+                pattern
+            );
+        }
+    }
+
+    // Note: These patterns would cause compile errors if used with the markdown_string! macro:
+    // Invalid examples (unbalanced formatting):
+    // "*unmatched bold" - unmatched asterisk
+    // "_unmatched italic" - unmatched underscore
+    // "`unmatched code" - unmatched backtick
+    // "~unmatched strike" - unmatched tilde
+    // "||unmatched spoiler" - unmatched pipes
+    // "[unmatched link" - unmatched square bracket
+    // "[text](unmatched url" - unmatched parenthesis
+
+    #[test]
+    fn test_escape_detection() {
+        // Test the escape detection logic directly
+        let test_string = "text\\.more";
+        let bytes = test_string.as_bytes();
+        let mut prev_char = 0u8;
+        let mut prev_was_escaping_backslash = false;
+
+        for (i, &current_char) in bytes.iter().enumerate() {
+            let is_escaped = prev_char == b'\\' && prev_was_escaping_backslash;
+            let new_prev_was_escaping_backslash = current_char == b'\\' && !is_escaped;
+
+            if current_char == b'.' {
+                assert!(
+                    is_escaped,
+                    "Period at position {} should be escaped in '{}'",
+                    i, test_string
+                );
+            }
+
+            prev_was_escaping_backslash = new_prev_was_escaping_backslash;
+            prev_char = current_char;
+        }
+    }
+
+    #[test]
+    fn test_check_markdownv2_format() {
+        use super::*;
+
+        assert_eq!(check_markdownv2_format("*{}* \\!", true), Ok(()));
+        assert_eq!(check_markdownv2_format("{0} {12}: [{1}]({0}/a)", true), Ok(()));
+        let err = check_markdownv2_format("{0", true).unwrap_err();
+        assert_eq!((err.offset(), err.kind()), (0, MarkdownErrorKind::Unescaped('{')));
+        let err = check_markdownv2_format("{a}", true).unwrap_err();
+        assert_eq!((err.offset(), err.kind()), (0, MarkdownErrorKind::Unescaped('{')));
+        let err = check_markdownv2_format("*{}*", false).unwrap_err();
+        assert_eq!((err.offset(), err.kind()), (1, MarkdownErrorKind::Unescaped('{')));
+        let err = check_markdownv2_format("Done.", true).unwrap_err();
+        assert_eq!((err.offset(), err.kind()), (4, MarkdownErrorKind::Unescaped('.')));
+        assert_eq!(
+            err.to_string(),
+            "Unescaped '.' in MarkdownV2 format string. Use \\. to escape it. (at byte 4)"
+        );
+        let err = check_markdownv2_format("a *b* *c", true).unwrap_err();
+        assert_eq!((err.offset(), err.kind()), (6, MarkdownErrorKind::UnmatchedAsterisk));
+        let err = check_markdownv2_format("see [docs", true).unwrap_err();
+        assert_eq!((err.offset(), err.kind()), (4, MarkdownErrorKind::UnclosedLinkText));
+        // Link URLs are checked, the reserved characters don't need escaping there
+        assert_eq!(check_markdownv2_format("[a](https://a.b/c_d?e=f#g) [b]({})", true), Ok(()));
+        assert_eq!(check_markdownv2_format("[a](tg://user?id=1) [b](http://a/\\))", false), Ok(()));
+        let err = check_markdownv2_format("[text](not a url)", true).unwrap_err();
+        assert_eq!((err.offset(), err.kind()), (7, MarkdownErrorKind::InvalidLinkUrl));
+        let err = check_markdownv2_format("[text](http://a b)", true).unwrap_err();
+        assert_eq!(err.kind(), MarkdownErrorKind::InvalidLinkUrl);
+        let err = check_markdownv2_format("[text]({})", false).unwrap_err();
+        assert_eq!(err.kind(), MarkdownErrorKind::InvalidLinkUrl);
+        // The custom emoji needs the tg://emoji URL
+        assert_eq!(check_markdownv2_format("![👍](tg://emoji?id=123) *![a](tg://emoji?id=1)*", false), Ok(()));
+        let err = check_markdownv2_format("![a](http://a.b)", false).unwrap_err();
+        assert_eq!((err.offset(), err.kind()), (5, MarkdownErrorKind::InvalidLinkUrl));
+        let err = check_markdownv2_format("a ![b] c", false).unwrap_err();
+        assert_eq!((err.offset(), err.kind()), (2, MarkdownErrorKind::Unescaped('!')));
+        let err = check_markdownv2_format("[a ![b](tg://emoji?id=1)](http://a)", false).unwrap_err();
+        assert_eq!((err.offset(), err.kind()), (3, MarkdownErrorKind::NestedLink));
+        let err = check_markdownv2_format("[text](http://a", true).unwrap_err();
+        assert_eq!((err.offset(), err.kind()), (6, MarkdownErrorKind::UnclosedLinkUrl));
+        // Entities must be properly nested
+        assert_eq!(check_markdownv2_format("*bold _italic_ ~s||p||~* __u_i_u__", true), Ok(()));
+        let err = check_markdownv2_format("*bold _italic* text_", true).unwrap_err();
+        assert_eq!((err.offset(), err.kind()), (13, MarkdownErrorKind::CrossingEntities));
+        let err = check_markdownv2_format("[a *b](http://a)*", true).unwrap_err();
+        assert_eq!((err.offset(), err.kind()), (5, MarkdownErrorKind::CrossingEntities));
+        let err = check_markdownv2_format("[a [b](http://b)](http://a)", true).unwrap_err();
+        assert_eq!((err.offset(), err.kind()), (3, MarkdownErrorKind::NestedLink));
+        let err = check_markdownv2_format("a | b", true).unwrap_err();
+        assert_eq!(err.kind(), MarkdownErrorKind::Unescaped('|'));
+        // The formatting characters inside the code are plain text
+        assert_eq!(check_markdownv2_format("`*a_ [b` ```\n_*~\n```", true), Ok(()));
+        // Reserved characters are allowed inside the code
+        assert_eq!(check_markdownv2_format("`a.b` ```\nx = 1\n```", false), Ok(()));
+        // The quote marker is allowed only at the beginning of a line
+        assert_eq!(check_markdownv2_format(">quote *{}*\n>two\nend", true), Ok(()));
+        assert_eq!(check_markdownv2_format("**>expandable\n>||p|| quote||\n>next", true), Ok(()));
+        let err = check_markdownv2_format("a > b", true).unwrap_err();
+        assert_eq!((err.offset(), err.kind()), (2, MarkdownErrorKind::Unescaped('>')));
+        let err = check_markdownv2_format("**>a|| b", true).unwrap_err();
+        assert_eq!((err.offset(), err.kind()), (4, MarkdownErrorKind::UnmatchedPipe));
+        let err = check_markdownv2_format("**>a\nb||", true).unwrap_err();
+        assert_eq!((err.offset(), err.kind()), (6, MarkdownErrorKind::UnmatchedPipe));
+    }
+}